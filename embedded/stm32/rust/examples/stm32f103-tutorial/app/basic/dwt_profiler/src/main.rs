@@ -0,0 +1,127 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::empty_loop)]
+
+use core::hint::black_box;
+
+use hardware::imu::{self, AttitudeFilter};
+use hardware::mpu6050::mpu6050_hal;
+use hardware::oled;
+use hardware::profile;
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m::prelude::_embedded_hal_blocking_delay_DelayMs;
+use cortex_m_rt::entry;
+use stm32f1xx_hal::pac;
+use stm32f1xx_hal::prelude::_fugit_RateExtU32;
+use stm32f1xx_hal::prelude::_stm32_hal_afio_AfioExt;
+use stm32f1xx_hal::prelude::_stm32_hal_flash_FlashExt;
+use stm32f1xx_hal::prelude::_stm32_hal_gpio_GpioExt;
+use stm32f1xx_hal::rcc::RccExt;
+use stm32f1xx_hal::serial::{self, Serial};
+use stm32f1xx_hal::time::U32Ext;
+use stm32f1xx_hal::timer::SysTimerExt;
+
+/// 采样周期(ms)
+const SAMPLE_PERIOD_MS: u32 = 10;
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let mut cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+    let mut afio = dp.AFIO.constrain();
+
+    let mut gpioa = dp.GPIOA.split();
+    let mut gpiob = dp.GPIOB.split();
+
+    // 冻结系统中所有时钟的配置，并将冻结的频率存储在时钟中
+    let clocks = rcc.cfgr.sysclk(72.MHz()).freeze(&mut flash.acr);
+
+    // 使能 DWT 周期计数器
+    profile::init(&mut cp.DCB, &mut cp.DWT, &clocks);
+
+    // 具有自定义精度的阻塞延迟函数
+    let mut delay = cp.SYST.delay(&clocks);
+
+    // USART1
+    let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+    let rx = gpioa.pa10;
+    let (mut tx, _rx) = Serial::new(
+        dp.USART1,
+        (tx, rx),
+        &mut afio.mapr,
+        serial::Config::default().baudrate(115_200.bps()),
+        &clocks,
+    )
+    .split();
+
+    // 初始化 OLED 显示屏
+    println!("load oled...");
+    let mut oled = oled::simple::init_oled(gpiob.pb8, gpiob.pb9, &mut gpiob.crh);
+
+    // MPU6050
+    let mpu_scl = gpiob.pb10.into_alternate_open_drain(&mut gpiob.crh);
+    let mpu_sda = gpiob.pb11.into_alternate_open_drain(&mut gpiob.crh);
+    let mut mpu = mpu6050_hal::Mpu6050::new((mpu_scl, mpu_sda), dp.I2C2, clocks).unwrap();
+
+    let dt = SAMPLE_PERIOD_MS as f32 / 1000.0;
+    let m = mpu.get_measurement().unwrap();
+    let mut complementary = imu::Complementary::new(0.98);
+    let mut madgwick = imu::Madgwick::new(0.1);
+    let mut mahony = imu::Mahony::new(0.5, 0.0);
+
+    // 基准测试
+    println!("overhead: {} cycles", profile::bench::overhead());
+    profile::bench("oled show_string", 20, || {
+        oled.show_string(1, 1, "Hello, World!")
+    });
+    profile::bench("oled show_num", 20, || oled.show_num(2, 1, 12345, 5));
+    profile::bench("mpu6050 read", 20, || {
+        black_box(mpu.get_measurement().unwrap());
+    });
+    profile::bench("complementary", 100, || {
+        complementary.update(black_box(&m), dt)
+    });
+    profile::bench("madgwick", 100, || madgwick.update(black_box(&m), dt));
+    profile::bench("mahony", 100, || mahony.update(black_box(&m), dt));
+
+    oled.clear();
+    oled.show_string(1, 1, "Roll:");
+    oled.show_string(2, 1, "Pitch:");
+    oled.show_string(3, 1, "Yaw:");
+
+    let mut count: u32 = 0;
+    loop {
+        let loop_scope = profile::scope("loop");
+
+        let m = profile::measure("mpu6050", || mpu.get_measurement().unwrap());
+        profile::measure("madgwick", || madgwick.update(&m, dt));
+
+        // 每100ms刷新一次显示
+        count += 1;
+        if count.is_multiple_of(10) {
+            let _scope = profile::scope("oled");
+            let angles = madgwick.euler();
+            oled.show_signed_num(1, 7, angles.roll as i32, 3);
+            oled.show_signed_num(2, 7, angles.pitch as i32, 3);
+            oled.show_signed_num(3, 7, angles.yaw as i32, 3);
+        }
+        drop(loop_scope);
+
+        // 每秒输出一次统计结果
+        if count.is_multiple_of(100) {
+            profile::report();
+            profile::write_report(&mut tx).unwrap();
+            profile::reset();
+        }
+
+        delay.delay_ms(SAMPLE_PERIOD_MS);
+    }
+}
@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+use core::mem::MaybeUninit;
+use core::ptr::addr_of_mut;
+
+use hardware::time::{self, Duration, Timeout, TimerId};
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m_rt::{entry, exception};
+use stm32f1xx_hal::gpio::{gpioa, Output, PushPull};
+use stm32f1xx_hal::pac;
+use stm32f1xx_hal::prelude::{_stm32_hal_flash_FlashExt, _stm32_hal_gpio_GpioExt};
+use stm32f1xx_hal::rcc::RccExt;
+
+/// LED，在定时器回调中翻转
+static mut LED: MaybeUninit<gpioa::PA0<Output<PushPull>>> = MaybeUninit::uninit();
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+
+    let mut gpioa = dp.GPIOA.split();
+    let mut gpiob = dp.GPIOB.split();
+
+    // 冻结系统中所有时钟的配置，并将冻结的频率存储在时钟中
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    // LED
+    let led = gpioa.pa0.into_push_pull_output(&mut gpioa.crl);
+    unsafe {
+        (*addr_of_mut!(LED)).write(led);
+    }
+
+    // KEY
+    let key = gpiob.pb1.into_pull_up_input(&mut gpiob.crl);
+
+    // 启动 SysTick，每1ms中断一次
+    let _mono = time::Mono::new(cp.SYST, &clocks);
+
+    // 每500ms翻转一次 LED
+    time::start_periodic(Duration::millis(500), toggle_led).unwrap();
+    // 3s后打印一次
+    time::start_once(Duration::secs(3), |_| println!("one shot timer")).unwrap();
+
+    // 每秒打印一次运行时间
+    let mut report = Timeout::new(Duration::secs(1));
+    // 按键消抖，电平保持20ms不变才认为有效
+    let mut debounce = Timeout::new(Duration::millis(20));
+    let mut last_level = key.is_low();
+    let mut pressed = false;
+
+    loop {
+        if report.is_expired() {
+            report.reset();
+            println!("uptime: {} us", time::now().ticks());
+        }
+
+        let level = key.is_low();
+        if level != last_level {
+            // 电平变化，重新计时
+            last_level = level;
+            debounce.reset();
+        } else if debounce.is_expired() && level != pressed {
+            pressed = level;
+            if pressed {
+                println!("key pressed at {} ms", time::millis());
+            }
+        }
+    }
+}
+
+/// 周期定时器回调，在 SysTick 中断中执行
+fn toggle_led(_id: TimerId) {
+    let led = unsafe { &mut *(*addr_of_mut!(LED)).as_mut_ptr() };
+    led.toggle();
+}
+
+#[exception]
+fn SysTick() {
+    time::tick();
+}
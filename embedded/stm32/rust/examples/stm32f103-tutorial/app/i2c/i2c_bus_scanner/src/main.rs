@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::empty_loop)]
+
+use core::cell::RefCell;
+
+use hardware::bus::{CompatI2c, I2cDevice};
+use hardware::mpu6050::conf::DEFAULT_SLAVE_ADDR;
+use hardware::mpu6050::mpu6050_i2c;
+use hardware::{i2c, oled};
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m_rt::entry;
+use stm32f1xx_hal::i2c::{BlockingI2c, Mode};
+use stm32f1xx_hal::pac;
+use stm32f1xx_hal::prelude::{
+    _fugit_RateExtU32, _stm32_hal_flash_FlashExt, _stm32_hal_gpio_GpioExt,
+};
+use stm32f1xx_hal::rcc::RccExt;
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+
+    let mut gpiob = dp.GPIOB.split();
+
+    // 冻结系统中所有时钟的配置，并将冻结的频率存储在时钟中
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    // 初始化 OLED 显示屏
+    println!("load oled...");
+    let mut oled = oled::simple::init_oled(gpiob.pb8, gpiob.pb9, &mut gpiob.crh);
+
+    // I2C2 总线包装为 embedded-hal 1.0 接口后放入 RefCell 中，由多个设备句柄共享
+    let scl = gpiob.pb10.into_alternate_open_drain(&mut gpiob.crh);
+    let sda = gpiob.pb11.into_alternate_open_drain(&mut gpiob.crh);
+    let i2c2 = BlockingI2c::i2c2(
+        dp.I2C2,
+        (scl, sda),
+        Mode::standard(100.kHz()),
+        clocks,
+        1000,
+        10,
+        1000,
+        1000,
+    );
+    let bus = RefCell::new(CompatI2c(i2c2));
+    let mut scanner = I2cDevice::new(&bus);
+
+    // 扫描总线
+    let addresses = i2c::scan(&mut scanner);
+    println!("found {} devices", addresses.len());
+    oled.show_string(1, 1, "Found:");
+    oled.show_num(1, 8, addresses.len() as u32, 3);
+    for (i, address) in addresses.iter().take(6).enumerate() {
+        println!("address: {:#04x}", address);
+        // 每行显示两个地址
+        oled.show_hex_num(2 + i as u8 / 2, 1 + (i as u8 % 2) * 4, *address as u32, 2);
+    }
+
+    // 通过另一个设备句柄，由 MPU6050 驱动访问
+    if i2c::probe(&mut scanner, DEFAULT_SLAVE_ADDR) {
+        let mut mpu = mpu6050_i2c::new(I2cDevice::new(&bus)).unwrap();
+        let id = mpu.get_id().unwrap();
+        println!("MPU6050 ID: {:#04x}", id);
+        oled.show_string(4, 9, "ID:");
+        oled.show_hex_num(4, 12, id as u32, 2);
+    }
+
+    loop {}
+}
@@ -42,19 +42,25 @@ fn main() -> ! {
     // MPU6050 初始化
     let mpu_scl = gpiob.pb10.into_alternate_open_drain(&mut gpiob.crh);
     let mpu_sda = gpiob.pb11.into_alternate_open_drain(&mut gpiob.crh);
-    let mut mpu = mpu6050_hal::Mpu6050::new((mpu_scl, mpu_sda), i2c2, clocks);
+    let mut mpu = mpu6050_hal::Mpu6050::new((mpu_scl, mpu_sda), i2c2, clocks).unwrap();
 
-    let id = mpu.get_id();
+    let id = mpu.get_id().unwrap();
     oled.show_string(1, 1, "ID:");
     oled.show_hex_num(1, 4, id as u32, 2);
 
     // 循环读取加速度和角速度数据
     loop {
-        let data = mpu.get_data();
+        let data = mpu.get_data().unwrap();
         // 打印读取到的数据
         println!("Accel: ({}, {}, {})", data.acc_x, data.acc_y, data.acc_z);
         println!("Gyro: ({}, {}, {})", data.gyro_x, data.gyro_y, data.gyro_z);
 
+        // 转换为物理单位
+        let m = data.to_measurement(mpu.accel_range(), mpu.gyro_range());
+        println!("Accel: ({} {} {}) m/s²", m.acc_x, m.acc_y, m.acc_z);
+        println!("Gyro: ({} {} {}) °/s", m.gyro_x, m.gyro_y, m.gyro_z);
+        println!("Temp: {} °C", m.temp);
+
         oled.show_signed_num(2, 1, data.acc_x as i32, 5);
         oled.show_signed_num(3, 1, data.acc_y as i32, 5);
         oled.show_signed_num(4, 1, data.acc_z as i32, 5);
@@ -0,0 +1,128 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::empty_loop)]
+
+use hardware::flash_store::FlashStore;
+use hardware::imu::{self, AttitudeFilter};
+use hardware::mpu6050::{mpu6050_hal, Config, DlpfBandwidth, GyroRange};
+use hardware::oled;
+use hardware::time::{self, Duration};
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m::prelude::_embedded_hal_blocking_delay_DelayMs;
+use cortex_m_rt::{entry, exception};
+use stm32f1xx_hal::pac;
+use stm32f1xx_hal::prelude::{_stm32_hal_flash_FlashExt, _stm32_hal_gpio_GpioExt};
+use stm32f1xx_hal::rcc::RccExt;
+
+/// 采样周期(ms)
+const SAMPLE_PERIOD_MS: u32 = 10;
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+    let syst = cp.SYST;
+    let i2c2 = dp.I2C2;
+
+    let mut gpiob = dp.GPIOB.split();
+
+    // 冻结系统中所有时钟的配置，并将冻结的频率存储在时钟中
+    let clocks: stm32f1xx_hal::rcc::Clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    // SysTick 作为单调时钟，用于测量两次更新的时间间隔，同时提供阻塞延迟
+    let mut delay = time::Mono::new(syst, &clocks);
+
+    // 初始化 OLED 显示屏
+    println!("load oled...");
+    let mut oled = oled::simple::init_oled(gpiob.pb8, gpiob.pb9, &mut gpiob.crh);
+
+    // MPU6050 初始化，100Hz采样率，44Hz低通滤波
+    let mpu_scl = gpiob.pb10.into_alternate_open_drain(&mut gpiob.crh);
+    let mpu_sda = gpiob.pb11.into_alternate_open_drain(&mut gpiob.crh);
+    let config = Config {
+        gyro_range: GyroRange::Deg500,
+        dlpf: DlpfBandwidth::Hz44,
+        sample_rate_divider: 9,
+        ..Default::default()
+    };
+    // 从内部 FLASH 加载校准数据
+    let flash_store = FlashStore::new();
+    flash_store.init_store();
+    let mut mpu = mpu6050_hal::Mpu6050::new_with_store(
+        (mpu_scl, mpu_sda),
+        i2c2,
+        clocks,
+        &config,
+        &flash_store,
+    )
+    .unwrap();
+
+    // 没有校准数据时保持水平静止，校准并保存
+    if !mpu.is_calibrated() {
+        println!("calibrate, keep still...");
+        oled.show_string(1, 1, "Calibrating...");
+        mpu.calibrate(200, &mut delay).unwrap();
+        mpu.save_calibration(&flash_store);
+    }
+    if let Some(calibration) = mpu.calibration() {
+        println!(
+            "gyro bias: {}, accel offset: {}",
+            calibration.gyro_bias, calibration.accel_offset
+        );
+    }
+
+    oled.show_string(1, 1, "Roll:         ");
+    oled.show_string(2, 1, "Pitch:");
+    oled.show_string(3, 1, "Yaw:");
+
+    let mut filter = imu::Madgwick::new(0.1);
+    let first = mpu.get_measurement().unwrap();
+    // 以加速度计算的姿态作为初始值，加快收敛
+    filter.set_quaternion(imu::Quaternion::from_euler(&imu::EulerAngles::from_accel(
+        &first,
+    )));
+
+    let mut last = time::now();
+    let mut count: u32 = 0;
+    loop {
+        let m = mpu.get_measurement().unwrap();
+        // 用实际经过的时间积分角速度，I2C 读取和显示刷新的耗时也计算在内
+        let now = time::now();
+        let dt = duration_secs(now - last);
+        last = now;
+        filter.update(&m, dt);
+
+        // 每100ms刷新一次显示
+        count += 1;
+        if count.is_multiple_of(10) {
+            let angles = filter.euler();
+            println!(
+                "roll: {}, pitch: {}, yaw: {}",
+                angles.roll, angles.pitch, angles.yaw
+            );
+            oled.show_signed_num(1, 7, angles.roll as i32, 3);
+            oled.show_signed_num(2, 7, angles.pitch as i32, 3);
+            oled.show_signed_num(3, 7, angles.yaw as i32, 3);
+        }
+
+        delay.delay_ms(SAMPLE_PERIOD_MS);
+    }
+}
+
+/// 时间间隔转换为秒
+fn duration_secs(duration: Duration) -> f32 {
+    duration.to_micros() as f32 / 1_000_000.0
+}
+
+#[exception]
+fn SysTick() {
+    time::tick();
+}
@@ -0,0 +1,172 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::empty_loop)]
+
+use core::mem::MaybeUninit;
+use core::ptr::addr_of_mut;
+
+use hardware::mpu6050::conf::{
+    MPU6050_DATA_SIZE, MPU6050_INT_DATA_READY, MPU6050_INT_FIFO_OVERFLOW,
+};
+use hardware::mpu6050::mpu6050_hal;
+use hardware::mpu6050::sampler::{Sample, Sampler};
+use hardware::mpu6050::{AccelGyroData, Config, DlpfBandwidth, Error, IntPinConfig};
+use hardware::time;
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::{entry, exception};
+use heapless::spsc::Queue;
+use stm32f1xx_hal::gpio::{gpiob, Alternate, Edge, ExtiPin, Floating, Input, OpenDrain};
+use stm32f1xx_hal::i2c;
+use stm32f1xx_hal::pac::{self, interrupt};
+use stm32f1xx_hal::prelude::{
+    _fugit_RateExtU32, _stm32_hal_afio_AfioExt, _stm32_hal_flash_FlashExt, _stm32_hal_gpio_GpioExt,
+};
+use stm32f1xx_hal::rcc::RccExt;
+
+type Mpu = mpu6050_hal::Mpu6050<(
+    gpiob::PB10<Alternate<OpenDrain>>,
+    gpiob::PB11<Alternate<OpenDrain>>,
+)>;
+
+/// 环形缓冲区，最多存放255个样本
+const QUEUE_SIZE: usize = 256;
+
+static mut QUEUE: Queue<Sample, QUEUE_SIZE> = Queue::new();
+static mut MPU: MaybeUninit<Mpu> = MaybeUninit::uninit();
+static mut SAMPLER: MaybeUninit<Sampler<'static, QUEUE_SIZE>> = MaybeUninit::uninit();
+static mut INT_PIN: MaybeUninit<gpiob::PB5<Input<Floating>>> = MaybeUninit::uninit();
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+    let mut afio = dp.AFIO.constrain();
+    let mut exti = dp.EXTI;
+
+    let mut gpiob = dp.GPIOB.split();
+
+    // 冻结系统中所有时钟的配置，并将冻结的频率存储在时钟中
+    let clocks = rcc
+        .cfgr
+        .use_hse(8.MHz())
+        .sysclk(72.MHz())
+        .pclk1(36.MHz())
+        .freeze(&mut flash.acr);
+
+    // SysTick 作为单调时钟，为样本提供时间戳
+    let _mono = time::Mono::new(cp.SYST, &clocks);
+
+    // MPU6050 初始化，1kHz采样率，I2C 使用400kHz快速模式
+    let mpu_scl = gpiob.pb10.into_alternate_open_drain(&mut gpiob.crh);
+    let mpu_sda = gpiob.pb11.into_alternate_open_drain(&mut gpiob.crh);
+    let config = Config {
+        dlpf: DlpfBandwidth::Hz184,
+        sample_rate_divider: 0,
+        ..Default::default()
+    };
+    let mode = i2c::Mode::fast(400.kHz(), i2c::DutyCycle::Ratio2to1);
+    let mut mpu =
+        mpu6050_hal::Mpu6050::new_with_mode((mpu_scl, mpu_sda), dp.I2C2, mode, clocks, &config)
+            .unwrap();
+    println!("sample rate: {} Hz", config.sample_rate_hz());
+
+    // INT 引脚输出高电平有效的50us脉冲，每次采样完成后触发
+    mpu.set_int_pin_config(&IntPinConfig::default()).unwrap();
+    mpu.enable_fifo().unwrap();
+    mpu.enable_interrupts(MPU6050_INT_DATA_READY | MPU6050_INT_FIFO_OVERFLOW)
+        .unwrap();
+
+    // 环形缓冲区，写入端交给中断
+    let (producer, mut consumer) = unsafe { (*addr_of_mut!(QUEUE)).split() };
+    let sampler = Sampler::new(producer, config.sample_rate_hz());
+
+    // INT 引脚接 PB5，上升沿触发外部中断
+    let mut int_pin = gpiob.pb5.into_floating_input(&mut gpiob.crl);
+    int_pin.make_interrupt_source(&mut afio);
+    int_pin.trigger_on_edge(&mut exti, Edge::Rising);
+    int_pin.enable_interrupt(&mut exti);
+
+    unsafe {
+        (*addr_of_mut!(MPU)).write(mpu);
+        (*addr_of_mut!(SAMPLER)).write(sampler);
+        (*addr_of_mut!(INT_PIN)).write(int_pin);
+        NVIC::unmask(interrupt::EXTI9_5);
+    }
+
+    let mut last_timestamp = time::now();
+    loop {
+        while let Some(sample) = consumer.dequeue() {
+            // 每秒打印一次
+            if sample.index % 1000 == 0 {
+                let (dropped, overflows) = cortex_m::interrupt::free(|_| {
+                    let sampler = unsafe { &*(*addr_of_mut!(SAMPLER)).as_ptr() };
+                    (sampler.dropped(), sampler.overflows())
+                });
+                let data = sample.data;
+                println!(
+                    "#{} {}us interval: {}us dropped: {} overflows: {}",
+                    sample.index,
+                    sample.timestamp.ticks(),
+                    (sample.timestamp - last_timestamp).to_micros(),
+                    dropped,
+                    overflows
+                );
+                println!(
+                    "Accel: ({}, {}, {}) Gyro: ({}, {}, {})",
+                    data.acc_x, data.acc_y, data.acc_z, data.gyro_x, data.gyro_y, data.gyro_z
+                );
+                last_timestamp = sample.timestamp;
+            }
+        }
+    }
+}
+
+/// MPU6050 INT 引脚中断
+#[interrupt]
+fn EXTI9_5() {
+    let int_pin = unsafe { &mut *(*addr_of_mut!(INT_PIN)).as_mut_ptr() };
+    if !int_pin.check_interrupt() {
+        return;
+    }
+    int_pin.clear_interrupt_pending_bit();
+    // 最新的样本在 INT 脉冲之前刚刚完成采样，尽早测量时间
+    let now = time::now();
+
+    let mpu = unsafe { &mut *(*addr_of_mut!(MPU)).as_mut_ptr() };
+    let sampler = unsafe { &mut *(*addr_of_mut!(SAMPLER)).as_mut_ptr() };
+
+    // 以测量的时间对齐时间戳，中断被延迟时 FIFO 中积压的样本依次向前推算
+    sampler.sync(now, mpu.fifo_count().unwrap() / MPU6050_DATA_SIZE);
+
+    // 取出 FIFO 中的全部样本，中断被延迟时也不会丢失数据
+    // read_fifo 读取中断状态检查溢出，同时清除中断
+    let mut buffer = [AccelGyroData::default(); 8];
+    loop {
+        match mpu.read_fifo(&mut buffer) {
+            Ok(0) => break,
+            Ok(count) => sampler.push_all(&buffer[..count]),
+            Err(Error::FifoOverflow) => {
+                sampler.on_overflow(now);
+                break;
+            }
+            Err(Error::Bus(_)) => {
+                println!("i2c error");
+                break;
+            }
+        }
+    }
+}
+
+#[exception]
+fn SysTick() {
+    time::tick();
+}
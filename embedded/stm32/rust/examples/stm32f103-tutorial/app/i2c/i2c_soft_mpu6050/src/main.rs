@@ -43,14 +43,14 @@ fn main() -> ! {
     mpu_scl.set_speed(&mut gpiob.crh, gpio::IOPinSpeed::Mhz50);
     mpu_sda.set_speed(&mut gpiob.crh, gpio::IOPinSpeed::Mhz50);
     let mut mpu = mpu6050_reg::Mpu6050::new(&mut mpu_scl, &mut mpu_sda, &mut delay);
-    mpu.init_mpu6050();
+    mpu.init_mpu6050().unwrap();
 
-    let id = mpu.get_id();
+    let id = mpu.get_id().unwrap();
     oled.show_string(1, 1, "ID:");
     oled.show_hex_num(1, 4, id as u32, 2);
 
     loop {
-        let data = mpu.get_data();
+        let data = mpu.get_data().unwrap();
 
         // 打印读取到的数据
         println!("Accel: ({}, {}, {})", data.acc_x, data.acc_y, data.acc_z);
@@ -0,0 +1,181 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::empty_loop)]
+
+use core::pin::pin;
+
+use hardware::asynch::{self, AsyncI2c, AsyncRx, AsyncTx, Either, ExtiInput};
+use hardware::mpu6050::conf::{DEFAULT_SLAVE_ADDR, MPU6050_ACCEL_XOUT_H, MPU6050_PWR_MGMT_1};
+use hardware::time::{self, Duration};
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::{entry, exception};
+use stm32f1xx_hal::gpio::{gpioa, Output, PullUp, PushPull};
+use stm32f1xx_hal::i2c::{BlockingI2c, Mode};
+use stm32f1xx_hal::pac::{self, interrupt, I2C2, USART1};
+use stm32f1xx_hal::prelude::_fugit_RateExtU32;
+use stm32f1xx_hal::prelude::_stm32_hal_afio_AfioExt;
+use stm32f1xx_hal::prelude::_stm32_hal_flash_FlashExt;
+use stm32f1xx_hal::prelude::_stm32_hal_gpio_GpioExt;
+use stm32f1xx_hal::rcc::RccExt;
+use stm32f1xx_hal::serial::{self, Serial};
+use stm32f1xx_hal::time::U32Ext;
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+    let mut afio = dp.AFIO.constrain();
+
+    let mut gpioa = dp.GPIOA.split();
+    let mut gpiob = dp.GPIOB.split();
+
+    // 冻结系统中所有时钟的配置，并将冻结的频率存储在时钟中
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    // 启动 SysTick，用于异步延时
+    let _mono = time::Mono::new(cp.SYST, &clocks);
+
+    // LED
+    let mut led = gpioa.pa0.into_push_pull_output(&mut gpioa.crl);
+
+    // 按键，按下时为低电平
+    let key = gpiob.pb1.into_pull_up_input(&mut gpiob.crl);
+    let mut key = ExtiInput::new(key, &mut afio);
+
+    // USART1
+    let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+    let rx = gpioa.pa10;
+    let (tx, rx) = Serial::new(
+        dp.USART1,
+        (tx, rx),
+        &mut afio.mapr,
+        serial::Config::default().baudrate(9600.bps()),
+        &clocks,
+    )
+    .split();
+    let mut tx = AsyncTx::new(tx);
+    let mut rx = AsyncRx::new(rx);
+
+    // I2C2
+    let scl = gpiob.pb10.into_alternate_open_drain(&mut gpiob.crh);
+    let sda = gpiob.pb11.into_alternate_open_drain(&mut gpiob.crh);
+    let i2c2 = BlockingI2c::i2c2(
+        dp.I2C2,
+        (scl, sda),
+        Mode::standard(100.kHz()),
+        clocks,
+        1000,
+        10,
+        1000,
+        1000,
+    );
+    let mut i2c: AsyncI2c<I2C2, _> = AsyncI2c::new(i2c2);
+
+    unsafe {
+        NVIC::unmask(interrupt::EXTI1);
+        NVIC::unmask(interrupt::USART1);
+        NVIC::unmask(interrupt::I2C2_EV);
+        NVIC::unmask(interrupt::I2C2_ER);
+    }
+
+    println!("run");
+    asynch::run([
+        pin!(blink(&mut led)),
+        pin!(echo(&mut rx, &mut tx, &mut key)),
+        pin!(accel(&mut i2c)),
+    ]);
+
+    loop {}
+}
+
+/// 每500ms翻转一次 LED
+async fn blink(led: &mut gpioa::PA0<Output<PushPull>>) {
+    loop {
+        led.toggle();
+        time::sleep(Duration::millis(500)).await;
+    }
+}
+
+/// 回显串口收到的数据，按键按下时发送提示
+async fn echo(
+    rx: &mut AsyncRx<USART1>,
+    tx: &mut AsyncTx<USART1>,
+    key: &mut ExtiInput<'B', 1, PullUp>,
+) {
+    loop {
+        match asynch::select(rx.read(), key.wait_for_falling_edge()).await {
+            Either::First(Ok(byte)) => tx.write(byte).await,
+            Either::First(Err(e)) => println!("serial error: {}", defmt::Debug2Format(&e)),
+            Either::Second(()) => {
+                tx.write_str("key pressed\r\n").await;
+                // 按键消抖
+                time::sleep(Duration::millis(20)).await;
+                key.wait_for_high().await;
+                time::sleep(Duration::millis(20)).await;
+            }
+        }
+    }
+}
+
+/// 每100ms读取一次 MPU6050 加速度
+async fn accel<BUS>(i2c: &mut AsyncI2c<I2C2, BUS>) {
+    // 解除睡眠
+    if let Err(e) = i2c
+        .write(DEFAULT_SLAVE_ADDR, &[MPU6050_PWR_MGMT_1, 0x01])
+        .await
+    {
+        println!("mpu6050 error: {}", e);
+        return;
+    }
+
+    let mut buf = [0; 6];
+    loop {
+        match i2c
+            .write_read(DEFAULT_SLAVE_ADDR, &[MPU6050_ACCEL_XOUT_H], &mut buf)
+            .await
+        {
+            Ok(()) => {
+                let x = i16::from_be_bytes([buf[0], buf[1]]);
+                let y = i16::from_be_bytes([buf[2], buf[3]]);
+                let z = i16::from_be_bytes([buf[4], buf[5]]);
+                println!("accel: {} {} {}", x, y, z);
+            }
+            Err(e) => println!("mpu6050 error: {}", e),
+        }
+        time::sleep(Duration::millis(100)).await;
+    }
+}
+
+#[exception]
+fn SysTick() {
+    time::tick();
+}
+
+#[interrupt]
+fn EXTI1() {
+    asynch::exti::on_interrupt();
+}
+
+#[interrupt]
+fn USART1() {
+    asynch::serial::on_interrupt::<USART1>();
+}
+
+#[interrupt]
+fn I2C2_EV() {
+    asynch::i2c::on_interrupt::<I2C2>();
+}
+
+#[interrupt]
+fn I2C2_ER() {
+    asynch::i2c::on_interrupt::<I2C2>();
+}
@@ -2,6 +2,7 @@
 #![no_main]
 #![allow(clippy::empty_loop)]
 
+use hardware::motor::{Motor, MotorConfig, SignMagnitude};
 use hardware::oled;
 
 use defmt::println;
@@ -10,14 +11,14 @@ use panic_probe as _;
 
 use cortex_m::prelude::_embedded_hal_blocking_delay_DelayMs;
 use cortex_m_rt::entry;
-use stm32f1xx_hal::gpio::{self, Alternate, IOPinSpeed, OutputSpeed};
-use stm32f1xx_hal::pac::{self, TIM2};
+use stm32f1xx_hal::gpio::{self, IOPinSpeed, OutputSpeed};
+use stm32f1xx_hal::pac;
 use stm32f1xx_hal::prelude::{
     _fugit_RateExtU32, _stm32_hal_afio_AfioExt, _stm32_hal_flash_FlashExt, _stm32_hal_gpio_GpioExt,
 };
 use stm32f1xx_hal::rcc::RccExt;
-use stm32f1xx_hal::timer::{Ch, PwmHz, SysDelay, SysTimerExt};
-use stm32f1xx_hal::timer::{Channel, PwmExt, Tim2NoRemap};
+use stm32f1xx_hal::timer::{PwmExt, Tim2NoRemap};
+use stm32f1xx_hal::timer::{SysDelay, SysTimerExt};
 
 #[entry]
 fn main() -> ! {
@@ -67,10 +68,6 @@ fn main() -> ! {
     let pwma = gpioa.pa2.into_alternate_push_pull(&mut gpioa.crl);
     let mut pwm = tim2.pwm_hz::<Tim2NoRemap, _, _>(pwma, &mut afio.mapr, 1.kHz(), &clocks);
 
-    // Enable clock on each of the channels
-    // https://docs.rs/stm32f1xx-hal/0.10.0/stm32f1xx_hal/timer/index.html
-    pwm.enable(Channel::C3);
-
     // Return to the original frequency
     pwm.set_period(10.kHz());
 
@@ -79,8 +76,15 @@ fn main() -> ! {
     let x = pwm.get_period();
     println!("period={:?} Hz", x.raw());
 
-    // 电机有足够的时间启动和停止
+    // 取出通道3，由电机驱动开启输出
+    let pwm_channel = pwm.split();
+
+    // 主循环每次调用 `tick` 速度最多变化 2%，从静止到全速需要 50 次循环，电机有足够的时间启动和停止
+    // 循环间隔为 10ms 延时加上刷新 OLED 的时间，按住按键时循环暂停，速度也保持不变
     // 防止电机无法达到稳定状态，或者因为速度变化太快而损坏电机。
+    let config = MotorConfig::default().ramp(20);
+    let mut motor = Motor::new(SignMagnitude::new(ain1, ain2, pwm_channel), config);
+
     delay.delay_ms(1000_u32);
 
     let mut speed = 0;
@@ -93,9 +97,11 @@ fn main() -> ! {
             if speed > 100 {
                 speed = -100;
             }
+            motor.set_speed_percent(speed as i8);
         }
-        set_speed(&mut ain1, &mut ain2, &mut pwm, speed);
-        oled.show_signed_num(1, 7, speed, 3);
+        motor.tick();
+        oled.show_signed_num(1, 7, motor.speed() as i32 / 10, 3);
+        delay.delay_ms(10_u32);
     }
 }
 
@@ -118,33 +124,3 @@ fn get_key_num(
     }
     key_num
 }
-
-/// 设置直流电机速度
-/// 设置电机的速度和方向，speed为-100到100之间的整数，负数表示反转，正数表示正转，绝对值越大速度越快
-fn set_speed(
-    ain1: &mut gpio::Pin<'A', 4, gpio::Output>,
-    ain2: &mut gpio::Pin<'A', 5, gpio::Output>,
-    pwm: &mut PwmHz<TIM2, Tim2NoRemap, Ch<2>, gpio::Pin<'A', 2, Alternate>>,
-    speed: i32,
-) {
-    let max_duty = pwm.get_max_duty();
-    if speed >= 0 {
-        // 设置正转方向
-        ain1.set_high();
-        ain2.set_low();
-        // 设置占空比
-        pwm.set_duty(
-            Channel::C3,
-            (max_duty as f32 * (speed as f32 / 100.0)) as u16,
-        );
-    } else {
-        // 设置反转方向
-        ain1.set_low();
-        ain2.set_high();
-        // 设置占空比
-        pwm.set_duty(
-            Channel::C3,
-            (max_duty as f32 * (-speed as f32 / 100.0)) as u16,
-        );
-    }
-}
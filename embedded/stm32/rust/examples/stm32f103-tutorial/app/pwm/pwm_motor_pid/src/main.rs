@@ -0,0 +1,285 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::empty_loop)]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::mem::MaybeUninit;
+use core::ptr::addr_of_mut;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use hardware::control::{Command, CommandReader, ControllerConfig, MotorController, QeiEncoder};
+use hardware::motor::{Motor, MotorConfig, SignMagnitude};
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m::interrupt::Mutex;
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use heapless::spsc::{Producer, Queue};
+use stm32f1xx_hal::gpio::{IOPinSpeed, Input, Output, OutputSpeed, Pin, PullUp};
+use stm32f1xx_hal::pac::{self, interrupt, TIM2, TIM3, TIM4, USART1};
+use stm32f1xx_hal::prelude::{
+    _fugit_RateExtU32, _stm32_hal_afio_AfioExt, _stm32_hal_flash_FlashExt, _stm32_hal_gpio_GpioExt,
+};
+use stm32f1xx_hal::qei::{Qei, QeiOptions};
+use stm32f1xx_hal::rcc::RccExt;
+use stm32f1xx_hal::serial::{self, Rx, Serial, Tx};
+use stm32f1xx_hal::time::U32Ext;
+use stm32f1xx_hal::timer::{
+    CounterHz, Event, PwmChannel, PwmExt, Tim2NoRemap, Tim3NoRemap, Timer, TimerExt,
+};
+
+type TQei = Qei<TIM3, Tim3NoRemap, (Pin<'A', 6, Input<PullUp>>, Pin<'A', 7, Input<PullUp>>)>;
+type TBridge = SignMagnitude<Pin<'A', 4, Output>, Pin<'A', 5, Output>, PwmChannel<TIM2, 2>>;
+type TController = MotorController<TBridge, TQei>;
+
+/// 控制频率(Hz)
+const CONTROL_RATE_HZ: u32 = 100;
+/// 每隔多少个控制周期输出一次数据
+const REPORT_INTERVAL: u32 = 10;
+/// 串口接收缓冲区，最多存放63个字节
+const RX_QUEUE_SIZE: usize = 64;
+
+static G_TIM: Mutex<RefCell<Option<CounterHz<TIM4>>>> = Mutex::new(RefCell::new(None));
+static G_CONTROLLER: Mutex<RefCell<Option<TController>>> = Mutex::new(RefCell::new(None));
+/// 控制周期计数
+static TICKS: AtomicU32 = AtomicU32::new(0);
+
+/// 串口接收环形缓冲区，主循环阻塞发送数据时接收的命令也不会丢失
+static mut RX_QUEUE: Queue<u8, RX_QUEUE_SIZE> = Queue::new();
+static mut RX: MaybeUninit<Rx<USART1>> = MaybeUninit::uninit();
+static mut RX_PRODUCER: MaybeUninit<Producer<'static, u8, RX_QUEUE_SIZE>> = MaybeUninit::uninit();
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+    let mut afio = dp.AFIO.constrain();
+    let mut nvic = cp.NVIC;
+
+    let mut gpioa = dp.GPIOA.split();
+
+    // 冻结系统中所有时钟的配置，并将冻结的频率存储在时钟中
+    let clocks = rcc.cfgr.sysclk(72.MHz()).freeze(&mut flash.acr);
+
+    // USART1，接收调参命令，输出数据
+    println!("load serial...");
+    let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+    let rx = gpioa.pa10;
+    let (mut tx, mut rx) = Serial::new(
+        dp.USART1,
+        (tx, rx),
+        &mut afio.mapr,
+        serial::Config::default().baudrate(115200.bps()),
+        &clocks,
+    )
+    .split();
+
+    // 直流电机方向引脚
+    println!("load motor...");
+    let mut ain1 = gpioa.pa4.into_push_pull_output(&mut gpioa.crl);
+    let mut ain2 = gpioa.pa5.into_push_pull_output(&mut gpioa.crl);
+    ain1.set_speed(&mut gpioa.crl, IOPinSpeed::Mhz50);
+    ain2.set_speed(&mut gpioa.crl, IOPinSpeed::Mhz50);
+
+    // pwma 速度控制引脚，TIM2 通道3
+    let pwma = gpioa.pa2.into_alternate_push_pull(&mut gpioa.crl);
+    let pwm = dp
+        .TIM2
+        .pwm_hz::<Tim2NoRemap, _, _>(pwma, &mut afio.mapr, 10.kHz(), &clocks);
+    // 加速度由控制器规划，电机不再限制
+    let motor = Motor::new(
+        SignMagnitude::new(ain1, ain2, pwm.split()),
+        MotorConfig::default(),
+    );
+
+    // 编码器，TIM3 编码器接口
+    println!("load rotary encoder ...");
+    let pa6 = gpioa.pa6.into_pull_up_input(&mut gpioa.crl);
+    let pa7 = gpioa.pa7.into_pull_up_input(&mut gpioa.crl);
+    let qei = Timer::new(dp.TIM3, &clocks).qei((pa6, pa7), &mut afio.mapr, QeiOptions::default());
+
+    let config = ControllerConfig {
+        rate_hz: CONTROL_RATE_HZ,
+        ..ControllerConfig::default()
+    };
+    let controller = MotorController::new(motor, QeiEncoder(qei), config);
+
+    // TIM4 定时中断，执行控制
+    println!("load timer...");
+    let mut timer = dp.TIM4.counter_hz(&clocks);
+    timer.start(CONTROL_RATE_HZ.Hz()).unwrap();
+    timer.listen(Event::Update);
+
+    // 移动到全局存储中
+    cortex_m::interrupt::free(|cs| {
+        G_TIM.borrow(cs).replace(Some(timer));
+        G_CONTROLLER.borrow(cs).replace(Some(controller));
+    });
+    unsafe {
+        nvic.set_priority(interrupt::TIM4, 1);
+        NVIC::unmask(interrupt::TIM4);
+    }
+
+    // 串口接收中断，写入端交给中断
+    let (producer, mut consumer) = unsafe { (*addr_of_mut!(RX_QUEUE)).split() };
+    rx.listen();
+    unsafe {
+        (*addr_of_mut!(RX)).write(rx);
+        (*addr_of_mut!(RX_PRODUCER)).write(producer);
+        NVIC::unmask(interrupt::USART1);
+    }
+
+    writeln!(tx, "setpoint,speed,position,output").unwrap();
+
+    let mut reader: CommandReader<32> = CommandReader::new();
+    let mut next_report = 0;
+    println!("loop ...");
+    loop {
+        // 串口命令
+        while let Some(byte) = consumer.dequeue() {
+            match reader.push(byte) {
+                Some(Ok(Command::Status)) => report_status(&mut tx),
+                Some(Ok(command)) => {
+                    println!("command: {}", command);
+                    with_controller(|controller| controller.apply(command));
+                }
+                Some(Err(e)) => {
+                    println!("command error: {}", e);
+                    writeln!(tx, "error: {:?}", e).unwrap();
+                }
+                None => {}
+            }
+        }
+
+        // 定时输出数据
+        let ticks = TICKS.load(Ordering::Relaxed);
+        if ticks.wrapping_sub(next_report) < u32::MAX / 2 {
+            next_report = ticks.wrapping_add(REPORT_INTERVAL);
+            let (setpoint, speed, position, output) = with_controller(|controller| {
+                (
+                    controller.speed_setpoint(),
+                    controller.speed(),
+                    controller.position(),
+                    controller.output(),
+                )
+            });
+            writeln!(
+                tx,
+                "{},{},{},{}",
+                setpoint as i32, speed as i32, position, output
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// 在临界区内访问控制器
+fn with_controller<R>(f: impl FnOnce(&mut TController) -> R) -> R {
+    cortex_m::interrupt::free(|cs| {
+        let mut controller = G_CONTROLLER.borrow(cs).borrow_mut();
+        f(controller.as_mut().unwrap())
+    })
+}
+
+/// 打印状态和参数
+fn report_status(tx: &mut Tx<USART1>) {
+    let (mode, position, target, speed, speed_gains, position_gains, profile) =
+        with_controller(|controller| {
+            (
+                controller.mode(),
+                controller.position(),
+                controller.target_position(),
+                controller.speed(),
+                controller.speed_pid().gains(),
+                controller.position_pid().gains(),
+                *controller.profile(),
+            )
+        });
+    println!(
+        "mode={} position={} target={} speed={}",
+        mode, position, target, speed
+    );
+    writeln!(
+        tx,
+        "mode={:?} position={} target={} speed={}",
+        mode, position, target, speed as i32
+    )
+    .unwrap();
+    writeln!(
+        tx,
+        "speed pid: kp={} ki={} kd={} kf={}",
+        Milli(speed_gains.kp),
+        Milli(speed_gains.ki),
+        Milli(speed_gains.kd),
+        Milli(speed_gains.kf)
+    )
+    .unwrap();
+    writeln!(
+        tx,
+        "position pid: kp={} ki={} kd={} kf={}",
+        Milli(position_gains.kp),
+        Milli(position_gains.ki),
+        Milli(position_gains.kd),
+        Milli(position_gains.kf)
+    )
+    .unwrap();
+    writeln!(
+        tx,
+        "vmax={} accel={}",
+        profile.max_velocity as i32, profile.acceleration as i32
+    )
+    .unwrap();
+}
+
+/// 保留3位小数输出
+/// 不使用 core::fmt 的浮点数格式化，其代码约占 15KB FLASH
+struct Milli(f32);
+
+impl core::fmt::Display for Milli {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // 四舍五入，转换为整数时向0截断
+        let scaled = self.0 * 1000.0;
+        let milli = if scaled < 0.0 {
+            scaled - 0.5
+        } else {
+            scaled + 0.5
+        } as i32;
+        let sign = if milli < 0 { "-" } else { "" };
+        let milli = milli.unsigned_abs();
+        write!(f, "{}{}.{:03}", sign, milli / 1000, milli % 1000)
+    }
+}
+
+/// 串口接收中断，将收到的字节写入环形缓冲区
+#[interrupt]
+fn USART1() {
+    let rx = unsafe { &mut *(*addr_of_mut!(RX)).as_mut_ptr() };
+    let producer = unsafe { &mut *(*addr_of_mut!(RX_PRODUCER)).as_mut_ptr() };
+    // 读取数据寄存器同时清除 RXNE，溢出等错误时丢弃该字节，命令解析时报错
+    if let Ok(byte) = rx.read() {
+        // 缓冲区满时丢弃
+        let _ = producer.enqueue(byte);
+    }
+}
+
+/// 中断调用函数，执行一次控制
+#[interrupt]
+fn TIM4() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(tim) = G_TIM.borrow(cs).borrow_mut().as_mut() {
+            tim.wait().unwrap();
+        }
+        if let Some(controller) = G_CONTROLLER.borrow(cs).borrow_mut().as_mut() {
+            controller.update();
+        }
+    });
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
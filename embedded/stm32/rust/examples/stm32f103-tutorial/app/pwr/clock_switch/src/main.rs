@@ -0,0 +1,147 @@
+#![no_std]
+#![no_main]
+
+use core::mem::MaybeUninit;
+use core::ptr::addr_of_mut;
+
+use hardware::clock::{self, ClockInfo, Preset};
+use hardware::time::{self, Duration, Timeout, TimerId};
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m_rt::{entry, exception};
+use nb::block;
+use stm32f1xx_hal::gpio::{gpioa, Output, PushPull};
+use stm32f1xx_hal::pac;
+use stm32f1xx_hal::prelude::_stm32_hal_afio_AfioExt;
+use stm32f1xx_hal::prelude::_stm32_hal_flash_FlashExt;
+use stm32f1xx_hal::prelude::_stm32_hal_gpio_GpioExt;
+use stm32f1xx_hal::rcc::RccExt;
+use stm32f1xx_hal::serial::{self, Serial};
+use stm32f1xx_hal::time::U32Ext;
+
+/// LED，在定时器回调中翻转
+static mut LED: MaybeUninit<gpioa::PA0<Output<PushPull>>> = MaybeUninit::uninit();
+
+/// 串口波特率，切换时钟后保持不变
+const BAUDRATE: u32 = 9600;
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut afio = dp.AFIO.constrain();
+
+    let mut gpioa = dp.GPIOA.split();
+
+    // 优先使用外部晶振，晶振不工作时使用 HSI 64MHz
+    let (mut clocks, mut high) =
+        clock::freeze_with_fallback(Preset::Hse72, Preset::Hsi64, &mut rcc, &mut flash.acr);
+    println!("preset: {}", high);
+    ClockInfo::new(&clocks).print();
+
+    // 外部晶振停振时触发 NMI
+    if high.uses_hse() {
+        clock::enable_css();
+    }
+
+    // LED
+    let led = gpioa.pa0.into_push_pull_output(&mut gpioa.crl);
+    unsafe {
+        (*addr_of_mut!(LED)).write(led);
+    }
+
+    // USART1
+    let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+    let rx = gpioa.pa10;
+    let mut serial = Serial::new(
+        dp.USART1,
+        (tx, rx),
+        &mut afio.mapr,
+        serial::Config::default().baudrate(BAUDRATE.bps()),
+        &clocks,
+    );
+
+    // 启动 SysTick，每1ms中断一次
+    let mut mono = time::Mono::new(cp.SYST, &clocks);
+
+    // 每500ms翻转一次 LED，切换主频后闪烁频率不变
+    time::start_periodic(Duration::millis(500), toggle_led).unwrap();
+
+    // 每5s在高性能和低功耗之间切换一次
+    let mut switch = Timeout::new(Duration::secs(5));
+    let mut report = Timeout::new(Duration::secs(1));
+    let mut preset = high;
+
+    loop {
+        if clock::take_hse_failure() {
+            // 硬件已自动切换到 HSI 8MHz，重新配置时钟和外设
+            println!("HSE failure, source: {}", clock::sysclk_source());
+            clocks = clock::fallback_to_hsi(&mut rcc, &mut flash.acr);
+            // 之后不再切换回外部晶振
+            high = Preset::Hsi64;
+            preset = high;
+            block!(serial.reconfigure(serial::Config::default().baudrate(BAUDRATE.bps()), &clocks))
+                .unwrap();
+            mono.reconfigure(&clocks);
+            ClockInfo::new(&clocks).print();
+        }
+
+        if switch.is_expired() {
+            switch.reset();
+
+            preset = if preset == Preset::LowPower8 {
+                high
+            } else {
+                Preset::LowPower8
+            };
+
+            // 等待串口发送完成再切换时钟
+            block!(serial.tx.flush()).unwrap();
+            clocks = match clock::switch(preset, &mut rcc, &mut flash.acr) {
+                Ok(clocks) => clocks,
+                Err(_) => {
+                    // 外部晶振不能起振，之后只使用 HSI
+                    println!("HSE not ready, fall back to HSI");
+                    high = Preset::Hsi64;
+                    preset = high;
+                    clock::fallback_to_hsi(&mut rcc, &mut flash.acr)
+                }
+            };
+            block!(serial.reconfigure(serial::Config::default().baudrate(BAUDRATE.bps()), &clocks))
+                .unwrap();
+            mono.reconfigure(&clocks);
+
+            println!("preset: {}", preset);
+            ClockInfo::new(&clocks).print();
+        }
+
+        if report.is_expired() {
+            report.reset();
+            hardware::serial::send_string(&mut serial.tx, "SYSCLK: ");
+            hardware::serial::send_number(&mut serial.tx, clocks.sysclk().to_Hz());
+            hardware::serial::send_string(&mut serial.tx, " Hz\r\n");
+        }
+    }
+}
+
+fn toggle_led(_id: TimerId) {
+    let led = unsafe { &mut *(*addr_of_mut!(LED)).as_mut_ptr() };
+    led.toggle();
+}
+
+#[exception]
+fn SysTick() {
+    time::tick();
+}
+
+#[exception]
+unsafe fn NonMaskableInt() {
+    clock::on_nmi();
+}
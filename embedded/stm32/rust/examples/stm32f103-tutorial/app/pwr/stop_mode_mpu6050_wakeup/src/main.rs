@@ -0,0 +1,191 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::empty_loop)]
+
+use core::mem::MaybeUninit;
+use core::ptr::{addr_of, addr_of_mut};
+
+use hardware::mpu6050::conf::{MPU6050_INT_FREE_FALL, MPU6050_INT_MOTION, MPU6050_INT_ZERO_MOTION};
+use hardware::mpu6050::mpu6050_hal;
+use hardware::mpu6050::{
+    AccelHpf, FreeFallDetection, IntPinConfig, MotionDetection, MotionEvent, ZeroMotionDetection,
+};
+use hardware::oled;
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m::asm::wfi;
+use cortex_m::peripheral::NVIC;
+use cortex_m::prelude::_embedded_hal_blocking_delay_DelayMs;
+use cortex_m_rt::entry;
+use stm32f1xx_hal::gpio::{gpiob, Edge, ExtiPin, Floating, Input};
+use stm32f1xx_hal::pac::{self, interrupt};
+use stm32f1xx_hal::prelude::{
+    _stm32_hal_afio_AfioExt, _stm32_hal_flash_FlashExt, _stm32_hal_gpio_GpioExt,
+};
+use stm32f1xx_hal::rcc::RccExt;
+use stm32f1xx_hal::timer::SysTimerExt;
+
+/// MPU6050 INT 引脚
+static mut INT_PIN: MaybeUninit<gpiob::PB5<Input<Floating>>> = MaybeUninit::uninit();
+
+/// 唤醒次数
+static mut WAKEUP_COUNT: u32 = 0;
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let mut cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+    let syst = cp.SYST;
+    let mut afio = dp.AFIO.constrain();
+    let mut exti = dp.EXTI;
+    let pwr = dp.PWR;
+
+    let mut gpiob = dp.GPIOB.split();
+
+    // 使用 HSI 时钟，从停止模式唤醒后无需重新配置时钟
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    // 具有自定义精度的阻塞延迟函数
+    let mut delay = syst.delay(&clocks);
+
+    // 初始化 OLED 显示屏
+    println!("load oled...");
+    let mut oled = oled::simple::init_oled(gpiob.pb8, gpiob.pb9, &mut gpiob.crh);
+
+    // 上电延时
+    delay.delay_ms(20u16);
+
+    // MPU6050 初始化
+    let mpu_scl = gpiob.pb10.into_alternate_open_drain(&mut gpiob.crh);
+    let mpu_sda = gpiob.pb11.into_alternate_open_drain(&mut gpiob.crh);
+    let mut mpu = mpu6050_hal::Mpu6050::new((mpu_scl, mpu_sda), dp.I2C2, clocks).unwrap();
+
+    // 运动和静止检测使用高通滤波后的加速度，去除重力的影响
+    mpu.set_accel_hpf(AccelHpf::Hz5).unwrap();
+    mpu.set_motion_detection(&MotionDetection {
+        threshold: 20,
+        duration_ms: 40,
+    })
+    .unwrap();
+    // 静止约1.3s
+    mpu.set_zero_motion_detection(&ZeroMotionDetection {
+        threshold: 8,
+        duration: 20,
+    })
+    .unwrap();
+    mpu.set_free_fall_detection(&FreeFallDetection {
+        threshold: 17,
+        duration_ms: 30,
+    })
+    .unwrap();
+
+    // 锁存中断，直到读取中断状态后 INT 引脚才恢复
+    mpu.set_int_pin_config(&IntPinConfig {
+        latch: true,
+        ..Default::default()
+    })
+    .unwrap();
+    mpu.enable_interrupts(MPU6050_INT_MOTION | MPU6050_INT_ZERO_MOTION | MPU6050_INT_FREE_FALL)
+        .unwrap();
+    // 清除已有的中断
+    mpu.read_motion_event().unwrap();
+
+    // INT 引脚接 PB5，上升沿触发外部中断
+    let mut int_pin = gpiob.pb5.into_floating_input(&mut gpiob.crl);
+    int_pin.make_interrupt_source(&mut afio);
+    int_pin.trigger_on_edge(&mut exti, Edge::Rising);
+    int_pin.enable_interrupt(&mut exti);
+
+    unsafe {
+        (*addr_of_mut!(INT_PIN)).write(int_pin);
+        NVIC::unmask(interrupt::EXTI9_5);
+    }
+
+    // 使能 PWR 时钟，选择深度睡眠
+    unsafe {
+        (*pac::RCC::ptr())
+            .apb1enr
+            .modify(|_, w| w.pwren().set_bit())
+    };
+    cp.SCB.set_sleepdeep();
+
+    oled.show_string(1, 1, "Wakeup:");
+    oled.show_string(2, 1, "Event:");
+    loop {
+        // 读取并清除中断状态
+        let event = mpu.read_motion_event().unwrap();
+        let name = match event {
+            Some(MotionEvent::Motion(status)) => {
+                println!(
+                    "motion x: {}{} y: {}{} z: {}{}",
+                    status.x_positive(),
+                    status.x_negative(),
+                    status.y_positive(),
+                    status.y_negative(),
+                    status.z_positive(),
+                    status.z_negative()
+                );
+                "Motion    "
+            }
+            Some(MotionEvent::ZeroMotion { still: true }) => "Still     ",
+            Some(MotionEvent::ZeroMotion { still: false }) => "Moving    ",
+            Some(MotionEvent::FreeFall) => "Free fall ",
+            None => "None      ",
+        };
+        println!("event: {}", name);
+        oled.show_num(1, 8, get_wakeup_count(), 5);
+        oled.show_string(2, 7, name);
+
+        pwr.cr.modify(|_, w| {
+            w
+                // 清除唤醒标识
+                .cwuf()
+                .set_bit()
+                // 进入停止模式
+                .lpds()
+                .set_bit()
+                .pdds()
+                .stop_mode()
+        });
+
+        // 读取状态之后又锁存了新的中断时 INT 引脚保持高电平，不会再产生上升沿，
+        // 关闭中断后检查引脚电平，避免错过唤醒；关闭中断时 WFI 仍会被挂起的中断唤醒
+        cortex_m::interrupt::free(|_| {
+            if !int_pin_is_high() {
+                // 请求低功耗模式
+                wfi();
+            }
+        });
+    }
+}
+
+/// MPU6050 INT 引脚中断
+#[interrupt]
+fn EXTI9_5() {
+    let int_pin = unsafe { &mut *(*addr_of_mut!(INT_PIN)).as_mut_ptr() };
+
+    if int_pin.check_interrupt() {
+        unsafe {
+            *addr_of_mut!(WAKEUP_COUNT) += 1;
+        }
+
+        int_pin.clear_interrupt_pending_bit();
+    }
+}
+
+/// 获取唤醒次数
+fn get_wakeup_count() -> u32 {
+    unsafe { *addr_of!(WAKEUP_COUNT) }
+}
+
+/// INT 引脚是否为高电平，即是否有未读取的中断
+fn int_pin_is_high() -> bool {
+    unsafe { (*addr_of!(INT_PIN)).assume_init_ref().is_high() }
+}
@@ -61,7 +61,7 @@ fn main() -> ! {
 
     // 创建一个Spi实例
     let pins = (sck, miso, mosi);
-    let mut w25q = w25q64_hal::W25Q64::new(spi1, pins, &mut cs, &mut afio.mapr, clocks);
+    let mut w25q = w25q64_hal::new(spi1, pins, &mut cs, &mut afio.mapr, clocks);
 
     delay.delay_ms(1000_u32);
 
@@ -0,0 +1,262 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::empty_loop)]
+
+use hardware::flash_store::FlashStore;
+use hardware::nrf24::network::{
+    node_address, unique_id, Gateway, GatewayConfig, GatewayEvent, Identity, Node, NodeEvent,
+};
+use hardware::nrf24::{Config, DataRate, Nrf24L01};
+use hardware::time::{self, Duration};
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m_rt::{entry, exception};
+use embedded_hal::blocking::spi::Transfer;
+use embedded_hal::digital::v2::OutputPin;
+use stm32f1xx_hal::pac;
+use stm32f1xx_hal::prelude::_fugit_RateExtU32;
+use stm32f1xx_hal::prelude::_stm32_hal_afio_AfioExt;
+use stm32f1xx_hal::prelude::_stm32_hal_flash_FlashExt;
+use stm32f1xx_hal::prelude::_stm32_hal_gpio_GpioExt;
+use stm32f1xx_hal::rcc::RccExt;
+use stm32f1xx_hal::spi::{self, Spi};
+
+/// 角色
+#[allow(dead_code)]
+#[derive(PartialEq, Eq)]
+enum Role {
+    /// 网关
+    Gateway,
+    /// 传感器节点
+    Node,
+}
+
+/// 当前角色，烧录节点时修改
+const ROLE: Role = Role::Gateway;
+/// 网关最多管理的节点数
+const MAX_NODES: usize = 16;
+/// 网关上电后处于配对模式的时间
+const PAIRING_WINDOW: Duration = Duration::secs(60);
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+    let mut afio = dp.AFIO.constrain();
+
+    let gpioa = dp.GPIOA.split();
+    let mut gpiob = dp.GPIOB.split();
+
+    // 冻结系统中所有时钟的配置，并将冻结的频率存储在时钟中
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    // 启动 SysTick，作为轮询调度的时间基准
+    let mut mono = time::Mono::new(cp.SYST, &clocks);
+
+    // 禁用 jtag 端口进行复用
+    let (_pa15, pb3, pb4) = afio.mapr.disable_jtag(gpioa.pa15, gpiob.pb3, gpiob.pb4);
+
+    // SPI1 重映射到 PB3、PB4、PB5
+    let sck = pb3.into_alternate_push_pull(&mut gpiob.crl);
+    let miso = pb4.into_pull_up_input(&mut gpiob.crl);
+    let mosi = gpiob.pb5.into_alternate_push_pull(&mut gpiob.crl);
+    let mode = spi::Mode {
+        polarity: spi::Polarity::IdleLow,
+        phase: spi::Phase::CaptureOnFirstTransition,
+    };
+    let spi = Spi::spi1(
+        dp.SPI1,
+        (sck, miso, mosi),
+        &mut afio.mapr,
+        mode,
+        1.MHz(),
+        clocks,
+    );
+
+    let ce = gpiob.pb6.into_push_pull_output(&mut gpiob.crl);
+    let csn = gpiob.pb7.into_push_pull_output(&mut gpiob.crl);
+
+    // 配对信息保存在内部 FLASH
+    let flash_store = FlashStore::new();
+    flash_store.init_store();
+
+    // 频道和地址由网络设置
+    let config = Config::default().data_rate(DataRate::R250Kbps);
+    let nrf24 = match Nrf24L01::new(spi, ce, csn, config, &mut mono) {
+        Ok(nrf24) => nrf24,
+        Err(e) => {
+            println!("init nrf24l01 error: {}", e);
+            loop {}
+        }
+    };
+
+    let uid = unique_id();
+    println!("uid: {:02X}", uid);
+
+    match ROLE {
+        Role::Gateway => run_gateway(nrf24, config, &uid, &flash_store),
+        Role::Node => run_node(nrf24, config, &uid, &flash_store),
+    }
+}
+
+/// 网关: 轮询节点，打印上行数据及节点状态
+fn run_gateway<SPI, CE, CSN, E>(
+    nrf24: Nrf24L01<SPI, CE, CSN>,
+    config: Config,
+    uid: &[u8; 12],
+    flash_store: &FlashStore,
+) -> !
+where
+    SPI: Transfer<u8, Error = E>,
+    CE: OutputPin,
+    CSN: OutputPin,
+{
+    let mut gateway: Gateway<_, _, _, MAX_NODES> =
+        match Gateway::new(nrf24, config, uid, GatewayConfig::default()) {
+            Ok(gateway) => gateway,
+            Err(e) => {
+                println!("init gateway error: {}", e);
+                loop {}
+            }
+        };
+    gateway.load_nodes(flash_store);
+    gateway.set_pairing(true);
+    println!(
+        "gateway prefix: {:02X}, {} nodes loaded, pairing ...",
+        gateway.prefix(),
+        gateway.nodes().count()
+    );
+
+    let pairing_end = time::now() + PAIRING_WINDOW;
+    let mut next_report = time::now();
+    let mut counter: u32 = 0;
+    loop {
+        let now = time::now();
+        if let Err(e) = gateway.poll(now) {
+            println!("poll error: {}", e);
+        }
+
+        if gateway.is_pairing() && now >= pairing_end {
+            gateway.set_pairing(false);
+            println!("pairing closed");
+        }
+
+        while let Some(event) = gateway.next_event() {
+            println!("{}", event);
+            if let GatewayEvent::Paired { .. } = event {
+                gateway.save_nodes(flash_store);
+            }
+        }
+
+        // 每5秒打印一次节点状态，并向在线节点发送下行数据
+        if now >= next_report {
+            next_report = now + Duration::secs(5);
+            counter += 1;
+            for node in gateway.nodes() {
+                println!("{}", node);
+            }
+
+            let mut online = [0u8; MAX_NODES];
+            let mut count = 0;
+            for node in gateway.nodes().filter(|node| node.online) {
+                online[count] = node.node_id;
+                count += 1;
+            }
+            for node_id in &online[..count] {
+                if let Err(e) = gateway.send_to(*node_id, &counter.to_le_bytes()) {
+                    println!("send to node {} error: {}", node_id, e);
+                }
+            }
+        }
+    }
+}
+
+/// 节点: 未配对时先配对，之后每秒上报一次计数
+fn run_node<SPI, CE, CSN, E>(
+    mut nrf24: Nrf24L01<SPI, CE, CSN>,
+    config: Config,
+    uid: &[u8; 12],
+    flash_store: &FlashStore,
+) -> !
+where
+    SPI: Transfer<u8, Error = E>,
+    CE: OutputPin,
+    CSN: OutputPin,
+{
+    let address = node_address(uid);
+    println!("node address: {:02X}", address);
+
+    let identity = match Identity::load(flash_store) {
+        Some(identity) => identity,
+        None => loop {
+            println!("pairing ...");
+            match Node::pair(
+                &mut nrf24,
+                config,
+                &address,
+                0..=125,
+                Duration::millis(5),
+                time::now,
+            ) {
+                Ok(identity) => {
+                    identity.save(flash_store);
+                    break identity;
+                }
+                Err(e) => println!("pairing error: {}", e),
+            }
+        },
+    };
+    println!("{}", identity);
+
+    let mut node = match Node::new(nrf24, config, address, identity) {
+        Ok(node) => node,
+        Err(e) => {
+            println!("init node error: {}", e);
+            loop {}
+        }
+    };
+
+    let mut counter: u32 = 0;
+    let mut next_send = time::now();
+    let mut connected = false;
+    loop {
+        let now = time::now();
+        if let Err(e) = node.poll(now) {
+            println!("poll error: {}", e);
+        }
+
+        while let Some(event) = node.next_event() {
+            match event {
+                NodeEvent::Downlink(packet) => println!("downlink: {=[u8]}", packet.as_ref()),
+                NodeEvent::Delivered => println!("delivered {}", counter),
+            }
+        }
+
+        // 网关轮询间隔远小于1秒，超过1秒没有被轮询视为断开
+        let state = node.is_connected(now, Duration::secs(1));
+        if state != connected {
+            connected = state;
+            println!("connected: {}", connected);
+        }
+
+        if now >= next_send && !node.is_busy() {
+            next_send = now + Duration::secs(1);
+            counter += 1;
+            if let Err(e) = node.send(&counter.to_le_bytes()) {
+                println!("send error: {}", e);
+            }
+        }
+    }
+}
+
+#[exception]
+fn SysTick() {
+    time::tick();
+}
@@ -0,0 +1,210 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::empty_loop)]
+
+use hardware::nrf24::{
+    CodecError, Config, DataRate, Message, Nrf24L01, Reader, Transport, TransportConfig, TxState,
+    Writer,
+};
+use hardware::time::{self, Duration};
+
+use core::mem::MaybeUninit;
+use core::ptr::addr_of_mut;
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m_rt::{entry, exception};
+use stm32f1xx_hal::pac;
+use stm32f1xx_hal::prelude::_fugit_RateExtU32;
+use stm32f1xx_hal::prelude::_stm32_hal_afio_AfioExt;
+use stm32f1xx_hal::prelude::_stm32_hal_flash_FlashExt;
+use stm32f1xx_hal::prelude::_stm32_hal_gpio_GpioExt;
+use stm32f1xx_hal::rcc::RccExt;
+use stm32f1xx_hal::spi::{self, Spi};
+
+/// 节点
+#[allow(dead_code)]
+#[derive(PartialEq, Eq)]
+enum Node {
+    /// 发送端
+    A,
+    /// 接收端
+    B,
+}
+
+/// 当前节点，烧录另一块开发板时修改
+const NODE: Node = Node::A;
+/// 节点 A 的接收地址
+const NODE_A_ADDR: &[u8] = b"nodeA";
+/// 节点 B 的接收地址
+const NODE_B_ADDR: &[u8] = b"nodeB";
+/// 单条消息的最大长度
+const MESSAGE_LEN: usize = 256;
+
+/// 传输会话号，放在启动时不被初始化的内存中
+/// 上电时为 RAM 中的随机值，之后每次复位加1，保证发送方复位前后的会话号不同
+#[link_section = ".uninit.SESSION"]
+static mut SESSION: MaybeUninit<u8> = MaybeUninit::uninit();
+
+/// 取出本次启动的会话号
+fn next_session() -> u8 {
+    unsafe {
+        let session = addr_of_mut!(SESSION).cast::<u8>();
+        let value = session.read_volatile().wrapping_add(1);
+        session.write_volatile(value);
+        value
+    }
+}
+
+/// 传感器消息，超过32字节，需要分片发送
+#[derive(Debug, defmt::Format)]
+struct Reading {
+    counter: u32,
+    uptime_ms: u32,
+    samples: [i16; 16],
+}
+
+impl Message for Reading {
+    const ID: u8 = 1;
+
+    fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+        w.u32(self.counter)?;
+        w.u32(self.uptime_ms)?;
+        for sample in self.samples {
+            w.i16(sample)?;
+        }
+        Ok(())
+    }
+
+    fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+        let counter = r.u32()?;
+        let uptime_ms = r.u32()?;
+        let mut samples = [0; 16];
+        for sample in samples.iter_mut() {
+            *sample = r.i16()?;
+        }
+        Ok(Reading {
+            counter,
+            uptime_ms,
+            samples,
+        })
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+    let mut afio = dp.AFIO.constrain();
+
+    let gpioa = dp.GPIOA.split();
+    let mut gpiob = dp.GPIOB.split();
+
+    // 冻结系统中所有时钟的配置，并将冻结的频率存储在时钟中
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    // 启动 SysTick，作为传输层的时间基准
+    let mut mono = time::Mono::new(cp.SYST, &clocks);
+
+    // 禁用 jtag 端口进行复用
+    let (_pa15, pb3, pb4) = afio.mapr.disable_jtag(gpioa.pa15, gpiob.pb3, gpiob.pb4);
+
+    // SPI1 重映射到 PB3、PB4、PB5
+    let sck = pb3.into_alternate_push_pull(&mut gpiob.crl);
+    let miso = pb4.into_pull_up_input(&mut gpiob.crl);
+    let mosi = gpiob.pb5.into_alternate_push_pull(&mut gpiob.crl);
+    let mode = spi::Mode {
+        polarity: spi::Polarity::IdleLow,
+        phase: spi::Phase::CaptureOnFirstTransition,
+    };
+    let spi = Spi::spi1(
+        dp.SPI1,
+        (sck, miso, mosi),
+        &mut afio.mapr,
+        mode,
+        1.MHz(),
+        clocks,
+    );
+
+    let ce = gpiob.pb6.into_push_pull_output(&mut gpiob.crl);
+    let csn = gpiob.pb7.into_push_pull_output(&mut gpiob.crl);
+
+    // 向对方的地址发送，用通道1接收发给自己的数据
+    let (own, peer) = match NODE {
+        Node::A => (NODE_A_ADDR, NODE_B_ADDR),
+        Node::B => (NODE_B_ADDR, NODE_A_ADDR),
+    };
+    let config = Config::default()
+        .data_rate(DataRate::R250Kbps)
+        .tx_address(peer)
+        .rx_address(1, own)
+        .dynamic_payload(true);
+
+    let nrf24 = match Nrf24L01::new(spi, ce, csn, config, &mut mono) {
+        Ok(nrf24) => nrf24,
+        Err(e) => {
+            println!("init nrf24l01 error: {}", e);
+            loop {}
+        }
+    };
+    let mut transport: Transport<_, MESSAGE_LEN> =
+        Transport::new(nrf24, TransportConfig::default(), next_session());
+    println!("init nrf24l01 transport ...");
+
+    let mut counter: u32 = 0;
+    let mut next_send = time::now();
+    loop {
+        let now = time::now();
+        if let Err(e) = transport.poll(now) {
+            println!("poll error: {}", e);
+        }
+
+        if NODE == Node::A && now >= next_send && transport.tx_state() != TxState::Sending {
+            match transport.tx_state() {
+                TxState::Delivered => println!("delivered {}", counter),
+                TxState::Failed => println!("failed {}", counter),
+                _ => {}
+            }
+
+            counter += 1;
+            let reading = Reading {
+                counter,
+                uptime_ms: time::millis() as u32,
+                samples: core::array::from_fn(|i| (counter as i16).wrapping_mul(i as i16)),
+            };
+            if let Err(e) = transport.send_message(&reading, now) {
+                println!("send error: {}", e);
+            }
+            next_send = now + Duration::millis(1000);
+
+            if counter.is_multiple_of(10) {
+                println!("link stats: {}", transport.stats());
+            }
+        }
+
+        if let Some(result) = transport.receive_message::<Reading>() {
+            match result {
+                Ok(reading) => {
+                    println!("received: {}", reading);
+                    if reading.counter.is_multiple_of(10) {
+                        println!("link stats: {}", transport.stats());
+                    }
+                }
+                Err(e) => println!("decode error: {}", e),
+            }
+        } else if let Some(message) = transport.receive() {
+            println!("unknown message: {=[u8]}", message);
+        }
+    }
+}
+
+#[exception]
+fn SysTick() {
+    time::tick();
+}
@@ -0,0 +1,103 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::empty_loop)]
+
+use core::cell::RefCell;
+
+use hardware::bus::SpiDevice;
+use hardware::oled;
+use hardware::spi::soft::{BitOrder, SoftSpi};
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m::asm::wfi;
+use cortex_m_rt::entry;
+use embedded_hal_1::spi::{Operation, MODE_0, MODE_3};
+use stm32f1xx_hal::gpio;
+use stm32f1xx_hal::gpio::OutputSpeed;
+use stm32f1xx_hal::pac;
+use stm32f1xx_hal::prelude::{
+    _fugit_RateExtU32, _stm32_hal_flash_FlashExt, _stm32_hal_gpio_GpioExt,
+};
+use stm32f1xx_hal::rcc::RccExt;
+use stm32f1xx_hal::timer::SysTimerExt;
+
+/// 读取 JEDEC ID 指令
+const W25Q64_JEDEC_ID: u8 = 0x9F;
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+    let syst = cp.SYST;
+
+    let mut gpioa = dp.GPIOA.split();
+    let mut gpiob = dp.GPIOB.split();
+
+    // 冻结系统中所有时钟的配置，并将冻结的频率存储在时钟中
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    // 具有自定义精度的阻塞延迟函数
+    let delay = syst.delay(&clocks);
+
+    // 初始化 OLED 显示屏
+    println!("load oled...");
+    let mut oled = oled::simple::init_oled(gpiob.pb8, gpiob.pb9, &mut gpiob.crh);
+
+    // 任意引脚都可以作为 SPI 引脚
+    let mut w_ss = gpioa.pa4.into_push_pull_output(&mut gpioa.crl);
+    let mut w_sck = gpioa.pa5.into_push_pull_output(&mut gpioa.crl);
+    let mut w_mosi = gpioa.pa7.into_push_pull_output(&mut gpioa.crl);
+    let w_miso = gpioa.pa6.into_pull_up_input(&mut gpioa.crl);
+    w_ss.set_speed(&mut gpioa.crl, gpio::IOPinSpeed::Mhz50);
+    w_sck.set_speed(&mut gpioa.crl, gpio::IOPinSpeed::Mhz50);
+    w_mosi.set_speed(&mut gpioa.crl, gpio::IOPinSpeed::Mhz50);
+
+    // 100kHz 模式0，高位先行
+    let spi = SoftSpi::new_with_delay(w_sck, w_mosi, w_miso, MODE_0, delay, 100.kHz());
+    let bus = RefCell::new(spi);
+    // 片选由设备句柄控制
+    let mut w25 = SpiDevice::new(&bus, w_ss);
+
+    oled.show_string(1, 1, "Mode0:");
+    oled.show_string(2, 1, "Mode3:");
+    oled.show_string(3, 1, "LSB:");
+
+    // W25Q64 支持模式0和模式3
+    let id = read_jedec_id(&mut w25);
+    println!("mode 0 jedec id: {:06X}", id);
+    oled.show_hex_num(1, 8, id, 6);
+
+    bus.borrow_mut().set_mode(MODE_3);
+    let id = read_jedec_id(&mut w25);
+    println!("mode 3 jedec id: {:06X}", id);
+    oled.show_hex_num(2, 8, id, 6);
+
+    // 低位先行时芯片无法识别指令，读到的数据无效
+    bus.borrow_mut().set_bit_order(BitOrder::LsbFirst);
+    let id = read_jedec_id(&mut w25);
+    println!("lsb first jedec id: {:06X}", id);
+    oled.show_hex_num(3, 8, id, 6);
+
+    loop {
+        wfi();
+    }
+}
+
+/// 读取 JEDEC ID，指令和数据在同一个片选周期内完成
+fn read_jedec_id<D: embedded_hal_1::spi::SpiDevice>(device: &mut D) -> u32 {
+    let mut id = [0; 3];
+    device
+        .transaction(&mut [
+            Operation::Write(&[W25Q64_JEDEC_ID]),
+            Operation::Read(&mut id),
+        ])
+        .unwrap();
+    ((id[0] as u32) << 16) | ((id[1] as u32) << 8) | id[2] as u32
+}
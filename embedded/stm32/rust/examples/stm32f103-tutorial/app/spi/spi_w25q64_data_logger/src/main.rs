@@ -0,0 +1,172 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::empty_loop)]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use hardware::mpu6050::{mpu6050_hal, Config};
+use hardware::time::{self, Duration, TimerId};
+use hardware::w25q64::logger::{self, DataLogger};
+use hardware::w25q64::w25q64_hal;
+
+use defmt::println;
+use defmt_rtt as _;
+use panic_probe as _;
+
+use cortex_m::prelude::_embedded_hal_adc_OneShot;
+use cortex_m_rt::{entry, exception};
+use stm32f1xx_hal::adc::Adc;
+use stm32f1xx_hal::gpio::{IOPinSpeed, OutputSpeed};
+use stm32f1xx_hal::i2c;
+use stm32f1xx_hal::pac;
+use stm32f1xx_hal::prelude::{
+    _fugit_RateExtU32, _stm32_hal_afio_AfioExt, _stm32_hal_flash_FlashExt, _stm32_hal_gpio_GpioExt,
+};
+use stm32f1xx_hal::rcc::RccExt;
+use stm32f1xx_hal::serial::{self, Serial};
+use stm32f1xx_hal::time::U32Ext;
+
+/// 记录区域: W25Q64 前 1MB
+const LOG_START_ADDRESS: u32 = 0x000000;
+const LOG_END_ADDRESS: u32 = 0x100000;
+/// 采样间隔(ms)
+const SAMPLE_INTERVAL_MS: u64 = 5;
+/// 每条记录的数据长度
+const PAYLOAD_LEN: usize = 14;
+
+/// 到期但还没有处理的采样次数，定时器回调增加，主循环清零
+static PENDING: AtomicU32 = AtomicU32::new(0);
+
+#[entry]
+fn main() -> ! {
+    // 获取对外设的访问对象
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let rcc = dp.RCC.constrain();
+    let mut afio = dp.AFIO.constrain();
+    let syst = cp.SYST;
+
+    let mut gpioa = dp.GPIOA.split();
+    let mut gpiob = dp.GPIOB.split();
+
+    // 冻结系统中所有时钟的配置，并将冻结的频率存储在时钟中
+    let clocks = rcc
+        .cfgr
+        .use_hse(8.MHz())
+        .sysclk(72.MHz())
+        .pclk1(36.MHz())
+        .freeze(&mut flash.acr);
+
+    // USART1
+    let tx = gpioa.pa9.into_alternate_push_pull(&mut gpioa.crh);
+    let rx = gpioa.pa10;
+    let serial = Serial::new(
+        dp.USART1,
+        (tx, rx),
+        &mut afio.mapr,
+        serial::Config::default().baudrate(115200.bps()),
+        &clocks,
+    );
+    let (mut tx, mut rx) = serial.split();
+
+    // ADC
+    let mut ch0 = gpioa.pa0.into_analog(&mut gpioa.crl);
+    let mut adc = Adc::adc1(dp.ADC1, clocks);
+
+    // MPU6050 初始化，I2C 使用400kHz快速模式，缩短每次采样占用主循环的时间
+    let mpu_scl = gpiob.pb10.into_alternate_open_drain(&mut gpiob.crh);
+    let mpu_sda = gpiob.pb11.into_alternate_open_drain(&mut gpiob.crh);
+    let mode = i2c::Mode::fast(400.kHz(), i2c::DutyCycle::Ratio2to1);
+    let mut mpu = mpu6050_hal::Mpu6050::new_with_mode(
+        (mpu_scl, mpu_sda),
+        dp.I2C2,
+        mode,
+        clocks,
+        &Config::default(),
+    )
+    .unwrap();
+
+    // W25Q64 初始化
+    let mut cs = gpioa.pa4.into_push_pull_output(&mut gpioa.crl);
+    cs.set_speed(&mut gpioa.crl, IOPinSpeed::Mhz50);
+    let mut sck = gpioa.pa5.into_alternate_push_pull(&mut gpioa.crl);
+    sck.set_speed(&mut gpioa.crl, IOPinSpeed::Mhz50);
+    let miso = gpioa.pa6.into_pull_up_input(&mut gpioa.crl);
+    let mut mosi = gpioa.pa7.into_alternate_push_pull(&mut gpioa.crl);
+    mosi.set_speed(&mut gpioa.crl, IOPinSpeed::Mhz50);
+    let pins = (sck, miso, mosi);
+    let mut w25q = w25q64_hal::new(dp.SPI1, pins, &mut cs, &mut afio.mapr, clocks);
+
+    // 挂载记录器
+    println!("mount logger...");
+    let mut logger = DataLogger::mount(&mut w25q, LOG_START_ADDRESS, LOG_END_ADDRESS).unwrap();
+
+    // 启动 SysTick，定时器回调只设置采样标志，I2C 和 ADC 读取在主循环中进行
+    let _mono = time::Mono::new(syst, &clocks);
+    time::start_periodic(Duration::millis(SAMPLE_INTERVAL_MS), sample).unwrap();
+
+    // 擦除扇区等耗时操作期间错过的采样数
+    let mut dropped = 0;
+    let mut buffer = [0; 64];
+    loop {
+        // 串口命令
+        match rx.read() {
+            Ok(b'd') => {
+                println!("dump csv...");
+                logger
+                    .dump_csv(&mut tx, &mut buffer, logger::write_i16_columns)
+                    .unwrap();
+            }
+            Ok(b'c') => {
+                println!("clear...");
+                logger.clear().unwrap();
+            }
+            Ok(b's') => {
+                println!("dropped samples: {}", dropped);
+            }
+            _ => {}
+        }
+
+        // 采样并写入 FLASH
+        let pending = PENDING.swap(0, Ordering::Relaxed);
+        if pending == 0 {
+            // 空闲时提前擦除下一个扇区
+            logger.prepare().unwrap();
+            continue;
+        }
+        dropped += pending - 1;
+
+        let timestamp = time::millis() as u32;
+        let data = mpu.get_data().unwrap();
+        let adc: u16 = adc.read(&mut ch0).unwrap();
+
+        let mut payload = [0; PAYLOAD_LEN];
+        for (i, value) in [
+            data.acc_x,
+            data.acc_y,
+            data.acc_z,
+            data.gyro_x,
+            data.gyro_y,
+            data.gyro_z,
+            adc as i16,
+        ]
+        .iter()
+        .enumerate()
+        {
+            payload[i * 2..i * 2 + 2].copy_from_slice(&value.to_le_bytes());
+        }
+        logger.append(timestamp, &payload).unwrap();
+    }
+}
+
+/// 采样定时器回调，在 SysTick 中断中执行，只记录到期的采样次数
+fn sample(_id: TimerId) {
+    PENDING.fetch_add(1, Ordering::Relaxed);
+}
+
+#[exception]
+fn SysTick() {
+    time::tick();
+}
@@ -0,0 +1,34 @@
+//! 全局执行器
+//! 调度逻辑由 `portable::asynch::executor` 实现，这里提供使用 `WFI` 休眠的全局执行器
+
+pub use portable::asynch::executor::{Executor, ReadyQueue, Task, MAX_TASKS};
+
+use core::future::Future;
+
+/// 没有就绪任务时休眠
+/// 在关中断的情况下检查并执行 WFI，避免检查之后、休眠之前发生的唤醒被错过
+/// 关中断时 WFI 仍然会被挂起的中断唤醒，开中断后再执行中断处理
+pub fn wfi_idle(queue: &ReadyQueue) {
+    cortex_m::interrupt::free(|_| {
+        if !queue.any() {
+            cortex_m::asm::wfi();
+        }
+    });
+}
+
+/// 全局就绪队列
+static QUEUE: ReadyQueue = ReadyQueue::new();
+/// 全局执行器
+static EXECUTOR: Executor = Executor::new(&QUEUE, wfi_idle);
+
+/// 使用全局执行器执行所有任务，直到全部完成
+/// 不能在任务中嵌套调用
+pub fn run<const N: usize>(tasks: [Task<'_>; N]) {
+    EXECUTOR.run(tasks)
+}
+
+/// 使用全局执行器执行单个 `Future` 直到完成
+/// 不能在任务中嵌套调用
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    EXECUTOR.block_on(future)
+}
@@ -0,0 +1,183 @@
+//! 异步外部中断输入
+//! 等待引脚电平变化时配置触发沿并打开 EXTI 中断，中断中关闭中断并唤醒任务
+//! 需要在对应的 EXTI 中断(EXTI0~EXTI4、EXTI9_5、EXTI15_10)中调用 `on_interrupt`，并在 NVIC 中打开中断
+//!
+//! ```rust
+//! let key = gpiob.pb1.into_pull_up_input(&mut gpiob.crl);
+//! let mut key = ExtiInput::new(key, &mut afio);
+//! unsafe { NVIC::unmask(interrupt::EXTI1) };
+//!
+//! loop {
+//!     key.wait_for_falling_edge().await;
+//!     led.toggle();
+//! }
+//!
+//! #[interrupt]
+//! fn EXTI1() {
+//!     asynch::exti::on_interrupt();
+//! }
+//! ```
+
+use super::waker::WakerCell;
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use stm32f1xx_hal::afio;
+use stm32f1xx_hal::gpio::{ExtiPin, Input, Pin};
+use stm32f1xx_hal::pac::{self, EXTI};
+
+/// GPIO 使用的 EXTI 线个数
+const LINES: usize = 16;
+
+/// 每条 EXTI 线上等待的任务
+static WAKERS: [WakerCell; LINES] = [const { WakerCell::new() }; LINES];
+
+/// 触发沿
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Edge {
+    Rising,
+    Falling,
+    Any,
+}
+
+fn regs() -> &'static pac::exti::RegisterBlock {
+    unsafe { &*EXTI::ptr() }
+}
+
+/// EXTI 中断处理，在 EXTI 中断中调用
+/// 多条 EXTI 线共用一个中断时，依次处理所有挂起的线
+pub fn on_interrupt() {
+    let exti = regs();
+    let pending = exti.pr.read().bits() & exti.imr.read().bits() & ((1 << LINES) - 1);
+    if pending == 0 {
+        return;
+    }
+
+    // 关闭中断表示已触发
+    exti.imr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !pending) });
+    exti.pr.write(|w| unsafe { w.bits(pending) });
+    for (line, waker) in WAKERS.iter().enumerate() {
+        if pending & (1 << line) != 0 {
+            waker.wake();
+        }
+    }
+}
+
+/// 支持异步等待的输入引脚
+pub struct ExtiInput<const P: char, const N: u8, MODE> {
+    pin: Pin<P, N, Input<MODE>>,
+}
+
+impl<const P: char, const N: u8, MODE> ExtiInput<P, N, MODE>
+where
+    Pin<P, N, Input<MODE>>: ExtiPin,
+{
+    /// 将引脚配置为 EXTI 中断源
+    /// 同一条 EXTI 线只能连接一个端口的引脚，如 PA1 和 PB1 不能同时使用
+    pub fn new(mut pin: Pin<P, N, Input<MODE>>, afio: &mut afio::Parts) -> Self {
+        pin.make_interrupt_source(afio);
+        ExtiInput { pin }
+    }
+
+    pub fn is_high(&self) -> bool {
+        self.pin.is_high()
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.pin.is_low()
+    }
+
+    /// 等待上升沿
+    pub async fn wait_for_rising_edge(&mut self) {
+        self.wait_for_edge(Edge::Rising).await
+    }
+
+    /// 等待下降沿
+    pub async fn wait_for_falling_edge(&mut self) {
+        self.wait_for_edge(Edge::Falling).await
+    }
+
+    /// 等待任意边沿
+    pub async fn wait_for_any_edge(&mut self) {
+        self.wait_for_edge(Edge::Any).await
+    }
+
+    /// 等待高电平，已经是高电平时立即返回
+    pub async fn wait_for_high(&mut self) {
+        if self.is_low() {
+            self.wait_for_rising_edge().await
+        }
+    }
+
+    /// 等待低电平，已经是低电平时立即返回
+    pub async fn wait_for_low(&mut self) {
+        if self.is_high() {
+            self.wait_for_falling_edge().await
+        }
+    }
+
+    /// 等待指定的边沿
+    pub async fn wait_for_edge(&mut self, edge: Edge) {
+        let mask = 1u32 << N;
+        let exti = regs();
+
+        // 配置触发沿，清除之前的挂起标志后打开中断
+        critical_section::with(|_| {
+            let rising = matches!(edge, Edge::Rising | Edge::Any);
+            let falling = matches!(edge, Edge::Falling | Edge::Any);
+            exti.rtsr.modify(|r, w| unsafe {
+                w.bits(if rising {
+                    r.bits() | mask
+                } else {
+                    r.bits() & !mask
+                })
+            });
+            exti.ftsr.modify(|r, w| unsafe {
+                w.bits(if falling {
+                    r.bits() | mask
+                } else {
+                    r.bits() & !mask
+                })
+            });
+            exti.pr.write(|w| unsafe { w.bits(mask) });
+            exti.imr.modify(|r, w| unsafe { w.bits(r.bits() | mask) });
+        });
+
+        let mut guard = Guard { mask };
+        poll_fn(|cx| {
+            WAKERS[N as usize].register(cx.waker());
+            if exti.imr.read().bits() & mask == 0 {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+        guard.mask = 0;
+    }
+
+    pub fn free(self) -> Pin<P, N, Input<MODE>> {
+        self.pin
+    }
+}
+
+/// 等待被取消(如 `select` 中其他 `Future` 先完成)时关闭中断
+struct Guard {
+    mask: u32,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if self.mask == 0 {
+            return;
+        }
+        let mask = self.mask;
+        critical_section::with(|_| {
+            regs()
+                .imr
+                .modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+        });
+    }
+}
@@ -0,0 +1,404 @@
+//! 异步 I2C 主机
+//! 使用已配置好的硬件 I2C，按参考手册中的主机收发流程操作寄存器
+//! 等待事件时打开 I2C 事件、缓冲和错误中断，中断中关闭中断并唤醒任务，等待期间 SCL 被拉低，不会丢失数据
+//! 需要在 I2C 事件中断和错误中断中都调用 `on_interrupt`，并在 NVIC 中打开这两个中断
+//! 传输的 `Future` 在完成前被丢弃(如 `select` 中另一个分支先完成)时，发送停止条件并关闭中断，释放总线
+//!
+//! ```rust
+//! let i2c = BlockingI2c::i2c2(dp.I2C2, (scl, sda), i2c::Mode::standard(100.kHz()), clocks, 1000, 10, 1000, 1000);
+//! let mut i2c: AsyncI2c<pac::I2C2, _> = AsyncI2c::new(i2c);
+//! unsafe {
+//!     NVIC::unmask(interrupt::I2C2_EV);
+//!     NVIC::unmask(interrupt::I2C2_ER);
+//! }
+//!
+//! let mut buf = [0; 14];
+//! i2c.write_read(MPU6050_ADDRESS, &[MPU6050_ACCEL_XOUT_H], &mut buf).await?;
+//!
+//! #[interrupt]
+//! fn I2C2_EV() {
+//!     asynch::i2c::on_interrupt::<pac::I2C2>();
+//! }
+//!
+//! #[interrupt]
+//! fn I2C2_ER() {
+//!     asynch::i2c::on_interrupt::<pac::I2C2>();
+//! }
+//! ```
+
+use super::future::yield_now;
+use super::waker::WakerCell;
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embedded_hal_1::i2c::{self as i2c_1, ErrorKind, NoAcknowledgeSource};
+use stm32f1xx_hal::pac::{self, i2c1::sr1, i2c1::RegisterBlock};
+
+/// I2C 错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Error {
+    /// 未收到应答
+    NoAcknowledge,
+    /// 仲裁丢失
+    ArbitrationLoss,
+    /// 总线错误
+    Bus,
+    /// 接收数据溢出
+    Overrun,
+    /// 总线一直处于忙状态
+    Timeout,
+}
+
+impl i2c_1::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match *self {
+            Error::NoAcknowledge => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            Error::ArbitrationLoss => ErrorKind::ArbitrationLoss,
+            Error::Bus => ErrorKind::Bus,
+            Error::Overrun => ErrorKind::Overrun,
+            Error::Timeout => ErrorKind::Other,
+        }
+    }
+}
+
+/// 支持异步传输的 I2C
+pub trait Instance {
+    /// I2C 寄存器
+    fn regs() -> &'static RegisterBlock;
+    /// 等待该 I2C 的任务
+    fn waker() -> &'static WakerCell;
+}
+
+macro_rules! instance {
+    ($($I2C:ident: $WAKER:ident,)+) => {
+        $(
+            static $WAKER: WakerCell = WakerCell::new();
+
+            impl Instance for pac::$I2C {
+                fn regs() -> &'static RegisterBlock {
+                    unsafe { &*pac::$I2C::ptr() }
+                }
+
+                fn waker() -> &'static WakerCell {
+                    &$WAKER
+                }
+            }
+        )+
+    };
+}
+
+instance! {
+    I2C1: I2C1_WAKER,
+    I2C2: I2C2_WAKER,
+}
+
+/// I2C 中断处理，在 I2C 事件中断和错误中断中调用
+/// 事件标志只有在读写寄存器后才会清除，这里关闭中断，由任务继续处理
+pub fn on_interrupt<I2C: Instance>() {
+    I2C::regs().cr2.modify(|_, w| {
+        w.itevten()
+            .clear_bit()
+            .itbufen()
+            .clear_bit()
+            .iterren()
+            .clear_bit()
+    });
+    I2C::waker().wake();
+}
+
+/// 等待总线空闲的最大调度轮数
+const BUSY_TIMEOUT: u32 = 10_000;
+
+/// 关闭 I2C 中断
+fn disable_interrupts(regs: &RegisterBlock) {
+    critical_section::with(|_| {
+        regs.cr2.modify(|_, w| {
+            w.itevten()
+                .clear_bit()
+                .itbufen()
+                .clear_bit()
+                .iterren()
+                .clear_bit()
+        })
+    });
+}
+
+/// 传输守卫
+/// 传输没有正常结束就被丢弃时，关闭中断并发送停止条件，避免总线被一直占用
+struct TransferGuard<I2C: Instance> {
+    armed: bool,
+    _i2c: PhantomData<I2C>,
+}
+
+impl<I2C: Instance> TransferGuard<I2C> {
+    fn new() -> Self {
+        TransferGuard {
+            armed: true,
+            _i2c: PhantomData,
+        }
+    }
+
+    /// 传输已正常结束
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl<I2C: Instance> Drop for TransferGuard<I2C> {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let regs = I2C::regs();
+        disable_interrupts(regs);
+        // 只有作为主机时才需要发送停止条件
+        if regs.sr2.read().msl().bit_is_set() {
+            regs.cr1.modify(|_, w| w.stop().set_bit());
+            while regs.cr1.read().stop().bit_is_set() {}
+        }
+        regs.cr1
+            .modify(|_, w| w.ack().clear_bit().pos().clear_bit());
+    }
+}
+
+/// 检查并清除错误标志
+fn check_errors(regs: &RegisterBlock) -> Result<(), Error> {
+    let sr1 = regs.sr1.read();
+    if sr1.af().bit_is_set() {
+        regs.sr1.modify(|_, w| w.af().clear_bit());
+        return Err(Error::NoAcknowledge);
+    }
+    if sr1.arlo().bit_is_set() {
+        regs.sr1.modify(|_, w| w.arlo().clear_bit());
+        return Err(Error::ArbitrationLoss);
+    }
+    if sr1.berr().bit_is_set() {
+        regs.sr1.modify(|_, w| w.berr().clear_bit());
+        return Err(Error::Bus);
+    }
+    if sr1.ovr().bit_is_set() {
+        regs.sr1.modify(|_, w| w.ovr().clear_bit());
+        return Err(Error::Overrun);
+    }
+    Ok(())
+}
+
+/// 异步 I2C 主机，7位地址
+/// BUS 为 HAL 中已配置好的 I2C，传输期间不使用
+pub struct AsyncI2c<I2C, BUS> {
+    bus: BUS,
+    _i2c: PhantomData<I2C>,
+}
+
+impl<I2C: Instance, BUS> AsyncI2c<I2C, BUS> {
+    pub fn new(bus: BUS) -> Self {
+        AsyncI2c {
+            bus,
+            _i2c: PhantomData,
+        }
+    }
+
+    /// 释放 I2C
+    pub fn free(self) -> BUS {
+        self.bus
+    }
+
+    /// 写数据
+    pub async fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error> {
+        let guard = TransferGuard::<I2C>::new();
+        let result = self.write_bytes(address, bytes).await;
+        self.stop();
+        guard.disarm();
+        result
+    }
+
+    /// 读数据
+    pub async fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        let guard = TransferGuard::<I2C>::new();
+        let result = self.read_bytes(address, buffer).await;
+        if result.is_err() {
+            self.stop();
+        }
+        self.wait_stop();
+        guard.disarm();
+        result
+    }
+
+    /// 先写后读，中间使用重复起始条件
+    pub async fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        let guard = TransferGuard::<I2C>::new();
+        let mut result = self.write_bytes(address, bytes).await;
+        if result.is_ok() {
+            result = self.read_bytes(address, buffer).await;
+        }
+        if result.is_err() {
+            self.stop();
+        }
+        self.wait_stop();
+        guard.disarm();
+        result
+    }
+
+    /// 等待 SR1 中的事件标志，出现错误时返回错误
+    async fn wait_for(&mut self, flag: impl Fn(&sr1::R) -> bool) -> Result<(), Error> {
+        let regs = I2C::regs();
+        poll_fn(|cx| {
+            check_errors(regs)?;
+            if flag(&regs.sr1.read()) {
+                return Poll::Ready(Ok(()));
+            }
+
+            I2C::waker().register(cx.waker());
+            critical_section::with(|_| {
+                regs.cr2.modify(|_, w| {
+                    w.itevten()
+                        .set_bit()
+                        .itbufen()
+                        .set_bit()
+                        .iterren()
+                        .set_bit()
+                })
+            });
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// 等待总线空闲
+    /// 已经是主机时(重复起始条件)不需要等待；从机在传输中途复位等情况下 SDA 被一直拉低，
+    /// 超时后返回错误，此时可以用 `SoftI2c::recover_bus` 恢复总线
+    async fn wait_bus_idle(&mut self) -> Result<(), Error> {
+        let regs = I2C::regs();
+        let mut rounds = 0;
+        loop {
+            let sr2 = regs.sr2.read();
+            if sr2.msl().bit_is_set() || sr2.busy().bit_is_clear() {
+                return Ok(());
+            }
+            if rounds == BUSY_TIMEOUT {
+                return Err(Error::Timeout);
+            }
+            rounds += 1;
+            // 忙状态的变化不产生中断，让出 CPU 后再检查
+            yield_now().await;
+        }
+    }
+
+    /// 发送起始条件和地址，等待从机应答
+    async fn start(&mut self, address: u8, read: bool) -> Result<(), Error> {
+        let regs = I2C::regs();
+        self.wait_bus_idle().await?;
+        regs.cr1.modify(|_, w| w.start().set_bit());
+        self.wait_for(|sr1| sr1.sb().bit_is_set()).await?;
+        regs.dr
+            .write(|w| unsafe { w.bits(((address << 1) | read as u8) as u32) });
+        self.wait_for(|sr1| sr1.addr().bit_is_set()).await
+    }
+
+    /// 读 SR1 后读 SR2，清除 ADDR 标志
+    fn clear_addr(&mut self) {
+        let regs = I2C::regs();
+        regs.sr1.read();
+        regs.sr2.read();
+    }
+
+    fn read_dr(&mut self) -> u8 {
+        I2C::regs().dr.read().bits() as u8
+    }
+
+    fn stop(&mut self) {
+        I2C::regs().cr1.modify(|_, w| w.stop().set_bit());
+    }
+
+    /// 等待停止条件发送完成
+    fn wait_stop(&mut self) {
+        while I2C::regs().cr1.read().stop().bit_is_set() {}
+    }
+
+    /// 发送地址和数据，不发送停止条件
+    async fn write_bytes(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error> {
+        let regs = I2C::regs();
+        self.start(address, false).await?;
+        self.clear_addr();
+
+        // 没有数据时只发送起始条件和地址，BTF 不会置位
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        for &byte in bytes {
+            self.wait_for(|sr1| sr1.tx_e().bit_is_set()).await?;
+            regs.dr.write(|w| unsafe { w.bits(byte as u32) });
+        }
+        self.wait_for(|sr1| sr1.btf().bit_is_set()).await
+    }
+
+    /// 发送地址并接收数据，接收最后一个字节前发送停止条件
+    async fn read_bytes(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        let regs = I2C::regs();
+        let len = buffer.len();
+        if len == 0 {
+            return Ok(());
+        }
+
+        // 接收2个字节时 NACK 作用于移位寄存器中的下一个字节
+        regs.cr1
+            .modify(|_, w| w.ack().set_bit().pos().bit(len == 2));
+        let result = self.start(address, true).await;
+        if result.is_err() {
+            regs.cr1.modify(|_, w| w.pos().clear_bit());
+            return result;
+        }
+
+        match len {
+            1 => {
+                regs.cr1.modify(|_, w| w.ack().clear_bit());
+                critical_section::with(|_| {
+                    self.clear_addr();
+                    self.stop();
+                });
+                self.wait_for(|sr1| sr1.rx_ne().bit_is_set()).await?;
+                buffer[0] = self.read_dr();
+            }
+            2 => {
+                critical_section::with(|_| {
+                    self.clear_addr();
+                    regs.cr1.modify(|_, w| w.ack().clear_bit());
+                });
+                self.wait_for(|sr1| sr1.btf().bit_is_set()).await?;
+                critical_section::with(|_| {
+                    self.stop();
+                    buffer[0] = self.read_dr();
+                });
+                buffer[1] = self.read_dr();
+                regs.cr1.modify(|_, w| w.pos().clear_bit());
+            }
+            _ => {
+                self.clear_addr();
+                for byte in buffer[..len - 3].iter_mut() {
+                    self.wait_for(|sr1| sr1.rx_ne().bit_is_set()).await?;
+                    *byte = self.read_dr();
+                }
+
+                // 倒数第3个字节在 DR 中，倒数第2个字节在移位寄存器中
+                self.wait_for(|sr1| sr1.btf().bit_is_set()).await?;
+                regs.cr1.modify(|_, w| w.ack().clear_bit());
+                buffer[len - 3] = self.read_dr();
+                self.wait_for(|sr1| sr1.btf().bit_is_set()).await?;
+                critical_section::with(|_| {
+                    self.stop();
+                    buffer[len - 2] = self.read_dr();
+                });
+                buffer[len - 1] = self.read_dr();
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+//! 异步驱动
+//! 单线程协作式执行器，以及串口、EXTI 输入、SPI、I2C 的异步版本，不使用 RTIC 也能同时等待多个外设
+//! 异步延时见 `time::sleep`，需要启动 `time::Mono` 并在 SysTick 中断中调用 `time::tick`
+//!
+//! 外设等待期间打开对应的中断，中断中唤醒任务；没有就绪的任务时执行器执行 `WFI` 休眠
+//!
+//! ```rust
+//! async fn blink(led: &mut Led) {
+//!     loop {
+//!         led.toggle();
+//!         time::sleep(Duration::millis(500)).await;
+//!     }
+//! }
+//!
+//! async fn echo(rx: &mut AsyncRx<USART1>, tx: &mut AsyncTx<USART1>) {
+//!     loop {
+//!         match select(rx.read(), key.wait_for_falling_edge()).await {
+//!             Either::First(Ok(byte)) => tx.write(byte).await,
+//!             Either::First(Err(_)) => {}
+//!             Either::Second(()) => tx.write_str("key\r\n").await,
+//!         }
+//!     }
+//! }
+//!
+//! asynch::run([pin!(blink(&mut led)), pin!(echo(&mut rx, &mut tx))]);
+//! ```
+
+pub mod executor;
+pub mod exti;
+pub mod i2c;
+pub mod serial;
+pub mod spi;
+pub mod waker;
+
+pub use portable::asynch::future;
+
+pub use crate::time::{sleep, sleep_until};
+pub use executor::{block_on, run, Executor, ReadyQueue, Task};
+pub use exti::ExtiInput;
+pub use future::{join, join3, select, select3, yield_now, Either, Either3};
+pub use i2c::AsyncI2c;
+pub use serial::{AsyncRx, AsyncTx};
+pub use spi::AsyncSpi;
+pub use waker::WakerCell;
@@ -0,0 +1,206 @@
+//! 异步串口
+//! 等待接收或发送时打开串口中断，中断中关闭中断并唤醒任务，等待期间 CPU 可以休眠或执行其他任务
+//! 需要在串口中断中调用 `on_interrupt`，并在 NVIC 中打开串口中断
+//!
+//! ```rust
+//! let (tx, rx) = serial.split();
+//! let mut tx = AsyncTx::new(tx);
+//! let mut rx = AsyncRx::new(rx);
+//! unsafe { NVIC::unmask(interrupt::USART1) };
+//!
+//! loop {
+//!     let byte = rx.read().await.unwrap();
+//!     tx.write(byte).await;
+//! }
+//!
+//! #[interrupt]
+//! fn USART1() {
+//!     asynch::serial::on_interrupt::<pac::USART1>();
+//! }
+//! ```
+
+use super::waker::WakerCell;
+
+use core::convert::Infallible;
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embedded_hal::serial;
+use stm32f1xx_hal::pac::{self, usart1::RegisterBlock};
+use stm32f1xx_hal::serial::{Error, Rx, Tx};
+
+/// 串口接收和发送的等待任务
+pub struct SerialWakers {
+    rx: WakerCell,
+    tx: WakerCell,
+}
+
+impl SerialWakers {
+    const fn new() -> Self {
+        SerialWakers {
+            rx: WakerCell::new(),
+            tx: WakerCell::new(),
+        }
+    }
+}
+
+/// 支持异步读写的串口
+pub trait Instance {
+    /// 串口寄存器
+    fn regs() -> &'static RegisterBlock;
+    /// 等待该串口的任务
+    fn wakers() -> &'static SerialWakers;
+}
+
+macro_rules! instance {
+    ($($USART:ident: $WAKERS:ident,)+) => {
+        $(
+            static $WAKERS: SerialWakers = SerialWakers::new();
+
+            impl Instance for pac::$USART {
+                fn regs() -> &'static RegisterBlock {
+                    unsafe { &*pac::$USART::ptr() }
+                }
+
+                fn wakers() -> &'static SerialWakers {
+                    &$WAKERS
+                }
+            }
+        )+
+    };
+}
+
+instance! {
+    USART1: USART1_WAKERS,
+    USART2: USART2_WAKERS,
+    USART3: USART3_WAKERS,
+}
+
+/// 串口中断处理，在串口中断中调用
+/// 关闭已触发的中断并唤醒等待的任务
+pub fn on_interrupt<USART: Instance>() {
+    let regs = USART::regs();
+    let sr = regs.sr.read();
+    let cr1 = regs.cr1.read();
+
+    // 接收中断同时响应溢出错误，由任务读取时返回错误
+    if cr1.rxneie().bit_is_set() && (sr.rxne().bit_is_set() || sr.ore().bit_is_set()) {
+        regs.cr1.modify(|_, w| w.rxneie().clear_bit());
+        USART::wakers().rx.wake();
+    }
+    if cr1.txeie().bit_is_set() && sr.txe().bit_is_set() {
+        regs.cr1.modify(|_, w| w.txeie().clear_bit());
+        USART::wakers().tx.wake();
+    }
+    if cr1.tcie().bit_is_set() && sr.tc().bit_is_set() {
+        regs.cr1.modify(|_, w| w.tcie().clear_bit());
+        USART::wakers().tx.wake();
+    }
+}
+
+/// 异步接收
+pub struct AsyncRx<USART> {
+    rx: Rx<USART>,
+}
+
+impl<USART> AsyncRx<USART>
+where
+    USART: Instance,
+    Rx<USART>: serial::Read<u8, Error = Error>,
+{
+    pub fn new(rx: Rx<USART>) -> Self {
+        AsyncRx { rx }
+    }
+
+    /// 接收一个字节
+    pub async fn read(&mut self) -> Result<u8, Error> {
+        poll_fn(|cx| match serial::Read::read(&mut self.rx) {
+            Ok(byte) => Poll::Ready(Ok(byte)),
+            Err(nb::Error::Other(e)) => Poll::Ready(Err(e)),
+            Err(nb::Error::WouldBlock) => {
+                USART::wakers().rx.register(cx.waker());
+                // 先注册再打开中断，期间收到的数据会立即触发中断
+                critical_section::with(|_| {
+                    USART::regs().cr1.modify(|_, w| w.rxneie().set_bit());
+                });
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// 接收数据直到填满缓冲区
+    pub async fn read_exact(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        for byte in buffer.iter_mut() {
+            *byte = self.read().await?;
+        }
+        Ok(())
+    }
+
+    pub fn free(self) -> Rx<USART> {
+        self.rx
+    }
+}
+
+/// 异步发送
+pub struct AsyncTx<USART> {
+    tx: Tx<USART>,
+}
+
+impl<USART> AsyncTx<USART>
+where
+    USART: Instance,
+    Tx<USART>: serial::Write<u8, Error = Infallible>,
+{
+    pub fn new(tx: Tx<USART>) -> Self {
+        AsyncTx { tx }
+    }
+
+    /// 发送一个字节，数据写入发送寄存器后返回
+    pub async fn write(&mut self, byte: u8) {
+        poll_fn(|cx| match serial::Write::write(&mut self.tx, byte) {
+            Ok(()) => Poll::Ready(()),
+            Err(nb::Error::Other(e)) => match e {},
+            Err(nb::Error::WouldBlock) => {
+                USART::wakers().tx.register(cx.waker());
+                critical_section::with(|_| {
+                    USART::regs().cr1.modify(|_, w| w.txeie().set_bit());
+                });
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// 发送全部数据
+    pub async fn write_all(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write(byte).await;
+        }
+    }
+
+    /// 发送字符串
+    pub async fn write_str(&mut self, s: &str) {
+        self.write_all(s.as_bytes()).await;
+    }
+
+    /// 等待发送完成
+    pub async fn flush(&mut self) {
+        poll_fn(|cx| match serial::Write::flush(&mut self.tx) {
+            Ok(()) => Poll::Ready(()),
+            Err(nb::Error::Other(e)) => match e {},
+            Err(nb::Error::WouldBlock) => {
+                USART::wakers().tx.register(cx.waker());
+                critical_section::with(|_| {
+                    USART::regs().cr1.modify(|_, w| w.tcie().set_bit());
+                });
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    pub fn free(self) -> Tx<USART> {
+        self.tx
+    }
+}
@@ -0,0 +1,152 @@
+//! 异步 SPI 主机
+//! 使用已配置好的硬件 SPI(8位数据)，逐字节传输，等待接收完成时打开 RXNE 中断，中断中关闭中断并唤醒任务
+//! SPI 时钟较高时数据通常已经就绪，不会进入中断；大量数据的传输建议使用 DMA
+//! 需要在 SPI 中断中调用 `on_interrupt`，并在 NVIC 中打开中断
+//!
+//! ```rust
+//! let spi = Spi::spi1(dp.SPI1, pins, &mut afio.mapr, MODE_0, 9.MHz(), clocks);
+//! let mut spi: AsyncSpi<pac::SPI1, _> = AsyncSpi::new(spi);
+//! unsafe { NVIC::unmask(interrupt::SPI1) };
+//!
+//! cs.set_low();
+//! spi.write(&[0x9F]).await;
+//! spi.read(&mut id).await;
+//! cs.set_high();
+//!
+//! #[interrupt]
+//! fn SPI1() {
+//!     asynch::spi::on_interrupt::<pac::SPI1>();
+//! }
+//! ```
+
+use super::waker::WakerCell;
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::ptr;
+use core::task::Poll;
+
+use stm32f1xx_hal::pac::{self, spi1::RegisterBlock};
+
+/// 只读时发送的数据
+pub const DUMMY_BYTE: u8 = 0xFF;
+
+/// 支持异步传输的 SPI
+pub trait Instance {
+    /// SPI 寄存器
+    fn regs() -> &'static RegisterBlock;
+    /// 等待该 SPI 的任务
+    fn waker() -> &'static WakerCell;
+}
+
+macro_rules! instance {
+    ($($SPI:ident: $WAKER:ident,)+) => {
+        $(
+            static $WAKER: WakerCell = WakerCell::new();
+
+            impl Instance for pac::$SPI {
+                fn regs() -> &'static RegisterBlock {
+                    unsafe { &*pac::$SPI::ptr() }
+                }
+
+                fn waker() -> &'static WakerCell {
+                    &$WAKER
+                }
+            }
+        )+
+    };
+}
+
+instance! {
+    SPI1: SPI1_WAKER,
+    SPI2: SPI2_WAKER,
+}
+
+/// SPI 中断处理，在 SPI 中断中调用
+pub fn on_interrupt<SPI: Instance>() {
+    let regs = SPI::regs();
+    if regs.cr2.read().rxneie().bit_is_set() && regs.sr.read().rxne().bit_is_set() {
+        regs.cr2.modify(|_, w| w.rxneie().clear_bit());
+        SPI::waker().wake();
+    }
+}
+
+/// 异步 SPI 主机
+/// BUS 为 HAL 中已配置好的 SPI，传输期间不使用
+pub struct AsyncSpi<SPI, BUS> {
+    bus: BUS,
+    _spi: PhantomData<SPI>,
+}
+
+impl<SPI: Instance, BUS> AsyncSpi<SPI, BUS> {
+    pub fn new(bus: BUS) -> Self {
+        AsyncSpi {
+            bus,
+            _spi: PhantomData,
+        }
+    }
+
+    /// 释放 SPI
+    pub fn free(self) -> BUS {
+        self.bus
+    }
+
+    /// 传输一个字节
+    pub async fn transfer_byte(&mut self, byte: u8) -> u8 {
+        let regs = SPI::regs();
+
+        // 丢弃被取消的传输留下的数据
+        if regs.sr.read().rxne().bit_is_set() {
+            unsafe { ptr::read_volatile(regs.dr.as_ptr() as *const u8) };
+        }
+        // 上一个字节已经接收，发送寄存器一定为空
+        while regs.sr.read().txe().bit_is_clear() {}
+        // 8位访问数据寄存器
+        unsafe { ptr::write_volatile(regs.dr.as_ptr() as *mut u8, byte) };
+
+        poll_fn(|cx| {
+            if regs.sr.read().rxne().bit_is_set() {
+                return Poll::Ready(());
+            }
+            SPI::waker().register(cx.waker());
+            critical_section::with(|_| regs.cr2.modify(|_, w| w.rxneie().set_bit()));
+            Poll::Pending
+        })
+        .await;
+
+        unsafe { ptr::read_volatile(regs.dr.as_ptr() as *const u8) }
+    }
+
+    /// 读取数据，发送 `DUMMY_BYTE`
+    pub async fn read(&mut self, words: &mut [u8]) {
+        for word in words.iter_mut() {
+            *word = self.transfer_byte(DUMMY_BYTE).await;
+        }
+    }
+
+    /// 发送数据，丢弃接收的数据
+    pub async fn write(&mut self, words: &[u8]) {
+        for &word in words {
+            self.transfer_byte(word).await;
+        }
+    }
+
+    /// 同时发送和接收，长度不同时多余的部分发送 `DUMMY_BYTE` 或丢弃接收的数据
+    pub async fn transfer(&mut self, read: &mut [u8], write: &[u8]) {
+        for i in 0..read.len().max(write.len()) {
+            let byte = self
+                .transfer_byte(write.get(i).copied().unwrap_or(DUMMY_BYTE))
+                .await;
+            if let Some(word) = read.get_mut(i) {
+                *word = byte;
+            }
+        }
+    }
+
+    /// 发送缓冲区中的数据，并用接收的数据替换
+    pub async fn transfer_in_place(&mut self, words: &mut [u8]) {
+        for word in words.iter_mut() {
+            *word = self.transfer_byte(*word).await;
+        }
+    }
+}
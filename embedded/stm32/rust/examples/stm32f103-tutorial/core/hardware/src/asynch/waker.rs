@@ -0,0 +1,45 @@
+//! 中断唤醒
+//! 保存等待中断的任务的 `Waker`，在中断中唤醒，一次只能有一个任务等待
+
+use core::cell::RefCell;
+use core::task::Waker;
+
+use critical_section::Mutex;
+
+/// 保存一个 `Waker`
+pub struct WakerCell {
+    waker: Mutex<RefCell<Option<Waker>>>,
+}
+
+impl WakerCell {
+    pub const fn new() -> Self {
+        WakerCell {
+            waker: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// 注册 `Waker`，替换之前注册的
+    pub fn register(&self, waker: &Waker) {
+        critical_section::with(|cs| {
+            let mut slot = self.waker.borrow_ref_mut(cs);
+            match slot.as_ref() {
+                Some(old) if old.will_wake(waker) => {}
+                _ => *slot = Some(waker.clone()),
+            }
+        });
+    }
+
+    /// 唤醒已注册的任务
+    pub fn wake(&self) {
+        let waker = critical_section::with(|cs| self.waker.borrow_ref_mut(cs).take());
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl Default for WakerCell {
+    fn default() -> Self {
+        WakerCell::new()
+    }
+}
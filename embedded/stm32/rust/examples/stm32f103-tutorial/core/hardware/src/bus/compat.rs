@@ -0,0 +1,66 @@
+//! embedded-hal 0.2 与 1.0 的适配
+//! stm32f1xx-hal 只实现了 0.2 的接口，包装后可以交给基于 1.0 接口的驱动使用
+//!
+//! ```rust
+//! let i2c = BlockingI2c::i2c2(dp.I2C2, (scl, sda), mode, clocks, 1000, 10, 1000, 1000);
+//! let mut mpu = mpu6050_i2c::new(CompatI2c(i2c));
+//! ```
+
+use embedded_hal::blocking::i2c as i2c_02;
+use embedded_hal_1::i2c::{self as i2c_1, Operation, SevenBitAddress};
+use embedded_hal_1::spi::{self as spi_1, ErrorKind};
+
+/// 包装 embedded-hal 0.2 外设的错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompatError<E>(pub E);
+
+impl<E: core::fmt::Debug> spi_1::Error for CompatError<E> {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+impl<E: core::fmt::Debug> i2c_1::Error for CompatError<E> {
+    fn kind(&self) -> i2c_1::ErrorKind {
+        i2c_1::ErrorKind::Other
+    }
+}
+
+/// 包装 embedded-hal 0.2 的阻塞 I2C，实现 1.0 的 `I2c` 接口
+/// 0.2 的接口没有通用的事务：先写后读合并为一次带重复起始信号的 `write_read`，
+/// 其余操作依次单独传输
+pub struct CompatI2c<I>(pub I);
+
+impl<I, E> i2c_1::ErrorType for CompatI2c<I>
+where
+    I: i2c_02::Write<Error = E> + i2c_02::Read<Error = E> + i2c_02::WriteRead<Error = E>,
+    E: core::fmt::Debug,
+{
+    type Error = CompatError<E>;
+}
+
+impl<I, E> i2c_1::I2c<SevenBitAddress> for CompatI2c<I>
+where
+    I: i2c_02::Write<Error = E> + i2c_02::Read<Error = E> + i2c_02::WriteRead<Error = E>,
+    E: core::fmt::Debug,
+{
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let i2c = &mut self.0;
+        match operations {
+            [Operation::Write(bytes), Operation::Read(buffer)] => {
+                i2c.write_read(address, bytes, buffer)
+            }
+            _ => operations
+                .iter_mut()
+                .try_for_each(|operation| match operation {
+                    Operation::Write(bytes) => i2c.write(address, bytes),
+                    Operation::Read(buffer) => i2c.read(address, buffer),
+                }),
+        }
+        .map_err(CompatError)
+    }
+}
@@ -0,0 +1,107 @@
+//! 共享 I2C 总线的设备句柄
+//! I2C 通过地址区分设备，每次传输锁定总线即可，
+//! 总线实现了 embedded-hal 0.2 或 1.0 的 I2C 接口时，设备句柄也实现对应的接口
+
+use super::BusMutex;
+
+use embedded_hal::blocking::i2c as i2c_02;
+use embedded_hal_1::i2c::{self as i2c_1, SevenBitAddress};
+
+/// I2C 设备句柄
+pub struct I2cDevice<M> {
+    bus: M,
+}
+
+impl<M> I2cDevice<M>
+where
+    M: BusMutex,
+{
+    /// bus: 总线互斥锁，见 `BusMutex`
+    pub fn new(bus: M) -> Self {
+        I2cDevice { bus }
+    }
+
+    /// 锁定总线，直接访问总线外设
+    pub fn lock<R>(&mut self, f: impl FnOnce(&mut M::Bus) -> R) -> R {
+        self.bus.lock(f)
+    }
+}
+
+impl<M> i2c_1::ErrorType for I2cDevice<M>
+where
+    M: BusMutex,
+    M::Bus: i2c_1::ErrorType,
+{
+    type Error = <M::Bus as i2c_1::ErrorType>::Error;
+}
+
+impl<M> i2c_1::I2c<SevenBitAddress> for I2cDevice<M>
+where
+    M: BusMutex,
+    M::Bus: i2c_1::I2c<SevenBitAddress>,
+{
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c_1::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.bus.lock(|bus| bus.transaction(address, operations))
+    }
+}
+
+impl<M> i2c_02::Write for I2cDevice<M>
+where
+    M: BusMutex,
+    M::Bus: i2c_02::Write,
+{
+    type Error = <M::Bus as i2c_02::Write>::Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.bus.lock(|bus| bus.write(address, bytes))
+    }
+}
+
+impl<M> i2c_02::Read for I2cDevice<M>
+where
+    M: BusMutex,
+    M::Bus: i2c_02::Read,
+{
+    type Error = <M::Bus as i2c_02::Read>::Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.bus.lock(|bus| bus.read(address, buffer))
+    }
+}
+
+impl<M> i2c_02::WriteRead for I2cDevice<M>
+where
+    M: BusMutex,
+    M::Bus: i2c_02::WriteRead,
+{
+    type Error = <M::Bus as i2c_02::WriteRead>::Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.bus.lock(|bus| bus.write_read(address, bytes, buffer))
+    }
+}
+
+impl<M> i2c_02::Transactional for I2cDevice<M>
+where
+    M: BusMutex,
+    M::Bus: i2c_02::Transactional,
+{
+    type Error = <M::Bus as i2c_02::Transactional>::Error;
+
+    fn exec(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c_02::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.bus.lock(|bus| bus.exec(address, operations))
+    }
+}
@@ -0,0 +1,91 @@
+//! 共享总线
+//! 多个设备挂在同一条 I2C 或 SPI 总线上时，总线外设只有一个所有者，
+//! 将总线放入互斥锁中，每个设备持有一个设备句柄，每次传输时锁定总线
+//!
+//! 根据总线的使用场景选择互斥锁：
+//! - `RefCell`: 只在同一个上下文中使用，开销最小，不能在中断中共享
+//! - `critical_section::Mutex<RefCell<_>>`: 传输期间关闭中断，可以在主循环和中断之间共享
+//! - `RticBus`: 使用 RTIC 共享资源的锁，按优先级天花板锁定
+//!
+//! ```rust
+//! let bus = RefCell::new(BlockingI2c::i2c2(dp.I2C2, (scl, sda), mode, clocks, 1000, 10, 1000, 1000));
+//! let mut mpu = I2cDevice::new(&bus);
+//! let mut sensor = I2cDevice::new(&bus);
+//!
+//! // 在中断中使用时，总线需要是静态的
+//! let spi_bus: &'static Mutex<RefCell<Spi1>> =
+//!     cortex_m::singleton!(: Mutex<RefCell<Spi1>> = Mutex::new(RefCell::new(spi))).unwrap();
+//! let mut flash = SpiDevice::new(spi_bus, flash_cs);
+//! let mut radio = SpiDevice::new(spi_bus, radio_cs);
+//! ```
+
+pub mod compat;
+pub mod i2c;
+pub mod spi;
+
+pub use compat::{CompatError, CompatI2c};
+pub use i2c::I2cDevice;
+pub use spi::{DeviceError, SpiDevice, SpinDelay};
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+/// 总线互斥锁
+pub trait BusMutex {
+    type Bus;
+
+    /// 锁定总线并执行操作
+    fn lock<R>(&mut self, f: impl FnOnce(&mut Self::Bus) -> R) -> R;
+}
+
+/// 只在同一个上下文中共享
+/// 中断中借用正在使用的总线会 panic
+impl<B> BusMutex for &RefCell<B> {
+    type Bus = B;
+
+    fn lock<R>(&mut self, f: impl FnOnce(&mut B) -> R) -> R {
+        f(&mut self.borrow_mut())
+    }
+}
+
+/// 在临界区中锁定，传输期间关闭中断
+impl<B> BusMutex for &Mutex<RefCell<B>> {
+    type Bus = B;
+
+    fn lock<R>(&mut self, f: impl FnOnce(&mut B) -> R) -> R {
+        critical_section::with(|cs| f(&mut self.borrow_ref_mut(cs)))
+    }
+}
+
+/// RTIC 共享资源
+/// 将任务上下文中的共享资源交给设备句柄，每次传输时调用资源的 `lock`
+///
+/// ```rust
+/// #[task(binds = EXTI9_5, shared = [i2c])]
+/// fn on_int(cx: on_int::Context) {
+///     let mut mpu = I2cDevice::new(RticBus::new(cx.shared.i2c));
+/// }
+/// ```
+pub struct RticBus<M>(M);
+
+impl<M> RticBus<M> {
+    pub fn new(mutex: M) -> Self {
+        RticBus(mutex)
+    }
+
+    pub fn into_inner(self) -> M {
+        self.0
+    }
+}
+
+impl<M> BusMutex for RticBus<M>
+where
+    M: rtic_core::Mutex,
+{
+    type Bus = M::T;
+
+    fn lock<R>(&mut self, f: impl FnOnce(&mut M::T) -> R) -> R {
+        self.0.lock(f)
+    }
+}
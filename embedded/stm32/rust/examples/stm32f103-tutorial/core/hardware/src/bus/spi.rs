@@ -0,0 +1,199 @@
+//! 共享 SPI 总线的设备句柄
+//! 每个设备持有自己的片选引脚，传输时锁定总线，拉低片选，传输结束后拉高片选再释放总线，
+//! 同一次锁定中的多个操作在同一个片选周期内完成
+
+use super::BusMutex;
+
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::blocking::spi as spi_02;
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal_1::spi::{self as spi_1, ErrorKind, Operation, SpiBus};
+
+/// 设备句柄错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceError<BUS, CS> {
+    /// SPI 总线错误
+    Spi(BUS),
+    /// 片选引脚错误
+    Cs(CS),
+}
+
+impl<BUS, CS> spi_1::Error for DeviceError<BUS, CS>
+where
+    BUS: spi_1::Error,
+    CS: core::fmt::Debug,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            DeviceError::Spi(e) => e.kind(),
+            DeviceError::Cs(_) => ErrorKind::ChipSelectFault,
+        }
+    }
+}
+
+/// 默认延时，忙等待，不需要时钟配置
+/// 按 F103 最高72MHz主频计算循环次数忙等待，主频较低时实际延时更长，但不会短于要求；
+/// 需要精确延时的驱动使用 `SpiDevice::new_with_delay`
+pub struct SpinDelay;
+
+/// F103 的最高主频(MHz)
+const MAX_SYSCLK_MHZ: u32 = 72;
+
+impl DelayUs<u32> for SpinDelay {
+    fn delay_us(&mut self, us: u32) {
+        cortex_m::asm::delay(us.saturating_mul(MAX_SYSCLK_MHZ));
+    }
+}
+
+/// SPI 设备句柄
+pub struct SpiDevice<M, CS, D = SpinDelay> {
+    bus: M,
+    cs: CS,
+    delay: D,
+}
+
+impl<M, CS> SpiDevice<M, CS, SpinDelay>
+where
+    M: BusMutex,
+    CS: OutputPin,
+{
+    /// bus: 总线互斥锁，见 `BusMutex`
+    /// cs: 片选引脚，创建时拉高
+    pub fn new(bus: M, cs: CS) -> Self {
+        Self::new_with_delay(bus, cs, SpinDelay)
+    }
+}
+
+impl<M, CS, D> SpiDevice<M, CS, D>
+where
+    M: BusMutex,
+    CS: OutputPin,
+{
+    /// delay: 用于 `Operation::DelayNs`，按微秒向上取整
+    pub fn new_with_delay(bus: M, mut cs: CS, delay: D) -> Self {
+        let _ = cs.set_high();
+        SpiDevice { bus, cs, delay }
+    }
+
+    /// 释放片选引脚和延时
+    pub fn free(self) -> (M, CS, D) {
+        (self.bus, self.cs, self.delay)
+    }
+
+    /// 锁定总线并拉低片选，在同一个片选周期内直接访问总线外设
+    pub fn transaction<R, E>(
+        &mut self,
+        f: impl FnOnce(&mut M::Bus) -> Result<R, E>,
+    ) -> Result<R, DeviceError<E, CS::Error>> {
+        select(&mut self.bus, &mut self.cs, f)
+    }
+}
+
+/// 锁定总线并拉低片选执行操作
+fn select<M, CS, R, E>(
+    bus: &mut M,
+    cs: &mut CS,
+    f: impl FnOnce(&mut M::Bus) -> Result<R, E>,
+) -> Result<R, DeviceError<E, CS::Error>>
+where
+    M: BusMutex,
+    CS: OutputPin,
+{
+    bus.lock(|bus| {
+        cs.set_low().map_err(DeviceError::Cs)?;
+        let result = f(bus).map_err(DeviceError::Spi);
+        // 出错时也要拉高片选
+        let deselect = cs.set_high().map_err(DeviceError::Cs);
+        let value = result?;
+        deselect?;
+        Ok(value)
+    })
+}
+
+impl<M, CS, D> spi_1::ErrorType for SpiDevice<M, CS, D>
+where
+    M: BusMutex,
+    M::Bus: spi_1::ErrorType,
+    CS: OutputPin,
+    CS::Error: core::fmt::Debug,
+{
+    type Error = DeviceError<<M::Bus as spi_1::ErrorType>::Error, CS::Error>;
+}
+
+impl<M, CS, D> spi_1::SpiDevice<u8> for SpiDevice<M, CS, D>
+where
+    M: BusMutex,
+    M::Bus: SpiBus<u8>,
+    CS: OutputPin,
+    CS::Error: core::fmt::Debug,
+    D: DelayUs<u32>,
+{
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let delay = &mut self.delay;
+        select(&mut self.bus, &mut self.cs, |bus| {
+            for operation in operations.iter_mut() {
+                match operation {
+                    Operation::Read(words) => bus.read(words)?,
+                    Operation::Write(words) => bus.write(words)?,
+                    Operation::Transfer(read, write) => bus.transfer(read, write)?,
+                    Operation::TransferInPlace(words) => bus.transfer_in_place(words)?,
+                    Operation::DelayNs(ns) => {
+                        // 等待之前的数据发送完成再延时
+                        bus.flush()?;
+                        delay.delay_us(ns.div_ceil(1000));
+                    }
+                }
+            }
+            bus.flush()
+        })
+    }
+}
+
+impl<M, CS, D> spi_02::Transfer<u8> for SpiDevice<M, CS, D>
+where
+    M: BusMutex,
+    M::Bus: spi_02::Transfer<u8>,
+    CS: OutputPin,
+{
+    type Error = DeviceError<<M::Bus as spi_02::Transfer<u8>>::Error, CS::Error>;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.transaction(|bus| bus.transfer(words))
+    }
+}
+
+impl<M, CS, D> spi_02::Write<u8> for SpiDevice<M, CS, D>
+where
+    M: BusMutex,
+    M::Bus: spi_02::Write<u8>,
+    CS: OutputPin,
+{
+    type Error = DeviceError<<M::Bus as spi_02::Write<u8>>::Error, CS::Error>;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.transaction(|bus| bus.write(words))
+    }
+}
+
+impl<M, CS, D, E> spi_02::Transactional<u8> for SpiDevice<M, CS, D>
+where
+    M: BusMutex,
+    M::Bus: spi_02::Transfer<u8, Error = E> + spi_02::Write<u8, Error = E>,
+    CS: OutputPin,
+{
+    type Error = DeviceError<E, CS::Error>;
+
+    fn exec(&mut self, operations: &mut [spi_02::Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.transaction(|bus| {
+            for operation in operations.iter_mut() {
+                match operation {
+                    spi_02::Operation::Write(words) => spi_02::Write::write(bus, words)?,
+                    spi_02::Operation::Transfer(words) => {
+                        spi_02::Transfer::transfer(bus, words)?;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
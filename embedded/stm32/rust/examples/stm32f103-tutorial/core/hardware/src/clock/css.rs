@@ -0,0 +1,61 @@
+//! 时钟安全系统(CSS)
+//! 使能后硬件监测 HSE，外部晶振停振时自动关闭 HSE 和 PLL，将系统时钟切换到 HSI 8MHz，并触发 NMI
+//! NMI 中必须清除 CSS 标志位，否则会反复进入中断；之后在主循环中重新配置时钟和外设
+//!
+//! ```rust
+//! clock::enable_css();
+//!
+//! loop {
+//!     if clock::take_hse_failure() {
+//!         let clocks = clock::fallback_to_hsi(&mut rcc, &mut flash.acr);
+//!         mono.reconfigure(&clocks);
+//!     }
+//! }
+//!
+//! #[exception]
+//! unsafe fn NonMaskableInt() {
+//!     clock::on_nmi();
+//! }
+//! ```
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use stm32f1xx_hal::pac::RCC;
+
+/// 是否检测到 HSE 故障
+static HSE_FAILED: AtomicBool = AtomicBool::new(false);
+
+/// 使能时钟安全系统
+/// HSE 就绪后才开始监测，只在使用 HSE 时有意义
+pub fn enable_css() {
+    let rcc = unsafe { &*RCC::ptr() };
+    rcc.cr.modify(|_, w| w.csson().set_bit());
+}
+
+/// 关闭时钟安全系统
+pub fn disable_css() {
+    let rcc = unsafe { &*RCC::ptr() };
+    rcc.cr.modify(|_, w| w.csson().clear_bit());
+}
+
+/// NMI 中断处理，在 NMI 中调用
+/// 清除 CSS 标志位并记录 HSE 故障，返回是否为 CSS 触发的 NMI
+pub fn on_nmi() -> bool {
+    let rcc = unsafe { &*RCC::ptr() };
+    if rcc.cir.read().cssf().bit_is_clear() {
+        return false;
+    }
+    rcc.cir.write(|w| w.cssc().set_bit());
+    HSE_FAILED.store(true, Ordering::Relaxed);
+    true
+}
+
+/// 是否发生过 HSE 故障
+pub fn hse_failed() -> bool {
+    HSE_FAILED.load(Ordering::Relaxed)
+}
+
+/// 取出 HSE 故障标志，每次故障只返回一次 true
+pub fn take_hse_failure() -> bool {
+    HSE_FAILED.swap(false, Ordering::Relaxed)
+}
@@ -0,0 +1,233 @@
+//! 时钟树配置
+//! 常用的时钟配置预设、时钟频率读取，HSE 故障检测及运行时切换系统时钟
+//!
+//! | 预设        | 时钟源   | SYSCLK | HCLK  | PCLK1 | PCLK2 | ADC   | USB |
+//! | ----------- | -------- | ------ | ----- | ----- | ----- | ----- | --- |
+//! | `Hsi8`      | HSI      | 8MHz   | 8MHz  | 8MHz  | 8MHz  | 4MHz  | 否  |
+//! | `Hsi64`     | HSI/2×16 | 64MHz  | 64MHz | 32MHz | 64MHz | 8MHz  | 否  |
+//! | `Hse72`     | HSE×9    | 72MHz  | 72MHz | 36MHz | 72MHz | 12MHz | 是  |
+//! | `LowPower8` | HSI      | 8MHz   | 8MHz  | 2MHz  | 2MHz  | 1MHz  | 否  |
+//! | `Usb48`     | HSE×6    | 48MHz  | 48MHz | 24MHz | 48MHz | 12MHz | 是  |
+//!
+//! ```rust
+//! let mut flash = dp.FLASH.constrain();
+//! let mut rcc = dp.RCC.constrain();
+//! // 没有外部晶振时使用 HSI 64MHz
+//! let (clocks, preset) = clock::freeze_with_fallback(Preset::Hse72, Preset::Hsi64, &mut rcc, &mut flash.acr);
+//! ClockInfo::new(&clocks).print();
+//!
+//! // 运行时降低主频，重新配置依赖时钟的外设
+//! let clocks = clock::switch(Preset::LowPower8, &mut rcc, &mut flash.acr).unwrap();
+//! block!(serial.reconfigure(serial::Config::default().baudrate(115_200.bps()), &clocks)).unwrap();
+//! mono.reconfigure(&clocks);
+//! ```
+
+pub mod css;
+
+pub use css::{enable_css, hse_failed, on_nmi, take_hse_failure};
+
+use defmt::println;
+use stm32f1xx_hal::flash::ACR;
+use stm32f1xx_hal::pac::RCC;
+use stm32f1xx_hal::prelude::_fugit_RateExtU32;
+use stm32f1xx_hal::rcc::{Clocks, Rcc, CFGR};
+
+/// 外部晶振频率
+pub const HSE_FREQ_MHZ: u32 = 8;
+
+/// 等待 HSE 就绪的最大次数
+const HSE_STARTUP_TIMEOUT: u32 = 0x5000;
+
+/// 时钟配置预设
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Preset {
+    /// 内部 8MHz 时钟，复位后的默认配置
+    Hsi8,
+    /// 内部时钟经 PLL 倍频到 64MHz，不使用 HSE 时的最高频率
+    Hsi64,
+    /// 外部 8MHz 晶振经 PLL 倍频到 72MHz，最高性能
+    Hse72,
+    /// 内部 8MHz 时钟，APB 总线分频，降低外设功耗
+    LowPower8,
+    /// 外部晶振经 PLL 倍频到 48MHz，USB 时钟不分频
+    Usb48,
+}
+
+impl Preset {
+    /// 是否使用外部晶振
+    pub fn uses_hse(&self) -> bool {
+        matches!(self, Preset::Hse72 | Preset::Usb48)
+    }
+
+    /// 将预设写入时钟配置
+    pub fn configure(&self, cfgr: CFGR) -> CFGR {
+        match self {
+            Preset::Hsi8 => cfgr.sysclk(8.MHz()),
+            Preset::Hsi64 => cfgr.sysclk(64.MHz()).pclk1(32.MHz()).adcclk(8.MHz()),
+            Preset::Hse72 => cfgr
+                .use_hse(HSE_FREQ_MHZ.MHz())
+                .sysclk(72.MHz())
+                .pclk1(36.MHz())
+                .pclk2(72.MHz())
+                .adcclk(12.MHz()),
+            Preset::LowPower8 => cfgr
+                .sysclk(8.MHz())
+                .hclk(8.MHz())
+                .pclk1(2.MHz())
+                .pclk2(2.MHz())
+                .adcclk(1.MHz()),
+            Preset::Usb48 => cfgr
+                .use_hse(HSE_FREQ_MHZ.MHz())
+                .sysclk(48.MHz())
+                .pclk1(24.MHz())
+                .pclk2(48.MHz())
+                .adcclk(12.MHz()),
+        }
+    }
+
+    /// 应用预设，同时设置 FLASH 等待周期
+    /// 使用 HSE 的预设在外部晶振不工作时会一直等待，见 `freeze_with_fallback`
+    pub fn freeze(&self, cfgr: CFGR, acr: &mut ACR) -> Clocks {
+        self.configure(cfgr).freeze(acr)
+    }
+}
+
+/// 外部晶振不能起振，时钟配置保持不变
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct HseNotReady;
+
+/// 系统时钟源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum SysclkSource {
+    Hsi,
+    Hse,
+    Pll,
+}
+
+/// 读取当前的系统时钟源
+/// HSE 故障后硬件会自动切换到 HSI，与 `Clocks` 中记录的配置不同
+pub fn sysclk_source() -> SysclkSource {
+    let rcc = unsafe { &*RCC::ptr() };
+    match rcc.cfgr.read().sws().bits() {
+        0b00 => SysclkSource::Hsi,
+        0b01 => SysclkSource::Hse,
+        _ => SysclkSource::Pll,
+    }
+}
+
+/// 各总线的时钟频率(Hz)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct ClockInfo {
+    pub sysclk: u32,
+    /// AHB 总线、内核及 SysTick
+    pub hclk: u32,
+    /// APB1 总线，USART2/3、I2C、SPI2
+    pub pclk1: u32,
+    /// APB2 总线，USART1、SPI1、GPIO
+    pub pclk2: u32,
+    /// TIM2~4 的时钟，APB1 分频时为 PCLK1 的2倍
+    pub pclk1_tim: u32,
+    /// TIM1 的时钟，APB2 分频时为 PCLK2 的2倍
+    pub pclk2_tim: u32,
+    /// ADC 时钟，最高14MHz
+    pub adcclk: u32,
+    /// USB 时钟是否为48MHz
+    pub usbclk_valid: bool,
+}
+
+impl ClockInfo {
+    pub fn new(clocks: &Clocks) -> Self {
+        ClockInfo {
+            sysclk: clocks.sysclk().raw(),
+            hclk: clocks.hclk().raw(),
+            pclk1: clocks.pclk1().raw(),
+            pclk2: clocks.pclk2().raw(),
+            pclk1_tim: clocks.pclk1_tim().raw(),
+            pclk2_tim: clocks.pclk2_tim().raw(),
+            adcclk: clocks.adcclk().raw(),
+            usbclk_valid: clocks.usbclk_valid(),
+        }
+    }
+
+    /// 打印各总线频率
+    pub fn print(&self) {
+        println!(
+            "SYSCLK: {} Hz, HCLK: {} Hz, PCLK1: {} Hz, PCLK2: {} Hz",
+            self.sysclk, self.hclk, self.pclk1, self.pclk2
+        );
+        println!(
+            "TIM2~4: {} Hz, TIM1: {} Hz, ADC: {} Hz, USB: {}",
+            self.pclk1_tim, self.pclk2_tim, self.adcclk, self.usbclk_valid
+        );
+    }
+}
+
+/// 检测外部晶振是否能够起振
+/// 打开 HSE 并等待就绪，超时后关闭 HSE
+pub fn hse_ready() -> bool {
+    let rcc = unsafe { &*RCC::ptr() };
+    rcc.cr.modify(|_, w| w.hseon().set_bit());
+    for _ in 0..HSE_STARTUP_TIMEOUT {
+        if rcc.cr.read().hserdy().bit_is_set() {
+            return true;
+        }
+    }
+    rcc.cr.modify(|_, w| w.hseon().clear_bit());
+    false
+}
+
+/// 取出时钟配置器，留下默认的配置器供下次切换时使用
+/// 可变借用 `Rcc` 保证没有其他地方在修改时钟配置
+fn cfgr(rcc: &mut Rcc) -> CFGR {
+    core::mem::take(&mut rcc.cfgr)
+}
+
+/// 应用预设，预设使用的外部晶振不工作时改用备用预设
+/// 返回实际使用的预设
+pub fn freeze_with_fallback(
+    preset: Preset,
+    fallback: Preset,
+    rcc: &mut Rcc,
+    acr: &mut ACR,
+) -> (Clocks, Preset) {
+    let preset = if preset.uses_hse() && !hse_ready() {
+        fallback
+    } else {
+        preset
+    };
+    (preset.freeze(cfgr(rcc), acr), preset)
+}
+
+/// 运行时切换系统时钟
+/// 先切换到 HSI 并关闭 PLL，再按预设重新配置，之后需要用返回的时钟重新配置串口、定时器等外设
+/// 切换期间不能有依赖时钟的传输在进行
+///
+/// 预设使用的外部晶振不能起振时返回错误，不修改时钟配置
+pub fn switch(preset: Preset, rcc: &mut Rcc, acr: &mut ACR) -> Result<Clocks, HseNotReady> {
+    if preset.uses_hse() && !hse_ready() {
+        return Err(HseNotReady);
+    }
+    Ok(reconfigure(preset, rcc, acr))
+}
+
+/// HSE 故障后切换到 HSI 64MHz
+pub fn fallback_to_hsi(rcc: &mut Rcc, acr: &mut ACR) -> Clocks {
+    reconfigure(Preset::Hsi64, rcc, acr)
+}
+
+/// 切换到 HSI，再按预设重新配置
+fn reconfigure(preset: Preset, rcc: &mut Rcc, acr: &mut ACR) -> Clocks {
+    let regs = unsafe { &*RCC::ptr() };
+    // PLL 作为系统时钟时不能修改配置
+    regs.cr.modify(|_, w| w.hsion().set_bit());
+    while regs.cr.read().hsirdy().bit_is_clear() {}
+    regs.cfgr.modify(|_, w| w.sw().hsi());
+    while sysclk_source() != SysclkSource::Hsi {}
+    regs.cr.modify(|_, w| w.pllon().clear_bit());
+    while regs.cr.read().pllrdy().bit_is_set() {}
+    if !preset.uses_hse() {
+        regs.cr.modify(|_, w| w.hseon().clear_bit());
+    }
+
+    preset.freeze(cfgr(rcc), acr)
+}
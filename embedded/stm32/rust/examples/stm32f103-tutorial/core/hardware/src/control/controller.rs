@@ -0,0 +1,40 @@
+//! 电机闭环控制器的硬件适配
+//! 控制算法在 `portable::control::controller` 中，这里为定时器编码器接口和 `Motor` 实现控制器需要的接口
+
+use embedded_hal::Qei;
+
+use crate::motor::{HBridge, Motor};
+use portable::control::{DutyOutput, Encoder};
+
+/// 驱动 H 桥电机、读取定时器编码器接口的闭环控制器
+pub type MotorController<B, Q> = portable::control::MotorController<Motor<B>, QeiEncoder<Q>>;
+
+/// 定时器编码器接口，计数器为16位
+pub struct QeiEncoder<Q>(pub Q);
+
+impl<Q> Encoder for QeiEncoder<Q>
+where
+    Q: Qei<Count = u16>,
+{
+    fn count(&self) -> u16 {
+        self.0.count()
+    }
+}
+
+impl<B: HBridge> DutyOutput for Motor<B> {
+    fn set_duty(&mut self, permille: i16) {
+        self.set_speed_permille(permille);
+    }
+
+    fn coast(&mut self) {
+        Motor::coast(self);
+    }
+
+    fn brake(&mut self) {
+        Motor::brake(self);
+    }
+
+    fn tick(&mut self) {
+        Motor::tick(self);
+    }
+}
@@ -0,0 +1,29 @@
+//! 闭环控制
+//! PID 控制器、梯形速度规划，以及基于编码器的直流电机速度/位置闭环控制
+//!
+//! - `pid`: 支持 f32 和 Q16.16 定点数，积分抗饱和、微分对测量值求导、输出限幅及前馈
+//! - `trapezoid`: 梯形速度规划，在线计算每个控制周期的位置和速度
+//! - `controller`: 编码器测速，速度环和位置环串级控制，驱动 `motor` 模块的 H 桥
+//! - `tuning`: 串口调参命令
+//!
+//! PID、梯形规划、调参命令和闭环控制算法由平台无关工具库 `portable::control` 提供，可以在主机上测试，
+//! 这里只把定时器编码器接口和 `Motor` 适配到控制器
+//!
+//! ```rust
+//! let motor = Motor::new(SignMagnitude::new(ain1, ain2, pwm_channel), MotorConfig::default());
+//! let qei = Timer::new(tim3, &clocks).qei((pa6, pa7), &mut afio.mapr, QeiOptions::default());
+//! let mut controller = MotorController::new(motor, QeiEncoder(qei), ControllerConfig::default());
+//! controller.set_speed(1000.0);
+//!
+//! // 100Hz 定时器中断
+//! controller.update();
+//! ```
+pub mod controller;
+
+pub use portable::control::{fixed, pid, trapezoid, tuning};
+
+pub use controller::{MotorController, QeiEncoder};
+pub use portable::control::{
+    Command, CommandReader, ControlMode, ControllerConfig, DutyOutput, Encoder, Fixed, Gain, Loop,
+    ParseError, Pid, PidGains, PidTerms, Scalar, Setpoint, Trapezoid,
+};
@@ -2,4 +2,5 @@
 pub mod flash_rw;
 pub use flash_rw::FlashStore;
 
+pub mod storage;
 pub mod store;
@@ -0,0 +1,135 @@
+//! embedded-storage 接口实现
+//! 使内部 FLASH 可以直接用于 sequential-storage、ekv 等生态库
+//! 只开放主存储器末尾预留的若干页，偏移地址从预留区起始地址开始计算，
+//! 超出预留区的偏移返回 `OutOfBounds`，避免擦写到程序代码
+//! 最后一页由参数存储模块(store)使用，不在预留区内
+
+use super::FlashStore;
+
+use embedded_storage::nor_flash::{
+    check_erase, check_read, check_write, ErrorType, NorFlash, NorFlashError, NorFlashErrorKind,
+    ReadNorFlash, RmwNorFlashStorage,
+};
+
+/// 主存储器起始地址
+const FLASH_BASE_ADDRESS: u32 = 0x0800_0000;
+/// 页大小为1KB
+pub const FLASH_PAGE_SIZE: usize = 1024;
+/// 主存储器容量为64KB(STM32F103C8T6)
+pub const FLASH_SIZE: usize = 64 * 1024;
+/// 预留给存储接口的页数
+/// 程序代码不能超过 FLASH_SIZE - (STORAGE_PAGES + 1) * FLASH_PAGE_SIZE，与 memory.x 中的 FLASH 区域一致
+pub const STORAGE_PAGES: usize = 8;
+/// 预留区容量
+pub const STORAGE_SIZE: usize = STORAGE_PAGES * FLASH_PAGE_SIZE;
+/// 预留区起始地址，位于参数存储页之前
+pub const STORAGE_START_ADDRESS: u32 =
+    FLASH_BASE_ADDRESS + (FLASH_SIZE - (STORAGE_PAGES + 1) * FLASH_PAGE_SIZE) as u32;
+
+/// 内部 FLASH 操作错误
+#[derive(Debug)]
+pub enum Error {
+    /// 地址或长度未按要求对齐
+    NotAligned,
+    /// 地址超出预留区
+    OutOfBounds,
+    /// 编程错误，目标地址未擦除
+    Programming,
+    /// 目标地址处于写保护状态
+    WriteProtected,
+}
+
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::NotAligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+impl From<NorFlashErrorKind> for Error {
+    fn from(kind: NorFlashErrorKind) -> Self {
+        match kind {
+            NorFlashErrorKind::NotAligned => Error::NotAligned,
+            _ => Error::OutOfBounds,
+        }
+    }
+}
+
+impl<'a> FlashStore<'a> {
+    /// 检查并清除状态寄存器中的错误标志
+    fn check_errors(&self) -> Result<(), Error> {
+        let sr = self.flash.sr.read();
+        let result = if sr.wrprterr().bit_is_set() {
+            Err(Error::WriteProtected)
+        } else if sr.pgerr().bit_is_set() {
+            Err(Error::Programming)
+        } else {
+            Ok(())
+        };
+        // 写1清除错误标志
+        self.flash
+            .sr
+            .write(|w| w.wrprterr().set_bit().pgerr().set_bit());
+        result
+    }
+
+    /// 创建按字节读写的存储适配器
+    /// 写入时自动完成页的读取、合并、擦除和回写
+    /// merge_buffer: 页合并缓冲区
+    pub fn storage<'b>(
+        &'b mut self,
+        merge_buffer: &'b mut [u8; FLASH_PAGE_SIZE],
+    ) -> RmwNorFlashStorage<'b, &'b mut Self> {
+        RmwNorFlashStorage::new(self, merge_buffer)
+    }
+}
+
+impl<'a> ErrorType for FlashStore<'a> {
+    type Error = Error;
+}
+
+impl<'a> ReadNorFlash for FlashStore<'a> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = FlashStore::flash_read_byte(STORAGE_START_ADDRESS + offset + i as u32);
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        STORAGE_SIZE
+    }
+}
+
+/// 内部 FLASH 只能对已擦除(0xFFFF)的半字编程，否则会产生编程错误，
+/// 因此不实现 MultiwriteNorFlash
+impl<'a> NorFlash for FlashStore<'a> {
+    const WRITE_SIZE: usize = 2;
+    const ERASE_SIZE: usize = FLASH_PAGE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+        for offset in (from..to).step_by(FLASH_PAGE_SIZE) {
+            self.flash_erase_page(STORAGE_START_ADDRESS + offset);
+            self.check_errors()?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+        for (i, half_word) in bytes.chunks_exact(2).enumerate() {
+            let address = STORAGE_START_ADDRESS + offset + i as u32 * 2;
+            let data = u16::from_le_bytes([half_word[0], half_word[1]]);
+            self.flash_program_half_word(address, data);
+            self.check_errors()?;
+        }
+        Ok(())
+    }
+}
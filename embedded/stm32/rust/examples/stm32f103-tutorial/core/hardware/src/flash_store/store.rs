@@ -33,8 +33,8 @@ impl<'a> FlashStore<'a> {
 
             // 循环STORE_COUNT次，除了第一个标志位
             for (i, _) in unsafe { STORE_DATA[1..].iter_mut().enumerate() } {
-                // 除了标志位的有效数据全部清0
-                let address = STORE_START_ADDRESS + i as u32 * 2;
+                // 除了标志位的有效数据全部清0，i 从标志位之后开始计数
+                let address = STORE_START_ADDRESS + (i as u32 + 1) * 2;
                 self.flash_program_half_word(address, 0x0000);
             }
         }
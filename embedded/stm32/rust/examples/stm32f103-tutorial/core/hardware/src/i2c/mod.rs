@@ -0,0 +1,7 @@
+//! I2C 总线工具库
+
+pub mod scan;
+pub mod soft;
+
+pub use scan::{probe, scan};
+pub use soft::SoftI2c;
@@ -0,0 +1,47 @@
+//! I2C 总线扫描
+//! 依次探测 0x08~0x77 的7位地址，返回有应答的地址，用于排查接线和确认设备地址
+//! 每个地址读取1个字节探测，不会误写 EEPROM 等设备
+//! 不使用不带数据的写操作: stm32f1xx-hal 的 `BlockingI2c::write` 在设备应答地址后会访问第一个数据字节，
+//! 空数据会导致 panic
+//! 使用 embedded-hal 1.0 的 `I2c` 接口，硬件 I2C 需要用 `bus::CompatI2c` 包装
+//!
+//! ```rust
+//! let addresses = hardware::i2c::scan(&mut i2c);
+//! for address in addresses.iter() {
+//!     println!("found: {:#04x}", address);
+//! }
+//! ```
+
+use embedded_hal_1::i2c::I2c;
+use heapless::Vec;
+
+/// 扫描的第一个地址，0x00~0x07 为保留地址
+pub const SCAN_FIRST: u8 = 0x08;
+/// 扫描的最后一个地址，0x78~0x7F 为保留地址
+pub const SCAN_LAST: u8 = 0x77;
+/// 最多可以扫描到的设备数
+pub const SCAN_CAPACITY: usize = (SCAN_LAST - SCAN_FIRST + 1) as usize;
+
+/// 探测指定地址是否有设备应答
+pub fn probe<I>(i2c: &mut I, address: u8) -> bool
+where
+    I: I2c,
+{
+    let mut buffer = [0];
+    i2c.read(address, &mut buffer).is_ok()
+}
+
+/// 扫描总线，返回有应答的地址
+pub fn scan<I>(i2c: &mut I) -> Vec<u8, SCAN_CAPACITY>
+where
+    I: I2c,
+{
+    let mut addresses = Vec::new();
+    for address in SCAN_FIRST..=SCAN_LAST {
+        if probe(i2c, address) {
+            // 容量与地址数相同，不会溢出
+            let _ = addresses.push(address);
+        }
+    }
+    addresses
+}
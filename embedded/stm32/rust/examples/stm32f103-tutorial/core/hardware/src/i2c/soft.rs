@@ -0,0 +1,426 @@
+//! 软件 I2C 主机
+//! 使用任意两个开漏输出引脚模拟 I2C 时序，同时实现 embedded-hal 0.2 和 1.0 的 I2C 接口，
+//! 可以直接交给基于 embedded-hal 的驱动使用
+//!
+//! - 地址为7位地址，读写位由驱动自动添加
+//! - 支持从机拉低 SCL 的时钟延展，超时返回错误
+//! - 地址或数据未收到应答时返回错误，并发送结束信号释放总线
+//! - 从机在传输中途复位导致 SDA 被拉低时，可以调用 `recover_bus` 恢复总线
+//!
+//! ```rust
+//! let mut scl = gpiob.pb10.into_open_drain_output(&mut gpiob.crh);
+//! let mut sda = gpiob.pb11.into_open_drain_output(&mut gpiob.crh);
+//! scl.set_speed(&mut gpiob.crh, gpio::IOPinSpeed::Mhz50);
+//! sda.set_speed(&mut gpiob.crh, gpio::IOPinSpeed::Mhz50);
+//! let mut i2c = SoftI2c::new(scl, sda, delay, 100.kHz());
+//!
+//! let mut id = [0];
+//! i2c.write_read(0x68, &[0x75], &mut id).unwrap();
+//! ```
+
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::blocking::i2c as i2c_02;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_hal_1::i2c::{self as i2c_1, ErrorKind, NoAcknowledgeSource, SevenBitAddress};
+use stm32f1xx_hal::time::Hertz;
+
+/// 默认时钟延展超时时间(us)，与 SMBus 规定的 25ms 相同
+pub const DEFAULT_STRETCH_TIMEOUT_US: u32 = 25_000;
+
+/// 总线恢复时最多发送的时钟数
+const RECOVERY_CLOCKS: usize = 9;
+
+/// 软件 I2C 错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// 未收到应答
+    NoAcknowledge(NoAcknowledgeSource),
+    /// 发送高电平时 SDA 被其他设备拉低
+    ArbitrationLoss,
+    /// 起始时 SDA 被拉低，总线被占用，可以尝试 `recover_bus`
+    BusBusy,
+    /// 等待从机释放 SCL 超时
+    Timeout,
+    /// 引脚读写失败
+    Pin,
+}
+
+impl i2c_1::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match *self {
+            Error::NoAcknowledge(source) => ErrorKind::NoAcknowledge(source),
+            Error::ArbitrationLoss => ErrorKind::ArbitrationLoss,
+            Error::BusBusy => ErrorKind::Bus,
+            Error::Timeout | Error::Pin => ErrorKind::Other,
+        }
+    }
+}
+
+/// 软件 I2C 主机
+/// SCL 和 SDA 都需要配置为开漏输出，并接上拉电阻
+/// SCL 也需要可读，用于检测时钟延展
+pub struct SoftI2c<Scl, Sda, Delay> {
+    scl: Scl,
+    sda: Sda,
+    delay: Delay,
+    /// 半个时钟周期(us)
+    half_period_us: u32,
+    /// 时钟延展超时时间(us)
+    stretch_timeout_us: u32,
+}
+
+impl<Scl, Sda, Delay> SoftI2c<Scl, Sda, Delay>
+where
+    Scl: OutputPin + InputPin,
+    Sda: OutputPin + InputPin,
+    Delay: DelayUs<u32>,
+{
+    /// 创建实例，并释放总线
+    /// frequency: SCL 时钟频率，延时精度为1us，实际频率受引脚翻转耗时影响会略低
+    pub fn new(scl: Scl, sda: Sda, delay: Delay, frequency: Hertz) -> Self {
+        let mut i2c = SoftI2c {
+            scl,
+            sda,
+            delay,
+            half_period_us: 0,
+            stretch_timeout_us: DEFAULT_STRETCH_TIMEOUT_US,
+        };
+        i2c.set_frequency(frequency);
+        // 总线空闲状态
+        let _ = i2c.scl.set_high();
+        let _ = i2c.sda.set_high();
+        i2c
+    }
+
+    /// 设置 SCL 时钟频率
+    pub fn set_frequency(&mut self, frequency: Hertz) {
+        self.half_period_us = (500_000 / frequency.raw().max(1)).max(1);
+    }
+
+    /// 设置时钟延展超时时间(us)
+    pub fn set_stretch_timeout_us(&mut self, timeout_us: u32) {
+        self.stretch_timeout_us = timeout_us;
+    }
+
+    /// 释放引脚和延时
+    pub fn free(self) -> (Scl, Sda, Delay) {
+        (self.scl, self.sda, self.delay)
+    }
+
+    /// 总线恢复
+    /// 从机在传输中途复位时可能一直拉低 SDA 等待时钟，
+    /// 释放 SDA 后最多发送9个时钟，直到从机释放 SDA，再发送结束信号
+    pub fn recover_bus(&mut self) -> Result<(), Error> {
+        self.sda_high()?;
+        for _ in 0..RECOVERY_CLOCKS {
+            if self.sda_is_high()? {
+                break;
+            }
+            self.scl_low()?;
+            self.wait();
+            self.scl_high()?;
+            self.wait();
+        }
+        if !self.sda_is_high()? {
+            return Err(Error::BusBusy);
+        }
+
+        self.scl_low()?;
+        self.wait();
+        self.stop()
+    }
+
+    /// 半个时钟周期的延时
+    fn wait(&mut self) {
+        self.delay.delay_us(self.half_period_us);
+    }
+
+    fn scl_low(&mut self) -> Result<(), Error> {
+        self.scl.set_low().map_err(|_| Error::Pin)
+    }
+
+    /// 释放 SCL，等待从机结束时钟延展
+    fn scl_high(&mut self) -> Result<(), Error> {
+        self.scl.set_high().map_err(|_| Error::Pin)?;
+
+        let mut waited = 0;
+        while self.scl.is_low().map_err(|_| Error::Pin)? {
+            if waited >= self.stretch_timeout_us {
+                return Err(Error::Timeout);
+            }
+            self.delay.delay_us(1);
+            waited += 1;
+        }
+        Ok(())
+    }
+
+    fn sda_low(&mut self) -> Result<(), Error> {
+        self.sda.set_low().map_err(|_| Error::Pin)
+    }
+
+    fn sda_high(&mut self) -> Result<(), Error> {
+        self.sda.set_high().map_err(|_| Error::Pin)
+    }
+
+    fn sda_is_high(&mut self) -> Result<bool, Error> {
+        self.sda.is_high().map_err(|_| Error::Pin)
+    }
+
+    /// 产生起始信号，也用于重复起始
+    fn start(&mut self) -> Result<(), Error> {
+        self.sda_high()?;
+        self.wait();
+        self.scl_high()?;
+        if !self.sda_is_high()? {
+            return Err(Error::BusBusy);
+        }
+        self.wait();
+        self.sda_low()?;
+        self.wait();
+        self.scl_low()
+    }
+
+    /// 产生结束信号
+    fn stop(&mut self) -> Result<(), Error> {
+        self.sda_low()?;
+        self.wait();
+        self.scl_high()?;
+        self.wait();
+        self.sda_high()?;
+        self.wait();
+        Ok(())
+    }
+
+    /// 发送一位，SCL 为低电平时改变 SDA
+    fn write_bit(&mut self, bit: bool) -> Result<(), Error> {
+        if bit {
+            self.sda_high()?;
+        } else {
+            self.sda_low()?;
+        }
+        self.wait();
+        self.scl_high()?;
+        self.wait();
+        // 释放 SDA 后读到低电平，说明有其他主机在发送
+        if bit && !self.sda_is_high()? {
+            return Err(Error::ArbitrationLoss);
+        }
+        self.scl_low()
+    }
+
+    /// 读取一位，SCL 为高电平时采样
+    fn read_bit(&mut self) -> Result<bool, Error> {
+        self.sda_high()?;
+        self.wait();
+        self.scl_high()?;
+        self.wait();
+        let bit = self.sda_is_high()?;
+        self.scl_low()?;
+        Ok(bit)
+    }
+
+    /// 发送一个字节，返回是否收到应答
+    fn write_byte(&mut self, byte: u8) -> Result<bool, Error> {
+        for i in 0..8 {
+            self.write_bit(byte & (0x80 >> i) != 0)?;
+        }
+        // 应答为低电平
+        Ok(!self.read_bit()?)
+    }
+
+    /// 读取一个字节，并发送应答或非应答
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+        let mut byte = 0;
+        for i in 0..8 {
+            if self.read_bit()? {
+                byte |= 0x80 >> i;
+            }
+        }
+        self.write_bit(!ack)?;
+        Ok(byte)
+    }
+
+    /// 发送起始信号和地址
+    fn address(&mut self, address: u8, read: bool) -> Result<(), Error> {
+        self.start()?;
+        if !self.write_byte((address << 1) | read as u8)? {
+            return Err(Error::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
+        Ok(())
+    }
+
+    /// 写操作
+    /// restart: 是否发送(重复)起始信号和地址，与上一个写操作相邻时不发送
+    fn write_operation(&mut self, address: u8, bytes: &[u8], restart: bool) -> Result<(), Error> {
+        if restart {
+            self.address(address, false)?;
+        }
+        for byte in bytes {
+            if !self.write_byte(*byte)? {
+                return Err(Error::NoAcknowledge(NoAcknowledgeSource::Data));
+            }
+        }
+        Ok(())
+    }
+
+    /// 读操作
+    /// restart: 是否发送(重复)起始信号和地址，与上一个读操作相邻时不发送
+    /// last: 后面没有相邻的读操作，最后一个字节发送非应答
+    fn read_operation(
+        &mut self,
+        address: u8,
+        buffer: &mut [u8],
+        restart: bool,
+        last: bool,
+    ) -> Result<(), Error> {
+        if restart {
+            self.address(address, true)?;
+        }
+        let len = buffer.len();
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = self.read_byte(!(last && i + 1 == len))?;
+        }
+        Ok(())
+    }
+
+    /// 结束传输
+    /// 出错时也要发送结束信号，释放总线
+    fn finish(&mut self, result: Result<(), Error>) -> Result<(), Error> {
+        let stop = self.stop();
+        result.and(stop)
+    }
+
+    /// 执行 embedded-hal 1.0 的操作序列
+    /// 相邻的同类操作之间不发送起始信号，读写切换时发送重复起始信号，最后发送结束信号
+    fn transaction_1(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c_1::Operation<'_>],
+    ) -> Result<(), Error> {
+        let mut previous_read = None;
+        for i in 0..operations.len() {
+            let next_read = matches!(operations.get(i + 1), Some(i2c_1::Operation::Read(_)));
+            match &mut operations[i] {
+                i2c_1::Operation::Write(bytes) => {
+                    self.write_operation(address, bytes, previous_read != Some(false))?;
+                    previous_read = Some(false);
+                }
+                i2c_1::Operation::Read(buffer) => {
+                    self.read_operation(address, buffer, previous_read != Some(true), !next_read)?;
+                    previous_read = Some(true);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 执行 embedded-hal 0.2 的操作序列，规则同上
+    fn transaction_02(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c_02::Operation<'_>],
+    ) -> Result<(), Error> {
+        let mut previous_read = None;
+        for i in 0..operations.len() {
+            let next_read = matches!(operations.get(i + 1), Some(i2c_02::Operation::Read(_)));
+            match &mut operations[i] {
+                i2c_02::Operation::Write(bytes) => {
+                    self.write_operation(address, bytes, previous_read != Some(false))?;
+                    previous_read = Some(false);
+                }
+                i2c_02::Operation::Read(buffer) => {
+                    self.read_operation(address, buffer, previous_read != Some(true), !next_read)?;
+                    previous_read = Some(true);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Scl, Sda, Delay> i2c_1::ErrorType for SoftI2c<Scl, Sda, Delay> {
+    type Error = Error;
+}
+
+impl<Scl, Sda, Delay> i2c_1::I2c<SevenBitAddress> for SoftI2c<Scl, Sda, Delay>
+where
+    Scl: OutputPin + InputPin,
+    Sda: OutputPin + InputPin,
+    Delay: DelayUs<u32>,
+{
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c_1::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let result = self.transaction_1(address, operations);
+        self.finish(result)
+    }
+}
+
+impl<Scl, Sda, Delay> i2c_02::Transactional for SoftI2c<Scl, Sda, Delay>
+where
+    Scl: OutputPin + InputPin,
+    Sda: OutputPin + InputPin,
+    Delay: DelayUs<u32>,
+{
+    type Error = Error;
+
+    fn exec(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c_02::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let result = self.transaction_02(address, operations);
+        self.finish(result)
+    }
+}
+
+impl<Scl, Sda, Delay> i2c_02::Write for SoftI2c<Scl, Sda, Delay>
+where
+    Scl: OutputPin + InputPin,
+    Sda: OutputPin + InputPin,
+    Delay: DelayUs<u32>,
+{
+    type Error = Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        let result = self.write_operation(address, bytes, true);
+        self.finish(result)
+    }
+}
+
+impl<Scl, Sda, Delay> i2c_02::Read for SoftI2c<Scl, Sda, Delay>
+where
+    Scl: OutputPin + InputPin,
+    Sda: OutputPin + InputPin,
+    Delay: DelayUs<u32>,
+{
+    type Error = Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let result = self.read_operation(address, buffer, true, true);
+        self.finish(result)
+    }
+}
+
+impl<Scl, Sda, Delay> i2c_02::WriteRead for SoftI2c<Scl, Sda, Delay>
+where
+    Scl: OutputPin + InputPin,
+    Sda: OutputPin + InputPin,
+    Delay: DelayUs<u32>,
+{
+    type Error = Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let result = self
+            .write_operation(address, bytes, true)
+            .and_then(|_| self.read_operation(address, buffer, true, true));
+        self.finish(result)
+    }
+}
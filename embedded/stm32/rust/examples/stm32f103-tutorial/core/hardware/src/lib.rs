@@ -3,10 +3,22 @@
 
 use panic_probe as _;
 
+pub mod asynch;
+pub mod bus;
+pub mod clock;
+pub mod control;
 pub mod flash_store;
+pub mod i2c;
 pub mod key;
+pub mod motor;
 pub mod mpu6050;
+pub mod nrf24;
 pub mod oled;
+pub mod profile;
 pub mod serial;
+pub mod spi;
 pub mod syst;
+pub mod time;
 pub mod w25q64;
+
+pub use portable::imu;
@@ -0,0 +1,148 @@
+//! H 桥的接法
+//! STM32 的 GPIO 操作不会出错，引脚错误被忽略
+
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::PwmPin;
+
+use super::{duty, MAX_SPEED};
+
+/// H 桥输出
+pub trait HBridge {
+    /// 按千分比输出，speed 在 -1000~1000 之间，正数正转，负数反转
+    fn drive(&mut self, speed: i16);
+
+    /// 滑行: 输出为高阻态，电机靠惯性慢慢停下
+    fn coast(&mut self);
+
+    /// 刹车: 电机两端短接，电机迅速停下
+    fn brake(&mut self);
+}
+
+/// 符号-幅值接法
+/// 两个方向引脚决定转向，PWM 占空比决定速度，
+/// TB6612FNG 接 AIN1、AIN2、PWMA，L298N 接 IN1、IN2、ENA
+///
+/// | 状态 | IN1 | IN2 | PWM |
+/// | ---- | --- | --- | --- |
+/// | 正转 | 高  | 低  | 占空比 |
+/// | 反转 | 低  | 高  | 占空比 |
+/// | 滑行 | 低  | 低  | 0 |
+/// | 刹车 | 高  | 高  | 100% |
+pub struct SignMagnitude<IN1, IN2, PWM> {
+    in1: IN1,
+    in2: IN2,
+    pwm: PWM,
+}
+
+impl<IN1, IN2, PWM> SignMagnitude<IN1, IN2, PWM>
+where
+    IN1: OutputPin,
+    IN2: OutputPin,
+    PWM: PwmPin<Duty = u16>,
+{
+    /// 创建实例，开启 PWM 输出，电机处于滑行状态
+    pub fn new(in1: IN1, in2: IN2, mut pwm: PWM) -> Self {
+        pwm.enable();
+        let mut bridge = SignMagnitude { in1, in2, pwm };
+        bridge.coast();
+        bridge
+    }
+
+    /// 释放引脚
+    pub fn free(self) -> (IN1, IN2, PWM) {
+        (self.in1, self.in2, self.pwm)
+    }
+}
+
+impl<IN1, IN2, PWM> HBridge for SignMagnitude<IN1, IN2, PWM>
+where
+    IN1: OutputPin,
+    IN2: OutputPin,
+    PWM: PwmPin<Duty = u16>,
+{
+    fn drive(&mut self, speed: i16) {
+        if speed >= 0 {
+            let _ = self.in1.set_high();
+            let _ = self.in2.set_low();
+        } else {
+            let _ = self.in1.set_low();
+            let _ = self.in2.set_high();
+        }
+        let max_duty = self.pwm.get_max_duty();
+        self.pwm.set_duty(duty(max_duty, speed.unsigned_abs()));
+    }
+
+    fn coast(&mut self) {
+        self.pwm.set_duty(0);
+        let _ = self.in1.set_low();
+        let _ = self.in2.set_low();
+    }
+
+    fn brake(&mut self) {
+        let _ = self.in1.set_high();
+        let _ = self.in2.set_high();
+        let max_duty = self.pwm.get_max_duty();
+        self.pwm.set_duty(max_duty);
+    }
+}
+
+/// 锁定反相接法
+/// PWM 接 IN1，经反相器接 IN2，50% 占空比时平均电压为0，
+/// 大于 50% 正转，小于 50% 反转；使能引脚接 L298N 的 ENA 或 TB6612FNG 的 PWMA
+///
+/// 低速时电流方向不断切换，转速更平稳，刹车也更有力，但静止时也有纹波电流
+pub struct LockedAntiphase<EN, PWM> {
+    enable: EN,
+    pwm: PWM,
+}
+
+impl<EN, PWM> LockedAntiphase<EN, PWM>
+where
+    EN: OutputPin,
+    PWM: PwmPin<Duty = u16>,
+{
+    /// 创建实例，开启 PWM 输出，电机处于滑行状态
+    pub fn new(enable: EN, mut pwm: PWM) -> Self {
+        pwm.enable();
+        let mut bridge = LockedAntiphase { enable, pwm };
+        bridge.coast();
+        bridge
+    }
+
+    /// 释放引脚
+    pub fn free(self) -> (EN, PWM) {
+        (self.enable, self.pwm)
+    }
+
+    /// 平均电压为0的占空比
+    fn set_neutral(&mut self) {
+        let max_duty = self.pwm.get_max_duty();
+        self.pwm.set_duty(max_duty / 2);
+    }
+}
+
+impl<EN, PWM> HBridge for LockedAntiphase<EN, PWM>
+where
+    EN: OutputPin,
+    PWM: PwmPin<Duty = u16>,
+{
+    fn drive(&mut self, speed: i16) {
+        let speed = speed.clamp(-MAX_SPEED, MAX_SPEED);
+        // -1000~1000 映射到 0~1000
+        let permille = ((speed + MAX_SPEED) / 2) as u16;
+        let max_duty = self.pwm.get_max_duty();
+        self.pwm.set_duty(duty(max_duty, permille));
+        let _ = self.enable.set_high();
+    }
+
+    fn coast(&mut self) {
+        let _ = self.enable.set_low();
+        self.set_neutral();
+    }
+
+    fn brake(&mut self) {
+        // 两个半桥轮流导通，平均电压为0，电机两端相当于短接
+        self.set_neutral();
+        let _ = self.enable.set_high();
+    }
+}
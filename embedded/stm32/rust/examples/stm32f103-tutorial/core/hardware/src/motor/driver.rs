@@ -0,0 +1,222 @@
+//! 单路电机
+//! 在 H 桥输出之上增加死区补偿、加速度限制及停止方式
+
+use super::bridge::HBridge;
+use super::MAX_SPEED;
+
+/// 速度为0时的停止方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum StopMode {
+    /// 滑行，电机靠惯性慢慢停下
+    Coast,
+    /// 刹车，电机迅速停下并保持
+    Brake,
+}
+
+/// 电机参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct MotorConfig {
+    inverted: bool,
+    dead_zone: u16,
+    ramp: u16,
+    stop_mode: StopMode,
+}
+
+impl Default for MotorConfig {
+    /// 不反转方向、无死区补偿、不限制加速度、滑行停止
+    fn default() -> Self {
+        MotorConfig {
+            inverted: false,
+            dead_zone: 0,
+            ramp: 0,
+            stop_mode: StopMode::Coast,
+        }
+    }
+}
+
+impl MotorConfig {
+    /// 反转方向，电机接线反了或左右两侧电机镜像安装时使用
+    pub fn inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    /// 死区补偿(‰)，电机刚好能转动的占空比
+    /// 非零速度按比例映射到 dead_zone~1000，避免低速时电机不转
+    pub fn dead_zone(mut self, permille: u16) -> Self {
+        self.dead_zone = permille.min(MAX_SPEED as u16);
+        self
+    }
+
+    /// 加速度限制，每次 `Motor::tick` 速度最多变化的千分比，为0时不限制
+    pub fn ramp(mut self, step: u16) -> Self {
+        self.ramp = step.min(2 * MAX_SPEED as u16);
+        self
+    }
+
+    /// 速度为0时的停止方式
+    pub fn stop_mode(mut self, mode: StopMode) -> Self {
+        self.stop_mode = mode;
+        self
+    }
+
+    /// 是否反转方向
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// 死区补偿(‰)
+    pub fn get_dead_zone(&self) -> u16 {
+        self.dead_zone
+    }
+
+    /// 加速度限制，0表示不限制
+    pub fn get_ramp(&self) -> u16 {
+        self.ramp
+    }
+
+    /// 停止方式
+    pub fn get_stop_mode(&self) -> StopMode {
+        self.stop_mode
+    }
+}
+
+/// 单路电机
+pub struct Motor<B> {
+    bridge: B,
+    config: MotorConfig,
+    /// 目标速度(‰)
+    target: i16,
+    /// 当前输出的速度(‰)，限制加速度时逐步接近目标速度
+    speed: i16,
+}
+
+impl<B> Motor<B>
+where
+    B: HBridge,
+{
+    /// 创建实例，电机按停止方式停止
+    pub fn new(bridge: B, config: MotorConfig) -> Self {
+        let mut motor = Motor {
+            bridge,
+            config,
+            target: 0,
+            speed: 0,
+        };
+        motor.apply();
+        motor
+    }
+
+    /// 释放 H 桥
+    pub fn free(self) -> B {
+        self.bridge
+    }
+
+    /// 电机参数
+    pub fn config(&self) -> &MotorConfig {
+        &self.config
+    }
+
+    /// 修改电机参数，立即生效
+    pub fn set_config(&mut self, config: MotorConfig) {
+        self.config = config;
+        if config.ramp == 0 {
+            self.speed = self.target;
+        }
+        self.apply();
+    }
+
+    /// 设置目标速度(‰)，-1000~1000，超出范围时取边界值
+    /// 不限制加速度时立即生效，否则由 `tick` 逐步调整
+    pub fn set_speed_permille(&mut self, speed: i16) {
+        self.target = speed.clamp(-MAX_SPEED, MAX_SPEED);
+        if self.config.ramp == 0 {
+            self.speed = self.target;
+            self.apply();
+        }
+    }
+
+    /// 设置目标速度(%)，-100~100
+    pub fn set_speed_percent(&mut self, speed: i8) {
+        self.set_speed_permille(speed.clamp(-100, 100) as i16 * 10);
+    }
+
+    /// 目标速度(‰)
+    pub fn target(&self) -> i16 {
+        self.target
+    }
+
+    /// 当前输出的速度(‰)，未经过死区补偿
+    pub fn speed(&self) -> i16 {
+        self.speed
+    }
+
+    /// 是否还在加速或减速
+    pub fn is_ramping(&self) -> bool {
+        self.speed != self.target
+    }
+
+    /// 按加速度限制调整一次速度，需要按固定周期调用
+    pub fn tick(&mut self) {
+        if self.speed == self.target {
+            return;
+        }
+        let step = self.config.ramp as i16;
+        self.speed = if step == 0 {
+            self.target
+        } else if self.speed < self.target {
+            self.target.min(self.speed + step)
+        } else {
+            self.target.max(self.speed - step)
+        };
+        self.apply();
+    }
+
+    /// 按停止方式减速到0
+    pub fn stop(&mut self) {
+        self.set_speed_permille(0);
+    }
+
+    /// 立即滑行，不受加速度限制
+    pub fn coast(&mut self) {
+        self.target = 0;
+        self.speed = 0;
+        self.bridge.coast();
+    }
+
+    /// 立即刹车，不受加速度限制
+    pub fn brake(&mut self) {
+        self.target = 0;
+        self.speed = 0;
+        self.bridge.brake();
+    }
+
+    /// 输出当前速度
+    fn apply(&mut self) {
+        if self.speed == 0 {
+            match self.config.stop_mode {
+                StopMode::Coast => self.bridge.coast(),
+                StopMode::Brake => self.bridge.brake(),
+            }
+            return;
+        }
+
+        let speed = if self.config.inverted {
+            -self.speed
+        } else {
+            self.speed
+        };
+        self.bridge.drive(compensate(speed, self.config.dead_zone));
+    }
+}
+
+/// 死区补偿，非零速度映射到 dead_zone~1000
+fn compensate(speed: i16, dead_zone: u16) -> i16 {
+    if speed == 0 {
+        return 0;
+    }
+    let dead_zone = dead_zone as i32;
+    let max = MAX_SPEED as i32;
+    let magnitude = dead_zone + speed.unsigned_abs() as i32 * (max - dead_zone) / max;
+    (magnitude * speed.signum() as i32) as i16
+}
@@ -0,0 +1,108 @@
+//! 两路电机，用于差速驱动的小车
+//! TB6612FNG 的 A、B 两路共用一个 STBY 待机引脚
+
+use embedded_hal::digital::v2::OutputPin;
+
+use super::bridge::HBridge;
+use super::driver::Motor;
+use super::{Standby, MAX_SPEED};
+
+/// 左右两路电机
+pub struct DualMotor<L, R, STBY> {
+    left: Motor<L>,
+    right: Motor<R>,
+    standby: Standby<STBY>,
+}
+
+impl<L, R, STBY> DualMotor<L, R, STBY>
+where
+    L: HBridge,
+    R: HBridge,
+    STBY: OutputPin,
+{
+    /// 创建实例，驱动模块退出待机
+    pub fn new(left: Motor<L>, right: Motor<R>, standby: STBY) -> Self {
+        DualMotor {
+            left,
+            right,
+            standby: Standby::new(standby),
+        }
+    }
+
+    /// 释放左右电机及待机引脚
+    pub fn free(self) -> (Motor<L>, Motor<R>, STBY) {
+        (self.left, self.right, self.standby.free())
+    }
+
+    /// 左侧电机
+    pub fn left(&mut self) -> &mut Motor<L> {
+        &mut self.left
+    }
+
+    /// 右侧电机
+    pub fn right(&mut self) -> &mut Motor<R> {
+        &mut self.right
+    }
+
+    /// 分别设置左右电机的目标速度(‰)
+    pub fn set_speeds(&mut self, left: i16, right: i16) {
+        self.left.set_speed_permille(left);
+        self.right.set_speed_permille(right);
+    }
+
+    /// 差速驱动，throttle 为前进速度，turn 为转向速度，正数右转，单位均为‰
+    /// 左右速度超出范围时按比例缩小，保持转弯半径不变
+    pub fn arcade(&mut self, throttle: i16, turn: i16) {
+        let throttle = throttle.clamp(-MAX_SPEED, MAX_SPEED) as i32;
+        let turn = turn.clamp(-MAX_SPEED, MAX_SPEED) as i32;
+        let mut left = throttle + turn;
+        let mut right = throttle - turn;
+
+        let peak = left.abs().max(right.abs());
+        if peak > MAX_SPEED as i32 {
+            left = left * MAX_SPEED as i32 / peak;
+            right = right * MAX_SPEED as i32 / peak;
+        }
+        self.set_speeds(left as i16, right as i16);
+    }
+
+    /// 按加速度限制调整一次两路电机的速度，需要按固定周期调用
+    pub fn tick(&mut self) {
+        self.left.tick();
+        self.right.tick();
+    }
+
+    /// 两路电机按停止方式减速到0
+    pub fn stop(&mut self) {
+        self.left.stop();
+        self.right.stop();
+    }
+
+    /// 两路电机立即滑行
+    pub fn coast(&mut self) {
+        self.left.coast();
+        self.right.coast();
+    }
+
+    /// 两路电机立即刹车
+    pub fn brake(&mut self) {
+        self.left.brake();
+        self.right.brake();
+    }
+
+    /// 进入待机，两路电机滑行，降低功耗
+    pub fn sleep(&mut self) {
+        self.coast();
+        self.standby.disable();
+    }
+
+    /// 退出待机，电机保持停止，需要重新设置速度
+    pub fn wake(&mut self) {
+        self.standby.enable();
+    }
+
+    /// 是否处于待机
+    pub fn is_sleeping(&self) -> bool {
+        !self.standby.is_enabled()
+    }
+}
@@ -0,0 +1,83 @@
+//! H 桥直流电机驱动，适用于 TB6612FNG、L298N 等驱动模块
+//! 速度为带符号的千分比(-1000~1000)，正数正转，负数反转，也可以按百分比设置
+//!
+//! - `bridge`: H 桥的两种接法，符号-幅值(两个方向引脚 + PWM)及锁定反相(一路 PWM 同时控制方向和速度)
+//! - `driver`: 死区补偿、加速度限制及停止方式(滑行或刹车)
+//! - `dual`: 两路电机及共用的待机引脚，用于差速驱动的小车
+//!
+//! ```rust
+//! let bridge = SignMagnitude::new(ain1, ain2, pwm_channel);
+//! let config = MotorConfig::default().dead_zone(80).ramp(20);
+//! let mut motor = Motor::new(bridge, config);
+//!
+//! motor.set_speed_percent(50);
+//! loop {
+//!     // 每 10ms 调用一次，速度每次最多变化 20‰，0.5s 从静止加速到全速
+//!     motor.tick();
+//!     delay.delay_ms(10_u32);
+//! }
+//! ```
+
+pub mod bridge;
+pub mod driver;
+pub mod dual;
+
+pub use bridge::{HBridge, LockedAntiphase, SignMagnitude};
+pub use driver::{Motor, MotorConfig, StopMode};
+pub use dual::DualMotor;
+
+use embedded_hal::digital::v2::OutputPin;
+
+/// 满速对应的千分比
+pub const MAX_SPEED: i16 = 1000;
+
+/// 驱动模块的待机引脚，如 TB6612FNG 的 STBY
+/// 低电平时两路输出均为高阻态，电机滑行
+/// STM32 的 GPIO 操作不会出错，引脚错误被忽略
+pub struct Standby<P> {
+    pin: P,
+    enabled: bool,
+}
+
+impl<P> Standby<P>
+where
+    P: OutputPin,
+{
+    /// 创建实例，驱动模块退出待机
+    pub fn new(pin: P) -> Self {
+        let mut standby = Standby {
+            pin,
+            enabled: false,
+        };
+        standby.enable();
+        standby
+    }
+
+    /// 释放引脚
+    pub fn free(self) -> P {
+        self.pin
+    }
+
+    /// 退出待机，驱动模块正常工作
+    pub fn enable(&mut self) {
+        let _ = self.pin.set_high();
+        self.enabled = true;
+    }
+
+    /// 进入待机
+    pub fn disable(&mut self) {
+        let _ = self.pin.set_low();
+        self.enabled = false;
+    }
+
+    /// 是否正常工作
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// 占空比
+/// permille: 0~1000
+fn duty(max_duty: u16, permille: u16) -> u16 {
+    (max_duty as u32 * permille.min(MAX_SPEED as u16) as u32 / MAX_SPEED as u32) as u16
+}
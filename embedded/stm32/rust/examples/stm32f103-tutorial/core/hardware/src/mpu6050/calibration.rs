@@ -0,0 +1,270 @@
+//! 零偏校准
+//! 静止时累计多个样本求平均，得到陀螺仪零偏和加速度计偏移，
+//! 也可以将芯片六个面依次朝上，额外得到加速度计各轴的增益
+//! 校准结果以原始值保存在内部 FLASH 的参数存储中，在软件中修正读数
+//!
+//! ```rust
+//! let flash_store = FlashStore::new();
+//! flash_store.init_store();
+//! let mut mpu = mpu6050_hal::Mpu6050::new_with_store(pins, i2c2, clocks, &Config::default(), &flash_store).unwrap();
+//! if !mpu.is_calibrated() {
+//!     // 保持静止
+//!     mpu.calibrate(200, &mut delay).unwrap();
+//!     mpu.save_calibration(&flash_store);
+//! }
+//! ```
+
+use super::{AccelGyroData, AccelRange, GyroRange};
+use crate::flash_store::FlashStore;
+
+/// 校准数据在参数存储中的起始序号
+pub const CALIBRATION_STORE_INDEX: usize = 16;
+/// 校准数据占用的参数个数
+pub const CALIBRATION_STORE_COUNT: usize = 13;
+/// 校准数据的标志位
+const CALIBRATION_MAGIC: u16 = 0xCA1B;
+/// 加速度计增益的定点数1.0
+pub const ACCEL_GAIN_ONE: u16 = 0x8000;
+
+/// 校准数据，均为对应量程下的原始值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Calibration {
+    /// 校准时的加速度计量程
+    pub accel_range: AccelRange,
+    /// 校准时的陀螺仪量程
+    pub gyro_range: GyroRange,
+    /// 陀螺仪零偏
+    pub gyro_bias: [i16; 3],
+    /// 加速度计偏移
+    pub accel_offset: [i16; 3],
+    /// 加速度计增益，定点数，`ACCEL_GAIN_ONE` 表示1.0
+    pub accel_gain: [u16; 3],
+}
+
+impl Calibration {
+    /// 不做任何修正的校准数据
+    pub fn identity(accel_range: AccelRange, gyro_range: GyroRange) -> Self {
+        Calibration {
+            accel_range,
+            gyro_range,
+            gyro_bias: [0; 3],
+            accel_offset: [0; 3],
+            accel_gain: [ACCEL_GAIN_ONE; 3],
+        }
+    }
+
+    /// 修正原始数据
+    pub fn apply(&self, data: &mut AccelGyroData) {
+        let accel = |raw: i16, axis: usize| {
+            let value = (raw as i32 - self.accel_offset[axis] as i32)
+                * self.accel_gain[axis] as i32
+                / ACCEL_GAIN_ONE as i32;
+            value.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+        };
+        data.acc_x = accel(data.acc_x, 0);
+        data.acc_y = accel(data.acc_y, 1);
+        data.acc_z = accel(data.acc_z, 2);
+        data.gyro_x = data.gyro_x.saturating_sub(self.gyro_bias[0]);
+        data.gyro_y = data.gyro_y.saturating_sub(self.gyro_bias[1]);
+        data.gyro_z = data.gyro_z.saturating_sub(self.gyro_bias[2]);
+    }
+
+    /// 换算到新的量程
+    /// 偏移按灵敏度比例缩放，增益与量程无关
+    pub fn rescale(&self, accel_range: AccelRange, gyro_range: GyroRange) -> Self {
+        let accel_ratio = accel_range.sensitivity() / self.accel_range.sensitivity();
+        let gyro_ratio = gyro_range.sensitivity() / self.gyro_range.sensitivity();
+        let scale = |value: i16, ratio: f32| (value as f32 * ratio) as i16;
+
+        Calibration {
+            accel_range,
+            gyro_range,
+            gyro_bias: self.gyro_bias.map(|v| scale(v, gyro_ratio)),
+            accel_offset: self.accel_offset.map(|v| scale(v, accel_ratio)),
+            accel_gain: self.accel_gain,
+        }
+    }
+
+    /// 从参数存储中读取
+    /// 需要先调用 `FlashStore::init_store` 将 FLASH 数据加载到 SRAM
+    /// 标志位或校验和不正确时返回 None
+    pub fn load(store: &FlashStore) -> Option<Self> {
+        let mut words = [0; CALIBRATION_STORE_COUNT];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = store.get_store(CALIBRATION_STORE_INDEX + i);
+        }
+        if words[0] != CALIBRATION_MAGIC || words[12] != checksum(&words[..12]) {
+            return None;
+        }
+
+        let accel_range = match words[1] >> 8 {
+            0 => AccelRange::G2,
+            1 => AccelRange::G4,
+            2 => AccelRange::G8,
+            3 => AccelRange::G16,
+            _ => return None,
+        };
+        let gyro_range = match words[1] & 0xFF {
+            0 => GyroRange::Deg250,
+            1 => GyroRange::Deg500,
+            2 => GyroRange::Deg1000,
+            3 => GyroRange::Deg2000,
+            _ => return None,
+        };
+
+        Some(Calibration {
+            accel_range,
+            gyro_range,
+            gyro_bias: [words[2] as i16, words[3] as i16, words[4] as i16],
+            accel_offset: [words[5] as i16, words[6] as i16, words[7] as i16],
+            accel_gain: [words[8], words[9], words[10]],
+        })
+    }
+
+    /// 保存到参数存储，并写入 FLASH
+    pub fn save(&self, store: &FlashStore) {
+        let mut words = [0; CALIBRATION_STORE_COUNT];
+        words[0] = CALIBRATION_MAGIC;
+        words[1] = ((self.accel_range as u16) << 8) | self.gyro_range as u16;
+        for axis in 0..3 {
+            words[2 + axis] = self.gyro_bias[axis] as u16;
+            words[5 + axis] = self.accel_offset[axis] as u16;
+            words[8 + axis] = self.accel_gain[axis];
+        }
+        // 第12个参数保留
+        words[12] = checksum(&words[..12]);
+
+        for (i, word) in words.iter().enumerate() {
+            store.set_store(CALIBRATION_STORE_INDEX + i, *word);
+        }
+        store.store_save();
+    }
+
+    /// 清除参数存储中的校准数据
+    pub fn erase(store: &FlashStore) {
+        for i in 0..CALIBRATION_STORE_COUNT {
+            store.set_store(CALIBRATION_STORE_INDEX + i, 0);
+        }
+        store.store_save();
+    }
+}
+
+/// 校验和
+fn checksum(words: &[u16]) -> u16 {
+    !words.iter().fold(0u16, |sum, word| sum.wrapping_add(*word))
+}
+
+/// 静止校准
+/// 芯片水平放置、Z轴朝上，计算陀螺仪零偏和加速度计偏移
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Calibrator {
+    accel_sum: [i32; 3],
+    gyro_sum: [i32; 3],
+    count: i32,
+}
+
+impl Calibrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 累计一个样本
+    pub fn add_sample(&mut self, data: &AccelGyroData) {
+        self.accel_sum[0] += data.acc_x as i32;
+        self.accel_sum[1] += data.acc_y as i32;
+        self.accel_sum[2] += data.acc_z as i32;
+        self.gyro_sum[0] += data.gyro_x as i32;
+        self.gyro_sum[1] += data.gyro_y as i32;
+        self.gyro_sum[2] += data.gyro_z as i32;
+        self.count += 1;
+    }
+
+    /// 已累计的样本数
+    pub fn count(&self) -> usize {
+        self.count as usize
+    }
+
+    /// 计算校准数据
+    /// Z轴的偏移扣除1g
+    pub fn finish(&self, accel_range: AccelRange, gyro_range: GyroRange) -> Calibration {
+        let mut calibration = Calibration::identity(accel_range, gyro_range);
+        if self.count == 0 {
+            return calibration;
+        }
+
+        let one_g = accel_range.sensitivity() as i32;
+        for axis in 0..3 {
+            calibration.gyro_bias[axis] = (self.gyro_sum[axis] / self.count) as i16;
+            calibration.accel_offset[axis] = (self.accel_sum[axis] / self.count) as i16;
+        }
+        calibration.accel_offset[2] = (self.accel_sum[2] / self.count - one_g) as i16;
+        calibration
+    }
+}
+
+/// 朝上的面
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Face {
+    XUp = 0,
+    XDown = 1,
+    YUp = 2,
+    YDown = 3,
+    ZUp = 4,
+    ZDown = 5,
+}
+
+/// 六面校准
+/// 依次将芯片的六个面朝上静止放置，每个面累计若干样本
+/// 朝上的轴测得+1g，朝下的轴测得-1g，由此计算各轴的偏移和增益
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SixFaceCalibrator {
+    sums: [i32; 6],
+    counts: [i32; 6],
+    gyro: Calibrator,
+}
+
+impl SixFaceCalibrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 累计指定面朝上时的一个样本
+    pub fn add_sample(&mut self, face: Face, data: &AccelGyroData) {
+        let value = match face {
+            Face::XUp | Face::XDown => data.acc_x,
+            Face::YUp | Face::YDown => data.acc_y,
+            Face::ZUp | Face::ZDown => data.acc_z,
+        };
+        self.sums[face as usize] += value as i32;
+        self.counts[face as usize] += 1;
+        // 陀螺仪零偏只需静止即可，与朝向无关
+        self.gyro.add_sample(data);
+    }
+
+    /// 六个面是否都已采样
+    pub fn is_complete(&self) -> bool {
+        self.counts.iter().all(|&count| count > 0)
+    }
+
+    /// 计算校准数据，有面未采样时返回 None
+    pub fn finish(&self, accel_range: AccelRange, gyro_range: GyroRange) -> Option<Calibration> {
+        if !self.is_complete() {
+            return None;
+        }
+
+        let mut calibration = self.gyro.finish(accel_range, gyro_range);
+        let one_g = accel_range.sensitivity() as i32;
+        for axis in 0..3 {
+            let up = self.sums[axis * 2] / self.counts[axis * 2];
+            let down = self.sums[axis * 2 + 1] / self.counts[axis * 2 + 1];
+            let span = up - down;
+            if span <= 0 {
+                return None;
+            }
+            calibration.accel_offset[axis] = ((up + down) / 2) as i16;
+            let gain = 2 * one_g * ACCEL_GAIN_ONE as i32 / span;
+            calibration.accel_gain[axis] = gain.clamp(0, u16::MAX as i32) as u16;
+        }
+        Some(calibration)
+    }
+}
@@ -35,3 +35,67 @@ pub const MPU6050_PWR_MGMT_1: u8 = 0x6B;
 pub const MPU6050_PWR_MGMT_2: u8 = 0x6C;
 // IIC地址寄存器(默认数值0x68，只读)
 pub const MPU6050_WHO_AM_I: u8 = 0x75;
+
+// FIFO 使能，选择写入 FIFO 的数据
+pub const MPU6050_FIFO_EN: u8 = 0x23;
+// INT 引脚配置
+pub const MPU6050_INT_PIN_CFG: u8 = 0x37;
+// 中断使能
+pub const MPU6050_INT_ENABLE: u8 = 0x38;
+// 中断状态，读取后清除
+pub const MPU6050_INT_STATUS: u8 = 0x3A;
+// 用户控制，FIFO 使能及复位
+pub const MPU6050_USER_CTRL: u8 = 0x6A;
+// FIFO 中的字节数
+pub const MPU6050_FIFO_COUNTH: u8 = 0x72;
+pub const MPU6050_FIFO_COUNTL: u8 = 0x73;
+// FIFO 读写
+pub const MPU6050_FIFO_R_W: u8 = 0x74;
+
+// FIFO_EN: 温度、陀螺仪X/Y/Z轴、加速度
+pub const MPU6050_FIFO_EN_TEMP: u8 = 0x80;
+pub const MPU6050_FIFO_EN_XG: u8 = 0x40;
+pub const MPU6050_FIFO_EN_YG: u8 = 0x20;
+pub const MPU6050_FIFO_EN_ZG: u8 = 0x10;
+pub const MPU6050_FIFO_EN_ACCEL: u8 = 0x08;
+
+// INT_PIN_CFG: 低电平有效、开漏输出、锁存、任意读取清除中断
+pub const MPU6050_INT_LEVEL_LOW: u8 = 0x80;
+pub const MPU6050_INT_OPEN_DRAIN: u8 = 0x40;
+pub const MPU6050_INT_LATCH: u8 = 0x20;
+pub const MPU6050_INT_RD_CLEAR: u8 = 0x10;
+
+// INT_ENABLE/INT_STATUS: FIFO 溢出、数据就绪
+pub const MPU6050_INT_FIFO_OVERFLOW: u8 = 0x10;
+pub const MPU6050_INT_DATA_READY: u8 = 0x01;
+
+// USER_CTRL: FIFO 使能、FIFO 复位
+pub const MPU6050_USER_CTRL_FIFO_EN: u8 = 0x40;
+pub const MPU6050_USER_CTRL_FIFO_RESET: u8 = 0x04;
+
+// FIFO 大小(字节)
+pub const MPU6050_FIFO_SIZE: usize = 1024;
+// 突发读取的数据长度：加速度、温度、角速度共14字节
+pub const MPU6050_DATA_SIZE: usize = 14;
+
+// 自由落体检测阈值及持续时间(1ms/LSB)
+pub const MPU6050_FF_THR: u8 = 0x1D;
+pub const MPU6050_FF_DUR: u8 = 0x1E;
+// 运动检测阈值及持续时间(1ms/LSB)
+pub const MPU6050_MOT_THR: u8 = 0x1F;
+pub const MPU6050_MOT_DUR: u8 = 0x20;
+// 静止检测阈值及持续时间(64ms/LSB)
+pub const MPU6050_ZRMOT_THR: u8 = 0x21;
+pub const MPU6050_ZRMOT_DUR: u8 = 0x22;
+// 运动检测状态，读取后清除
+pub const MPU6050_MOT_DETECT_STATUS: u8 = 0x61;
+// 运动检测控制，加速度计上电延时及计数器递减速率
+pub const MPU6050_MOT_DETECT_CTRL: u8 = 0x69;
+
+// INT_ENABLE/INT_STATUS: 自由落体、运动、静止
+pub const MPU6050_INT_FREE_FALL: u8 = 0x80;
+pub const MPU6050_INT_MOTION: u8 = 0x40;
+pub const MPU6050_INT_ZERO_MOTION: u8 = 0x20;
+
+// ACCEL_CONFIG: 数字高通滤波器位
+pub const MPU6050_ACCEL_HPF_MASK: u8 = 0x07;
@@ -0,0 +1,345 @@
+//! 公共驱动
+//! 配置、校准、中断、FIFO 及运动检测只依赖寄存器读写，在这里实现一次，
+//! HAL 版本和寄存器版本只需实现 `Registers`
+//! 寄存器读写失败时返回 `Error::Bus`，不会 panic
+
+use super::calibration::{Calibration, Calibrator};
+use super::conf::*;
+use super::{
+    AccelGyroData, AccelHpf, AccelRange, ClockSource, Config, DlpfBandwidth, Error,
+    FreeFallDetection, GyroRange, IntPinConfig, InterruptStatus, Measurement, MotionDetection,
+    MotionEvent, MotionStatus, ZeroMotionDetection,
+};
+use crate::flash_store::FlashStore;
+
+use embedded_hal::blocking::delay::DelayMs;
+
+/// MPU6050 寄存器读写
+pub trait Registers {
+    /// 总线错误
+    type Error;
+
+    /// 写寄存器
+    fn write_reg(&mut self, reg_address: u8, data: u8) -> Result<(), Self::Error>;
+
+    /// 连续读取多个寄存器
+    /// 寄存器地址自动递增，FIFO_R_W 除外
+    fn read_regs(&mut self, reg_address: u8, buffer: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+/// MPU6050 芯片
+pub struct Mpu6050<R> {
+    regs: R,
+    accel_range: AccelRange,
+    gyro_range: GyroRange,
+    calibration: Option<Calibration>,
+}
+
+impl<R> Mpu6050<R>
+where
+    R: Registers,
+{
+    /// 由寄存器读写接口创建，不写入任何配置
+    /// 量程按默认配置记录，需要调用 `configure` 使两者一致
+    pub fn from_registers(regs: R) -> Self {
+        let config = Config::default();
+        Mpu6050 {
+            regs,
+            accel_range: config.accel_range,
+            gyro_range: config.gyro_range,
+            calibration: None,
+        }
+    }
+
+    /// 寄存器读写接口
+    pub fn registers(&mut self) -> &mut R {
+        &mut self.regs
+    }
+
+    /// 释放寄存器读写接口
+    pub fn release(self) -> R {
+        self.regs
+    }
+
+    /// 写寄存器
+    pub fn write_reg(&mut self, reg_address: u8, data: u8) -> Result<(), Error<R::Error>> {
+        self.regs.write_reg(reg_address, data).map_err(Error::Bus)
+    }
+
+    /// 读寄存器
+    pub fn read_reg(&mut self, reg_address: u8) -> Result<u8, Error<R::Error>> {
+        let mut buffer = [0];
+        self.read_regs(reg_address, &mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    /// 连续读取多个寄存器
+    /// 寄存器地址自动递增，FIFO_R_W 除外
+    pub fn read_regs(&mut self, reg_address: u8, buffer: &mut [u8]) -> Result<(), Error<R::Error>> {
+        self.regs.read_regs(reg_address, buffer).map_err(Error::Bus)
+    }
+
+    /// 写入全部配置
+    pub fn configure(&mut self, config: &Config) -> Result<(), Error<R::Error>> {
+        // 解除休眠状态并选择时钟源
+        self.set_clock_source(config.clock_source)?;
+        self.write_reg(MPU6050_PWR_MGMT_2, 0x00)?;
+        self.set_sample_rate_divider(config.sample_rate_divider)?;
+        self.set_dlpf(config.dlpf)?;
+        self.set_gyro_range(config.gyro_range)?;
+        self.set_accel_range(config.accel_range)
+    }
+
+    /// 选择时钟源，同时解除休眠状态
+    pub fn set_clock_source(&mut self, source: ClockSource) -> Result<(), Error<R::Error>> {
+        self.write_reg(MPU6050_PWR_MGMT_1, source as u8)
+    }
+
+    /// 设置采样率分频
+    pub fn set_sample_rate_divider(&mut self, divider: u8) -> Result<(), Error<R::Error>> {
+        self.write_reg(MPU6050_SMPLRT_DIV, divider)
+    }
+
+    /// 设置低通滤波带宽
+    pub fn set_dlpf(&mut self, bandwidth: DlpfBandwidth) -> Result<(), Error<R::Error>> {
+        self.write_reg(MPU6050_CONFIG, bandwidth as u8)
+    }
+
+    /// 设置陀螺仪量程，不自检
+    pub fn set_gyro_range(&mut self, range: GyroRange) -> Result<(), Error<R::Error>> {
+        self.write_reg(MPU6050_GYRO_CONFIG, range.bits())?;
+        self.gyro_range = range;
+        self.set_calibration(self.calibration);
+        Ok(())
+    }
+
+    /// 设置加速度计量程，不自检
+    pub fn set_accel_range(&mut self, range: AccelRange) -> Result<(), Error<R::Error>> {
+        // 保留高通滤波器的设置
+        let hpf = self.read_reg(MPU6050_ACCEL_CONFIG)? & MPU6050_ACCEL_HPF_MASK;
+        self.write_reg(MPU6050_ACCEL_CONFIG, range.bits() | hpf)?;
+        self.accel_range = range;
+        self.set_calibration(self.calibration);
+        Ok(())
+    }
+
+    /// 当前加速度计量程
+    pub fn accel_range(&self) -> AccelRange {
+        self.accel_range
+    }
+
+    /// 当前陀螺仪量程
+    pub fn gyro_range(&self) -> GyroRange {
+        self.gyro_range
+    }
+
+    /// 获取 MPU6050 ID
+    pub fn get_id(&mut self) -> Result<u8, Error<R::Error>> {
+        self.read_reg(MPU6050_WHO_AM_I)
+    }
+
+    /// 获取 MPU6050 原始数据
+    /// 读取加速度、温度和角速度数据，不做校准修正
+    pub fn get_raw_data(&mut self) -> Result<AccelGyroData, Error<R::Error>> {
+        // 创建一个缓冲区用于存储数据
+        let mut buffer = [0; MPU6050_DATA_SIZE];
+
+        // 从mpu6050中读取14个字节的数据，包括加速度、温度和角速度
+        self.read_regs(MPU6050_ACCEL_XOUT_H, &mut buffer)?;
+
+        // 将数据转换为有符号的16位整数
+        Ok(AccelGyroData::from_be_bytes(&buffer))
+    }
+
+    /// 获取经过校准修正的数据
+    pub fn get_data(&mut self) -> Result<AccelGyroData, Error<R::Error>> {
+        let mut data = self.get_raw_data()?;
+        if let Some(calibration) = &self.calibration {
+            calibration.apply(&mut data);
+        }
+        Ok(data)
+    }
+
+    /// 当前的校准数据
+    pub fn calibration(&self) -> Option<Calibration> {
+        self.calibration
+    }
+
+    /// 是否已加载校准数据
+    pub fn is_calibrated(&self) -> bool {
+        self.calibration.is_some()
+    }
+
+    /// 设置校准数据，自动换算到当前量程
+    pub fn set_calibration(&mut self, calibration: Option<Calibration>) {
+        self.calibration = calibration.map(|c| c.rescale(self.accel_range, self.gyro_range));
+    }
+
+    /// 从参数存储中加载校准数据
+    /// 返回是否存在有效的校准数据
+    pub fn load_calibration(&mut self, store: &FlashStore) -> bool {
+        self.set_calibration(Calibration::load(store));
+        self.is_calibrated()
+    }
+
+    /// 保存当前的校准数据
+    pub fn save_calibration(&self, store: &FlashStore) {
+        if let Some(calibration) = self.calibration {
+            calibration.save(store);
+        }
+    }
+
+    /// 静止校准
+    /// 芯片需水平放置、Z轴朝上并保持静止，每隔5ms采样一次
+    /// samples: 求平均的样本数
+    pub fn calibrate<D: DelayMs<u32>>(
+        &mut self,
+        samples: usize,
+        delay: &mut D,
+    ) -> Result<Calibration, Error<R::Error>> {
+        let mut calibrator = Calibrator::new();
+        for _ in 0..samples {
+            calibrator.add_sample(&self.get_raw_data()?);
+            delay.delay_ms(5);
+        }
+        let calibration = calibrator.finish(self.accel_range, self.gyro_range);
+        self.calibration = Some(calibration);
+        Ok(calibration)
+    }
+
+    /// 配置 INT 引脚
+    pub fn set_int_pin_config(&mut self, config: &IntPinConfig) -> Result<(), Error<R::Error>> {
+        self.write_reg(MPU6050_INT_PIN_CFG, config.bits())
+    }
+
+    /// 使能中断
+    /// interrupts: `MPU6050_INT_*` 的组合，为0时关闭全部中断
+    pub fn enable_interrupts(&mut self, interrupts: u8) -> Result<(), Error<R::Error>> {
+        self.write_reg(MPU6050_INT_ENABLE, interrupts)
+    }
+
+    /// 读取中断状态，同时清除中断
+    pub fn read_interrupt_status(&mut self) -> Result<InterruptStatus, Error<R::Error>> {
+        Ok(InterruptStatus(self.read_reg(MPU6050_INT_STATUS)?))
+    }
+
+    /// 使能 FIFO，每次采样写入加速度、温度和角速度共14字节
+    pub fn enable_fifo(&mut self) -> Result<(), Error<R::Error>> {
+        self.write_reg(
+            MPU6050_FIFO_EN,
+            MPU6050_FIFO_EN_ACCEL
+                | MPU6050_FIFO_EN_TEMP
+                | MPU6050_FIFO_EN_XG
+                | MPU6050_FIFO_EN_YG
+                | MPU6050_FIFO_EN_ZG,
+        )?;
+        self.reset_fifo()
+    }
+
+    /// 关闭 FIFO
+    pub fn disable_fifo(&mut self) -> Result<(), Error<R::Error>> {
+        self.write_reg(MPU6050_FIFO_EN, 0x00)?;
+        let value = self.read_reg(MPU6050_USER_CTRL)? & !MPU6050_USER_CTRL_FIFO_EN;
+        self.write_reg(MPU6050_USER_CTRL, value)
+    }
+
+    /// 复位 FIFO，清空其中的数据
+    pub fn reset_fifo(&mut self) -> Result<(), Error<R::Error>> {
+        // 复位前需要先关闭 FIFO，USER_CTRL 的其他位保持不变
+        let value = self.read_reg(MPU6050_USER_CTRL)?
+            & !(MPU6050_USER_CTRL_FIFO_EN | MPU6050_USER_CTRL_FIFO_RESET);
+        self.write_reg(MPU6050_USER_CTRL, value)?;
+        self.write_reg(MPU6050_USER_CTRL, value | MPU6050_USER_CTRL_FIFO_RESET)?;
+        self.write_reg(MPU6050_USER_CTRL, value | MPU6050_USER_CTRL_FIFO_EN)
+    }
+
+    /// FIFO 中的字节数
+    pub fn fifo_count(&mut self) -> Result<usize, Error<R::Error>> {
+        let mut buffer = [0; 2];
+        self.read_regs(MPU6050_FIFO_COUNTH, &mut buffer)?;
+        Ok(u16::from_be_bytes(buffer) as usize)
+    }
+
+    /// 读取 FIFO 中的样本，经过校准修正
+    /// 返回读取的样本数，FIFO 溢出时复位 FIFO 并返回 `Error::FifoOverflow`
+    /// 通过 INT_STATUS 检查溢出，读取后中断状态被清除
+    pub fn read_fifo(&mut self, samples: &mut [AccelGyroData]) -> Result<usize, Error<R::Error>> {
+        let status = self.read_interrupt_status()?;
+        let count = self.fifo_count()?;
+        // FIFO 写满后新数据覆盖旧数据，FIFO 中的样本不再连续，字节数停留在1024
+        // 溢出标志在字节数读出之前就可能已经置位，两者都要检查
+        if status.fifo_overflow() || count >= MPU6050_FIFO_SIZE {
+            self.reset_fifo()?;
+            return Err(Error::FifoOverflow);
+        }
+
+        let count = (count / MPU6050_DATA_SIZE).min(samples.len());
+        let mut buffer = [0; MPU6050_DATA_SIZE];
+        for sample in samples[..count].iter_mut() {
+            // FIFO_R_W 的地址不会自动递增，连续读取即依次取出 FIFO 中的数据
+            self.read_regs(MPU6050_FIFO_R_W, &mut buffer)?;
+            *sample = AccelGyroData::from_be_bytes(&buffer);
+            if let Some(calibration) = &self.calibration {
+                calibration.apply(sample);
+            }
+        }
+        Ok(count)
+    }
+
+    /// 设置加速度计高通滤波器
+    pub fn set_accel_hpf(&mut self, hpf: AccelHpf) -> Result<(), Error<R::Error>> {
+        let value = self.read_reg(MPU6050_ACCEL_CONFIG)? & !MPU6050_ACCEL_HPF_MASK;
+        self.write_reg(MPU6050_ACCEL_CONFIG, value | hpf as u8)
+    }
+
+    /// 配置运动检测
+    /// 还需通过 `enable_interrupts` 使能 `MPU6050_INT_MOTION`
+    pub fn set_motion_detection(
+        &mut self,
+        detection: &MotionDetection,
+    ) -> Result<(), Error<R::Error>> {
+        self.write_reg(MPU6050_MOT_THR, detection.threshold)?;
+        self.write_reg(MPU6050_MOT_DUR, detection.duration_ms)?;
+        // 加速度计上电延时1ms，检测计数器每次减1
+        self.write_reg(MPU6050_MOT_DETECT_CTRL, 0x15)
+    }
+
+    /// 配置静止检测
+    /// 还需通过 `enable_interrupts` 使能 `MPU6050_INT_ZERO_MOTION`
+    pub fn set_zero_motion_detection(
+        &mut self,
+        detection: &ZeroMotionDetection,
+    ) -> Result<(), Error<R::Error>> {
+        self.write_reg(MPU6050_ZRMOT_THR, detection.threshold)?;
+        self.write_reg(MPU6050_ZRMOT_DUR, detection.duration)
+    }
+
+    /// 配置自由落体检测
+    /// 还需通过 `enable_interrupts` 使能 `MPU6050_INT_FREE_FALL`
+    pub fn set_free_fall_detection(
+        &mut self,
+        detection: &FreeFallDetection,
+    ) -> Result<(), Error<R::Error>> {
+        self.write_reg(MPU6050_FF_THR, detection.threshold)?;
+        self.write_reg(MPU6050_FF_DUR, detection.duration_ms)?;
+        self.write_reg(MPU6050_MOT_DETECT_CTRL, 0x15)
+    }
+
+    /// 读取运动检测状态，同时清除状态
+    pub fn read_motion_status(&mut self) -> Result<MotionStatus, Error<R::Error>> {
+        Ok(MotionStatus(self.read_reg(MPU6050_MOT_DETECT_STATUS)?))
+    }
+
+    /// 读取并清除中断状态，解析触发的运动检测事件
+    pub fn read_motion_event(&mut self) -> Result<Option<MotionEvent>, Error<R::Error>> {
+        let status = self.read_interrupt_status()?;
+        let motion = self.read_motion_status()?;
+        Ok(status.event(motion))
+    }
+
+    /// 读取数据并转换为物理单位
+    pub fn get_measurement(&mut self) -> Result<Measurement, Error<R::Error>> {
+        let data = self.get_data()?;
+        Ok(data.to_measurement(self.accel_range, self.gyro_range))
+    }
+}
@@ -1,12 +1,23 @@
 //! 软件I2C读写MPU6050
 //! MPU6050 是一个6轴姿态传感器，可以测量芯片自身X、Y、Z轴的加速度、角速度参数，
 //! 通过数据融合，可进一步得到姿态角，常应用于平衡车、飞行器等需要检测自身姿态的场景。
+pub mod calibration;
 pub mod conf;
+pub mod driver;
 pub mod mpu6050_hal;
+pub mod mpu6050_i2c;
 pub mod mpu6050_reg;
+pub mod sampler;
+
+pub use portable::imu::Measurement;
+
+use conf::*;
+
+/// 标准重力加速度(m/s²)
+pub const GRAVITY: f32 = 9.80665;
 
 /// 加速度和角速度数据
-#[derive(Default)]
+#[derive(Debug, Default, Clone, Copy)]
 pub struct AccelGyroData {
     pub acc_x: i16,
     pub acc_y: i16,
@@ -14,4 +25,345 @@ pub struct AccelGyroData {
     pub gyro_x: i16,
     pub gyro_y: i16,
     pub gyro_z: i16,
+    /// 温度原始值
+    pub temp: i16,
+}
+
+impl AccelGyroData {
+    /// 由突发读取或 FIFO 中的14字节数据解析
+    /// 依次为加速度、温度、角速度，高字节在前
+    pub fn from_be_bytes(buffer: &[u8; MPU6050_DATA_SIZE]) -> Self {
+        let word = |i: usize| i16::from_be_bytes([buffer[i], buffer[i + 1]]);
+        AccelGyroData {
+            acc_x: word(0),
+            acc_y: word(2),
+            acc_z: word(4),
+            temp: word(6),
+            gyro_x: word(8),
+            gyro_y: word(10),
+            gyro_z: word(12),
+        }
+    }
+
+    /// 按量程转换为物理单位
+    pub fn to_measurement(&self, accel_range: AccelRange, gyro_range: GyroRange) -> Measurement {
+        let acc = |raw: i16| raw as f32 / accel_range.sensitivity() * GRAVITY;
+        let gyro = |raw: i16| raw as f32 / gyro_range.sensitivity();
+        Measurement {
+            acc_x: acc(self.acc_x),
+            acc_y: acc(self.acc_y),
+            acc_z: acc(self.acc_z),
+            gyro_x: gyro(self.gyro_x),
+            gyro_y: gyro(self.gyro_y),
+            gyro_z: gyro(self.gyro_z),
+            // 数据手册给出的换算公式
+            temp: self.temp as f32 / 340.0 + 36.53,
+        }
+    }
+}
+
+/// 加速度计量程
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccelRange {
+    /// ±2g
+    G2 = 0,
+    /// ±4g
+    G4 = 1,
+    /// ±8g
+    G8 = 2,
+    /// ±16g
+    G16 = 3,
+}
+
+impl AccelRange {
+    /// ACCEL_CONFIG 寄存器中的 AFS_SEL 位
+    pub fn bits(self) -> u8 {
+        (self as u8) << 3
+    }
+
+    /// 灵敏度(LSB/g)
+    pub fn sensitivity(self) -> f32 {
+        match self {
+            AccelRange::G2 => 16384.0,
+            AccelRange::G4 => 8192.0,
+            AccelRange::G8 => 4096.0,
+            AccelRange::G16 => 2048.0,
+        }
+    }
+}
+
+/// 陀螺仪量程
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GyroRange {
+    /// ±250°/s
+    Deg250 = 0,
+    /// ±500°/s
+    Deg500 = 1,
+    /// ±1000°/s
+    Deg1000 = 2,
+    /// ±2000°/s
+    Deg2000 = 3,
+}
+
+impl GyroRange {
+    /// GYRO_CONFIG 寄存器中的 FS_SEL 位
+    pub fn bits(self) -> u8 {
+        (self as u8) << 3
+    }
+
+    /// 灵敏度(LSB/(°/s))
+    pub fn sensitivity(self) -> f32 {
+        match self {
+            GyroRange::Deg250 => 131.0,
+            GyroRange::Deg500 => 65.5,
+            GyroRange::Deg1000 => 32.8,
+            GyroRange::Deg2000 => 16.4,
+        }
+    }
+}
+
+/// 数字低通滤波器带宽(加速度计)
+/// 带宽越低噪声越小，延迟越大
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DlpfBandwidth {
+    /// 260Hz，关闭滤波，陀螺仪输出频率为8kHz
+    Hz260 = 0,
+    Hz184 = 1,
+    Hz94 = 2,
+    Hz44 = 3,
+    Hz21 = 4,
+    Hz10 = 5,
+    Hz5 = 6,
+}
+
+/// 时钟源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockSource {
+    /// 内部8MHz振荡器
+    Internal = 0,
+    /// X轴陀螺仪 PLL，比内部振荡器更稳定
+    PllGyroX = 1,
+    PllGyroY = 2,
+    PllGyroZ = 3,
+    /// 外部32.768kHz参考
+    PllExternal32k = 4,
+    /// 外部19.2MHz参考
+    PllExternal19m = 5,
+    /// 停止时钟
+    Stop = 7,
+}
+
+/// MPU6050 配置
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub clock_source: ClockSource,
+    pub accel_range: AccelRange,
+    pub gyro_range: GyroRange,
+    pub dlpf: DlpfBandwidth,
+    /// 采样率分频，采样率 = 陀螺仪输出频率 / (1 + 分频)
+    pub sample_rate_divider: u8,
+}
+
+impl Default for Config {
+    /// 陀螺仪X轴时钟，±16g，±2000°/s，5Hz低通滤波，100Hz采样率
+    fn default() -> Self {
+        Config {
+            clock_source: ClockSource::PllGyroX,
+            accel_range: AccelRange::G16,
+            gyro_range: GyroRange::Deg2000,
+            dlpf: DlpfBandwidth::Hz5,
+            sample_rate_divider: 9,
+        }
+    }
+}
+
+impl Config {
+    /// 采样率(Hz)
+    pub fn sample_rate_hz(&self) -> u32 {
+        let gyro_output_rate = match self.dlpf {
+            DlpfBandwidth::Hz260 => 8000,
+            _ => 1000,
+        };
+        gyro_output_rate / (1 + self.sample_rate_divider as u32)
+    }
+}
+
+/// INT 引脚配置
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IntPinConfig {
+    /// 低电平有效，默认高电平有效
+    pub active_low: bool,
+    /// 开漏输出，默认推挽输出
+    pub open_drain: bool,
+    /// 保持中断电平直到清除，默认输出50us脉冲
+    pub latch: bool,
+    /// 读取任意寄存器即清除中断，默认只有读取 INT_STATUS 才清除
+    pub clear_on_any_read: bool,
+}
+
+impl IntPinConfig {
+    /// INT_PIN_CFG 寄存器的值
+    pub fn bits(&self) -> u8 {
+        let mut bits = 0;
+        if self.active_low {
+            bits |= MPU6050_INT_LEVEL_LOW;
+        }
+        if self.open_drain {
+            bits |= MPU6050_INT_OPEN_DRAIN;
+        }
+        if self.latch {
+            bits |= MPU6050_INT_LATCH;
+        }
+        if self.clear_on_any_read {
+            bits |= MPU6050_INT_RD_CLEAR;
+        }
+        bits
+    }
+}
+
+/// 中断状态
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InterruptStatus(pub u8);
+
+impl InterruptStatus {
+    /// 新数据就绪
+    pub fn data_ready(&self) -> bool {
+        self.0 & MPU6050_INT_DATA_READY != 0
+    }
+
+    /// FIFO 溢出
+    pub fn fifo_overflow(&self) -> bool {
+        self.0 & MPU6050_INT_FIFO_OVERFLOW != 0
+    }
+
+    /// 检测到运动
+    pub fn motion(&self) -> bool {
+        self.0 & MPU6050_INT_MOTION != 0
+    }
+
+    /// 进入或退出静止状态
+    pub fn zero_motion(&self) -> bool {
+        self.0 & MPU6050_INT_ZERO_MOTION != 0
+    }
+
+    /// 检测到自由落体
+    pub fn free_fall(&self) -> bool {
+        self.0 & MPU6050_INT_FREE_FALL != 0
+    }
+
+    /// 结合运动检测状态解析触发的事件
+    /// 多个事件同时触发时，依次以自由落体、运动、静止为准
+    pub fn event(&self, motion: MotionStatus) -> Option<MotionEvent> {
+        if self.free_fall() {
+            Some(MotionEvent::FreeFall)
+        } else if self.motion() {
+            Some(MotionEvent::Motion(motion))
+        } else if self.zero_motion() {
+            Some(MotionEvent::ZeroMotion {
+                still: motion.zero_motion(),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// 运动检测状态，指示触发运动中断的轴和方向
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MotionStatus(pub u8);
+
+impl MotionStatus {
+    pub fn x_negative(&self) -> bool {
+        self.0 & 0x80 != 0
+    }
+
+    pub fn x_positive(&self) -> bool {
+        self.0 & 0x40 != 0
+    }
+
+    pub fn y_negative(&self) -> bool {
+        self.0 & 0x20 != 0
+    }
+
+    pub fn y_positive(&self) -> bool {
+        self.0 & 0x10 != 0
+    }
+
+    pub fn z_negative(&self) -> bool {
+        self.0 & 0x08 != 0
+    }
+
+    pub fn z_positive(&self) -> bool {
+        self.0 & 0x04 != 0
+    }
+
+    /// 为1表示进入静止状态，为0表示离开静止状态
+    pub fn zero_motion(&self) -> bool {
+        self.0 & 0x01 != 0
+    }
+}
+
+/// 运动检测事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionEvent {
+    /// 被拿起或晃动
+    Motion(MotionStatus),
+    /// 进入(still 为 true)或离开静止状态
+    ZeroMotion { still: bool },
+    /// 跌落
+    FreeFall,
+}
+
+/// 加速度计数字高通滤波器
+/// 运动和静止检测使用高通滤波后的加速度，自由落体检测不受影响
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccelHpf {
+    /// 关闭滤波
+    Reset = 0,
+    Hz5 = 1,
+    Hz2_5 = 2,
+    Hz1_25 = 3,
+    Hz0_63 = 4,
+    /// 保持当前值，之后输出与该值的差
+    Hold = 7,
+}
+
+/// 运动检测
+/// 任一轴高通滤波后的加速度超过阈值并持续指定时间时触发
+#[derive(Debug, Clone, Copy)]
+pub struct MotionDetection {
+    /// 阈值，约 2mg/LSB
+    pub threshold: u8,
+    /// 持续时间(ms)
+    pub duration_ms: u8,
+}
+
+/// 静止检测
+/// 各轴高通滤波后的加速度均低于阈值并持续指定时间时触发，离开静止状态时再次触发
+#[derive(Debug, Clone, Copy)]
+pub struct ZeroMotionDetection {
+    /// 阈值，约 2mg/LSB
+    pub threshold: u8,
+    /// 持续时间，64ms/LSB
+    pub duration: u8,
+}
+
+/// 自由落体检测
+/// 三个轴的加速度均低于阈值并持续指定时间时触发
+#[derive(Debug, Clone, Copy)]
+pub struct FreeFallDetection {
+    /// 阈值，约 2mg/LSB
+    pub threshold: u8,
+    /// 持续时间(ms)
+    pub duration_ms: u8,
+}
+
+/// 驱动错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// 寄存器读写失败，如 I2C 无应答
+    Bus(E),
+    /// FIFO 溢出，最早的数据已被覆盖
+    /// 溢出后 FIFO 中的数据不再按样本对齐，驱动会复位 FIFO
+    FifoOverflow,
 }
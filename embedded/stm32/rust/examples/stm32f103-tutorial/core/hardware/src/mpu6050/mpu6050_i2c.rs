@@ -0,0 +1,79 @@
+//! embedded-hal 1.0 I2C 版本实现
+//! 可以使用任意实现了 `I2c` 接口的总线，如软件 I2C、共享总线的设备句柄，
+//! stm32f1xx-hal 的硬件 I2C 需要用 `bus::CompatI2c` 包装
+//!
+//! ```rust
+//! let bus = RefCell::new(CompatI2c(i2c2));
+//! let mut mpu = mpu6050_i2c::new(I2cDevice::new(&bus)).unwrap();
+//! let id = mpu.get_id().unwrap();
+//! ```
+
+use super::conf::*;
+use super::driver::{self, Registers};
+use super::{Config, Error};
+use crate::flash_store::FlashStore;
+
+use embedded_hal_1::i2c::I2c;
+
+/// I2C 总线上的 MPU6050
+pub type Mpu6050<I2C> = driver::Mpu6050<I2cRegisters<I2C>>;
+
+/// 通过 embedded-hal 1.0 的 I2C 读写寄存器
+pub struct I2cRegisters<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C> I2cRegisters<I2C> {
+    pub fn new(i2c: I2C) -> Self {
+        I2cRegisters { i2c }
+    }
+
+    /// 释放 I2C 总线
+    pub fn free(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl<I2C> Registers for I2cRegisters<I2C>
+where
+    I2C: I2c,
+{
+    type Error = I2C::Error;
+
+    fn write_reg(&mut self, reg_address: u8, data: u8) -> Result<(), Self::Error> {
+        self.i2c.write(DEFAULT_SLAVE_ADDR, &[reg_address, data])
+    }
+
+    fn read_regs(&mut self, reg_address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.i2c
+            .write_read(DEFAULT_SLAVE_ADDR, &[reg_address], buffer)
+    }
+}
+
+/// 初始化 MPU6050
+/// 使用默认配置唤醒传感器
+pub fn new<I2C: I2c>(i2c: I2C) -> Result<Mpu6050<I2C>, Error<I2C::Error>> {
+    new_with_config(i2c, &Config::default())
+}
+
+/// 按指定配置初始化 MPU6050
+pub fn new_with_config<I2C: I2c>(
+    i2c: I2C,
+    config: &Config,
+) -> Result<Mpu6050<I2C>, Error<I2C::Error>> {
+    let mut mpu = Mpu6050::from_registers(I2cRegisters::new(i2c));
+    mpu.configure(config)?;
+    Ok(mpu)
+}
+
+/// 按指定配置初始化 MPU6050，并从参数存储中加载校准数据
+/// 需要先调用 `FlashStore::init_store`
+pub fn new_with_store<I2C: I2c>(
+    i2c: I2C,
+    config: &Config,
+    store: &FlashStore,
+) -> Result<Mpu6050<I2C>, Error<I2C::Error>> {
+    let mut mpu = new_with_config(i2c, config)?;
+    mpu.load_calibration(store);
+    Ok(mpu)
+}
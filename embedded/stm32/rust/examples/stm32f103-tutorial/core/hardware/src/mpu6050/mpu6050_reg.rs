@@ -2,11 +2,17 @@
 #![allow(unused)]
 
 use super::conf::*;
-pub use super::AccelGyroData;
+use super::driver::{self, Registers};
+pub use super::{
+    AccelGyroData, AccelHpf, AccelRange, ClockSource, Config, DlpfBandwidth, Error,
+    FreeFallDetection, GyroRange, IntPinConfig, InterruptStatus, Measurement, MotionDetection,
+    MotionEvent, MotionStatus, ZeroMotionDetection,
+};
+use crate::flash_store::FlashStore;
 
 use embedded_hal::{
     digital::v2::{InputPin, OutputPin, StatefulOutputPin},
-    prelude::_embedded_hal_blocking_delay_DelayUs,
+    prelude::{_embedded_hal_blocking_delay_DelayMs, _embedded_hal_blocking_delay_DelayUs},
 };
 use stm32f1xx_hal::{
     gpio::{self, OutputSpeed},
@@ -15,8 +21,15 @@ use stm32f1xx_hal::{
 
 const DEFAULT_SLAVE_ADDR: u8 = 0xD0;
 
-/// MPU6050 芯片
-pub struct Mpu6050<'a, Scl, Sda>
+/// 从机没有应答
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Nack;
+
+/// 软件 I2C 上的 MPU6050
+pub type Mpu6050<'a, Scl, Sda> = driver::Mpu6050<SoftRegisters<'a, Scl, Sda>>;
+
+/// 通过软件 I2C 读写寄存器
+pub struct SoftRegisters<'a, Scl, Sda>
 where
     Scl: OutputPin,
     <Scl as OutputPin>::Error: core::fmt::Debug,
@@ -28,7 +41,7 @@ where
     delay: &'a mut SysDelay,
 }
 
-impl<'a, Scl, Sda> Mpu6050<'a, Scl, Sda>
+impl<'a, Scl, Sda> SoftRegisters<'a, Scl, Sda>
 where
     Scl: OutputPin,
     <Scl as OutputPin>::Error: core::fmt::Debug,
@@ -37,7 +50,7 @@ where
     <Sda as OutputPin>::Error: core::fmt::Debug,
 {
     pub fn new(scl: &'a mut Scl, sda: &'a mut Sda, delay: &'a mut SysDelay) -> Self {
-        Mpu6050 { scl, sda, delay }
+        SoftRegisters { scl, sda, delay }
     }
 
     fn i2c_w_scl(&mut self, bit_value: u8) {
@@ -123,6 +136,16 @@ where
         ack_bit
     }
 
+    /// 发送八位数据并检查应答，无应答时发送终止信号并返回错误
+    fn i2c_send_byte_acked(&mut self, byte: u8) -> Result<(), Nack> {
+        self.i2c_send_byte(byte);
+        if self.i2c_receive_ack() != 0 {
+            self.i2c_stop();
+            return Err(Nack);
+        }
+        Ok(())
+    }
+
     /// I2C 初始化
     /// open-drain output pin 10,11
     pub fn init_i2c(&mut self) {
@@ -131,7 +154,7 @@ where
     }
 }
 
-impl<'a, Scl, Sda> Mpu6050<'a, Scl, Sda>
+impl<'a, Scl, Sda> Registers for SoftRegisters<'a, Scl, Sda>
 where
     Scl: OutputPin,
     <Scl as OutputPin>::Error: core::fmt::Debug,
@@ -139,55 +162,71 @@ where
     <Sda as InputPin>::Error: core::fmt::Debug,
     <Sda as OutputPin>::Error: core::fmt::Debug,
 {
+    type Error = Nack;
+
     /// MPU6050 写寄存器函数
     /// reg_address：寄存器地址
     /// data：待写入寄存器值
-    pub fn write_reg(&mut self, reg_address: u8, data: u8) {
+    fn write_reg(&mut self, reg_address: u8, data: u8) -> Result<(), Nack> {
         // 发送起始信号
         self.i2c_start();
 
         // 发送设备地址
-        self.i2c_send_byte(DEFAULT_SLAVE_ADDR);
-        self.i2c_receive_ack();
+        self.i2c_send_byte_acked(DEFAULT_SLAVE_ADDR)?;
 
         // 发送寄存器地址
-        self.i2c_send_byte(reg_address);
-        self.i2c_receive_ack();
+        self.i2c_send_byte_acked(reg_address)?;
 
         // 写数据到寄存器
-        self.i2c_send_byte(data);
-        self.i2c_receive_ack();
+        self.i2c_send_byte_acked(data)?;
 
         self.i2c_stop();
+        Ok(())
     }
 
-    /// 读取寄存器
-    pub fn read_reg(&mut self, reg_address: u8) -> i16 {
+    /// 连续读取多个寄存器
+    /// 寄存器地址自动递增，FIFO_R_W 除外
+    fn read_regs(&mut self, reg_address: u8, buffer: &mut [u8]) -> Result<(), Nack> {
+        if buffer.is_empty() {
+            return Ok(());
+        }
+
         // 发送起始信号
         self.i2c_start();
 
         // 发送设备地址
-        self.i2c_send_byte(DEFAULT_SLAVE_ADDR);
-        self.i2c_receive_ack();
+        self.i2c_send_byte_acked(DEFAULT_SLAVE_ADDR)?;
 
         // 发送寄存器地址
-        self.i2c_send_byte(reg_address);
-        self.i2c_receive_ack();
+        self.i2c_send_byte_acked(reg_address)?;
 
         // 发送重复起始信号
         self.i2c_start();
         // 发送读模式设备地址
-        self.i2c_send_byte(DEFAULT_SLAVE_ADDR | 0x01);
-        self.i2c_receive_ack();
+        self.i2c_send_byte_acked(DEFAULT_SLAVE_ADDR | 0x01)?;
 
-        // 读寄存器数据
-        let data = self.i2c_receive_byte();
-        // 非应答信号
-        self.i2c_send_ack(1);
+        // 除最后一个字节外发送应答，继续读取下一个寄存器
+        let last = buffer.len() - 1;
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = self.i2c_receive_byte();
+            self.i2c_send_ack(if i == last { 1 } else { 0 });
+        }
 
         self.i2c_stop();
+        Ok(())
+    }
+}
 
-        data as i16
+impl<'a, Scl, Sda> Mpu6050<'a, Scl, Sda>
+where
+    Scl: OutputPin,
+    <Scl as OutputPin>::Error: core::fmt::Debug,
+    Sda: InputPin + OutputPin,
+    <Sda as InputPin>::Error: core::fmt::Debug,
+    <Sda as OutputPin>::Error: core::fmt::Debug,
+{
+    pub fn new(scl: &'a mut Scl, sda: &'a mut Sda, delay: &'a mut SysDelay) -> Self {
+        Mpu6050::from_registers(SoftRegisters::new(scl, sda, delay))
     }
 
     /// MPU6050 初始化
@@ -198,57 +237,26 @@ where
     /// scl.set_speed(&mut gpiob.crh, gpio::IOPinSpeed::Mhz50);
     /// hardware::mpu6050::init_mpu6050(&mut scl, &mut sda);
     /// ```
-    pub fn init_mpu6050(&mut self) {
-        // I2C 初始化
-        self.init_i2c();
-
-        // 解除休眠状态
-        self.write_reg(MPU6050_PWR_MGMT_1, 0x01);
-        self.write_reg(MPU6050_PWR_MGMT_2, 0x00);
-        // 陀螺仪采样率，典型值：0x07(125Hz)
-        self.write_reg(MPU6050_SMPLRT_DIV, 0x09);
-        // 低通滤波频率，典型值：0x06(5Hz)
-        self.write_reg(MPU6050_CONFIG, 0x06);
-        // 陀螺仪自检及测量范围，典型值：0x18(不自检，2000deg/s)
-        self.write_reg(MPU6050_GYRO_CONFIG, 0x18);
-        // 加速计自检、测量范围及高通滤波频率，典型值：0x01(不自检，2G，5Hz)
-        self.write_reg(MPU6050_ACCEL_CONFIG, 0x18);
+    pub fn init_mpu6050(&mut self) -> Result<(), Error<Nack>> {
+        self.init_mpu6050_with_config(&Config::default())
     }
 
-    /// 获取 MPU6050 ID
-    pub fn get_id(&mut self) -> u8 {
-        self.read_reg(MPU6050_WHO_AM_I) as u8
+    /// 按指定配置初始化 MPU6050
+    pub fn init_mpu6050_with_config(&mut self, config: &Config) -> Result<(), Error<Nack>> {
+        // I2C 初始化
+        self.registers().init_i2c();
+        self.configure(config)
     }
 
-    /// 基本数据读取
-    /// 连续读两个寄存器并合成 16 位数据
-    pub fn get_data(&mut self) -> AccelGyroData {
-        let mut data = AccelGyroData::default();
-
-        let data_h = self.read_reg(MPU6050_ACCEL_XOUT_H);
-        let data_l = self.read_reg(MPU6050_ACCEL_XOUT_L);
-        data.acc_x = (data_h << 8) | data_l;
-
-        let data_h = self.read_reg(MPU6050_ACCEL_YOUT_H);
-        let data_l = self.read_reg(MPU6050_ACCEL_YOUT_L);
-        data.acc_y = (data_h << 8) | data_l;
-
-        let data_h = self.read_reg(MPU6050_ACCEL_ZOUT_H);
-        let data_l = self.read_reg(MPU6050_ACCEL_ZOUT_L);
-        data.acc_z = (data_h << 8) | data_l;
-
-        let data_h = self.read_reg(MPU6050_GYRO_XOUT_H);
-        let data_l = self.read_reg(MPU6050_GYRO_XOUT_L);
-        data.gyro_x = (data_h << 8) | data_l;
-
-        let data_h = self.read_reg(MPU6050_GYRO_YOUT_H);
-        let data_l = self.read_reg(MPU6050_GYRO_YOUT_L);
-        data.gyro_y = (data_h << 8) | data_l;
-
-        let data_h = self.read_reg(MPU6050_GYRO_ZOUT_H);
-        let data_l = self.read_reg(MPU6050_GYRO_ZOUT_L);
-        data.gyro_z = (data_h << 8) | data_l;
-
-        data
+    /// 按指定配置初始化 MPU6050，并从参数存储中加载校准数据
+    /// 需要先调用 `FlashStore::init_store`
+    pub fn init_mpu6050_with_store(
+        &mut self,
+        config: &Config,
+        store: &FlashStore,
+    ) -> Result<(), Error<Nack>> {
+        self.init_mpu6050_with_config(config)?;
+        self.load_calibration(store);
+        Ok(())
     }
 }
@@ -0,0 +1,133 @@
+//! 中断驱动的采样器
+//! 在 INT 引脚的外部中断中读取 FIFO，将带时间戳的样本写入环形缓冲区，主循环从中取出
+//! 时间戳以 `time::now()` 测得的时间为基准：每次读取 FIFO 之前调用 `sync` 重新对齐，
+//! FIFO 中最新的样本在此之前刚刚完成采样，之前的样本按采样周期依次向前推算，
+//! 读取期间新到达的样本按采样周期向后推算。每次中断都重新对齐，
+//! MPU6050 的采样时钟与系统时钟之间的偏差不会累积
+//!
+//! ```rust
+//! static mut QUEUE: Queue<Sample, 256> = Queue::new();
+//! let (producer, mut consumer) = unsafe { (*addr_of_mut!(QUEUE)).split() };
+//! let mut sampler = Sampler::new(producer, config.sample_rate_hz());
+//!
+//! // 外部中断中
+//! let now = time::now();
+//! sampler.sync(now, mpu.fifo_count().unwrap() / MPU6050_DATA_SIZE);
+//! let mut buffer = [AccelGyroData::default(); 8];
+//! match mpu.read_fifo(&mut buffer) {
+//!     Ok(count) => sampler.push_all(&buffer[..count]),
+//!     Err(Error::FifoOverflow) => sampler.on_overflow(now),
+//!     Err(Error::Bus(_)) => println!("i2c error"),
+//! }
+//!
+//! // 主循环中
+//! while let Some(sample) = consumer.dequeue() {}
+//! ```
+
+use super::AccelGyroData;
+use crate::time::{Duration, Instant};
+
+use heapless::spsc::Producer;
+
+/// 带时间戳的样本
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// 样本序号，FIFO 溢出丢失的样本也计入序号
+    pub index: u32,
+    /// 采样时间
+    pub timestamp: Instant,
+    pub data: AccelGyroData,
+}
+
+/// 采样器，运行在中断中，持有环形缓冲区的写入端
+pub struct Sampler<'q, const N: usize> {
+    producer: Producer<'q, Sample, N>,
+    period: Duration,
+    index: u32,
+    /// 下一个样本的采样时间
+    next: Instant,
+    /// 上一个样本的采样时间
+    last: Option<Instant>,
+    /// 缓冲区已满而丢弃的样本数
+    dropped: u32,
+    /// FIFO 溢出次数
+    overflows: u32,
+}
+
+impl<'q, const N: usize> Sampler<'q, N> {
+    /// sample_rate_hz: MPU6050 的采样率，见 `Config::sample_rate_hz`
+    pub fn new(producer: Producer<'q, Sample, N>, sample_rate_hz: u32) -> Self {
+        Sampler {
+            producer,
+            period: Duration::micros(1_000_000 / sample_rate_hz.max(1) as u64),
+            index: 0,
+            next: Instant::from_ticks(0),
+            last: None,
+            dropped: 0,
+            overflows: 0,
+        }
+    }
+
+    /// 写入一个样本
+    /// 返回 false 表示缓冲区已满，样本被丢弃
+    pub fn push(&mut self, data: AccelGyroData) -> bool {
+        let sample = Sample {
+            index: self.index,
+            timestamp: self.next,
+            data,
+        };
+        self.index = self.index.wrapping_add(1);
+        self.last = Some(self.next);
+        self.next += self.period;
+
+        if self.producer.enqueue(sample).is_err() {
+            self.dropped += 1;
+            return false;
+        }
+        true
+    }
+
+    /// 写入从 FIFO 中读取的多个样本
+    pub fn push_all(&mut self, samples: &[AccelGyroData]) {
+        for data in samples {
+            self.push(*data);
+        }
+    }
+
+    /// 以测量的时间对齐之后写入的样本
+    /// now: 读取 FIFO 之前测得的时间
+    /// pending: 此时 FIFO 中的样本数，其中最新的样本以 now 作为时间戳
+    pub fn sync(&mut self, now: Instant, pending: usize) {
+        let pending = pending.max(1) as u32;
+        self.next = now - self.period * (pending - 1);
+    }
+
+    /// FIFO 溢出，FIFO 已被复位
+    /// now: 读取 FIFO 之前测得的时间
+    ///
+    /// 溢出时 FIFO 中的样本不再连续，复位后全部丢弃。丢失的样本数按上一个样本到现在
+    /// 经过的采样周期数估算，序号随之推进；复位后的样本由下一次 `sync` 重新对齐时间戳
+    pub fn on_overflow(&mut self, now: Instant) {
+        self.overflows += 1;
+        if let Some(last) = self.last {
+            let lost = (now - last).ticks() / self.period.ticks();
+            self.index = self.index.wrapping_add(lost as u32);
+        }
+        self.last = Some(now);
+    }
+
+    /// 缓冲区已满而丢弃的样本数
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// FIFO 溢出次数
+    pub fn overflows(&self) -> u32 {
+        self.overflows
+    }
+
+    /// 采样周期
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
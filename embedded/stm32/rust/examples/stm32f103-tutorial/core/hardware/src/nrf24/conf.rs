@@ -0,0 +1,121 @@
+//! nRF24L01 指令和寄存器
+
+/// 读寄存器，低5位为寄存器地址
+pub const NRF24_R_REGISTER: u8 = 0x00;
+/// 写寄存器，低5位为寄存器地址，只能在待机或掉电模式下写入
+pub const NRF24_W_REGISTER: u8 = 0x20;
+/// 读取接收的数据
+pub const NRF24_R_RX_PAYLOAD: u8 = 0x61;
+/// 写入要发送的数据
+pub const NRF24_W_TX_PAYLOAD: u8 = 0xA0;
+/// 清空发送缓冲区
+pub const NRF24_FLUSH_TX: u8 = 0xE1;
+/// 清空接收缓冲区
+pub const NRF24_FLUSH_RX: u8 = 0xE2;
+/// 重新发送上一个数据包
+pub const NRF24_REUSE_TX_PL: u8 = 0xE3;
+/// 激活 FEATURE 等寄存器，只有 nRF24L01 需要，nRF24L01+ 忽略该指令
+pub const NRF24_ACTIVATE: u8 = 0x50;
+/// `NRF24_ACTIVATE` 指令的数据
+pub const NRF24_ACTIVATE_DATA: u8 = 0x73;
+/// 读取接收缓冲区中第一个数据包的长度
+pub const NRF24_R_RX_PL_WID: u8 = 0x60;
+/// 写入应答时附带的数据，低3位为通道号
+pub const NRF24_W_ACK_PAYLOAD: u8 = 0xA8;
+/// 写入要发送的数据，接收方不应答
+pub const NRF24_W_TX_PAYLOAD_NOACK: u8 = 0xB0;
+/// 空操作，用于读取状态寄存器
+pub const NRF24_NOP: u8 = 0xFF;
+
+/// 配置寄存器
+pub const NRF24_CONFIG: u8 = 0x00;
+/// 自动应答使能
+pub const NRF24_EN_AA: u8 = 0x01;
+/// 接收通道使能
+pub const NRF24_EN_RXADDR: u8 = 0x02;
+/// 地址宽度
+pub const NRF24_SETUP_AW: u8 = 0x03;
+/// 自动重发延时和次数
+pub const NRF24_SETUP_RETR: u8 = 0x04;
+/// 射频频道
+pub const NRF24_RF_CH: u8 = 0x05;
+/// 射频参数
+pub const NRF24_RF_SETUP: u8 = 0x06;
+/// 状态寄存器
+pub const NRF24_STATUS: u8 = 0x07;
+/// 发送统计
+pub const NRF24_OBSERVE_TX: u8 = 0x08;
+/// 接收功率检测
+pub const NRF24_RPD: u8 = 0x09;
+/// 接收通道0的地址，通道1~5的地址依次递增
+pub const NRF24_RX_ADDR_P0: u8 = 0x0A;
+/// 发送地址
+pub const NRF24_TX_ADDR: u8 = 0x10;
+/// 接收通道0的数据长度，通道1~5的依次递增
+pub const NRF24_RX_PW_P0: u8 = 0x11;
+/// 缓冲区状态
+pub const NRF24_FIFO_STATUS: u8 = 0x17;
+/// 动态数据长度使能
+pub const NRF24_DYNPD: u8 = 0x1C;
+/// 功能寄存器
+pub const NRF24_FEATURE: u8 = 0x1D;
+
+/// CONFIG: 屏蔽接收中断
+pub const NRF24_CONFIG_MASK_RX_DR: u8 = 1 << 6;
+/// CONFIG: 屏蔽发送完成中断
+pub const NRF24_CONFIG_MASK_TX_DS: u8 = 1 << 5;
+/// CONFIG: 屏蔽达到最大重发次数中断
+pub const NRF24_CONFIG_MASK_MAX_RT: u8 = 1 << 4;
+/// CONFIG: CRC 使能
+pub const NRF24_CONFIG_EN_CRC: u8 = 1 << 3;
+/// CONFIG: CRC 长度，0为1字节，1为2字节
+pub const NRF24_CONFIG_CRCO: u8 = 1 << 2;
+/// CONFIG: 上电
+pub const NRF24_CONFIG_PWR_UP: u8 = 1 << 1;
+/// CONFIG: 1为接收模式，0为发送模式
+pub const NRF24_CONFIG_PRIM_RX: u8 = 1 << 0;
+
+/// RF_SETUP: 250kbps
+pub const NRF24_RF_SETUP_RF_DR_LOW: u8 = 1 << 5;
+/// RF_SETUP: 2Mbps
+pub const NRF24_RF_SETUP_RF_DR_HIGH: u8 = 1 << 3;
+/// RF_SETUP: 发射功率的偏移
+pub const NRF24_RF_SETUP_RF_PWR_SHIFT: u8 = 1;
+/// RF_SETUP: 低噪声放大器增益，只有 nRF24L01 有效
+pub const NRF24_RF_SETUP_LNA_HCURR: u8 = 1 << 0;
+
+/// STATUS: 收到数据
+pub const NRF24_STATUS_RX_DR: u8 = 1 << 6;
+/// STATUS: 发送完成，开启自动应答时收到应答才置位
+pub const NRF24_STATUS_TX_DS: u8 = 1 << 5;
+/// STATUS: 达到最大重发次数
+pub const NRF24_STATUS_MAX_RT: u8 = 1 << 4;
+/// STATUS: 接收缓冲区中第一个数据包的通道号，0b111表示为空
+pub const NRF24_STATUS_RX_P_NO_MASK: u8 = 0b1110;
+/// STATUS: 发送缓冲区已满
+pub const NRF24_STATUS_TX_FULL: u8 = 1 << 0;
+
+/// FIFO_STATUS: 发送缓冲区已满
+pub const NRF24_FIFO_STATUS_TX_FULL: u8 = 1 << 5;
+/// FIFO_STATUS: 发送缓冲区为空
+pub const NRF24_FIFO_STATUS_TX_EMPTY: u8 = 1 << 4;
+/// FIFO_STATUS: 接收缓冲区已满
+pub const NRF24_FIFO_STATUS_RX_FULL: u8 = 1 << 1;
+/// FIFO_STATUS: 接收缓冲区为空
+pub const NRF24_FIFO_STATUS_RX_EMPTY: u8 = 1 << 0;
+
+/// FEATURE: 动态数据长度
+pub const NRF24_FEATURE_EN_DPL: u8 = 1 << 2;
+/// FEATURE: 应答附带数据
+pub const NRF24_FEATURE_EN_ACK_PAY: u8 = 1 << 1;
+/// FEATURE: 允许发送不需要应答的数据
+pub const NRF24_FEATURE_EN_DYN_ACK: u8 = 1 << 0;
+
+/// 接收通道个数
+pub const NRF24_PIPES: usize = 6;
+/// 单个数据包的最大长度
+pub use portable::nrf24::NRF24_MAX_PAYLOAD;
+/// 最大频道，频率为 2400 + 频道 MHz
+pub const NRF24_MAX_CHANNEL: u8 = 125;
+/// 上电后进入待机模式的时间(us)
+pub const NRF24_POWER_UP_US: u32 = 5_000;
@@ -0,0 +1,455 @@
+//! nRF24L01 无线参数配置
+//! 链式调用设置参数，`Nrf24L01::new` 或 `configure` 时检查参数是否有效
+//!
+//! ```rust
+//! let config = Config::default()
+//!     .channel(76)
+//!     .data_rate(DataRate::R250Kbps)
+//!     .power(PowerLevel::Min)
+//!     .tx_address(b"node0")
+//!     .rx_address(1, b"node1")
+//!     .auto_retransmit(750, 5)
+//!     .dynamic_payload(true);
+//! ```
+
+use super::conf::*;
+
+/// 空中传输速率
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum DataRate {
+    /// 250kbps，距离最远，只有 nRF24L01+ 支持
+    R250Kbps,
+    R1Mbps,
+    R2Mbps,
+}
+
+impl DataRate {
+    /// RF_SETUP 中的速率位
+    pub fn bits(&self) -> u8 {
+        match self {
+            DataRate::R250Kbps => NRF24_RF_SETUP_RF_DR_LOW,
+            DataRate::R1Mbps => 0,
+            DataRate::R2Mbps => NRF24_RF_SETUP_RF_DR_HIGH,
+        }
+    }
+}
+
+/// 发射功率
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum PowerLevel {
+    /// -18dBm
+    Min,
+    /// -12dBm
+    Low,
+    /// -6dBm
+    High,
+    /// 0dBm
+    Max,
+}
+
+impl PowerLevel {
+    /// RF_SETUP 中的功率位
+    pub fn bits(&self) -> u8 {
+        let level = match self {
+            PowerLevel::Min => 0b00,
+            PowerLevel::Low => 0b01,
+            PowerLevel::High => 0b10,
+            PowerLevel::Max => 0b11,
+        };
+        level << NRF24_RF_SETUP_RF_PWR_SHIFT
+    }
+}
+
+/// CRC 长度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum CrcLength {
+    /// 不校验，开启自动应答时芯片会强制使用 CRC
+    Disabled,
+    OneByte,
+    TwoBytes,
+}
+
+impl CrcLength {
+    /// CONFIG 中的 CRC 位
+    pub fn bits(&self) -> u8 {
+        match self {
+            CrcLength::Disabled => 0,
+            CrcLength::OneByte => NRF24_CONFIG_EN_CRC,
+            CrcLength::TwoBytes => NRF24_CONFIG_EN_CRC | NRF24_CONFIG_CRCO,
+        }
+    }
+}
+
+/// 地址宽度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum AddressWidth {
+    Three,
+    Four,
+    Five,
+}
+
+impl AddressWidth {
+    /// 地址字节数
+    pub fn bytes(&self) -> usize {
+        match self {
+            AddressWidth::Three => 3,
+            AddressWidth::Four => 4,
+            AddressWidth::Five => 5,
+        }
+    }
+
+    /// SETUP_AW 寄存器的值
+    pub fn bits(&self) -> u8 {
+        self.bytes() as u8 - 2
+    }
+}
+
+/// 配置错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum ConfigError {
+    /// 频道超过125
+    Channel,
+    /// 接收通道编号不在0~5之间
+    Pipe,
+    /// 地址长度与地址宽度不符
+    AddressLength,
+    /// 通道2~5的地址除最低字节外必须与通道1相同
+    AddressPrefix,
+    /// 固定数据长度不在1~32之间
+    PayloadSize,
+    /// 重发延时不在250~4000us之间，或不是250us的整数倍
+    RetransmitDelay,
+    /// 重发次数超过15
+    RetransmitCount,
+    /// 自动应答需要开启 CRC
+    CrcRequired,
+    /// 动态数据长度需要开启自动应答
+    DynamicPayloadRequiresAutoAck,
+    /// 应答附带数据需要开启动态数据长度
+    AckPayloadRequiresDynamicPayload,
+}
+
+/// 接收通道配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct PipeConfig {
+    /// 是否使能
+    pub enabled: bool,
+    /// 接收地址，低字节在前，通道2~5只使用最低字节
+    pub address: [u8; 5],
+    /// 地址的有效长度
+    pub address_len: u8,
+    /// 自动应答
+    pub auto_ack: bool,
+}
+
+impl PipeConfig {
+    /// 有效的地址字节
+    pub fn address(&self) -> &[u8] {
+        &self.address[..self.address_len as usize]
+    }
+}
+
+/// 无线参数配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Config {
+    pub(super) channel: u8,
+    pub(super) data_rate: DataRate,
+    pub(super) power: PowerLevel,
+    pub(super) crc: CrcLength,
+    pub(super) address_width: AddressWidth,
+    pub(super) tx_address: [u8; 5],
+    pub(super) tx_address_len: u8,
+    pub(super) pipes: [PipeConfig; NRF24_PIPES],
+    pub(super) retransmit_delay_us: u16,
+    pub(super) retransmit_count: u8,
+    pub(super) payload_size: u8,
+    pub(super) dynamic_payload: bool,
+    pub(super) ack_payload: bool,
+    /// 设置过不存在的接收通道，在 `validate` 中报告
+    pub(super) invalid_pipe: bool,
+}
+
+impl Default for Config {
+    /// 频道76、1Mbps、0dBm、2字节 CRC、5字节地址，
+    /// 使能通道0和通道1并开启自动应答，重发间隔500us、最多3次，固定32字节数据
+    fn default() -> Self {
+        let pipe = |enabled, lsb| PipeConfig {
+            enabled,
+            address: [lsb, 0xC2, 0xC2, 0xC2, 0xC2],
+            address_len: 5,
+            auto_ack: true,
+        };
+        Config {
+            channel: 76,
+            data_rate: DataRate::R1Mbps,
+            power: PowerLevel::Max,
+            crc: CrcLength::TwoBytes,
+            address_width: AddressWidth::Five,
+            tx_address: [0xE7; 5],
+            tx_address_len: 5,
+            pipes: [
+                PipeConfig {
+                    address: [0xE7; 5],
+                    ..pipe(true, 0xE7)
+                },
+                pipe(true, 0xC2),
+                pipe(false, 0xC3),
+                pipe(false, 0xC4),
+                pipe(false, 0xC5),
+                pipe(false, 0xC6),
+            ],
+            retransmit_delay_us: 500,
+            retransmit_count: 3,
+            payload_size: NRF24_MAX_PAYLOAD as u8,
+            dynamic_payload: false,
+            ack_payload: false,
+            invalid_pipe: false,
+        }
+    }
+}
+
+impl Config {
+    /// 射频频道 0~125，频率为 2400 + channel MHz
+    pub fn channel(mut self, channel: u8) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// 空中传输速率
+    pub fn data_rate(mut self, data_rate: DataRate) -> Self {
+        self.data_rate = data_rate;
+        self
+    }
+
+    /// 发射功率
+    pub fn power(mut self, power: PowerLevel) -> Self {
+        self.power = power;
+        self
+    }
+
+    /// CRC 长度
+    pub fn crc(mut self, crc: CrcLength) -> Self {
+        self.crc = crc;
+        self
+    }
+
+    /// 地址宽度，修改后需要重新设置长度相符的地址
+    pub fn address_width(mut self, width: AddressWidth) -> Self {
+        self.address_width = width;
+        self
+    }
+
+    /// 发送地址
+    /// 开启自动应答时通道0的地址在发送期间会被临时设置为发送地址，用于接收应答
+    pub fn tx_address(mut self, address: &[u8]) -> Self {
+        let len = address.len().min(5);
+        self.tx_address[..len].copy_from_slice(&address[..len]);
+        self.tx_address_len = address.len() as u8;
+        self
+    }
+
+    /// 设置接收通道的地址并使能该通道
+    /// 通道2~5的地址除最低字节(第一个字节)外必须与通道1相同
+    pub fn rx_address(mut self, pipe: usize, address: &[u8]) -> Self {
+        if !self.check_pipe(pipe) {
+            return self;
+        }
+        let len = address.len().min(5);
+        let config = &mut self.pipes[pipe];
+        config.address[..len].copy_from_slice(&address[..len]);
+        config.address_len = address.len() as u8;
+        config.enabled = true;
+        self
+    }
+
+    /// 使能或关闭接收通道
+    pub fn pipe_enabled(mut self, pipe: usize, enabled: bool) -> Self {
+        if self.check_pipe(pipe) {
+            self.pipes[pipe].enabled = enabled;
+        }
+        self
+    }
+
+    /// 所有通道的自动应答
+    pub fn auto_ack(mut self, enabled: bool) -> Self {
+        for pipe in self.pipes.iter_mut() {
+            pipe.auto_ack = enabled;
+        }
+        self
+    }
+
+    /// 单个通道的自动应答
+    pub fn pipe_auto_ack(mut self, pipe: usize, enabled: bool) -> Self {
+        if self.check_pipe(pipe) {
+            self.pipes[pipe].auto_ack = enabled;
+        }
+        self
+    }
+
+    /// 检查通道编号，无效时记录错误并忽略该设置
+    fn check_pipe(&mut self, pipe: usize) -> bool {
+        let valid = pipe < NRF24_PIPES;
+        self.invalid_pipe |= !valid;
+        valid
+    }
+
+    /// 自动重发，延时 250~4000us(250us的整数倍)，次数 0~15，次数为0时关闭
+    pub fn auto_retransmit(mut self, delay_us: u16, count: u8) -> Self {
+        self.retransmit_delay_us = delay_us;
+        self.retransmit_count = count;
+        self
+    }
+
+    /// 固定数据长度 1~32，关闭动态数据长度时使用
+    pub fn payload_size(mut self, size: u8) -> Self {
+        self.payload_size = size;
+        self
+    }
+
+    /// 动态数据长度，所有使能的通道都使用
+    pub fn dynamic_payload(mut self, enabled: bool) -> Self {
+        self.dynamic_payload = enabled;
+        self
+    }
+
+    /// 应答附带数据
+    pub fn ack_payload(mut self, enabled: bool) -> Self {
+        self.ack_payload = enabled;
+        self
+    }
+
+    /// 接收通道的配置
+    pub fn pipe(&self, pipe: usize) -> &PipeConfig {
+        &self.pipes[pipe]
+    }
+
+    /// 发送地址
+    pub fn get_tx_address(&self) -> &[u8] {
+        &self.tx_address[..self.tx_address_len as usize]
+    }
+
+    /// 固定数据长度
+    pub fn get_payload_size(&self) -> u8 {
+        self.payload_size
+    }
+
+    /// 是否使用动态数据长度
+    pub fn is_dynamic_payload(&self) -> bool {
+        self.dynamic_payload
+    }
+
+    /// 是否开启应答附带数据
+    pub fn is_ack_payload(&self) -> bool {
+        self.ack_payload
+    }
+
+    /// 是否有通道开启了自动应答
+    pub fn is_auto_ack(&self) -> bool {
+        self.pipes.iter().any(|pipe| pipe.enabled && pipe.auto_ack)
+    }
+
+    /// 检查参数是否有效
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.channel > NRF24_MAX_CHANNEL {
+            return Err(ConfigError::Channel);
+        }
+        if self.invalid_pipe {
+            return Err(ConfigError::Pipe);
+        }
+
+        let width = self.address_width.bytes();
+        if self.tx_address_len as usize != width {
+            return Err(ConfigError::AddressLength);
+        }
+        for (i, pipe) in self.pipes.iter().enumerate() {
+            if !pipe.enabled {
+                continue;
+            }
+            if pipe.address_len as usize != width {
+                return Err(ConfigError::AddressLength);
+            }
+            if i >= 2 && pipe.address()[1..] != self.pipes[1].address()[1..] {
+                return Err(ConfigError::AddressPrefix);
+            }
+        }
+
+        if !(1..=NRF24_MAX_PAYLOAD as u8).contains(&self.payload_size) {
+            return Err(ConfigError::PayloadSize);
+        }
+        if !(250..=4000).contains(&self.retransmit_delay_us)
+            || !self.retransmit_delay_us.is_multiple_of(250)
+        {
+            return Err(ConfigError::RetransmitDelay);
+        }
+        if self.retransmit_count > 15 {
+            return Err(ConfigError::RetransmitCount);
+        }
+
+        let auto_ack = self.pipes.iter().any(|pipe| pipe.auto_ack);
+        if auto_ack && self.crc == CrcLength::Disabled {
+            return Err(ConfigError::CrcRequired);
+        }
+        if self.dynamic_payload && self.pipes.iter().any(|pipe| pipe.enabled && !pipe.auto_ack) {
+            return Err(ConfigError::DynamicPayloadRequiresAutoAck);
+        }
+        if self.ack_payload && !self.dynamic_payload {
+            return Err(ConfigError::AckPayloadRequiresDynamicPayload);
+        }
+        Ok(())
+    }
+
+    /// CONFIG 寄存器的值，不含上电和收发模式位
+    pub(super) fn config_bits(&self) -> u8 {
+        self.crc.bits()
+    }
+
+    /// EN_AA 寄存器的值
+    pub(super) fn en_aa_bits(&self) -> u8 {
+        self.pipe_bits(|pipe| pipe.auto_ack)
+    }
+
+    /// EN_RXADDR 寄存器的值
+    pub(super) fn en_rxaddr_bits(&self) -> u8 {
+        self.pipe_bits(|pipe| pipe.enabled)
+    }
+
+    /// SETUP_RETR 寄存器的值
+    pub(super) fn setup_retr_bits(&self) -> u8 {
+        let delay = (self.retransmit_delay_us / 250 - 1) as u8;
+        (delay << 4) | self.retransmit_count
+    }
+
+    /// RF_SETUP 寄存器的值
+    pub(super) fn rf_setup_bits(&self) -> u8 {
+        self.data_rate.bits() | self.power.bits() | NRF24_RF_SETUP_LNA_HCURR
+    }
+
+    /// DYNPD 寄存器的值
+    pub(super) fn dynpd_bits(&self) -> u8 {
+        if self.dynamic_payload {
+            self.pipe_bits(|pipe| pipe.enabled)
+        } else {
+            0
+        }
+    }
+
+    /// FEATURE 寄存器的值
+    pub(super) fn feature_bits(&self) -> u8 {
+        let mut bits = NRF24_FEATURE_EN_DYN_ACK;
+        if self.dynamic_payload {
+            bits |= NRF24_FEATURE_EN_DPL;
+        }
+        if self.ack_payload {
+            bits |= NRF24_FEATURE_EN_ACK_PAY;
+        }
+        bits
+    }
+
+    fn pipe_bits(&self, f: impl Fn(&PipeConfig) -> bool) -> u8 {
+        self.pipes
+            .iter()
+            .enumerate()
+            .filter(|(_, pipe)| f(pipe))
+            .fold(0, |bits, (i, _)| bits | (1 << i))
+    }
+}
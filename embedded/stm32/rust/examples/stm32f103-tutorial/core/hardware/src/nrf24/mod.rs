@@ -0,0 +1,34 @@
+//! SPI 驱动 nRF24L01 2.4GHz 无线通信模块
+//! 通过 `Config` 链式设置频道、速率、功率、地址、自动应答、重发和动态数据长度等参数
+//!
+//! ```rust
+//! let config = Config::default()
+//!     .data_rate(DataRate::R250Kbps)
+//!     .tx_address(b"node0")
+//!     .rx_address(0, b"node0");
+//! let mut nrf24 = Nrf24L01::new(spi, ce, csn, config, &mut delay)?;
+//!
+//! // 发送
+//! nrf24.transmit(b"hello")?;
+//!
+//! // 接收
+//! nrf24.listen()?;
+//! if let Ok(payload) = nrf24.read() {
+//!     println!("pipe {}: {}", payload.pipe(), payload.as_ref());
+//! }
+//! ```
+//!
+//! `transport` 在数据包之上实现分片重组、应答重发和消息序列化，用于发送超过32字节的消息，
+//! 协议层与模拟信道在平台无关工具库 `portable::nrf24` 中，可以在主机上测试
+//! `network` 实现一个网关和多个节点组成的星型网络，支持配对和在线状态检测
+pub mod conf;
+pub mod config;
+pub mod network;
+pub mod nrf24_hal;
+pub mod transport;
+
+pub use config::{AddressWidth, Config, ConfigError, CrcLength, DataRate, PipeConfig, PowerLevel};
+pub use nrf24_hal::{Error, Mode, Nrf24L01, Observe, Payload};
+pub use portable::nrf24::message;
+pub use portable::nrf24::message::{CodecError, Message, Reader, Writer};
+pub use transport::{LinkStats, Radio, Transport, TransportConfig, TxState};
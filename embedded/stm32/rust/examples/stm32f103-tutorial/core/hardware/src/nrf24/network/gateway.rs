@@ -0,0 +1,447 @@
+//! 星型网络的网关
+//! 网关处于接收模式，按固定间隔依次轮询已配对的节点，根据回复判断节点是否在线
+
+use heapless::{Deque, Vec};
+
+use super::store;
+use super::*;
+use crate::flash_store::FlashStore;
+use crate::nrf24::conf::NRF24_MAX_PAYLOAD;
+use crate::nrf24::nrf24_hal::{Nrf24L01, Payload};
+use crate::time::{Duration, Instant};
+
+use embedded_hal::blocking::spi::Transfer;
+use embedded_hal::digital::v2::OutputPin;
+
+/// 网关最多缓存的事件数
+const GATEWAY_EVENT_CAPACITY: usize = 16;
+
+/// 网关参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GatewayConfig {
+    /// 网络所在的频道
+    pub channel: u8,
+    /// 相邻两次轮询的间隔
+    pub poll_interval: Duration,
+    /// 等待节点回复的时间
+    pub response_timeout: Duration,
+    /// 超过该时间没有收到回复的节点视为离线
+    pub offline_timeout: Duration,
+}
+
+impl Default for GatewayConfig {
+    /// 频道76，每50ms轮询一个节点，等待回复10ms，3s没有回复视为离线
+    fn default() -> Self {
+        GatewayConfig {
+            channel: 76,
+            poll_interval: Duration::millis(50),
+            response_timeout: Duration::millis(10),
+            offline_timeout: Duration::secs(3),
+        }
+    }
+}
+
+/// 网关事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum GatewayEvent {
+    /// 节点完成配对
+    Paired { node_id: u8, address: [u8; 5] },
+    /// 节点上线
+    Online(u8),
+    /// 节点离线
+    Offline(u8),
+    /// 收到节点的上行数据
+    Data { node_id: u8, packet: Packet },
+    /// 下行数据已被节点收到
+    Delivered(u8),
+}
+
+/// 节点状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeInfo {
+    /// 节点编号
+    pub node_id: u8,
+    /// 组号
+    pub group: u8,
+    /// 节点的接收地址
+    pub address: [u8; 5],
+    /// 是否在线
+    pub online: bool,
+    /// 上一次收到回复的时间
+    pub last_seen: Option<Instant>,
+    /// 没有回复的轮询次数
+    pub missed_polls: u32,
+    /// 上一次收到回复时是否检测到大于 -64dBm 的信号(RPD)
+    pub carrier: bool,
+}
+
+impl defmt::Format for NodeInfo {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "node {} group {} address {=[u8]:x} online {} last seen {} ms missed {} carrier {}",
+            self.node_id,
+            self.group,
+            self.address,
+            self.online,
+            self.last_seen.map(|at| at.ticks() / 1000),
+            self.missed_polls,
+            self.carrier
+        );
+    }
+}
+
+/// 节点表中的一项
+struct NodeEntry {
+    info: NodeInfo,
+    /// 上一次收到的上行数据序号
+    uplink_seq: Option<u8>,
+    /// 下行数据的序号
+    downlink_seq: u8,
+    /// 等待发送的下行数据
+    downlink: Option<Packet>,
+}
+
+impl NodeEntry {
+    fn new(node_id: u8, address: [u8; 5]) -> Self {
+        NodeEntry {
+            info: NodeInfo {
+                node_id,
+                group: group_of(node_id),
+                address,
+                online: false,
+                last_seen: None,
+                missed_polls: 0,
+                carrier: false,
+            },
+            uplink_seq: None,
+            downlink_seq: 0,
+            downlink: None,
+        }
+    }
+}
+
+/// 网关，最多管理 `N` 个节点
+pub struct Gateway<SPI, CE, CSN, const N: usize> {
+    nrf: Nrf24L01<SPI, CE, CSN>,
+    config: GatewayConfig,
+    prefix: [u8; 4],
+    nodes: Vec<NodeEntry, N>,
+    pairing: bool,
+    /// 下一个轮询的节点在节点表中的位置
+    next: usize,
+    next_poll_at: Option<Instant>,
+    /// 正在等待回复的节点编号和轮询时间
+    awaiting: Option<(u8, Instant)>,
+    events: Deque<GatewayEvent, GATEWAY_EVENT_CAPACITY>,
+}
+
+impl<SPI, CE, CSN, E, const N: usize> Gateway<SPI, CE, CSN, N>
+where
+    SPI: Transfer<u8, Error = E>,
+    CE: OutputPin,
+    CSN: OutputPin,
+{
+    /// 通道0监听配对地址，通道1~5监听各组的地址，地址前缀由网关的唯一ID计算得到
+    pub fn new(
+        mut nrf: Nrf24L01<SPI, CE, CSN>,
+        base: Config,
+        uid: &[u8; 12],
+        config: GatewayConfig,
+    ) -> Result<Self, Error<E>> {
+        let prefix = network_prefix(uid);
+        let mut radio_config = network_config(base, config.channel)
+            .tx_address(&PAIRING_ADDRESS)
+            .rx_address(0, &PAIRING_ADDRESS);
+        for group in 0..GROUPS {
+            let pipe = group_pipe(group) as usize;
+            radio_config = radio_config.rx_address(pipe, &group_address(&prefix, group));
+        }
+        nrf.configure(radio_config)?;
+        nrf.listen()?;
+
+        Ok(Gateway {
+            nrf,
+            config,
+            prefix,
+            nodes: Vec::new(),
+            pairing: false,
+            next: 0,
+            next_poll_at: None,
+            awaiting: None,
+            events: Deque::new(),
+        })
+    }
+
+    /// 释放无线模块
+    pub fn free(self) -> Nrf24L01<SPI, CE, CSN> {
+        self.nrf
+    }
+
+    /// 网关各通道的地址前缀
+    pub fn prefix(&self) -> &[u8; 4] {
+        &self.prefix
+    }
+
+    /// 开启或关闭配对模式，关闭时忽略配对请求
+    pub fn set_pairing(&mut self, enabled: bool) {
+        self.pairing = enabled;
+    }
+
+    /// 是否处于配对模式
+    pub fn is_pairing(&self) -> bool {
+        self.pairing
+    }
+
+    /// 所有节点的状态
+    pub fn nodes(&self) -> impl Iterator<Item = &NodeInfo> {
+        self.nodes.iter().map(|entry| &entry.info)
+    }
+
+    /// 节点的状态
+    pub fn node(&self, node_id: u8) -> Option<&NodeInfo> {
+        self.nodes().find(|info| info.node_id == node_id)
+    }
+
+    /// 从节点表中删除节点，节点需要重新配对
+    pub fn remove(&mut self, node_id: u8) -> bool {
+        let Some(index) = self.position(node_id) else {
+            return false;
+        };
+        self.nodes.remove(index);
+        if self.awaiting.is_some_and(|(id, _)| id == node_id) {
+            self.awaiting = None;
+        }
+        true
+    }
+
+    /// 从参数存储中恢复节点表，节点上电后无需重新配对
+    pub fn load_nodes(&mut self, flash_store: &FlashStore) {
+        self.nodes.clear();
+        for (node_id, address) in store::load_nodes(flash_store) {
+            if self.nodes.push(NodeEntry::new(node_id, address)).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// 将节点表保存到参数存储，并写入 FLASH
+    pub fn save_nodes(&self, flash_store: &FlashStore) {
+        let nodes = self.nodes().map(|info| (info.node_id, info.address));
+        store::save_nodes(flash_store, nodes);
+    }
+
+    /// 缓存下行数据，下次轮询该节点时发送
+    pub fn send_to(&mut self, node_id: u8, data: &[u8]) -> Result<(), Error<E>> {
+        let index = self.position(node_id).ok_or(Error::UnknownNode)?;
+        let entry = &mut self.nodes[index];
+        if entry.downlink.is_some() {
+            return Err(Error::Busy);
+        }
+        entry.downlink = Some(Packet::new(data).ok_or(Error::PayloadLength)?);
+        Ok(())
+    }
+
+    /// 取出一个事件
+    pub fn next_event(&mut self) -> Option<GatewayEvent> {
+        self.events.pop_front()
+    }
+
+    /// 接收数据、轮询节点并更新在线状态，需要在主循环中不断调用
+    /// 节点表已满时拒绝配对请求并返回 `Error::TableFull`
+    pub fn poll(&mut self, now: Instant) -> Result<(), Error<E>> {
+        loop {
+            match self.nrf.read() {
+                Ok(payload) => self.handle_frame(&payload, now)?,
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => return Err(e.into()),
+            }
+        }
+
+        // 等待回复超时
+        if let Some((node_id, sent_at)) = self.awaiting {
+            if elapsed(now, sent_at) >= self.config.response_timeout {
+                self.awaiting = None;
+                if let Some(index) = self.position(node_id) {
+                    self.nodes[index].info.missed_polls += 1;
+                }
+            }
+        }
+
+        // 长时间没有回复的节点离线
+        for entry in self.nodes.iter_mut() {
+            let info = &mut entry.info;
+            if info.online
+                && info
+                    .last_seen
+                    .is_some_and(|last| elapsed(now, last) > self.config.offline_timeout)
+            {
+                info.online = false;
+                push_event(&mut self.events, GatewayEvent::Offline(info.node_id));
+            }
+        }
+
+        if self.awaiting.is_none()
+            && !self.nodes.is_empty()
+            && self.next_poll_at.is_none_or(|at| now >= at)
+        {
+            let index = self.next % self.nodes.len();
+            self.next = index + 1;
+            self.next_poll_at = Some(now + self.config.poll_interval);
+            self.send_poll(index, now)?;
+        }
+        Ok(())
+    }
+
+    /// 轮询节点，附带下行数据
+    fn send_poll(&mut self, index: usize, now: Instant) -> Result<(), Error<E>> {
+        let entry = &self.nodes[index];
+        let (node_id, address) = (entry.info.node_id, entry.info.address);
+
+        let mut frame = [0; NRF24_MAX_PAYLOAD];
+        frame[..NET_HEADER_LEN].copy_from_slice(&[FRAME_POLL, node_id, entry.downlink_seq]);
+        let mut len = NET_HEADER_LEN;
+        if let Some(packet) = &entry.downlink {
+            frame[len..len + packet.len()].copy_from_slice(packet.as_ref());
+            len += packet.len();
+        }
+
+        let result = self.transmit_to(&address, &frame[..len]);
+        let entry = &mut self.nodes[index];
+        match result {
+            Ok(true) => {
+                // 下行数据送达后才使用新的序号，节点据此去重
+                if entry.downlink.take().is_some() {
+                    entry.downlink_seq = entry.downlink_seq.wrapping_add(1);
+                    push_event(&mut self.events, GatewayEvent::Delivered(node_id));
+                }
+                self.awaiting = Some((node_id, now));
+            }
+            // 节点不在范围内或没有上电
+            Ok(false) => entry.info.missed_polls += 1,
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    fn handle_frame(&mut self, payload: &Payload, now: Instant) -> Result<(), Error<E>> {
+        let frame = payload.as_ref();
+        if frame.is_empty() {
+            return Ok(());
+        }
+        match frame[0] {
+            FRAME_PAIR_REQUEST if payload.pipe() == 0 && frame.len() >= 6 => {
+                let mut address = [0; 5];
+                address.copy_from_slice(&frame[1..6]);
+                self.handle_pair_request(address)
+            }
+            FRAME_DATA if frame.len() >= NET_HEADER_LEN => {
+                let (node_id, seq) = (frame[1], frame[2]);
+                let Some(index) = self.position(node_id) else {
+                    return Ok(());
+                };
+                if payload.pipe() != group_pipe(group_of(node_id)) {
+                    return Ok(());
+                }
+                let carrier = self.nrf.carrier_detected()?;
+                self.handle_data(index, seq, &frame[NET_HEADER_LEN..], carrier, now);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn handle_pair_request(&mut self, address: [u8; 5]) -> Result<(), Error<E>> {
+        if !self.pairing {
+            return Ok(());
+        }
+
+        // 已配对过的节点使用原来的编号
+        let paired = self.nodes().find(|info| info.address == address);
+        let node_id = match paired.map(|info| info.node_id) {
+            Some(node_id) => node_id,
+            None => {
+                let node_id = (1..=u8::MAX)
+                    .find(|id| self.position(*id).is_none())
+                    .ok_or(Error::TableFull)?;
+                self.nodes
+                    .push(NodeEntry::new(node_id, address))
+                    .map_err(|_| Error::TableFull)?;
+                node_id
+            }
+        };
+
+        let group = group_of(node_id);
+        let p = self.prefix;
+        let response = [
+            FRAME_PAIR_RESPONSE,
+            node_id,
+            group,
+            self.config.channel,
+            p[0],
+            p[1],
+            p[2],
+            p[3],
+        ];
+        // 节点没有收到回复时会重新发送配对请求
+        if self.transmit_to(&address, &response)? {
+            push_event(&mut self.events, GatewayEvent::Paired { node_id, address });
+        }
+        Ok(())
+    }
+
+    fn handle_data(&mut self, index: usize, seq: u8, data: &[u8], carrier: bool, now: Instant) {
+        let entry = &mut self.nodes[index];
+        let node_id = entry.info.node_id;
+        entry.info.last_seen = Some(now);
+        entry.info.carrier = carrier;
+        if !entry.info.online {
+            entry.info.online = true;
+            push_event(&mut self.events, GatewayEvent::Online(node_id));
+        }
+        if self.awaiting.is_some_and(|(id, _)| id == node_id) {
+            self.awaiting = None;
+        }
+
+        // 节点没有收到应答时会用相同的序号重发
+        if data.is_empty() || entry.uplink_seq == Some(seq) {
+            return;
+        }
+        entry.uplink_seq = Some(seq);
+        if let Some(packet) = Packet::new(data) {
+            push_event(&mut self.events, GatewayEvent::Data { node_id, packet });
+        }
+    }
+
+    /// 向节点发送一帧后回到接收模式，返回是否收到硬件应答
+    fn transmit_to(&mut self, address: &[u8; 5], frame: &[u8]) -> Result<bool, Error<E>> {
+        self.nrf.set_tx_address(address)?;
+        let result = self.nrf.transmit(frame);
+        self.nrf.listen()?;
+        match result {
+            Ok(()) => Ok(true),
+            Err(nrf24_hal::Error::MaxRetransmits) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn position(&self, node_id: u8) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|entry| entry.info.node_id == node_id)
+    }
+}
+
+/// 从 `since` 到 `now` 经过的时间
+fn elapsed(now: Instant, since: Instant) -> Duration {
+    now.checked_duration_since(since)
+        .unwrap_or(Duration::from_ticks(0))
+}
+
+/// 事件队列已满时丢弃最早的事件
+fn push_event(events: &mut Deque<GatewayEvent, GATEWAY_EVENT_CAPACITY>, event: GatewayEvent) {
+    if events.is_full() {
+        events.pop_front();
+    }
+    let _ = events.push_back(event);
+}
@@ -0,0 +1,221 @@
+//! nRF24L01 星型网络
+//! 多个传感器节点向一个网关上报数据，网关轮询各节点，节点只在被轮询时回复，避免多个节点同时发送
+//!
+//! - 地址: 节点的接收地址由 STM32 96位唯一ID计算得到，网关各通道的地址前缀由网关的唯一ID计算得到
+//! - 分组: 网关的通道0只用于配对，通道1~5各对应一组节点，节点编号对5取余为组号，
+//!   各组的地址都由网络前缀组成，不同网关的节点不会互相干扰
+//! - 配对: 节点依次在各频道上向配对地址发送请求，收到硬件应答即找到网关所在的频道，
+//!   网关分配节点编号后回复，节点将编号、组号、频道和地址前缀保存在内部 FLASH
+//! - 轮询: 网关按固定间隔依次轮询已配对的节点，轮询帧可以附带下行数据，
+//!   节点回复上行数据或心跳，超过一定时间没有回复的节点视为离线
+//!
+//! 每个帧为一个数据包，第一个字节为帧类型:
+//! - 配对请求: 节点地址(5字节)
+//! - 配对回复: 节点编号、组号、频道、地址前缀(4字节)
+//! - 轮询: 节点编号、序号、下行数据
+//! - 上行数据: 节点编号、序号、上行数据，没有数据时为心跳
+//!
+//! ```rust
+//! // 网关
+//! let mut gateway: Gateway<_, _, _, 16> = Gateway::new(nrf24, base_config, &unique_id(), GatewayConfig::default())?;
+//! gateway.load_nodes(&flash_store);
+//! gateway.set_pairing(true);
+//! loop {
+//!     gateway.poll(time::now())?;
+//!     while let Some(event) = gateway.next_event() {
+//!         println!("{}", event);
+//!     }
+//! }
+//!
+//! // 节点
+//! let identity = match Identity::load(&flash_store) {
+//!     Some(identity) => identity,
+//!     None => {
+//!         let identity = Node::pair(&mut nrf24, base_config, &address, 0..=125, 5.millis(), time::now)?;
+//!         identity.save(&flash_store);
+//!         identity
+//!     }
+//! };
+//! let mut node = Node::new(nrf24, base_config, address, identity)?;
+//! node.send(b"hello")?;
+//! ```
+
+pub mod gateway;
+pub mod node;
+pub mod store;
+
+pub use gateway::{Gateway, GatewayConfig, GatewayEvent, NodeInfo};
+pub use node::{Node, NodeEvent};
+pub use store::{Identity, IDENTITY_STORE_INDEX, NODES_STORE_INDEX};
+
+use core::ptr::read_volatile;
+
+use super::conf::NRF24_MAX_PAYLOAD;
+use super::config::{AddressWidth, Config};
+use super::nrf24_hal;
+
+/// 配对地址，网关的通道0始终监听该地址
+pub const PAIRING_ADDRESS: [u8; 5] = *b"PAIR0";
+/// 分组个数，网关的通道0用于配对，其余5个通道各对应一组
+pub const GROUPS: u8 = 5;
+/// 帧头长度: 帧类型、节点编号、序号
+pub const NET_HEADER_LEN: usize = 3;
+/// 单个帧最多携带的数据长度
+pub const NET_PAYLOAD_LEN: usize = NRF24_MAX_PAYLOAD - NET_HEADER_LEN;
+
+/// 产品唯一身份标识寄存器的地址
+const UID_ADDRESS: u32 = 0x1FFF_F7E8;
+
+/// 组地址最低字节的起始值，组号依次加1
+const GROUP_ADDRESS_BASE: u8 = 0xC1;
+
+/// 帧类型: 配对请求
+const FRAME_PAIR_REQUEST: u8 = 0x01;
+/// 帧类型: 配对回复
+const FRAME_PAIR_RESPONSE: u8 = 0x02;
+/// 帧类型: 轮询
+const FRAME_POLL: u8 = 0x03;
+/// 帧类型: 上行数据
+const FRAME_DATA: u8 = 0x04;
+
+/// 网络错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// 无线模块错误
+    Radio(nrf24_hal::Error<E>),
+    /// 数据为空或超过 `NET_PAYLOAD_LEN`
+    PayloadLength,
+    /// 上一条数据还没有发出
+    Busy,
+    /// 所有频道上都没有找到处于配对模式的网关
+    PairingTimeout,
+    /// 网关的节点表已满
+    TableFull,
+    /// 节点编号不存在
+    UnknownNode,
+}
+
+impl<E> From<nrf24_hal::Error<E>> for Error<E> {
+    fn from(e: nrf24_hal::Error<E>) -> Self {
+        Error::Radio(e)
+    }
+}
+
+impl<E> defmt::Format for Error<E> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Error::Radio(e) => defmt::write!(f, "Radio({})", e),
+            Error::PayloadLength => defmt::write!(f, "PayloadLength"),
+            Error::Busy => defmt::write!(f, "Busy"),
+            Error::PairingTimeout => defmt::write!(f, "PairingTimeout"),
+            Error::TableFull => defmt::write!(f, "TableFull"),
+            Error::UnknownNode => defmt::write!(f, "UnknownNode"),
+        }
+    }
+}
+
+/// 网络中收发的数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Packet {
+    len: u8,
+    data: [u8; NET_PAYLOAD_LEN],
+}
+
+impl Packet {
+    /// 数据长度需要在 1~`NET_PAYLOAD_LEN` 之间
+    pub fn new(data: &[u8]) -> Option<Self> {
+        if data.is_empty() || data.len() > NET_PAYLOAD_LEN {
+            return None;
+        }
+        let mut packet = Packet {
+            len: data.len() as u8,
+            data: [0; NET_PAYLOAD_LEN],
+        };
+        packet.data[..data.len()].copy_from_slice(data);
+        Some(packet)
+    }
+
+    /// 数据长度
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl AsRef<[u8]> for Packet {
+    fn as_ref(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+}
+
+/// 读取 STM32 的96位唯一ID
+pub fn unique_id() -> [u8; 12] {
+    let mut uid = [0; 12];
+    for (i, byte) in uid.iter_mut().enumerate() {
+        // SAFETY: 唯一ID寄存器为只读的系统存储区
+        *byte = unsafe { read_volatile((UID_ADDRESS + i as u32) as *const u8) };
+    }
+    uid
+}
+
+/// FNV-1a 哈希
+fn fnv1a(data: &[u8], seed: u32) -> u32 {
+    data.iter().fold(0x811C_9DC5 ^ seed, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// 将地址中全0或全1、0x55/0xAA交替的字节替换掉，这些字节容易与前导码或噪声混淆
+fn sanitize(byte: u8) -> u8 {
+    match byte {
+        0x00 | 0xFF | 0x55 | 0xAA => byte ^ 0x3C,
+        _ => byte,
+    }
+}
+
+/// 由唯一ID计算节点的接收地址
+pub fn node_address(uid: &[u8; 12]) -> [u8; 5] {
+    let low = fnv1a(uid, 0).to_le_bytes();
+    let high = fnv1a(uid, 0x5A5A_5A5A).to_le_bytes();
+    [low[0], low[1], low[2], low[3], high[0]].map(sanitize)
+}
+
+/// 由唯一ID计算网关各通道的地址前缀
+pub fn network_prefix(uid: &[u8; 12]) -> [u8; 4] {
+    fnv1a(uid, 0xA5A5_A5A5).to_le_bytes().map(sanitize)
+}
+
+/// 节点所在的组
+pub fn group_of(node_id: u8) -> u8 {
+    node_id % GROUPS
+}
+
+/// 网关接收指定组数据的通道
+pub fn group_pipe(group: u8) -> u8 {
+    group + 1
+}
+
+/// 网关接收指定组数据的地址
+/// 由组号和网络前缀组成，低字节在前，配对地址只用于配对
+pub fn group_address(prefix: &[u8; 4], group: u8) -> [u8; 5] {
+    [
+        GROUP_ADDRESS_BASE + group,
+        prefix[0],
+        prefix[1],
+        prefix[2],
+        prefix[3],
+    ]
+}
+
+/// 网络使用的无线配置: 开启自动应答和动态数据长度，接收地址由调用方设置
+fn network_config(base: Config, channel: u8) -> Config {
+    base.channel(channel)
+        .address_width(AddressWidth::Five)
+        .auto_ack(true)
+        .dynamic_payload(true)
+        .ack_payload(false)
+}
@@ -0,0 +1,238 @@
+//! 星型网络的传感器节点
+//! 节点始终处于接收模式，收到网关的轮询后回复上行数据或心跳
+
+use core::ops::RangeInclusive;
+
+use heapless::Deque;
+
+use super::store::Identity;
+use super::*;
+use crate::nrf24::conf::NRF24_MAX_PAYLOAD;
+use crate::nrf24::nrf24_hal::Nrf24L01;
+use crate::time::{Duration, Instant};
+
+use embedded_hal::blocking::spi::Transfer;
+use embedded_hal::digital::v2::OutputPin;
+
+/// 节点最多缓存的事件数
+const NODE_EVENT_CAPACITY: usize = 4;
+
+/// 节点事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum NodeEvent {
+    /// 收到网关的下行数据
+    Downlink(Packet),
+    /// 上行数据已被网关收到
+    Delivered,
+}
+
+/// 传感器节点
+pub struct Node<SPI, CE, CSN> {
+    nrf: Nrf24L01<SPI, CE, CSN>,
+    address: [u8; 5],
+    identity: Identity,
+    /// 等待发送的上行数据
+    uplink: Option<Packet>,
+    /// 上行数据的序号，网关据此去重
+    seq: u8,
+    /// 上一次收到的下行数据序号
+    downlink_seq: Option<u8>,
+    /// 上一次被轮询的时间
+    last_poll: Option<Instant>,
+    events: Deque<NodeEvent, NODE_EVENT_CAPACITY>,
+}
+
+impl<SPI, CE, CSN, E> Node<SPI, CE, CSN>
+where
+    SPI: Transfer<u8, Error = E>,
+    CE: OutputPin,
+    CSN: OutputPin,
+{
+    /// 在各频道上查找处于配对模式的网关并获取节点编号
+    /// 每个频道发送一次配对请求，收到硬件应答后等待网关回复 `response_timeout`，
+    /// 返回的配对信息需要由调用方保存
+    pub fn pair(
+        nrf: &mut Nrf24L01<SPI, CE, CSN>,
+        base: Config,
+        address: &[u8; 5],
+        channels: RangeInclusive<u8>,
+        response_timeout: Duration,
+        mut now: impl FnMut() -> Instant,
+    ) -> Result<Identity, Error<E>> {
+        let mut request = [0; 6];
+        request[0] = FRAME_PAIR_REQUEST;
+        request[1..].copy_from_slice(address);
+
+        for channel in channels {
+            let config = network_config(base, channel)
+                .tx_address(&PAIRING_ADDRESS)
+                .rx_address(1, address);
+            nrf.configure(config)?;
+
+            // 没有收到硬件应答说明该频道上没有处于配对模式的网关
+            match nrf.transmit(&request) {
+                Ok(()) => {}
+                Err(nrf24_hal::Error::MaxRetransmits) => continue,
+                Err(e) => return Err(e.into()),
+            }
+
+            nrf.listen()?;
+            let deadline = now() + response_timeout;
+            while now() < deadline {
+                let payload = match nrf.read() {
+                    Ok(payload) => payload,
+                    Err(nb::Error::WouldBlock) => continue,
+                    Err(nb::Error::Other(e)) => return Err(e.into()),
+                };
+                let frame = payload.as_ref();
+                if payload.pipe() != 1 || frame.len() < 8 || frame[0] != FRAME_PAIR_RESPONSE {
+                    continue;
+                }
+                let identity = Identity {
+                    node_id: frame[1],
+                    group: frame[2],
+                    channel: frame[3],
+                    prefix: [frame[4], frame[5], frame[6], frame[7]],
+                };
+                if identity.channel == channel && identity.group < GROUPS {
+                    return Ok(identity);
+                }
+            }
+        }
+        Err(Error::PairingTimeout)
+    }
+
+    /// 使用配对信息加入网络，进入接收模式等待网关轮询
+    pub fn new(
+        mut nrf: Nrf24L01<SPI, CE, CSN>,
+        base: Config,
+        address: [u8; 5],
+        identity: Identity,
+    ) -> Result<Self, Error<E>> {
+        let config = network_config(base, identity.channel)
+            .tx_address(&group_address(&identity.prefix, identity.group))
+            .rx_address(1, &address);
+        nrf.configure(config)?;
+        nrf.listen()?;
+
+        Ok(Node {
+            nrf,
+            address,
+            identity,
+            uplink: None,
+            seq: 0,
+            downlink_seq: None,
+            last_poll: None,
+            events: Deque::new(),
+        })
+    }
+
+    /// 释放无线模块
+    pub fn free(self) -> Nrf24L01<SPI, CE, CSN> {
+        self.nrf
+    }
+
+    /// 配对信息
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    /// 节点的接收地址
+    pub fn address(&self) -> &[u8; 5] {
+        &self.address
+    }
+
+    /// 上一次被轮询的时间
+    pub fn last_poll(&self) -> Option<Instant> {
+        self.last_poll
+    }
+
+    /// 最近 `timeout` 内是否被网关轮询过
+    pub fn is_connected(&self, now: Instant, timeout: Duration) -> bool {
+        self.last_poll
+            .and_then(|last| now.checked_duration_since(last))
+            .is_some_and(|elapsed| elapsed <= timeout)
+    }
+
+    /// 上一条上行数据是否还没有发出
+    pub fn is_busy(&self) -> bool {
+        self.uplink.is_some()
+    }
+
+    /// 缓存上行数据，下次被轮询时发送
+    pub fn send(&mut self, data: &[u8]) -> Result<(), Error<E>> {
+        if self.uplink.is_some() {
+            return Err(Error::Busy);
+        }
+        self.uplink = Some(Packet::new(data).ok_or(Error::PayloadLength)?);
+        Ok(())
+    }
+
+    /// 取出一个事件
+    pub fn next_event(&mut self) -> Option<NodeEvent> {
+        self.events.pop_front()
+    }
+
+    /// 处理网关的轮询，需要在主循环中不断调用
+    pub fn poll(&mut self, now: Instant) -> Result<(), Error<E>> {
+        loop {
+            let payload = match self.nrf.read() {
+                Ok(payload) => payload,
+                Err(nb::Error::WouldBlock) => return Ok(()),
+                Err(nb::Error::Other(e)) => return Err(e.into()),
+            };
+            let frame = payload.as_ref();
+            if payload.pipe() == 1
+                && frame.len() >= NET_HEADER_LEN
+                && frame[0] == FRAME_POLL
+                && frame[1] == self.identity.node_id
+            {
+                self.handle_poll(frame, now)?;
+            }
+        }
+    }
+
+    fn handle_poll(&mut self, frame: &[u8], now: Instant) -> Result<(), Error<E>> {
+        self.last_poll = Some(now);
+
+        // 网关没有收到应答时会用相同的序号重发下行数据
+        let seq = frame[2];
+        if frame.len() > NET_HEADER_LEN && self.downlink_seq != Some(seq) {
+            self.downlink_seq = Some(seq);
+            if let Some(packet) = Packet::new(&frame[NET_HEADER_LEN..]) {
+                self.push_event(NodeEvent::Downlink(packet));
+            }
+        }
+
+        let mut reply = [0; NRF24_MAX_PAYLOAD];
+        reply[..NET_HEADER_LEN].copy_from_slice(&[FRAME_DATA, self.identity.node_id, self.seq]);
+        let mut len = NET_HEADER_LEN;
+        if let Some(packet) = &self.uplink {
+            reply[len..len + packet.len()].copy_from_slice(packet.as_ref());
+            len += packet.len();
+        }
+
+        let result = self.nrf.transmit(&reply[..len]);
+        self.nrf.listen()?;
+        match result {
+            Ok(()) => {
+                if self.uplink.take().is_some() {
+                    self.seq = self.seq.wrapping_add(1);
+                    self.push_event(NodeEvent::Delivered);
+                }
+                Ok(())
+            }
+            // 没有收到应答时保留上行数据，下次轮询时用相同的序号重发
+            Err(nrf24_hal::Error::MaxRetransmits) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 事件队列已满时丢弃最早的事件
+    fn push_event(&mut self, event: NodeEvent) {
+        if self.events.is_full() {
+            self.events.pop_front();
+        }
+        let _ = self.events.push_back(event);
+    }
+}
@@ -0,0 +1,144 @@
+//! 配对信息存储
+//! 节点的配对信息和网关的节点表保存在内部 FLASH 的参数存储中
+//! 需要先调用 `FlashStore::init_store` 将 FLASH 数据加载到 SRAM
+
+use heapless::Vec;
+
+use super::GROUPS;
+use crate::flash_store::FlashStore;
+
+/// 节点配对信息在参数存储中的起始序号
+pub const IDENTITY_STORE_INDEX: usize = 32;
+/// 节点配对信息占用的参数个数
+pub const IDENTITY_STORE_COUNT: usize = 6;
+/// 网关节点表在参数存储中的起始序号
+pub const NODES_STORE_INDEX: usize = 40;
+/// 网关节点表最多保存的节点数
+pub const NODES_STORE_CAPACITY: usize = 32;
+/// 每个节点占用的参数个数: 节点编号和地址
+const NODE_STORE_WORDS: usize = 3;
+
+/// 节点配对信息的标志位
+/// 组地址的分配方式改变后修改，旧的配对信息失效，节点重新配对
+const IDENTITY_MAGIC: u16 = 0x4E45;
+/// 网关节点表的标志位
+const NODES_MAGIC: u16 = 0x4757;
+
+/// 节点的配对信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Identity {
+    /// 网关分配的节点编号，从1开始
+    pub node_id: u8,
+    /// 组号，网关在通道 `group + 1` 上接收该节点的数据
+    pub group: u8,
+    /// 网关所在的频道
+    pub channel: u8,
+    /// 网关各通道的地址前缀
+    pub prefix: [u8; 4],
+}
+
+impl Identity {
+    /// 从参数存储中读取
+    /// 标志位或校验和不正确时返回 None
+    pub fn load(store: &FlashStore) -> Option<Self> {
+        let mut words = [0; IDENTITY_STORE_COUNT];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = store.get_store(IDENTITY_STORE_INDEX + i);
+        }
+        if words[0] != IDENTITY_MAGIC || words[5] != checksum(&words[..5]) {
+            return None;
+        }
+        let [node_id, group] = words[1].to_le_bytes();
+        if group >= GROUPS {
+            return None;
+        }
+        let [channel, _] = words[2].to_le_bytes();
+        let [p0, p1] = words[3].to_le_bytes();
+        let [p2, p3] = words[4].to_le_bytes();
+        Some(Identity {
+            node_id,
+            group,
+            channel,
+            prefix: [p0, p1, p2, p3],
+        })
+    }
+
+    /// 保存到参数存储，并写入 FLASH
+    pub fn save(&self, store: &FlashStore) {
+        let mut words = [0; IDENTITY_STORE_COUNT];
+        words[0] = IDENTITY_MAGIC;
+        words[1] = u16::from_le_bytes([self.node_id, self.group]);
+        words[2] = self.channel as u16;
+        words[3] = u16::from_le_bytes([self.prefix[0], self.prefix[1]]);
+        words[4] = u16::from_le_bytes([self.prefix[2], self.prefix[3]]);
+        words[5] = checksum(&words[..5]);
+
+        for (i, word) in words.iter().enumerate() {
+            store.set_store(IDENTITY_STORE_INDEX + i, *word);
+        }
+        store.store_save();
+    }
+
+    /// 清除配对信息，下次启动时重新配对
+    pub fn erase(store: &FlashStore) {
+        for i in 0..IDENTITY_STORE_COUNT {
+            store.set_store(IDENTITY_STORE_INDEX + i, 0);
+        }
+        store.store_save();
+    }
+}
+
+/// 读取网关节点表，返回节点编号和地址
+/// 标志位或校验和不正确时返回空表
+pub(super) fn load_nodes(store: &FlashStore) -> Vec<(u8, [u8; 5]), NODES_STORE_CAPACITY> {
+    let count = store.get_store(NODES_STORE_INDEX + 1) as usize;
+    let valid = store.get_store(NODES_STORE_INDEX) == NODES_MAGIC
+        && count <= NODES_STORE_CAPACITY
+        && store.get_store(NODES_STORE_INDEX + 2 + count * NODE_STORE_WORDS)
+            == nodes_checksum(store, count);
+
+    let mut nodes = Vec::new();
+    if !valid {
+        return nodes;
+    }
+    for i in 0..count {
+        let index = NODES_STORE_INDEX + 2 + i * NODE_STORE_WORDS;
+        let [node_id, a0] = store.get_store(index).to_le_bytes();
+        let [a1, a2] = store.get_store(index + 1).to_le_bytes();
+        let [a3, a4] = store.get_store(index + 2).to_le_bytes();
+        // 容量与 NODES_STORE_CAPACITY 相同，不会溢出
+        let _ = nodes.push((node_id, [a0, a1, a2, a3, a4]));
+    }
+    nodes
+}
+
+/// 保存网关节点表并写入 FLASH，超过 `NODES_STORE_CAPACITY` 的节点不保存
+pub(super) fn save_nodes(store: &FlashStore, nodes: impl Iterator<Item = (u8, [u8; 5])>) {
+    let mut count = 0;
+    for (node_id, address) in nodes.take(NODES_STORE_CAPACITY) {
+        let index = NODES_STORE_INDEX + 2 + count * NODE_STORE_WORDS;
+        store.set_store(index, u16::from_le_bytes([node_id, address[0]]));
+        store.set_store(index + 1, u16::from_le_bytes([address[1], address[2]]));
+        store.set_store(index + 2, u16::from_le_bytes([address[3], address[4]]));
+        count += 1;
+    }
+    store.set_store(NODES_STORE_INDEX, NODES_MAGIC);
+    store.set_store(NODES_STORE_INDEX + 1, count as u16);
+    store.set_store(
+        NODES_STORE_INDEX + 2 + count * NODE_STORE_WORDS,
+        nodes_checksum(store, count),
+    );
+    store.store_save();
+}
+
+/// 节点表的校验和，包含标志位和节点个数
+fn nodes_checksum(store: &FlashStore, count: usize) -> u16 {
+    let len = 2 + count * NODE_STORE_WORDS;
+    let words = (0..len).map(|i| store.get_store(NODES_STORE_INDEX + i));
+    !words.fold(0u16, |sum, word| sum.wrapping_add(word))
+}
+
+/// 校验和
+fn checksum(words: &[u16]) -> u16 {
+    !words.iter().fold(0u16, |sum, word| sum.wrapping_add(*word))
+}
@@ -0,0 +1,527 @@
+use super::conf::*;
+use super::config::{Config, ConfigError};
+
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::blocking::spi::Transfer;
+use embedded_hal::digital::v2::OutputPin;
+
+/// nRF24L01 操作错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// SPI 通信错误
+    Spi(E),
+    /// CE 或 CSN 引脚设置失败
+    Pin,
+    /// 读回的寄存器值与写入的不一致，模块未连接或接线错误
+    NotFound,
+    /// 配置参数无效
+    Config(ConfigError),
+    /// 数据长度为0或超过32字节
+    PayloadLength,
+    /// 发送缓冲区已满
+    TxFull,
+    /// 达到最大重发次数仍未收到应答，数据包已丢弃
+    MaxRetransmits,
+    /// 未开启应答附带数据
+    AckPayloadDisabled,
+}
+
+impl<E> From<ConfigError> for Error<E> {
+    fn from(e: ConfigError) -> Self {
+        Error::Config(e)
+    }
+}
+
+impl<E> defmt::Format for Error<E> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Error::Spi(_) => defmt::write!(f, "Spi"),
+            Error::Pin => defmt::write!(f, "Pin"),
+            Error::NotFound => defmt::write!(f, "NotFound"),
+            Error::Config(e) => defmt::write!(f, "Config({})", e),
+            Error::PayloadLength => defmt::write!(f, "PayloadLength"),
+            Error::TxFull => defmt::write!(f, "TxFull"),
+            Error::MaxRetransmits => defmt::write!(f, "MaxRetransmits"),
+            Error::AckPayloadDisabled => defmt::write!(f, "AckPayloadDisabled"),
+        }
+    }
+}
+
+/// 收到的数据包
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Payload {
+    pipe: u8,
+    len: u8,
+    data: [u8; NRF24_MAX_PAYLOAD],
+}
+
+impl Payload {
+    /// 接收通道号
+    pub fn pipe(&self) -> u8 {
+        self.pipe
+    }
+
+    /// 数据长度
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl AsRef<[u8]> for Payload {
+    fn as_ref(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+}
+
+/// 发送统计
+pub use portable::nrf24::Observe;
+
+/// 工作模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Mode {
+    /// 掉电
+    PowerDown,
+    /// 待机
+    Standby,
+    /// 接收
+    Rx,
+    /// 发送
+    Tx,
+}
+
+/// nRF24L01 驱动
+/// SPI 需要配置为模式0，时钟不超过 8MHz
+pub struct Nrf24L01<SPI, CE, CSN> {
+    spi: SPI,
+    ce: CE,
+    csn: CSN,
+    config: Config,
+    mode: Mode,
+}
+
+impl<SPI, CE, CSN, E> Nrf24L01<SPI, CE, CSN>
+where
+    SPI: Transfer<u8, Error = E>,
+    CE: OutputPin,
+    CSN: OutputPin,
+{
+    /// 检查模块是否存在，写入配置并上电进入待机模式
+    pub fn new(
+        spi: SPI,
+        ce: CE,
+        csn: CSN,
+        config: Config,
+        delay: &mut impl DelayUs<u32>,
+    ) -> Result<Self, Error<E>> {
+        config.validate()?;
+
+        let mut nrf = Nrf24L01 {
+            spi,
+            ce,
+            csn,
+            config,
+            mode: Mode::PowerDown,
+        };
+        nrf.ce_low()?;
+        nrf.csn_high()?;
+        // 芯片上电后需要 100ms 才能接收 SPI 指令
+        delay.delay_us(100_000);
+
+        // 写入后读回地址宽度寄存器，判断模块是否存在
+        nrf.write_register(NRF24_SETUP_AW, 0b01)?;
+        if nrf.read_register(NRF24_SETUP_AW)? != 0b01 {
+            return Err(Error::NotFound);
+        }
+
+        nrf.write_register(NRF24_CONFIG, 0)?;
+        nrf.apply_config()?;
+
+        nrf.write_register(NRF24_CONFIG, nrf.config.config_bits() | NRF24_CONFIG_PWR_UP)?;
+        delay.delay_us(NRF24_POWER_UP_US);
+        nrf.mode = Mode::Standby;
+
+        nrf.flush_tx()?;
+        nrf.flush_rx()?;
+        nrf.clear_interrupts()?;
+        Ok(nrf)
+    }
+
+    /// 释放 SPI 和引脚
+    pub fn free(self) -> (SPI, CE, CSN) {
+        (self.spi, self.ce, self.csn)
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// 当前工作模式
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// 修改配置，完成后进入待机模式
+    /// 掉电状态下调用时需要等待 `NRF24_POWER_UP_US` 后再开始收发
+    pub fn configure(&mut self, config: Config) -> Result<(), Error<E>> {
+        config.validate()?;
+        self.standby()?;
+        self.config = config;
+        self.apply_config()?;
+        self.write_register(
+            NRF24_CONFIG,
+            self.config.config_bits() | NRF24_CONFIG_PWR_UP,
+        )?;
+        self.mode = Mode::Standby;
+        Ok(())
+    }
+
+    /// 写入配置寄存器，需要在待机或掉电模式下调用
+    fn apply_config(&mut self) -> Result<(), Error<E>> {
+        let config = self.config;
+        self.write_register(NRF24_RF_CH, config.channel)?;
+        self.write_register(NRF24_RF_SETUP, config.rf_setup_bits())?;
+        self.write_register(NRF24_SETUP_AW, config.address_width.bits())?;
+        self.write_register(NRF24_SETUP_RETR, config.setup_retr_bits())?;
+        self.write_register(NRF24_EN_AA, config.en_aa_bits())?;
+        self.write_register(NRF24_EN_RXADDR, config.en_rxaddr_bits())?;
+
+        self.write_buf(NRF24_TX_ADDR, config.get_tx_address())?;
+        for pipe in 0..NRF24_PIPES {
+            let pipe_config = config.pipe(pipe);
+            let reg = NRF24_RX_ADDR_P0 + pipe as u8;
+            if pipe < 2 {
+                self.write_buf(reg, pipe_config.address())?;
+            } else {
+                self.write_register(reg, pipe_config.address[0])?;
+            }
+            self.write_register(NRF24_RX_PW_P0 + pipe as u8, config.payload_size)?;
+        }
+
+        // nRF24L01 需要先激活 FEATURE 和 DYNPD 寄存器，nRF24L01+ 可以直接写入
+        let feature = config.feature_bits();
+        self.write_register(NRF24_FEATURE, feature)?;
+        if self.read_register(NRF24_FEATURE)? != feature {
+            self.command(&mut [NRF24_ACTIVATE, NRF24_ACTIVATE_DATA])?;
+            self.write_register(NRF24_FEATURE, feature)?;
+        }
+        self.write_register(NRF24_DYNPD, config.dynpd_bits())
+    }
+
+    /// 上电进入待机模式
+    pub fn power_up(&mut self, delay: &mut impl DelayUs<u32>) -> Result<(), Error<E>> {
+        if self.mode != Mode::PowerDown {
+            return Ok(());
+        }
+        self.write_register(
+            NRF24_CONFIG,
+            self.config.config_bits() | NRF24_CONFIG_PWR_UP,
+        )?;
+        delay.delay_us(NRF24_POWER_UP_US);
+        self.mode = Mode::Standby;
+        Ok(())
+    }
+
+    /// 掉电，电流约 900nA，寄存器内容保持不变
+    pub fn power_down(&mut self) -> Result<(), Error<E>> {
+        self.ce_low()?;
+        self.write_register(NRF24_CONFIG, self.config.config_bits())?;
+        self.mode = Mode::PowerDown;
+        Ok(())
+    }
+
+    /// 停止收发，进入待机模式
+    pub fn standby(&mut self) -> Result<(), Error<E>> {
+        self.ce_low()?;
+        if self.mode != Mode::PowerDown {
+            self.mode = Mode::Standby;
+        }
+        Ok(())
+    }
+
+    /// 进入接收模式
+    pub fn listen(&mut self) -> Result<(), Error<E>> {
+        if self.mode == Mode::Rx {
+            return Ok(());
+        }
+        self.ce_low()?;
+        // 发送时通道0的地址被设置为发送地址，这里恢复
+        let pipe0 = *self.config.pipe(0);
+        if pipe0.enabled {
+            self.write_buf(NRF24_RX_ADDR_P0, pipe0.address())?;
+        }
+        self.write_register(NRF24_EN_RXADDR, self.config.en_rxaddr_bits())?;
+        self.write_register(
+            NRF24_CONFIG,
+            self.config.config_bits() | NRF24_CONFIG_PWR_UP | NRF24_CONFIG_PRIM_RX,
+        )?;
+        self.mode = Mode::Rx;
+        self.ce_high()
+    }
+
+    /// 进入发送模式
+    fn enter_tx(&mut self) -> Result<(), Error<E>> {
+        if self.mode == Mode::Tx {
+            return Ok(());
+        }
+        self.ce_low()?;
+        // 开启自动应答时应答包发往发送地址，用通道0接收
+        if self.config.pipe(0).auto_ack {
+            let mut address = [0; 5];
+            let tx_address = self.config.get_tx_address();
+            address[..tx_address.len()].copy_from_slice(tx_address);
+            self.write_buf(NRF24_RX_ADDR_P0, &address[..tx_address.len()])?;
+            let en_rxaddr = self.config.en_rxaddr_bits() | 1;
+            self.write_register(NRF24_EN_RXADDR, en_rxaddr)?;
+        }
+        self.write_register(
+            NRF24_CONFIG,
+            self.config.config_bits() | NRF24_CONFIG_PWR_UP,
+        )?;
+        self.mode = Mode::Tx;
+        Ok(())
+    }
+
+    /// 修改发送地址，地址长度需要与地址宽度一致
+    pub fn set_tx_address(&mut self, address: &[u8]) -> Result<(), Error<E>> {
+        let config = self.config.tx_address(address);
+        config.validate()?;
+        self.config = config;
+        self.write_buf(NRF24_TX_ADDR, address)?;
+        // 发送模式下同步修改通道0的地址
+        if self.mode == Mode::Tx && self.config.pipe(0).auto_ack {
+            self.write_buf(NRF24_RX_ADDR_P0, address)?;
+        }
+        Ok(())
+    }
+
+    /// 修改频道
+    pub fn set_channel(&mut self, channel: u8) -> Result<(), Error<E>> {
+        let config = self.config.channel(channel);
+        config.validate()?;
+        self.config = config;
+        self.write_register(NRF24_RF_CH, channel)
+    }
+
+    /// 将数据写入发送缓冲区并开始发送，不等待发送完成
+    /// 发送缓冲区已满时返回 `Error::TxFull`，可以稍后调用 `poll_send` 等待缓冲区空出
+    pub fn send(&mut self, payload: &[u8]) -> Result<(), Error<E>> {
+        self.write_payload(NRF24_W_TX_PAYLOAD, payload)
+    }
+
+    /// 发送数据，接收方不回复应答，也不会重发
+    pub fn send_no_ack(&mut self, payload: &[u8]) -> Result<(), Error<E>> {
+        self.write_payload(NRF24_W_TX_PAYLOAD_NOACK, payload)
+    }
+
+    fn write_payload(&mut self, command: u8, payload: &[u8]) -> Result<(), Error<E>> {
+        if payload.is_empty() || payload.len() > NRF24_MAX_PAYLOAD {
+            return Err(Error::PayloadLength);
+        }
+        // 固定数据长度时补齐
+        let len = if self.config.dynamic_payload {
+            payload.len()
+        } else {
+            let size = self.config.payload_size as usize;
+            if payload.len() > size {
+                return Err(Error::PayloadLength);
+            }
+            size
+        };
+
+        self.enter_tx()?;
+        if self.status()? & NRF24_STATUS_TX_FULL != 0 {
+            return Err(Error::TxFull);
+        }
+
+        let mut buf = [0; NRF24_MAX_PAYLOAD + 1];
+        buf[0] = command;
+        buf[1..=payload.len()].copy_from_slice(payload);
+        self.command(&mut buf[..=len])?;
+
+        // CE 保持高电平，发送缓冲区中的数据依次发出
+        self.ce_high()
+    }
+
+    /// 查询发送结果
+    /// 收到应答(或关闭自动应答时发送完成)返回 `Ok`，
+    /// 达到最大重发次数时清空发送缓冲区并返回 `Error::MaxRetransmits`
+    pub fn poll_send(&mut self) -> nb::Result<(), Error<E>> {
+        let status = self.status()?;
+        if status & NRF24_STATUS_MAX_RT != 0 {
+            self.flush_tx()?;
+            self.write_register(NRF24_STATUS, NRF24_STATUS_MAX_RT)?;
+            return Err(nb::Error::Other(Error::MaxRetransmits));
+        }
+        if status & NRF24_STATUS_TX_DS != 0 {
+            self.write_register(NRF24_STATUS, NRF24_STATUS_TX_DS)?;
+            return Ok(());
+        }
+        if self.read_register(NRF24_FIFO_STATUS)? & NRF24_FIFO_STATUS_TX_EMPTY != 0 {
+            return Ok(());
+        }
+        Err(nb::Error::WouldBlock)
+    }
+
+    /// 发送数据并等待发送完成
+    pub fn transmit(&mut self, payload: &[u8]) -> Result<(), Error<E>> {
+        loop {
+            match self.send(payload) {
+                Ok(()) => break,
+                // 缓冲区已满时等待前面的数据发送完成
+                Err(Error::TxFull) => nb::block!(self.poll_send())?,
+                Err(e) => return Err(e),
+            }
+        }
+        nb::block!(self.poll_send())
+    }
+
+    /// 接收缓冲区中第一个数据包的通道号，为空时返回 None
+    pub fn data_pipe(&mut self) -> Result<Option<u8>, Error<E>> {
+        if self.read_register(NRF24_FIFO_STATUS)? & NRF24_FIFO_STATUS_RX_EMPTY != 0 {
+            return Ok(None);
+        }
+        let pipe = (self.status()? & NRF24_STATUS_RX_P_NO_MASK) >> 1;
+        Ok((pipe < NRF24_PIPES as u8).then_some(pipe))
+    }
+
+    /// 读取一个数据包，接收缓冲区为空时返回 `WouldBlock`
+    pub fn read(&mut self) -> nb::Result<Payload, Error<E>> {
+        let Some(pipe) = self.data_pipe()? else {
+            return Err(nb::Error::WouldBlock);
+        };
+
+        let len = if self.config.dynamic_payload {
+            let mut buf = [NRF24_R_RX_PL_WID, 0];
+            self.command(&mut buf)?;
+            // 长度无效时数据包已损坏，需要丢弃
+            if buf[1] as usize > NRF24_MAX_PAYLOAD {
+                self.flush_rx()?;
+                return Err(nb::Error::WouldBlock);
+            }
+            buf[1] as usize
+        } else {
+            self.config.payload_size as usize
+        };
+
+        let mut buf = [0; NRF24_MAX_PAYLOAD + 1];
+        buf[0] = NRF24_R_RX_PAYLOAD;
+        self.command(&mut buf[..=len])?;
+        self.write_register(NRF24_STATUS, NRF24_STATUS_RX_DR)?;
+
+        let mut payload = Payload {
+            pipe,
+            len: len as u8,
+            data: [0; NRF24_MAX_PAYLOAD],
+        };
+        payload.data[..len].copy_from_slice(&buf[1..=len]);
+        Ok(payload)
+    }
+
+    /// 写入下一次回复应答时附带的数据
+    /// 接收方调用，每个通道最多缓存3个
+    pub fn write_ack_payload(&mut self, pipe: u8, payload: &[u8]) -> Result<(), Error<E>> {
+        if !self.config.ack_payload {
+            return Err(Error::AckPayloadDisabled);
+        }
+        if payload.is_empty() || payload.len() > NRF24_MAX_PAYLOAD {
+            return Err(Error::PayloadLength);
+        }
+        if self.status()? & NRF24_STATUS_TX_FULL != 0 {
+            return Err(Error::TxFull);
+        }
+
+        let mut buf = [0; NRF24_MAX_PAYLOAD + 1];
+        buf[0] = NRF24_W_ACK_PAYLOAD | (pipe & 0b111);
+        buf[1..=payload.len()].copy_from_slice(payload);
+        self.command(&mut buf[..=payload.len()])
+    }
+
+    /// 读取发送统计
+    pub fn observe(&mut self) -> Result<Observe, Error<E>> {
+        let value = self.read_register(NRF24_OBSERVE_TX)?;
+        Ok(Observe {
+            lost: value >> 4,
+            retries: value & 0x0F,
+        })
+    }
+
+    /// 当前频道是否检测到大于 -64dBm 的信号，需要在接收模式下至少持续 170us
+    pub fn carrier_detected(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.read_register(NRF24_RPD)? & 1 != 0)
+    }
+
+    /// 读取状态寄存器
+    pub fn status(&mut self) -> Result<u8, Error<E>> {
+        let mut buf = [NRF24_NOP];
+        self.command(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    /// 清除中断标志
+    pub fn clear_interrupts(&mut self) -> Result<(), Error<E>> {
+        self.write_register(
+            NRF24_STATUS,
+            NRF24_STATUS_RX_DR | NRF24_STATUS_TX_DS | NRF24_STATUS_MAX_RT,
+        )
+    }
+
+    /// 清空发送缓冲区
+    pub fn flush_tx(&mut self) -> Result<(), Error<E>> {
+        self.command(&mut [NRF24_FLUSH_TX])
+    }
+
+    /// 清空接收缓冲区
+    pub fn flush_rx(&mut self) -> Result<(), Error<E>> {
+        self.command(&mut [NRF24_FLUSH_RX])
+    }
+
+    /// 读寄存器
+    pub fn read_register(&mut self, reg: u8) -> Result<u8, Error<E>> {
+        let mut buf = [NRF24_R_REGISTER | reg, 0];
+        self.command(&mut buf)?;
+        Ok(buf[1])
+    }
+
+    /// 写寄存器
+    pub fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Error<E>> {
+        self.command(&mut [NRF24_W_REGISTER | reg, value])
+    }
+
+    /// 写多字节寄存器，用于写入地址
+    fn write_buf(&mut self, reg: u8, data: &[u8]) -> Result<(), Error<E>> {
+        let mut buf = [0; 6];
+        buf[0] = NRF24_W_REGISTER | reg;
+        buf[1..=data.len()].copy_from_slice(data);
+        self.command(&mut buf[..=data.len()])
+    }
+
+    /// 发送指令，返回的数据覆盖 buf，第一个字节为状态寄存器
+    fn command(&mut self, buf: &mut [u8]) -> Result<(), Error<E>> {
+        self.csn_low()?;
+        let result = self.spi.transfer(buf).map(|_| ()).map_err(Error::Spi);
+        self.csn_high()?;
+        result
+    }
+
+    fn ce_high(&mut self) -> Result<(), Error<E>> {
+        self.ce.set_high().map_err(|_| Error::Pin)
+    }
+
+    fn ce_low(&mut self) -> Result<(), Error<E>> {
+        self.ce.set_low().map_err(|_| Error::Pin)
+    }
+
+    fn csn_high(&mut self) -> Result<(), Error<E>> {
+        self.csn.set_high().map_err(|_| Error::Pin)
+    }
+
+    fn csn_low(&mut self) -> Result<(), Error<E>> {
+        self.csn.set_low().map_err(|_| Error::Pin)
+    }
+}
@@ -0,0 +1,56 @@
+//! 可靠消息传输
+//! 分片重组、应答重发和去重由平台无关工具库 `portable::nrf24::transport` 提供，这里为 nRF24L01 实现 `Radio`
+//!
+//! ```rust
+//! let mut transport: Transport<_, 1024> = Transport::new(nrf24, TransportConfig::default(), session);
+//! transport.send(b"hello", time::now())?;
+//! loop {
+//!     transport.poll(time::now())?;
+//!     if let Some(message) = transport.receive() {
+//!         println!("{}", message);
+//!     }
+//! }
+//! ```
+
+pub use portable::nrf24::transport::*;
+
+use super::conf::NRF24_MAX_PAYLOAD;
+use super::nrf24_hal::{self, Nrf24L01, Observe};
+
+use embedded_hal::blocking::spi::Transfer;
+use embedded_hal::digital::v2::OutputPin;
+
+/// nRF24L01 发送完成后回到接收模式
+impl<SPI, CE, CSN, E> Radio for Nrf24L01<SPI, CE, CSN>
+where
+    SPI: Transfer<u8, Error = E>,
+    CE: OutputPin,
+    CSN: OutputPin,
+{
+    type Error = nrf24_hal::Error<E>;
+
+    fn transmit(&mut self, frame: &[u8]) -> Result<bool, Self::Error> {
+        let result = match Nrf24L01::transmit(self, frame) {
+            Ok(()) => Ok(true),
+            Err(nrf24_hal::Error::MaxRetransmits) => Ok(false),
+            Err(e) => Err(e),
+        };
+        self.listen()?;
+        result
+    }
+
+    fn receive(&mut self, buf: &mut [u8; NRF24_MAX_PAYLOAD]) -> nb::Result<usize, Self::Error> {
+        self.listen()?;
+        let payload = self.read()?;
+        buf[..payload.len()].copy_from_slice(payload.as_ref());
+        Ok(payload.len())
+    }
+
+    fn observe(&mut self) -> Result<Observe, Self::Error> {
+        Nrf24L01::observe(self)
+    }
+
+    fn carrier_detected(&mut self) -> Result<bool, Self::Error> {
+        Nrf24L01::carrier_detected(self)
+    }
+}
@@ -0,0 +1,40 @@
+//! 基准测试
+//! 在目标板上反复执行函数，统计每次执行的时钟周期数，已扣除读取计数器本身的开销
+//! 函数的返回值和参数可以用 `core::hint::black_box` 包裹，防止被编译器优化掉
+//!
+//! ```rust
+//! profile::bench("oled show_string", 100, || oled.show_string(1, 1, "Hello"));
+//! profile::bench("madgwick", 1000, || filter.update(black_box(&m), dt));
+//! ```
+
+use super::{cycles, print_stats, Stats};
+
+/// 测量读取周期计数器的开销
+/// 多次测量取最小值
+pub fn overhead() -> u32 {
+    let mut min = u32::MAX;
+    for _ in 0..8 {
+        let start = cycles();
+        let end = cycles();
+        min = min.min(end.wrapping_sub(start));
+    }
+    min
+}
+
+/// 执行函数 iterations 次，通过 defmt 输出并返回统计结果
+/// 正式测量前先执行一次预热，排除首次执行时缓存、FLASH 预取等的影响
+pub fn bench(name: &str, iterations: u32, mut f: impl FnMut()) -> Stats {
+    f();
+
+    let overhead = overhead();
+    let mut stats = Stats::new();
+    for _ in 0..iterations {
+        let start = cycles();
+        f();
+        let elapsed = cycles().wrapping_sub(start);
+        stats.record(elapsed.saturating_sub(overhead));
+    }
+
+    print_stats(name, &stats);
+    stats
+}
@@ -0,0 +1,191 @@
+//! 代码耗时分析
+//! 使用 Cortex-M3 DWT 的周期计数器 CYCCNT 测量代码段的执行时间，精度为1个内核时钟周期
+//! CYCCNT 为32位计数器，72MHz 下约59s溢出一次，单次测量的代码段不能超过这个时间
+//!
+//! - `scope`: 作用域计时，离开作用域时按名称累计最小/最大/平均耗时
+//! - `report`、`write_report`: 通过 defmt 或串口输出统计结果
+//! - `bench`: 在目标板上反复执行函数，测量驱动函数的耗时
+//!
+//! ```rust
+//! profile::init(&mut cp.DCB, &mut cp.DWT, &clocks);
+//!
+//! loop {
+//!     let m = {
+//!         let _scope = profile::scope("mpu6050");
+//!         mpu.get_measurement().unwrap()
+//!     };
+//!     profile::measure("madgwick", || filter.update(&m, dt));
+//! }
+//!
+//! profile::report();
+//! profile::write_report(&mut tx).unwrap();
+//! ```
+
+pub mod bench;
+
+pub use portable::stats;
+
+pub use bench::bench;
+pub use stats::{Profiler, ProfilerFull, Stats};
+
+use core::cell::RefCell;
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use cortex_m::peripheral::{DCB, DWT};
+use critical_section::Mutex;
+use defmt::println;
+use stm32f1xx_hal::rcc::Clocks;
+
+/// 全局统计最多保存的代码段个数
+pub const SECTION_CAPACITY: usize = 16;
+
+/// 内核时钟频率，用于将周期数换算为时间
+static CORE_HZ: AtomicU32 = AtomicU32::new(0);
+/// 全局统计
+static PROFILER: Mutex<RefCell<Profiler<SECTION_CAPACITY>>> =
+    Mutex::new(RefCell::new(Profiler::new()));
+
+/// 使能 DWT 周期计数器
+pub fn init(dcb: &mut DCB, dwt: &mut DWT, clocks: &Clocks) {
+    dcb.enable_trace();
+    DWT::unlock();
+    dwt.set_cycle_count(0);
+    dwt.enable_cycle_counter();
+    set_core_clock(clocks);
+}
+
+/// 切换系统时钟后更新内核时钟频率
+pub fn set_core_clock(clocks: &Clocks) {
+    CORE_HZ.store(clocks.hclk().raw(), Ordering::Relaxed);
+}
+
+/// 当前周期计数
+pub fn cycles() -> u32 {
+    DWT::cycle_count()
+}
+
+/// 周期数换算为微秒
+pub fn cycles_to_us(cycles: u32) -> u32 {
+    let hz = CORE_HZ.load(Ordering::Relaxed);
+    if hz == 0 {
+        return 0;
+    }
+    (cycles as u64 * 1_000_000 / hz as u64) as u32
+}
+
+/// 作用域计时
+/// 离开作用域时将耗时记录到全局统计
+#[must_use = "计时在离开作用域时结束，需要绑定到变量"]
+pub struct Scope {
+    name: &'static str,
+    start: u32,
+}
+
+impl Scope {
+    /// 开始计时
+    pub fn new(name: &'static str) -> Self {
+        Scope {
+            name,
+            start: cycles(),
+        }
+    }
+
+    /// 开始计时后经过的周期数
+    pub fn elapsed(&self) -> u32 {
+        cycles().wrapping_sub(self.start)
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        // 代码段数量超过容量时不记录
+        let _ = record(self.name, self.elapsed());
+    }
+}
+
+/// 开始作用域计时
+pub fn scope(name: &'static str) -> Scope {
+    Scope::new(name)
+}
+
+/// 执行函数并记录耗时
+pub fn measure<R>(name: &'static str, f: impl FnOnce() -> R) -> R {
+    let _scope = Scope::new(name);
+    f()
+}
+
+/// 记录一次耗时
+pub fn record(name: &'static str, cycles: u32) -> Result<(), ProfilerFull> {
+    critical_section::with(|cs| PROFILER.borrow_ref_mut(cs).record(name, cycles))
+}
+
+/// 代码段的统计
+pub fn get(name: &str) -> Option<Stats> {
+    critical_section::with(|cs| PROFILER.borrow_ref(cs).get(name).copied())
+}
+
+/// 清空统计
+pub fn reset() {
+    critical_section::with(|cs| PROFILER.borrow_ref_mut(cs).reset());
+}
+
+/// 复制一份全局统计，输出时不占用临界区
+fn snapshot() -> Profiler<SECTION_CAPACITY> {
+    critical_section::with(|cs| PROFILER.borrow_ref(cs).clone())
+}
+
+/// 通过 defmt 输出统计结果
+pub fn report() {
+    println!("profile report:");
+    for (name, stats) in snapshot().iter() {
+        print_stats(name, stats);
+    }
+}
+
+/// 通过串口等输出统计结果
+pub fn write_report<W: fmt::Write>(w: &mut W) -> fmt::Result {
+    w.write_str("profile report:\r\n")?;
+    for (name, stats) in snapshot().iter() {
+        write_stats(w, name, stats)?;
+    }
+    Ok(())
+}
+
+/// 通过 defmt 输出一个代码段的统计
+pub fn print_stats(name: &str, stats: &Stats) {
+    let (Some(min), Some(mean), Some(max)) = (stats.min(), stats.mean(), stats.max()) else {
+        println!("{}: no samples", name);
+        return;
+    };
+    println!(
+        "{}: count {}, min {} cycles ({} us), mean {} cycles ({} us), max {} cycles ({} us)",
+        name,
+        stats.count(),
+        min,
+        cycles_to_us(min),
+        mean,
+        cycles_to_us(mean),
+        max,
+        cycles_to_us(max)
+    );
+}
+
+/// 输出一个代码段的统计
+pub fn write_stats<W: fmt::Write>(w: &mut W, name: &str, stats: &Stats) -> fmt::Result {
+    let (Some(min), Some(mean), Some(max)) = (stats.min(), stats.mean(), stats.max()) else {
+        return write!(w, "{}: no samples\r\n", name);
+    };
+    write!(
+        w,
+        "{}: count {}, min {} cycles ({} us), mean {} cycles ({} us), max {} cycles ({} us)\r\n",
+        name,
+        stats.count(),
+        min,
+        cycles_to_us(min),
+        mean,
+        cycles_to_us(mean),
+        max,
+        cycles_to_us(max)
+    )
+}
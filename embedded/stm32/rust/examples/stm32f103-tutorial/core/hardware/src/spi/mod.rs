@@ -0,0 +1,5 @@
+//! SPI 总线工具库
+
+pub mod soft;
+
+pub use soft::SoftSpi;
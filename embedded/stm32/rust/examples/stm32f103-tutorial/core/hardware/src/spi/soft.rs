@@ -0,0 +1,365 @@
+//! 软件 SPI 主机
+//! 使用任意三个引脚模拟 SPI 时序，支持模式0~3、高位或低位先行、8位和16位数据，
+//! 实现了 embedded-hal 1.0 的 `SpiBus` 和 0.2 的 `Transfer`、`Write`，片选由设备驱动或 `bus::SpiDevice` 控制
+//!
+//! - 不需要读取数据的设备(如显示屏)，MISO 可以使用 `NoMiso`
+//! - 默认不延时，以引脚翻转的最快速度传输；需要降低速度时使用 `new_with_delay` 指定时钟频率
+//!
+//! ```rust
+//! let sck = gpioa.pa5.into_push_pull_output(&mut gpioa.crl);
+//! let mosi = gpioa.pa7.into_push_pull_output(&mut gpioa.crl);
+//! let miso = gpioa.pa6.into_pull_up_input(&mut gpioa.crl);
+//! let mut spi = SoftSpi::new(sck, mosi, miso, MODE_0);
+//!
+//! let mut buffer = [0x9F, 0, 0, 0];
+//! spi.transfer_in_place(&mut buffer).unwrap();
+//! ```
+
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::blocking::spi as spi_02;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_hal_1::spi::{self as spi_1, ErrorKind, Mode, Phase, Polarity, SpiBus};
+use stm32f1xx_hal::time::Hertz;
+
+/// 软件 SPI 错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// 引脚读写失败
+    Pin,
+}
+
+impl spi_1::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// 数据位顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOrder {
+    /// 高位先行，大多数设备使用
+    MsbFirst,
+    /// 低位先行
+    LsbFirst,
+}
+
+/// 不延时，以引脚翻转的最快速度传输
+pub struct NoWait;
+
+impl DelayUs<u32> for NoWait {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+/// 没有 MISO 引脚，读取的数据始终为0
+pub struct NoMiso;
+
+impl InputPin for NoMiso {
+    type Error = core::convert::Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+/// 数据字长
+pub trait Word: Copy + 'static {
+    /// 位数
+    const BITS: u8;
+
+    fn to_bits(self) -> u16;
+
+    fn from_bits(bits: u16) -> Self;
+}
+
+impl Word for u8 {
+    const BITS: u8 = 8;
+
+    fn to_bits(self) -> u16 {
+        self as u16
+    }
+
+    fn from_bits(bits: u16) -> Self {
+        bits as u8
+    }
+}
+
+impl Word for u16 {
+    const BITS: u8 = 16;
+
+    fn to_bits(self) -> u16 {
+        self
+    }
+
+    fn from_bits(bits: u16) -> Self {
+        bits
+    }
+}
+
+/// 软件 SPI 主机
+pub struct SoftSpi<Sck, Mosi, Miso, Delay = NoWait> {
+    sck: Sck,
+    mosi: Mosi,
+    miso: Miso,
+    delay: Delay,
+    mode: Mode,
+    bit_order: BitOrder,
+    /// 半个时钟周期(us)，为0时不延时
+    half_period_us: u32,
+}
+
+impl<Sck, Mosi, Miso> SoftSpi<Sck, Mosi, Miso, NoWait>
+where
+    Sck: OutputPin,
+    Mosi: OutputPin,
+    Miso: InputPin,
+{
+    /// 创建实例，不延时，高位先行
+    pub fn new(sck: Sck, mosi: Mosi, miso: Miso, mode: Mode) -> Self {
+        let mut spi = SoftSpi {
+            sck,
+            mosi,
+            miso,
+            delay: NoWait,
+            mode,
+            bit_order: BitOrder::MsbFirst,
+            half_period_us: 0,
+        };
+        spi.set_mode(mode);
+        spi
+    }
+}
+
+impl<Sck, Mosi, Miso, Delay> SoftSpi<Sck, Mosi, Miso, Delay>
+where
+    Sck: OutputPin,
+    Mosi: OutputPin,
+    Miso: InputPin,
+    Delay: DelayUs<u32>,
+{
+    /// 创建实例，按指定的时钟频率延时，高位先行
+    /// frequency: SCK 时钟频率，延时精度为1us，实际频率受引脚翻转耗时影响会略低
+    pub fn new_with_delay(
+        sck: Sck,
+        mosi: Mosi,
+        miso: Miso,
+        mode: Mode,
+        delay: Delay,
+        frequency: Hertz,
+    ) -> Self {
+        let mut spi = SoftSpi {
+            sck,
+            mosi,
+            miso,
+            delay,
+            mode,
+            bit_order: BitOrder::MsbFirst,
+            half_period_us: 0,
+        };
+        spi.set_frequency(frequency);
+        spi.set_mode(mode);
+        spi
+    }
+
+    /// 设置 SPI 模式，SCK 回到空闲电平
+    /// 需要在片选无效时调用
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        let _ = self.sck_idle();
+    }
+
+    /// 当前 SPI 模式
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// 设置数据位顺序
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.bit_order = bit_order;
+    }
+
+    /// 当前数据位顺序
+    pub fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    /// 设置 SCK 时钟频率
+    pub fn set_frequency(&mut self, frequency: Hertz) {
+        self.half_period_us = (500_000 / frequency.raw().max(1)).max(1);
+    }
+
+    /// 释放引脚和延时
+    pub fn free(self) -> (Sck, Mosi, Miso, Delay) {
+        (self.sck, self.mosi, self.miso, self.delay)
+    }
+
+    /// 半个时钟周期的延时
+    fn wait(&mut self) {
+        if self.half_period_us > 0 {
+            self.delay.delay_us(self.half_period_us);
+        }
+    }
+
+    /// SCK 回到空闲电平
+    fn sck_idle(&mut self) -> Result<(), Error> {
+        match self.mode.polarity {
+            Polarity::IdleLow => self.sck.set_low(),
+            Polarity::IdleHigh => self.sck.set_high(),
+        }
+        .map_err(|_| Error::Pin)
+    }
+
+    /// SCK 切换到有效电平
+    fn sck_active(&mut self) -> Result<(), Error> {
+        match self.mode.polarity {
+            Polarity::IdleLow => self.sck.set_high(),
+            Polarity::IdleHigh => self.sck.set_low(),
+        }
+        .map_err(|_| Error::Pin)
+    }
+
+    fn write_mosi(&mut self, bit: bool) -> Result<(), Error> {
+        if bit {
+            self.mosi.set_high().map_err(|_| Error::Pin)
+        } else {
+            self.mosi.set_low().map_err(|_| Error::Pin)
+        }
+    }
+
+    fn read_miso(&mut self) -> Result<bool, Error> {
+        self.miso.is_high().map_err(|_| Error::Pin)
+    }
+
+    /// 交换一位
+    /// 模式0、2在第一个边沿采样，数据需要在第一个边沿之前准备好；
+    /// 模式1、3在第一个边沿移出数据，第二个边沿采样
+    fn swap_bit(&mut self, bit: bool) -> Result<bool, Error> {
+        let received = match self.mode.phase {
+            Phase::CaptureOnFirstTransition => {
+                self.write_mosi(bit)?;
+                self.wait();
+                self.sck_active()?;
+                self.wait();
+                let received = self.read_miso()?;
+                self.sck_idle()?;
+                received
+            }
+            Phase::CaptureOnSecondTransition => {
+                self.sck_active()?;
+                self.write_mosi(bit)?;
+                self.wait();
+                self.sck_idle()?;
+                let received = self.read_miso()?;
+                self.wait();
+                received
+            }
+        };
+        Ok(received)
+    }
+
+    /// 交换一个字
+    pub fn swap_word<W: Word>(&mut self, word: W) -> Result<W, Error> {
+        let bits = word.to_bits();
+        let mut received = 0u16;
+        for i in 0..W::BITS {
+            let shift = match self.bit_order {
+                BitOrder::MsbFirst => W::BITS - 1 - i,
+                BitOrder::LsbFirst => i,
+            };
+            if self.swap_bit(bits & (1 << shift) != 0)? {
+                received |= 1 << shift;
+            }
+        }
+        Ok(W::from_bits(received))
+    }
+}
+
+impl<Sck, Mosi, Miso, Delay> spi_1::ErrorType for SoftSpi<Sck, Mosi, Miso, Delay> {
+    type Error = Error;
+}
+
+impl<Sck, Mosi, Miso, Delay, W> SpiBus<W> for SoftSpi<Sck, Mosi, Miso, Delay>
+where
+    Sck: OutputPin,
+    Mosi: OutputPin,
+    Miso: InputPin,
+    Delay: DelayUs<u32>,
+    W: Word,
+{
+    fn read(&mut self, words: &mut [W]) -> Result<(), Self::Error> {
+        for word in words.iter_mut() {
+            *word = self.swap_word(W::from_bits(0))?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[W]) -> Result<(), Self::Error> {
+        for word in words {
+            self.swap_word(*word)?;
+        }
+        Ok(())
+    }
+
+    /// 长度不同时按较长的一方传输，多出的部分发送0或丢弃读取的数据
+    fn transfer(&mut self, read: &mut [W], write: &[W]) -> Result<(), Self::Error> {
+        for i in 0..read.len().max(write.len()) {
+            let word = write.get(i).copied().unwrap_or(W::from_bits(0));
+            let received = self.swap_word(word)?;
+            if let Some(slot) = read.get_mut(i) {
+                *slot = received;
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [W]) -> Result<(), Self::Error> {
+        for word in words.iter_mut() {
+            *word = self.swap_word(*word)?;
+        }
+        Ok(())
+    }
+
+    /// 每一位都是同步传输的，没有缓冲数据
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// 实现 embedded-hal 0.2 的阻塞接口
+macro_rules! impl_blocking_spi {
+    ($($word:ty),+) => {$(
+        impl<Sck, Mosi, Miso, Delay> spi_02::Transfer<$word> for SoftSpi<Sck, Mosi, Miso, Delay>
+        where
+            Sck: OutputPin,
+            Mosi: OutputPin,
+            Miso: InputPin,
+            Delay: DelayUs<u32>,
+        {
+            type Error = Error;
+
+            fn transfer<'w>(&mut self, words: &'w mut [$word]) -> Result<&'w [$word], Self::Error> {
+                SpiBus::transfer_in_place(self, words)?;
+                Ok(words)
+            }
+        }
+
+        impl<Sck, Mosi, Miso, Delay> spi_02::Write<$word> for SoftSpi<Sck, Mosi, Miso, Delay>
+        where
+            Sck: OutputPin,
+            Mosi: OutputPin,
+            Miso: InputPin,
+            Delay: DelayUs<u32>,
+        {
+            type Error = Error;
+
+            fn write(&mut self, words: &[$word]) -> Result<(), Self::Error> {
+                SpiBus::write(self, words)
+            }
+        }
+    )+};
+}
+
+impl_blocking_spi!(u8, u16);
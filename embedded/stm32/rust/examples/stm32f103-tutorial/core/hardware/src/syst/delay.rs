@@ -1,5 +1,7 @@
 //! 延时工具库
 
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal_1::delay::DelayNs;
 use stm32f1xx_hal::flash;
 use stm32f1xx_hal::rcc;
 use stm32f1xx_hal::timer::{SysDelay, SysTimerExt};
@@ -16,3 +18,32 @@ pub fn sys_delay(
     // 具有自定义精度的阻塞延迟
     syst.delay(&clocks)
 }
+
+/// 忙等待延时
+/// 按系统时钟计算循环次数，不占用定时器，可以复制给多个驱动使用
+#[derive(Debug, Clone, Copy)]
+pub struct BusyDelay {
+    /// 系统时钟频率(Hz)
+    sysclk: u32,
+}
+
+impl BusyDelay {
+    pub fn new(clocks: &rcc::Clocks) -> Self {
+        BusyDelay {
+            sysclk: clocks.sysclk().raw(),
+        }
+    }
+}
+
+impl DelayNs for BusyDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        let cycles = (self.sysclk as u64 * ns as u64).div_ceil(1_000_000_000);
+        cortex_m::asm::delay(cycles as u32);
+    }
+}
+
+impl DelayUs<u32> for BusyDelay {
+    fn delay_us(&mut self, us: u32) {
+        DelayNs::delay_us(self, us);
+    }
+}
@@ -0,0 +1,155 @@
+//! 系统时间基准
+//! SysTick 每1ms中断一次，在中断中累计系统运行时间，读取时加上 SysTick 当前计数值，得到微秒精度的单调时间
+//!
+//! - `now`: 当前时间，`fugit::Instant`，上电后约58万年才会溢出
+//! - `Timeout`、`Deadline`: 非阻塞的超时判断，用于等待外设、按键消抖等
+//! - `start_once`、`start_periodic`: 软件定时器，回调函数在 SysTick 中断中执行
+//! - `Mono`: 同时实现了阻塞延时和 RTIC 的 `Monotonic`
+//! - `sleep`、`sleep_until`: 异步延时，配合 `asynch` 中的执行器使用
+//!
+//! 应用需要在 SysTick 中断中调用 `tick`，RTIC 应用将 `Mono` 绑定到 SysTick 后由 RTIC 调用
+//!
+//! ```rust
+//! let mono = time::Mono::new(cp.SYST, &clocks);
+//!
+//! time::start_periodic(500.millis(), |_| toggle_led()).unwrap();
+//! let timeout = time::Timeout::new(100.millis());
+//! while !ready() {
+//!     if timeout.is_expired() {
+//!         break;
+//!     }
+//! }
+//!
+//! #[exception]
+//! fn SysTick() {
+//!     time::tick();
+//! }
+//! ```
+
+pub mod mono;
+pub mod sleep;
+pub mod timeout;
+pub mod wheel;
+
+pub use mono::Mono;
+pub use sleep::{sleep, sleep_until, Sleep};
+pub use timeout::{Deadline, Timeout, TimeoutError};
+pub use wheel::{TimerFull, TimerId, TimerWheel};
+
+use core::cell::{Cell, RefCell};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use cortex_m::peripheral::SYST;
+use critical_section::{CriticalSection, Mutex};
+
+/// SysTick 中断频率
+pub const TICK_HZ: u32 = 1_000;
+/// 时间精度，1us
+pub const TIMER_HZ: u32 = 1_000_000;
+/// 全局软件定时器的个数
+pub const TIMER_CAPACITY: usize = 8;
+
+/// 时间点
+pub type Instant = fugit::TimerInstantU64<TIMER_HZ>;
+/// 时间间隔
+pub type Duration = fugit::TimerDurationU64<TIMER_HZ>;
+
+/// SysTick 计数溢出标志位
+const SYST_CSR_COUNTFLAG: u32 = 1 << 16;
+
+/// 系统运行的毫秒数，只在 SysTick 中断中累加
+static MILLIS: Mutex<Cell<u64>> = Mutex::new(Cell::new(0));
+/// `now` 读取 CSR 时清除了溢出标志，这次溢出留给 SysTick 中断累加
+static WRAPPED: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
+/// SysTick 重装值，为0表示还没有启动
+static RELOAD: AtomicU32 = AtomicU32::new(0);
+/// 全局软件定时器
+static TIMERS: Mutex<RefCell<TimerWheel<TIMER_CAPACITY>>> =
+    Mutex::new(RefCell::new(TimerWheel::new()));
+
+/// 当前时间
+/// SysTick 启动之前始终为0
+pub fn now() -> Instant {
+    critical_section::with(|cs| {
+        let reload = RELOAD.load(Ordering::Relaxed);
+        if reload == 0 {
+            return Instant::from_ticks(0);
+        }
+
+        let mut current = SYST::get_current();
+        let mut millis = MILLIS.borrow(cs).get();
+        // 先读计数值再读溢出标志，已经溢出但 SysTick 中断还没有执行时重新读取计数值，
+        // 并补上这1ms，在临界区或更高优先级的中断中读取时间也不会倒退
+        if wrapped(cs) {
+            current = SYST::get_current();
+            millis += 1;
+        }
+        let micros = (reload - current) as u64 * 1000 / (reload as u64 + 1);
+        Instant::from_ticks(millis * 1000 + micros)
+    })
+}
+
+/// 系统运行的毫秒数
+pub fn millis() -> u64 {
+    now().ticks() / 1000
+}
+
+/// SysTick 中断处理，在 SysTick 中断中调用
+/// 更新系统时间，执行到期的软件定时器并唤醒到期的异步延时
+pub fn tick() {
+    critical_section::with(count_wrap);
+
+    let now = now();
+    sleep::wake_expired(now);
+
+    // 在临界区外执行回调，回调中可以启动或取消定时器
+    let expired = critical_section::with(|cs| TIMERS.borrow_ref_mut(cs).expire(now));
+    for (id, callback) in expired {
+        callback(id);
+    }
+}
+
+/// SysTick 是否已经溢出但还没有累加到毫秒数
+/// 读取 CSR 会清除溢出标志，清除后记录在 `WRAPPED` 中，由 `tick` 累加，
+/// 软件触发的 SysTick 中断(如 RTIC 调度时)不会累加
+fn wrapped(cs: CriticalSection) -> bool {
+    // SAFETY: 只读取寄存器，清除的溢出标志记录在 WRAPPED 中
+    let csr = unsafe { (*SYST::PTR).csr.read() };
+    let wrapped = WRAPPED.borrow(cs);
+    if csr & SYST_CSR_COUNTFLAG != 0 {
+        wrapped.set(true);
+    }
+    wrapped.get()
+}
+
+/// SysTick 溢出时累加毫秒数
+/// 只在 SysTick 中断和重新设置重装值时调用，每次溢出只会累加一次
+pub(crate) fn count_wrap(cs: CriticalSection) {
+    if wrapped(cs) {
+        WRAPPED.borrow(cs).set(false);
+        let millis = MILLIS.borrow(cs);
+        millis.set(millis.get() + 1);
+    }
+}
+
+/// 启动单次定时器，延时结束后在 SysTick 中断中执行回调
+pub fn start_once(delay: Duration, callback: fn(TimerId)) -> Result<TimerId, TimerFull> {
+    let now = now();
+    critical_section::with(|cs| TIMERS.borrow_ref_mut(cs).start(now, delay, None, callback))
+}
+
+/// 启动周期定时器，每个周期在 SysTick 中断中执行一次回调
+pub fn start_periodic(period: Duration, callback: fn(TimerId)) -> Result<TimerId, TimerFull> {
+    let now = now();
+    critical_section::with(|cs| {
+        TIMERS
+            .borrow_ref_mut(cs)
+            .start(now, period, Some(period), callback)
+    })
+}
+
+/// 取消定时器
+/// 定时器不存在或单次定时器已经执行时返回 false
+pub fn cancel(id: TimerId) -> bool {
+    critical_section::with(|cs| TIMERS.borrow_ref_mut(cs).cancel(id))
+}
@@ -0,0 +1,139 @@
+//! SysTick 单调时钟
+//! 配置 SysTick 每1ms中断一次，提供阻塞延时，并实现 RTIC 的 `Monotonic`
+//!
+//! RTIC 应用中绑定到 SysTick，RTIC 在中断中调用 `on_interrupt` 更新时间，应用不需要再定义 SysTick 中断
+//!
+//! ```rust
+//! #[rtic::app(device = stm32f1xx_hal::pac, dispatchers = [USART1])]
+//! mod app {
+//!     #[monotonic(binds = SysTick, default = true)]
+//!     type MonoTimer = hardware::time::Mono;
+//!
+//!     #[init]
+//!     fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+//!         let mono = hardware::time::Mono::new(cx.core.SYST, &clocks);
+//!         blink::spawn_after(500.millis()).unwrap();
+//!         (Shared {}, Local {}, init::Monotonics(mono))
+//!     }
+//! }
+//! ```
+
+use super::{count_wrap, now, tick, Duration, Instant, MILLIS, RELOAD, TICK_HZ, WRAPPED};
+
+use core::sync::atomic::Ordering;
+
+use cortex_m::peripheral::syst::SystClkSource;
+use cortex_m::peripheral::SYST;
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use stm32f1xx_hal::rcc::Clocks;
+
+/// SysTick 单调时钟
+pub struct Mono {
+    syst: SYST,
+}
+
+impl Mono {
+    /// 启动 SysTick，使用内核时钟(HCLK)，每1ms中断一次
+    pub fn new(mut syst: SYST, clocks: &Clocks) -> Self {
+        let reload = clocks.hclk().raw() / TICK_HZ - 1;
+
+        syst.disable_counter();
+        syst.set_clock_source(SystClkSource::Core);
+        syst.set_reload(reload);
+        syst.clear_current();
+        RELOAD.store(reload, Ordering::Relaxed);
+        syst.enable_interrupt();
+        syst.enable_counter();
+
+        Mono { syst }
+    }
+
+    /// 切换系统时钟后按新的内核时钟重新设置重装值
+    /// 当前毫秒内已经过的时间会被舍弃
+    pub fn reconfigure(&mut self, clocks: &Clocks) {
+        let reload = clocks.hclk().raw() / TICK_HZ - 1;
+        critical_section::with(|cs| {
+            // 计数值清零前累加已经发生的溢出
+            count_wrap(cs);
+            self.syst.set_reload(reload);
+            self.syst.clear_current();
+            RELOAD.store(reload, Ordering::Relaxed);
+        });
+    }
+
+    /// 当前时间
+    pub fn now(&self) -> Instant {
+        now()
+    }
+
+    /// 停止 SysTick，释放外设
+    pub fn free(mut self) -> SYST {
+        self.syst.disable_interrupt();
+        self.syst.disable_counter();
+        RELOAD.store(0, Ordering::Relaxed);
+        self.syst
+    }
+
+    /// 忙等待指定时间
+    pub fn delay(&mut self, duration: Duration) {
+        let start = now();
+        while now() - start < duration {}
+    }
+}
+
+impl DelayUs<u32> for Mono {
+    fn delay_us(&mut self, us: u32) {
+        self.delay(Duration::micros(us as u64));
+    }
+}
+
+impl DelayUs<u16> for Mono {
+    fn delay_us(&mut self, us: u16) {
+        self.delay(Duration::micros(us as u64));
+    }
+}
+
+impl DelayMs<u32> for Mono {
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay(Duration::millis(ms as u64));
+    }
+}
+
+impl DelayMs<u16> for Mono {
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay(Duration::millis(ms as u64));
+    }
+}
+
+impl rtic_monotonic::Monotonic for Mono {
+    type Instant = Instant;
+    type Duration = Duration;
+
+    /// SysTick 中断还用于累计时间，队列为空时也不能关闭
+    const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
+
+    fn now(&mut self) -> Self::Instant {
+        now()
+    }
+
+    /// SysTick 每1ms中断一次，RTIC 在每次中断中检查到期的任务，不需要比较寄存器
+    fn set_compare(&mut self, _instant: Self::Instant) {}
+
+    fn clear_compare_flag(&mut self) {}
+
+    fn zero() -> Self::Instant {
+        Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        critical_section::with(|cs| {
+            MILLIS.borrow(cs).set(0);
+            WRAPPED.borrow(cs).set(false);
+        });
+        self.syst.clear_current();
+    }
+
+    fn on_interrupt(&mut self) {
+        tick();
+    }
+}
@@ -0,0 +1,102 @@
+//! 异步延时
+//! 等待期间将任务的 `Waker` 和到期时间加入等待列表，由 `tick` 在 SysTick 中断中唤醒到期的任务
+//! 等待列表已满时退化为轮询，任务每次被执行时检查是否到期
+//!
+//! ```rust
+//! loop {
+//!     led.toggle();
+//!     time::sleep(Duration::millis(500)).await;
+//! }
+//! ```
+
+use super::{now, Duration, Instant};
+
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use critical_section::Mutex;
+use heapless::Vec;
+
+/// 同时等待的任务个数
+pub const SLEEPER_CAPACITY: usize = 8;
+
+/// 等待列表
+static SLEEPERS: Mutex<RefCell<Vec<(Instant, Waker), SLEEPER_CAPACITY>>> =
+    Mutex::new(RefCell::new(Vec::new()));
+
+/// 延时到指定时间点
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Sleep {
+    deadline: Instant,
+}
+
+impl Sleep {
+    /// 到期的时间点
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if now() >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        let registered = critical_section::with(|cs| {
+            let mut sleepers = SLEEPERS.borrow_ref_mut(cs);
+            // 同一个任务同时等待多个延时(如 `select`)时只保留最早的到期时间
+            if let Some((deadline, _)) = sleepers
+                .iter_mut()
+                .find(|(_, waker)| waker.will_wake(cx.waker()))
+            {
+                *deadline = (*deadline).min(self.deadline);
+                return true;
+            }
+            sleepers.push((self.deadline, cx.waker().clone())).is_ok()
+        });
+        if !registered {
+            cx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    }
+}
+
+/// 延时指定时间
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        deadline: now() + duration,
+    }
+}
+
+/// 延时到指定时间点
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep { deadline }
+}
+
+/// 唤醒到期的任务，在 `tick` 中调用
+pub(super) fn wake_expired(now: Instant) {
+    let mut expired: Vec<Waker, SLEEPER_CAPACITY> = Vec::new();
+    critical_section::with(|cs| {
+        let mut sleepers = SLEEPERS.borrow_ref_mut(cs);
+        let mut i = 0;
+        while i < sleepers.len() {
+            if sleepers[i].0 <= now {
+                let (_, waker) = sleepers.swap_remove(i);
+                // 容量相同，不会溢出
+                let _ = expired.push(waker);
+            } else {
+                i += 1;
+            }
+        }
+    });
+
+    // 在临界区外唤醒
+    for waker in expired {
+        waker.wake();
+    }
+}
@@ -0,0 +1,137 @@
+//! 非阻塞超时
+//! `Deadline` 是一个固定的截止时间，`Timeout` 记录开始时间，可以重新开始计时
+//!
+//! ```rust
+//! // 等待串口数据，最多等待10ms
+//! let deadline = Deadline::after(10.millis());
+//! let byte = deadline.block(|| serial.read())?;
+//!
+//! // 按键消抖，电平保持20ms不变才认为有效
+//! if key.is_low() != pressed {
+//!     debounce.reset();
+//! } else if debounce.is_expired() {
+//!     // 状态稳定
+//! }
+//! ```
+
+use super::{now, Duration, Instant};
+
+/// 超时错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutError<E> {
+    /// 超过截止时间
+    Timeout,
+    /// 操作返回的错误
+    Other(E),
+}
+
+/// 截止时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    at: Instant,
+}
+
+impl Deadline {
+    /// 从当前时间开始，经过指定时间后到期
+    pub fn after(duration: Duration) -> Self {
+        Deadline {
+            at: now() + duration,
+        }
+    }
+
+    /// 在指定时间点到期
+    pub fn at(instant: Instant) -> Self {
+        Deadline { at: instant }
+    }
+
+    /// 到期的时间点
+    pub fn instant(&self) -> Instant {
+        self.at
+    }
+
+    /// 是否已到期
+    pub fn is_expired(&self) -> bool {
+        now() >= self.at
+    }
+
+    /// 剩余时间，已到期时为0
+    pub fn remaining(&self) -> Duration {
+        self.at
+            .checked_duration_since(now())
+            .unwrap_or(Duration::from_ticks(0))
+    }
+
+    /// 反复执行非阻塞操作，直到完成或到期
+    pub fn block<T, E>(
+        &self,
+        mut f: impl FnMut() -> nb::Result<T, E>,
+    ) -> Result<T, TimeoutError<E>> {
+        loop {
+            match f() {
+                Ok(value) => return Ok(value),
+                Err(nb::Error::Other(e)) => return Err(TimeoutError::Other(e)),
+                Err(nb::Error::WouldBlock) => {
+                    if self.is_expired() {
+                        return Err(TimeoutError::Timeout);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 可以重新开始计时的超时
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeout {
+    start: Instant,
+    duration: Duration,
+}
+
+impl Timeout {
+    /// 从当前时间开始计时
+    pub fn new(duration: Duration) -> Self {
+        Timeout {
+            start: now(),
+            duration,
+        }
+    }
+
+    /// 重新开始计时
+    pub fn reset(&mut self) {
+        self.start = now();
+    }
+
+    /// 修改超时时间，不重新计时
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    /// 超时时间
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// 开始计时后经过的时间
+    pub fn elapsed(&self) -> Duration {
+        now()
+            .checked_duration_since(self.start)
+            .unwrap_or(Duration::from_ticks(0))
+    }
+
+    /// 是否已超时
+    pub fn is_expired(&self) -> bool {
+        self.elapsed() >= self.duration
+    }
+
+    /// 剩余时间，已超时为0
+    pub fn remaining(&self) -> Duration {
+        self.duration
+            .checked_sub(self.elapsed())
+            .unwrap_or(Duration::from_ticks(0))
+    }
+
+    /// 截止时间
+    pub fn deadline(&self) -> Deadline {
+        Deadline::at(self.start + self.duration)
+    }
+}
@@ -0,0 +1,148 @@
+//! 软件定时器
+//! 固定容量的定时器表，不需要动态内存，由周期性的中断驱动
+//! 记录最近的到期时间，没有定时器到期时中断中只做一次比较
+
+use super::{Duration, Instant};
+
+use heapless::Vec;
+
+/// 定时器编号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerId(u32);
+
+/// 定时器已满
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerFull;
+
+#[derive(Clone, Copy)]
+struct Timer {
+    id: TimerId,
+    deadline: Instant,
+    /// 周期定时器的周期，单次定时器为 None
+    period: Option<Duration>,
+    callback: fn(TimerId),
+}
+
+/// 软件定时器表
+pub struct TimerWheel<const N: usize> {
+    timers: [Option<Timer>; N],
+    /// 最近的到期时间，没有定时器时为 None
+    next_deadline: Option<Instant>,
+    next_id: u32,
+}
+
+impl<const N: usize> TimerWheel<N> {
+    pub const fn new() -> Self {
+        TimerWheel {
+            timers: [None; N],
+            next_deadline: None,
+            next_id: 0,
+        }
+    }
+
+    /// 启动定时器
+    /// delay: 第一次到期的延时
+    /// period: 周期定时器的周期，最短1ms，单次定时器为 None
+    pub fn start(
+        &mut self,
+        now: Instant,
+        delay: Duration,
+        period: Option<Duration>,
+        callback: fn(TimerId),
+    ) -> Result<TimerId, TimerFull> {
+        let slot = self
+            .timers
+            .iter_mut()
+            .find(|timer| timer.is_none())
+            .ok_or(TimerFull)?;
+
+        let id = TimerId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        let deadline = now + delay;
+        *slot = Some(Timer {
+            id,
+            deadline,
+            period: period.map(|period| period.max(Duration::millis(1))),
+            callback,
+        });
+
+        if self.next_deadline.is_none_or(|next| deadline < next) {
+            self.next_deadline = Some(deadline);
+        }
+        Ok(id)
+    }
+
+    /// 取消定时器
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        for slot in self.timers.iter_mut() {
+            if slot.is_some_and(|timer| timer.id == id) {
+                *slot = None;
+                self.update_next_deadline();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// 定时器是否还在运行
+    pub fn is_active(&self, id: TimerId) -> bool {
+        self.timers.iter().flatten().any(|timer| timer.id == id)
+    }
+
+    /// 运行中的定时器个数
+    pub fn len(&self) -> usize {
+        self.timers.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.next_deadline.is_none()
+    }
+
+    /// 取出到期的定时器，返回需要执行的回调
+    /// 单次定时器到期后删除，周期定时器按周期推进到期时间，错过的周期只执行一次
+    pub fn expire(&mut self, now: Instant) -> Vec<(TimerId, fn(TimerId)), N> {
+        let mut expired = Vec::new();
+        match self.next_deadline {
+            Some(next) if next <= now => {}
+            _ => return expired,
+        }
+
+        for slot in self.timers.iter_mut() {
+            let Some(timer) = slot else {
+                continue;
+            };
+            if timer.deadline > now {
+                continue;
+            }
+
+            // 容量与定时器个数相同，不会溢出
+            let _ = expired.push((timer.id, timer.callback));
+            match timer.period {
+                Some(period) => {
+                    // 以到期时间而不是当前时间推进，避免周期累计误差
+                    while timer.deadline <= now {
+                        timer.deadline += period;
+                    }
+                }
+                None => *slot = None,
+            }
+        }
+        self.update_next_deadline();
+        expired
+    }
+
+    fn update_next_deadline(&mut self) {
+        self.next_deadline = self
+            .timers
+            .iter()
+            .flatten()
+            .map(|timer| timer.deadline)
+            .min();
+    }
+}
+
+impl<const N: usize> Default for TimerWheel<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
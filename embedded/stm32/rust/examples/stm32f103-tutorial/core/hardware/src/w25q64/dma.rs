@@ -0,0 +1,263 @@
+//! DMA 批量传输
+//! SPI1 的接收和发送分别使用 DMA1 通道2和通道3
+//! 传输期间 CPU 可以继续执行其他任务，通过 `is_done` 查询是否完成
+//!
+//! ```rust
+//! let dma1 = dp.DMA1.split();
+//! let w25q = w25q64_hal::new_with_frequency(spi1, pins, &mut cs, &mut afio.mapr, 18.MHz(), clocks);
+//! let w25q = dma::W25Q64Dma::new(w25q, dma1.2, dma1.3);
+//!
+//! static mut BUFFER: [u8; 4096] = [0; 4096];
+//! let transfer = w25q.start_read(0x000000, unsafe { &mut BUFFER }).unwrap();
+//! while !transfer.is_done() {
+//!     // 处理其他任务
+//! }
+//! let (buffer, w25q) = transfer.wait();
+//! ```
+
+use core::ops::{Deref, DerefMut};
+use core::ptr::addr_of_mut;
+
+use super::conf::*;
+use super::w25q64_hal::{Error, Spi1Device, W25Q64};
+use crate::bus::CompatError;
+use crate::syst::BusyDelay;
+
+use cortex_m::prelude::_embedded_hal_blocking_spi_Write;
+use embedded_hal::digital::v2::OutputPin;
+use stm32f1xx_hal::dma::{dma1, ReadWriteDma, Transfer, W};
+use stm32f1xx_hal::pac::SPI1;
+use stm32f1xx_hal::spi::{Master, Pins, Spi1NoRemap, SpiRxTxDma};
+
+/// 读取时发送的虚拟字节，放在 FLASH 中
+static DUMMY: [u8; W25Q64_SECTOR_SIZE] = [W25Q64_DUMMY_BYTE; W25Q64_SECTOR_SIZE];
+/// 页编程时丢弃的接收数据
+static mut DISCARD: [u8; W25Q64_PAGE_SIZE] = [0; W25Q64_PAGE_SIZE];
+
+type SpiDma<PINS> = SpiRxTxDma<SPI1, Spi1NoRemap, PINS, Master, dma1::C2, dma1::C3>;
+type SpiBuffers = (&'static mut [u8], &'static [u8]);
+type SpiTransfer<PINS> = Transfer<W, SpiBuffers, SpiDma<PINS>>;
+
+/// 支持 DMA 传输的 W25Q64
+/// 可以通过解引用调用 W25Q64 的阻塞接口
+pub struct W25Q64Dma<'a, PINS, SS>
+where
+    PINS: Pins<Spi1NoRemap>,
+    SS: OutputPin,
+    <SS as OutputPin>::Error: core::fmt::Debug,
+{
+    w25q: W25Q64<'a, PINS, SS>,
+    rx: dma1::C2,
+    tx: dma1::C3,
+}
+
+impl<'a, PINS, SS> W25Q64Dma<'a, PINS, SS>
+where
+    PINS: Pins<Spi1NoRemap>,
+    SS: OutputPin,
+    <SS as OutputPin>::Error: core::fmt::Debug,
+{
+    pub fn new(w25q: W25Q64<'a, PINS, SS>, rx: dma1::C2, tx: dma1::C3) -> Self {
+        W25Q64Dma { w25q, rx, tx }
+    }
+
+    /// 释放 W25Q64 和 DMA 通道
+    pub fn release(self) -> (W25Q64<'a, PINS, SS>, dma1::C2, dma1::C3) {
+        (self.w25q, self.rx, self.tx)
+    }
+
+    /// 开始快速读取数据
+    /// address: 目标地址
+    /// buffer: 用于存放数据，1字节到4KB，长度超出范围时返回 `Error::InvalidArgument`
+    pub fn start_read(
+        self,
+        address: u32,
+        buffer: &'static mut [u8],
+    ) -> Result<DmaRead<'a, PINS, SS>, (Error, Self)> {
+        // 长度为0时 DMA 传输不会完成
+        if buffer.is_empty() || buffer.len() > DUMMY.len() {
+            return Err((Error::InvalidArgument, self));
+        }
+
+        let cmd = [
+            W25Q64_FAST_READ,      // 快速读取数据的指令
+            (address >> 16) as u8, // 地址23~16位
+            (address >> 8) as u8,  // 地址15~8位
+            address as u8,         // 地址7~0位
+            W25Q64_DUMMY_BYTE,     // 虚拟字节
+        ];
+        let len = buffer.len();
+        let inner = self.start(&cmd, buffer, &DUMMY[..len])?;
+        Ok(DmaRead { inner })
+    }
+
+    /// 开始页编程
+    /// 传输完成后需要通过 `poll_idle` 或 `wait_for_idle` 等待编程结束
+    /// address: 页地址
+    /// data: 要写入的数据，1到256字节，长度超出范围时返回 `Error::InvalidArgument`
+    pub fn start_page_program(
+        mut self,
+        address: u32,
+        data: &'static [u8],
+    ) -> Result<DmaWrite<'a, PINS, SS>, (Error, Self)> {
+        if data.is_empty() || data.len() > W25Q64_PAGE_SIZE {
+            return Err((Error::InvalidArgument, self));
+        }
+
+        if let Err(e) = self.w25q.write_enable() {
+            return Err((e, self));
+        }
+
+        let cmd = [
+            W25Q64_PAGE_PROGRAM,   // 页编程的指令
+            (address >> 16) as u8, // 地址23~16位
+            (address >> 8) as u8,  // 地址15~8位
+            address as u8,         // 地址7~0位
+        ];
+        // 同一时间只有一个传输持有 DMA 通道，丢弃缓冲区不会被同时使用
+        let discard = unsafe { &mut (&mut *addr_of_mut!(DISCARD))[..data.len()] };
+        let inner = self.start(&cmd, discard, data)?;
+        Ok(DmaWrite { inner })
+    }
+
+    /// 拉低片选并发送命令，然后启动 DMA 传输数据
+    fn start(
+        self,
+        cmd: &[u8],
+        rx_buffer: &'static mut [u8],
+        tx_buffer: &'static [u8],
+    ) -> Result<DmaTransfer<'a, PINS, SS>, (Error, Self)> {
+        let W25Q64Dma { w25q, rx, tx } = self;
+        let (device, _) = w25q.release();
+        let (mut spi, ss, delay) = device.into_parts();
+
+        ss.set_low().unwrap();
+        if let Err(e) = spi.write(cmd) {
+            ss.set_high().unwrap();
+            let w25q = W25Q64::new(Spi1Device::new(spi, ss, delay), delay);
+            return Err((Error::Spi(CompatError(e)), W25Q64Dma { w25q, rx, tx }));
+        }
+
+        let transfer = spi.with_rx_tx_dma(rx, tx).read_write(rx_buffer, tx_buffer);
+        Ok(DmaTransfer {
+            transfer,
+            ss,
+            delay,
+        })
+    }
+}
+
+impl<'a, PINS, SS> Deref for W25Q64Dma<'a, PINS, SS>
+where
+    PINS: Pins<Spi1NoRemap>,
+    SS: OutputPin,
+    <SS as OutputPin>::Error: core::fmt::Debug,
+{
+    type Target = W25Q64<'a, PINS, SS>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.w25q
+    }
+}
+
+impl<'a, PINS, SS> DerefMut for W25Q64Dma<'a, PINS, SS>
+where
+    PINS: Pins<Spi1NoRemap>,
+    SS: OutputPin,
+    <SS as OutputPin>::Error: core::fmt::Debug,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.w25q
+    }
+}
+
+/// 进行中的 DMA 传输
+struct DmaTransfer<'a, PINS, SS>
+where
+    PINS: Pins<Spi1NoRemap>,
+    SS: OutputPin,
+    <SS as OutputPin>::Error: core::fmt::Debug,
+{
+    transfer: SpiTransfer<PINS>,
+    ss: &'a mut SS,
+    delay: BusyDelay,
+}
+
+impl<'a, PINS, SS> DmaTransfer<'a, PINS, SS>
+where
+    PINS: Pins<Spi1NoRemap>,
+    SS: OutputPin,
+    <SS as OutputPin>::Error: core::fmt::Debug,
+{
+    fn is_done(&self) -> bool {
+        self.transfer.is_done()
+    }
+
+    /// 等待传输完成，拉高片选并恢复阻塞模式
+    fn wait(self) -> (SpiBuffers, W25Q64Dma<'a, PINS, SS>) {
+        let (buffers, dma) = self.transfer.wait();
+        let (spi, rx, tx) = dma.release();
+
+        // 创建设备句柄时拉高片选
+        let w25q = W25Q64::new(Spi1Device::new(spi, self.ss, self.delay), self.delay);
+        (buffers, W25Q64Dma { w25q, rx, tx })
+    }
+}
+
+/// 进行中的 DMA 读取
+pub struct DmaRead<'a, PINS, SS>
+where
+    PINS: Pins<Spi1NoRemap>,
+    SS: OutputPin,
+    <SS as OutputPin>::Error: core::fmt::Debug,
+{
+    inner: DmaTransfer<'a, PINS, SS>,
+}
+
+impl<'a, PINS, SS> DmaRead<'a, PINS, SS>
+where
+    PINS: Pins<Spi1NoRemap>,
+    SS: OutputPin,
+    <SS as OutputPin>::Error: core::fmt::Debug,
+{
+    /// 传输是否完成
+    pub fn is_done(&self) -> bool {
+        self.inner.is_done()
+    }
+
+    /// 等待传输完成
+    /// 返回存放数据的缓冲区
+    pub fn wait(self) -> (&'static mut [u8], W25Q64Dma<'a, PINS, SS>) {
+        let ((buffer, _), w25q) = self.inner.wait();
+        (buffer, w25q)
+    }
+}
+
+/// 进行中的 DMA 页编程
+pub struct DmaWrite<'a, PINS, SS>
+where
+    PINS: Pins<Spi1NoRemap>,
+    SS: OutputPin,
+    <SS as OutputPin>::Error: core::fmt::Debug,
+{
+    inner: DmaTransfer<'a, PINS, SS>,
+}
+
+impl<'a, PINS, SS> DmaWrite<'a, PINS, SS>
+where
+    PINS: Pins<Spi1NoRemap>,
+    SS: OutputPin,
+    <SS as OutputPin>::Error: core::fmt::Debug,
+{
+    /// 传输是否完成
+    pub fn is_done(&self) -> bool {
+        self.inner.is_done()
+    }
+
+    /// 等待传输完成
+    /// 返回写入的数据
+    pub fn wait(self) -> (&'static [u8], W25Q64Dma<'a, PINS, SS>) {
+        let ((_, data), w25q) = self.inner.wait();
+        (data, w25q)
+    }
+}
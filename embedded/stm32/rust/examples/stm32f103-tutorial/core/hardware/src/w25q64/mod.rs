@@ -1,4 +1,11 @@
 //! SPI 读写 W25Q64 非易失性存储器
-pub mod conf;
+//! 芯片命令、embedded-storage 接口、数据记录器及文件系统与外设无关，由 `portable` 库实现
+pub mod dma;
 pub mod w25q64_hal;
 pub mod w25q64_reg;
+
+pub use portable::w25q64::{advanced, conf, driver, logger, storage};
+pub use portable::w25q64::{Error, W25Q64};
+
+#[cfg(feature = "littlefs")]
+pub use portable::w25q64::fs;
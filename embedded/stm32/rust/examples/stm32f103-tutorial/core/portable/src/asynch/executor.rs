@@ -0,0 +1,160 @@
+//! 单线程协作式执行器
+//! 每个任务对应就绪队列中的一个标志位，任务的 `Waker` 只设置自己的标志位，可以在中断中调用
+//! 执行器依次轮询已就绪的任务，每轮结束后调用空闲函数，固件中执行 `WFI` 休眠，等待中断唤醒
+//!
+//! 任务固定在调用者的栈上，不需要堆内存，任务个数最多为 `MAX_TASKS`
+//!
+//! ```rust
+//! static QUEUE: ReadyQueue = ReadyQueue::new();
+//! static EXECUTOR: Executor = Executor::new(&QUEUE, |_| {});
+//!
+//! EXECUTOR.run([pin!(task_a()), pin!(task_b())]);
+//! ```
+
+use core::future::Future;
+use core::pin::{pin, Pin};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+/// 最多同时执行的任务个数
+pub const MAX_TASKS: usize = 32;
+
+/// 任务，固定在栈上的 `Future`
+pub type Task<'a> = Pin<&'a mut dyn Future<Output = ()>>;
+
+/// 唤醒时设置任务的就绪标志
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake, drop);
+
+unsafe fn clone(ptr: *const ()) -> RawWaker {
+    RawWaker::new(ptr, &VTABLE)
+}
+
+unsafe fn wake(ptr: *const ()) {
+    (*(ptr as *const AtomicBool)).store(true, Ordering::Release);
+}
+
+unsafe fn drop(_ptr: *const ()) {}
+
+/// 就绪队列
+pub struct ReadyQueue {
+    flags: [AtomicBool; MAX_TASKS],
+}
+
+impl ReadyQueue {
+    pub const fn new() -> Self {
+        ReadyQueue {
+            flags: [const { AtomicBool::new(false) }; MAX_TASKS],
+        }
+    }
+
+    /// 将任务标记为就绪
+    pub fn wake(&self, index: usize) {
+        self.flags[index].store(true, Ordering::Release);
+    }
+
+    /// 任务是否就绪
+    pub fn is_ready(&self, index: usize) -> bool {
+        self.flags[index].load(Ordering::Acquire)
+    }
+
+    /// 是否有任务就绪
+    pub fn any(&self) -> bool {
+        self.flags.iter().any(|flag| flag.load(Ordering::Acquire))
+    }
+
+    /// 取出任务的就绪标志
+    fn take(&self, index: usize) -> bool {
+        self.flags[index].swap(false, Ordering::AcqRel)
+    }
+
+    /// 清除所有就绪标志
+    fn clear(&self) {
+        for flag in self.flags.iter() {
+            flag.store(false, Ordering::Release);
+        }
+    }
+
+    /// 唤醒指定任务的 `Waker`
+    pub fn waker(&'static self, index: usize) -> Waker {
+        let ptr = &self.flags[index] as *const AtomicBool as *const ();
+        // SAFETY: 就绪队列是静态变量，`VTABLE` 中的函数只访问该标志位
+        unsafe { Waker::from_raw(RawWaker::new(ptr, &VTABLE)) }
+    }
+}
+
+impl Default for ReadyQueue {
+    fn default() -> Self {
+        ReadyQueue::new()
+    }
+}
+
+/// 执行器
+pub struct Executor {
+    queue: &'static ReadyQueue,
+    idle: fn(&ReadyQueue),
+}
+
+impl Executor {
+    /// idle: 每轮轮询结束后调用，参数为就绪队列
+    /// 没有就绪任务时可以在其中休眠，需要自行处理检查之后、休眠之前发生的唤醒
+    pub const fn new(queue: &'static ReadyQueue, idle: fn(&ReadyQueue)) -> Self {
+        Executor { queue, idle }
+    }
+
+    /// 执行一轮，依次轮询已就绪的任务，完成的任务置为 `None`
+    /// 返回轮询的任务个数
+    pub fn poll(&self, tasks: &mut [Option<Task<'_>>]) -> usize {
+        let mut polled = 0;
+        for (index, slot) in tasks.iter_mut().enumerate() {
+            let Some(task) = slot else {
+                continue;
+            };
+            if !self.queue.take(index) {
+                continue;
+            }
+
+            let waker = self.queue.waker(index);
+            let mut cx = Context::from_waker(&waker);
+            if task.as_mut().poll(&mut cx).is_ready() {
+                *slot = None;
+            }
+            polled += 1;
+        }
+        polled
+    }
+
+    /// 执行所有任务，直到全部完成
+    pub fn run<const N: usize>(&self, tasks: [Task<'_>; N]) {
+        const { assert!(N <= MAX_TASKS, "too many tasks") };
+
+        let mut slots = tasks.map(Some);
+        // 所有任务都需要先轮询一次
+        self.queue.clear();
+        for index in 0..N {
+            self.queue.wake(index);
+        }
+
+        while slots.iter().any(Option::is_some) {
+            self.poll(&mut slots);
+            (self.idle)(self.queue);
+        }
+    }
+
+    /// 执行单个 `Future` 直到完成，返回结果
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = self.queue.waker(0);
+        let mut cx = Context::from_waker(&waker);
+
+        self.queue.clear();
+        self.queue.wake(0);
+        loop {
+            if self.queue.take(0) {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+            }
+            (self.idle)(self.queue);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! `Future` 组合
+//! - `select`、`select3`: 同时等待多个 `Future`，返回最先完成的一个，其余的被丢弃
+//! - `join`、`join3`: 同时等待多个 `Future`，全部完成后返回
+//! - `yield_now`: 让出 CPU，下一轮再继续执行
+
+use core::future::{poll_fn, Future};
+use core::pin::pin;
+use core::task::Poll;
+
+/// 两个 `Future` 中先完成的一个
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Either<A, B> {
+    First(A),
+    Second(B),
+}
+
+/// 三个 `Future` 中先完成的一个
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Either3<A, B, C> {
+    First(A),
+    Second(B),
+    Third(C),
+}
+
+/// 等待两个 `Future` 中的任意一个完成
+/// 同时完成时优先返回前面的
+pub async fn select<A: Future, B: Future>(a: A, b: B) -> Either<A::Output, B::Output> {
+    let mut a = pin!(a);
+    let mut b = pin!(b);
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = a.as_mut().poll(cx) {
+            return Poll::Ready(Either::First(output));
+        }
+        if let Poll::Ready(output) = b.as_mut().poll(cx) {
+            return Poll::Ready(Either::Second(output));
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// 等待三个 `Future` 中的任意一个完成
+pub async fn select3<A: Future, B: Future, C: Future>(
+    a: A,
+    b: B,
+    c: C,
+) -> Either3<A::Output, B::Output, C::Output> {
+    let mut a = pin!(a);
+    let mut b = pin!(b);
+    let mut c = pin!(c);
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = a.as_mut().poll(cx) {
+            return Poll::Ready(Either3::First(output));
+        }
+        if let Poll::Ready(output) = b.as_mut().poll(cx) {
+            return Poll::Ready(Either3::Second(output));
+        }
+        if let Poll::Ready(output) = c.as_mut().poll(cx) {
+            return Poll::Ready(Either3::Third(output));
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// 等待两个 `Future` 全部完成
+pub async fn join<A: Future, B: Future>(a: A, b: B) -> (A::Output, B::Output) {
+    let mut a = pin!(a);
+    let mut b = pin!(b);
+    let mut output_a = None;
+    let mut output_b = None;
+    poll_fn(|cx| {
+        if output_a.is_none() {
+            if let Poll::Ready(output) = a.as_mut().poll(cx) {
+                output_a = Some(output);
+            }
+        }
+        if output_b.is_none() {
+            if let Poll::Ready(output) = b.as_mut().poll(cx) {
+                output_b = Some(output);
+            }
+        }
+        match (output_a.take(), output_b.take()) {
+            (Some(a), Some(b)) => Poll::Ready((a, b)),
+            (a, b) => {
+                output_a = a;
+                output_b = b;
+                Poll::Pending
+            }
+        }
+    })
+    .await
+}
+
+/// 等待三个 `Future` 全部完成
+pub async fn join3<A: Future, B: Future, C: Future>(
+    a: A,
+    b: B,
+    c: C,
+) -> (A::Output, B::Output, C::Output) {
+    let ((a, b), c) = join(join(a, b), c).await;
+    (a, b, c)
+}
+
+/// 让出 CPU，其他就绪的任务执行后再继续
+pub async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
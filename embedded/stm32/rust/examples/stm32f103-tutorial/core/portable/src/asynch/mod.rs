@@ -0,0 +1,9 @@
+//! 异步执行
+//! 单线程协作式执行器及 `Future` 组合，不依赖硬件
+//! 固件中使用 `hardware::asynch`，其中的全局执行器在空闲时执行 `WFI` 休眠
+
+pub mod executor;
+pub mod future;
+
+pub use executor::{Executor, ReadyQueue, Task, MAX_TASKS};
+pub use future::{join, join3, select, select3, yield_now, Either, Either3};
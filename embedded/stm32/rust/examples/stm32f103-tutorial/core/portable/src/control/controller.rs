@@ -0,0 +1,409 @@
+//! 电机闭环控制
+//! 编码器测速 -> 速度环 PID -> PWM 占空比，位置模式在速度环外再加一层位置环:
+//!
+//! ```text
+//! 梯形规划 --位置--> 位置环 PID --速度修正--+
+//!          --速度----------------------------+--> 速度环 PID --占空比--> 电机
+//! ```
+//!
+//! `update` 需要在定时器中断中按 `ControllerConfig::rate_hz` 的频率调用，
+//! 电机参数中的加速度限制应设为0，速度变化由控制器规划
+//!
+//! 位置以 i32 累计，梯形规划只计算相对规划原点的 f32 偏移，每次设置目标位置时原点移到当前规划位置，
+//! 行程超过 2^24 计数时也不会损失精度
+//!
+//! 控制器只通过 `Encoder` 读取计数、通过 `DutyOutput` 输出占空比，
+//! 在单片机上由 `hardware::control` 适配定时器编码器接口和 H 桥，在主机上可以接 `sim::SimMotor` 测试
+
+use super::pid::Pid;
+use super::trapezoid::{Setpoint, Trapezoid};
+use super::tuning::{Command, Gain, Loop};
+
+/// 满占空比(‰)
+pub const MAX_DUTY: i16 = 1000;
+
+/// 编码器计数
+pub trait Encoder {
+    /// 16位计数值，溢出后回绕
+    fn count(&self) -> u16;
+}
+
+/// 电机的占空比输出
+pub trait DutyOutput {
+    /// 按占空比(‰)驱动，正数正转，负数反转
+    fn set_duty(&mut self, permille: i16);
+    /// 停止驱动，电机滑行
+    fn coast(&mut self);
+    /// 刹车
+    fn brake(&mut self);
+    /// 每个控制周期设置占空比后调用一次
+    fn tick(&mut self) {}
+}
+
+/// 控制模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ControlMode {
+    /// 不控制，电机滑行，仍然记录位置和速度
+    Idle,
+    /// 速度闭环
+    Speed,
+    /// 位置闭环，按梯形速度规划运动到目标位置
+    Position,
+}
+
+/// 控制器参数，速度单位为编码器计数/s，位置单位为编码器计数
+#[derive(Debug, Clone, Copy)]
+pub struct ControllerConfig {
+    /// 控制频率(Hz)，与调用 `update` 的定时器中断频率一致
+    pub rate_hz: u32,
+    /// 测速低通滤波系数 0~1，越小越平滑，为1时不滤波
+    pub speed_filter: f32,
+    /// 速度环，输出为占空比(‰)
+    pub speed_pid: Pid,
+    /// 位置环，输出为速度修正量
+    pub position_pid: Pid,
+    /// 最大速度
+    pub max_velocity: f32,
+    /// 加速度，速度模式和位置模式共用，小于等于0时不限制加速度
+    pub acceleration: f32,
+    /// 到位误差
+    pub position_tolerance: u32,
+}
+
+impl Default for ControllerConfig {
+    /// 默认参数只能让电机转起来，需要根据电机和编码器调参
+    fn default() -> Self {
+        let max = MAX_DUTY as f32;
+        ControllerConfig {
+            rate_hz: 100,
+            speed_filter: 0.5,
+            speed_pid: Pid::new(0.2, 2.0, 0.0).output_limits(-max, max),
+            position_pid: Pid::new(5.0, 0.0, 0.0).output_limits(-1000.0, 1000.0),
+            max_velocity: 2000.0,
+            acceleration: 4000.0,
+            position_tolerance: 5,
+        }
+    }
+}
+
+/// 电机闭环控制器
+pub struct MotorController<M, E> {
+    motor: M,
+    encoder: E,
+    speed_pid: Pid,
+    position_pid: Pid,
+    profile: Trapezoid,
+    speed_filter: f32,
+    position_tolerance: u32,
+    /// 控制周期(s)
+    dt: f32,
+    mode: ControlMode,
+    /// 上一次读取的编码器计数
+    last_count: u16,
+    /// 累计位置，不受16位计数溢出影响
+    position: i32,
+    /// 梯形规划的原点，规划的位置为相对原点的偏移
+    origin: i32,
+    /// 滤波后的速度
+    speed: f32,
+    /// 速度模式的目标速度
+    target_speed: f32,
+    /// 速度环当前的目标速度
+    speed_setpoint: f32,
+    /// 输出的占空比(‰)
+    output: i16,
+}
+
+impl<M, E> MotorController<M, E>
+where
+    M: DutyOutput,
+    E: Encoder,
+{
+    /// 创建实例，处于空闲模式，当前位置为0
+    pub fn new(mut motor: M, encoder: E, config: ControllerConfig) -> Self {
+        motor.coast();
+        let last_count = encoder.count();
+        let speed_limit = config.max_velocity.abs();
+        MotorController {
+            motor,
+            encoder,
+            speed_pid: config.speed_pid,
+            position_pid: config.position_pid.output_limits(-speed_limit, speed_limit),
+            profile: Trapezoid::new(config.max_velocity, config.acceleration),
+            speed_filter: clamp(config.speed_filter, 0.0, 1.0),
+            position_tolerance: config.position_tolerance,
+            dt: 1.0 / config.rate_hz.max(1) as f32,
+            mode: ControlMode::Idle,
+            last_count,
+            position: 0,
+            origin: 0,
+            speed: 0.0,
+            target_speed: 0.0,
+            speed_setpoint: 0.0,
+            output: 0,
+        }
+    }
+
+    /// 释放电机和编码器
+    pub fn free(self) -> (M, E) {
+        (self.motor, self.encoder)
+    }
+
+    /// 执行一次控制，需要在定时器中断中按固定频率调用
+    pub fn update(&mut self) {
+        let count = self.encoder.count();
+        // 两次读取之间的变化不超过 ±32767 时，按有符号数相减可以正确处理溢出
+        let delta = count.wrapping_sub(self.last_count) as i16 as i32;
+        self.last_count = count;
+        self.position = self.position.wrapping_add(delta);
+
+        let raw_speed = delta as f32 / self.dt;
+        self.speed += self.speed_filter * (raw_speed - self.speed);
+
+        let output = match self.mode {
+            ControlMode::Idle => return,
+            ControlMode::Speed => {
+                let step = self.profile.acceleration.max(0.0) * self.dt;
+                self.speed_setpoint = if step == 0.0 {
+                    self.target_speed
+                } else if self.speed_setpoint < self.target_speed {
+                    self.target_speed.min(self.speed_setpoint + step)
+                } else {
+                    self.target_speed.max(self.speed_setpoint - step)
+                };
+                self.speed_pid
+                    .update(self.speed_setpoint, self.speed, self.dt)
+            }
+            ControlMode::Position => {
+                let Setpoint { position, velocity } = self.profile.update(self.dt);
+                let measured = self.position.wrapping_sub(self.origin) as f32;
+                let correction = self.position_pid.update(position, measured, self.dt);
+                self.speed_setpoint = velocity + correction;
+                self.speed_pid
+                    .update(self.speed_setpoint, self.speed, self.dt)
+            }
+        };
+
+        self.output = output as i16;
+        self.motor.set_duty(self.output);
+        self.motor.tick();
+    }
+
+    /// 速度模式，按加速度逐渐改变到目标速度
+    pub fn set_speed(&mut self, speed: f32) {
+        let limit = self.profile.max_velocity.abs();
+        self.target_speed = clamp(speed, -limit, limit);
+        match self.mode {
+            ControlMode::Speed => {}
+            // 从位置模式切换时保持速度环的状态，从当前速度开始变化
+            ControlMode::Position => self.mode = ControlMode::Speed,
+            ControlMode::Idle => {
+                self.speed_pid.reset();
+                self.speed_setpoint = self.speed;
+                self.mode = ControlMode::Speed;
+            }
+        }
+    }
+
+    /// 位置模式，运动到绝对位置
+    pub fn move_to(&mut self, position: i32) {
+        if self.mode == ControlMode::Position {
+            // 原点移到当前规划位置的整数部分，偏移保持较小
+            let Setpoint { position, velocity } = self.profile.setpoint();
+            let shift = position as i32;
+            self.origin = self.origin.wrapping_add(shift);
+            self.profile.reset(position - shift as f32, velocity);
+            self.position_pid.shift(-(shift as f32));
+        } else {
+            if self.mode == ControlMode::Idle {
+                self.speed_pid.reset();
+            }
+            self.position_pid.reset();
+            self.origin = self.position;
+            self.profile.reset(0.0, self.speed);
+            self.mode = ControlMode::Position;
+        }
+        self.profile
+            .set_target(position.wrapping_sub(self.origin) as f32);
+    }
+
+    /// 位置模式，相对目标位置移动，不在位置模式时相对当前位置
+    pub fn move_by(&mut self, distance: i32) {
+        let base = match self.mode {
+            ControlMode::Position => self.target_position(),
+            _ => self.position,
+        };
+        self.move_to(base.wrapping_add(distance));
+    }
+
+    /// 减速停止，停止后保持速度为0
+    pub fn stop(&mut self) {
+        match self.mode {
+            ControlMode::Idle => {}
+            ControlMode::Speed => self.set_speed(0.0),
+            ControlMode::Position => {
+                // 以当前速度减速所需的距离
+                let velocity = self.profile.setpoint().velocity;
+                let acceleration = self.profile.acceleration.abs();
+                let distance = if acceleration > 0.0 {
+                    velocity * velocity.abs() / (2.0 * acceleration)
+                } else {
+                    0.0
+                };
+                self.profile
+                    .set_target(self.profile.setpoint().position + distance);
+            }
+        }
+    }
+
+    /// 停止控制，电机滑行
+    pub fn idle(&mut self) {
+        self.motor.coast();
+        self.enter_idle();
+    }
+
+    /// 停止控制，电机刹车
+    pub fn brake(&mut self) {
+        self.motor.brake();
+        self.enter_idle();
+    }
+
+    /// 设置当前位置，用于回零
+    /// 规划原点同时平移，正在进行的运动不受影响
+    pub fn set_position(&mut self, position: i32) {
+        let offset = position.wrapping_sub(self.position);
+        self.position = position;
+        self.origin = self.origin.wrapping_add(offset);
+    }
+
+    /// 执行调参命令，`Command::Status` 由调用方处理
+    pub fn apply(&mut self, command: Command) {
+        match command {
+            Command::Gain {
+                target,
+                gain,
+                value,
+            } => {
+                let pid = match target {
+                    Loop::Speed => &mut self.speed_pid,
+                    Loop::Position => &mut self.position_pid,
+                };
+                let mut gains = pid.gains();
+                match gain {
+                    Gain::Kp => gains.kp = value,
+                    Gain::Ki => gains.ki = value,
+                    Gain::Kd => gains.kd = value,
+                    Gain::Kf => gains.kf = value,
+                }
+                pid.set_gains(gains);
+            }
+            Command::Speed(speed) => self.set_speed(speed),
+            Command::MoveTo(position) => self.move_to(position),
+            Command::MoveBy(distance) => self.move_by(distance),
+            Command::MaxVelocity(velocity) => self.set_max_velocity(velocity),
+            Command::Acceleration(acceleration) => self.set_acceleration(acceleration),
+            Command::Stop => self.stop(),
+            Command::Idle => self.idle(),
+            Command::Zero => self.set_position(0),
+            Command::Status => {}
+        }
+    }
+
+    /// 修改最大速度
+    pub fn set_max_velocity(&mut self, velocity: f32) {
+        let velocity = velocity.abs();
+        self.profile.max_velocity = velocity;
+        self.position_pid.set_output_limits(-velocity, velocity);
+        self.target_speed = clamp(self.target_speed, -velocity, velocity);
+    }
+
+    /// 修改加速度，小于等于0时不限制
+    pub fn set_acceleration(&mut self, acceleration: f32) {
+        self.profile.acceleration = acceleration.max(0.0);
+    }
+
+    /// 控制模式
+    pub fn mode(&self) -> ControlMode {
+        self.mode
+    }
+
+    /// 当前位置
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// 当前速度
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// 速度环当前的目标速度
+    pub fn speed_setpoint(&self) -> f32 {
+        self.speed_setpoint
+    }
+
+    /// 位置模式的目标位置
+    pub fn target_position(&self) -> i32 {
+        self.origin.wrapping_add(self.profile.target() as i32)
+    }
+
+    /// 梯形规划的原点
+    pub fn profile_origin(&self) -> i32 {
+        self.origin
+    }
+
+    /// 梯形规划当前的位置(相对原点)和速度
+    pub fn profile_setpoint(&self) -> Setpoint {
+        self.profile.setpoint()
+    }
+
+    /// 输出的占空比(‰)
+    pub fn output(&self) -> i16 {
+        self.output
+    }
+
+    /// 位置模式下是否已经到位
+    pub fn is_settled(&self) -> bool {
+        self.mode == ControlMode::Position
+            && self.profile.is_done()
+            && (self.target_position().wrapping_sub(self.position)).unsigned_abs()
+                <= self.position_tolerance
+    }
+
+    /// 速度环
+    pub fn speed_pid(&mut self) -> &mut Pid {
+        &mut self.speed_pid
+    }
+
+    /// 位置环
+    pub fn position_pid(&mut self) -> &mut Pid {
+        &mut self.position_pid
+    }
+
+    /// 梯形规划器
+    pub fn profile(&self) -> &Trapezoid {
+        &self.profile
+    }
+
+    /// 电机
+    pub fn motor(&mut self) -> &mut M {
+        &mut self.motor
+    }
+
+    fn enter_idle(&mut self) {
+        self.mode = ControlMode::Idle;
+        self.speed_pid.reset();
+        self.position_pid.reset();
+        self.target_speed = 0.0;
+        self.speed_setpoint = 0.0;
+        self.output = 0;
+    }
+}
+
+/// 限制在 `min`~`max` 之间
+/// 不使用 `f32::clamp`，其参数检查的 panic 信息会链接浮点数格式化代码，占用约 15KB FLASH
+#[allow(clippy::manual_clamp)]
+fn clamp(value: f32, min: f32, max: f32) -> f32 {
+    value.max(min).min(max)
+}
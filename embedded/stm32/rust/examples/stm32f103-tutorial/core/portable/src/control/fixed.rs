@@ -0,0 +1,151 @@
+//! PID 使用的数值类型
+//! F103 没有 FPU，f32 运算由软件实现，对耗时敏感的控制环可以使用 Q16.16 定点数
+
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+/// PID 运算所需的数值类型，已实现 f32 和 `Fixed`
+pub trait Scalar:
+    Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn from_f32(value: f32) -> Self;
+
+    fn to_f32(self) -> f32;
+
+    /// 限制在 min~max 之间
+    fn clamp_to(self, min: Self, max: Self) -> Self {
+        if self < min {
+            min
+        } else if self > max {
+            max
+        } else {
+            self
+        }
+    }
+}
+
+impl Scalar for f32 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+}
+
+/// Q16.16 定点数
+/// 范围 -32768~32767.99998，精度约 0.000015，运算溢出时取边界值
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Fixed(i32);
+
+impl Fixed {
+    /// 小数位数
+    pub const FRAC_BITS: u32 = 16;
+    pub const MAX: Fixed = Fixed(i32::MAX);
+    pub const MIN: Fixed = Fixed(i32::MIN);
+
+    /// 由原始数据创建，即数值乘以 65536
+    pub const fn from_bits(bits: i32) -> Self {
+        Fixed(bits)
+    }
+
+    /// 原始数据
+    pub const fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    /// 由整数创建
+    pub const fn from_int(value: i16) -> Self {
+        Fixed((value as i32) << Self::FRAC_BITS)
+    }
+
+    /// 向零取整
+    pub const fn to_int(self) -> i32 {
+        if self.0 < 0 {
+            -(-(self.0 as i64) >> Self::FRAC_BITS) as i32
+        } else {
+            self.0 >> Self::FRAC_BITS
+        }
+    }
+
+    fn saturate(value: i64) -> Self {
+        Fixed(value.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+    }
+}
+
+impl Scalar for Fixed {
+    const ZERO: Self = Fixed(0);
+    const ONE: Self = Fixed(1 << Fixed::FRAC_BITS);
+
+    fn from_f32(value: f32) -> Self {
+        // f32 转整数时超出范围会取边界值
+        Fixed((value * (1 << Self::FRAC_BITS) as f32) as i32)
+    }
+
+    fn to_f32(self) -> f32 {
+        self.0 as f32 / (1 << Self::FRAC_BITS) as f32
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, rhs: Fixed) -> Fixed {
+        Fixed::saturate((self.0 as i64 * rhs.0 as i64) >> Fixed::FRAC_BITS)
+    }
+}
+
+impl Div for Fixed {
+    type Output = Fixed;
+
+    /// 除数为0时按符号取边界值
+    fn div(self, rhs: Fixed) -> Fixed {
+        if rhs.0 == 0 {
+            return if self.0 < 0 { Fixed::MIN } else { Fixed::MAX };
+        }
+        Fixed::saturate(((self.0 as i64) << Fixed::FRAC_BITS) / rhs.0 as i64)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Fixed {
+        Fixed(self.0.saturating_neg())
+    }
+}
+
+impl From<i16> for Fixed {
+    fn from(value: i16) -> Self {
+        Fixed::from_int(value)
+    }
+}
@@ -0,0 +1,22 @@
+//! 控制算法
+//! 与硬件无关的 PID 控制器、梯形速度规划、调参命令解析和电机闭环控制器
+//!
+//! - `pid`: 支持 f32 和 Q16.16 定点数，积分抗饱和、微分对测量值求导、输出限幅及前馈
+//! - `fixed`: Q16.16 定点数
+//! - `trapezoid`: 梯形速度规划，在线计算每个控制周期的位置和速度
+//! - `tuning`: 串口调参命令
+//! - `controller`: 编码器测速，速度环和位置环串级控制，通过 `Encoder` 和 `DutyOutput` 访问硬件
+//! - `sim`: 直流电机模型，用于在主机上测试
+
+pub mod controller;
+pub mod fixed;
+pub mod pid;
+pub mod sim;
+pub mod trapezoid;
+pub mod tuning;
+
+pub use controller::{ControlMode, ControllerConfig, DutyOutput, Encoder, MotorController};
+pub use fixed::{Fixed, Scalar};
+pub use pid::{Pid, PidGains, PidTerms};
+pub use trapezoid::{Setpoint, Trapezoid};
+pub use tuning::{Command, CommandReader, Gain, Loop, ParseError};
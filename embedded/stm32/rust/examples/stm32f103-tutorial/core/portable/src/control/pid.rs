@@ -0,0 +1,201 @@
+//! PID 控制器
+//!
+//! - 微分项对测量值求导，改变目标值时不会产生微分冲击，可选一阶低通滤波
+//! - 积分抗饱和: 输出饱和且误差会使饱和加深时停止积分，积分项本身也限制在输出范围内
+//! - 前馈: `kf * 目标值` 加上调用方给出的前馈量，如电机的稳态占空比
+//! - 积分累加的是 `ki * 误差 * dt`，运行中修改 ki 时输出不会跳变
+
+use super::fixed::Scalar;
+
+/// PID 参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PidGains<T = f32> {
+    /// 比例系数
+    pub kp: T,
+    /// 积分系数(每秒)
+    pub ki: T,
+    /// 微分系数(秒)
+    pub kd: T,
+    /// 目标值前馈系数
+    pub kf: T,
+}
+
+impl<T: Scalar> PidGains<T> {
+    pub fn new(kp: T, ki: T, kd: T) -> Self {
+        PidGains {
+            kp,
+            ki,
+            kd,
+            kf: T::ZERO,
+        }
+    }
+}
+
+/// 上一次计算的各项输出，用于调参时观察
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PidTerms<T = f32> {
+    pub p: T,
+    pub i: T,
+    pub d: T,
+    /// 前馈
+    pub f: T,
+    /// 限幅后的输出
+    pub output: T,
+}
+
+/// PID 控制器
+#[derive(Debug, Clone, Copy)]
+pub struct Pid<T = f32> {
+    gains: PidGains<T>,
+    output_min: T,
+    output_max: T,
+    /// 微分低通滤波系数 0~1，为0时不滤波
+    derivative_filter: T,
+    integral: T,
+    derivative: T,
+    last_measurement: Option<T>,
+    terms: PidTerms<T>,
+}
+
+impl<T: Scalar> Pid<T> {
+    /// 创建实例，输出范围为 -1~1
+    pub fn new(kp: T, ki: T, kd: T) -> Self {
+        Pid {
+            gains: PidGains::new(kp, ki, kd),
+            output_min: -T::ONE,
+            output_max: T::ONE,
+            derivative_filter: T::ZERO,
+            integral: T::ZERO,
+            derivative: T::ZERO,
+            last_measurement: None,
+            terms: PidTerms {
+                p: T::ZERO,
+                i: T::ZERO,
+                d: T::ZERO,
+                f: T::ZERO,
+                output: T::ZERO,
+            },
+        }
+    }
+
+    /// 设置输出范围
+    pub fn output_limits(mut self, min: T, max: T) -> Self {
+        self.set_output_limits(min, max);
+        self
+    }
+
+    /// 设置目标值前馈系数
+    pub fn feedforward(mut self, kf: T) -> Self {
+        self.gains.kf = kf;
+        self
+    }
+
+    /// 设置微分低通滤波系数 0~1，越大越平滑，延迟也越大
+    pub fn derivative_filter(mut self, alpha: T) -> Self {
+        self.derivative_filter = alpha.clamp_to(T::ZERO, T::ONE);
+        self
+    }
+
+    /// PID 参数
+    pub fn gains(&self) -> PidGains<T> {
+        self.gains
+    }
+
+    /// 修改 PID 参数，积分项保持不变
+    pub fn set_gains(&mut self, gains: PidGains<T>) {
+        self.gains = gains;
+    }
+
+    /// 输出范围
+    pub fn get_output_limits(&self) -> (T, T) {
+        (self.output_min, self.output_max)
+    }
+
+    /// 修改输出范围，min 大于 max 时交换
+    pub fn set_output_limits(&mut self, min: T, max: T) {
+        let (min, max) = if min > max { (max, min) } else { (min, max) };
+        self.output_min = min;
+        self.output_max = max;
+        self.integral = self.integral.clamp_to(min, max);
+    }
+
+    /// 积分项
+    pub fn integral(&self) -> T {
+        self.integral
+    }
+
+    /// 设置积分项，切换控制模式时用于无扰切换
+    pub fn set_integral(&mut self, integral: T) {
+        self.integral = integral.clamp_to(self.output_min, self.output_max);
+    }
+
+    /// 上一次计算的各项输出
+    pub fn terms(&self) -> PidTerms<T> {
+        self.terms
+    }
+
+    /// 目标值和测量值的零点同时平移 offset 时调用，平移上一次的测量值，微分项不会跳变
+    pub fn shift(&mut self, offset: T) {
+        self.last_measurement = self.last_measurement.map(|last| last + offset);
+    }
+
+    /// 清除积分和微分状态
+    pub fn reset(&mut self) {
+        self.integral = T::ZERO;
+        self.derivative = T::ZERO;
+        self.last_measurement = None;
+    }
+
+    /// 计算一次输出，dt 为距上一次计算的时间(秒)
+    pub fn update(&mut self, setpoint: T, measurement: T, dt: T) -> T {
+        self.update_with_feedforward(setpoint, measurement, T::ZERO, dt)
+    }
+
+    /// 计算一次输出，feedforward 直接加到输出上
+    pub fn update_with_feedforward(
+        &mut self,
+        setpoint: T,
+        measurement: T,
+        feedforward: T,
+        dt: T,
+    ) -> T {
+        let PidGains { kp, ki, kd, kf } = self.gains;
+        let error = setpoint - measurement;
+
+        let p = kp * error;
+
+        // 对测量值求导，第一次计算时没有上一次的测量值，微分为0
+        if let Some(last) = self.last_measurement {
+            if dt > T::ZERO {
+                let raw = -(kd * ((measurement - last) / dt));
+                let alpha = self.derivative_filter;
+                self.derivative = alpha * self.derivative + (T::ONE - alpha) * raw;
+            }
+        }
+        self.last_measurement = Some(measurement);
+        let d = self.derivative;
+
+        let f = kf * setpoint + feedforward;
+
+        // 输出已经饱和且误差会使饱和加深时不再积分
+        let integral = (self.integral + ki * error * dt).clamp_to(self.output_min, self.output_max);
+        let unclamped = p + integral + d + f;
+        let winding_up = (unclamped > self.output_max && error > T::ZERO)
+            || (unclamped < self.output_min && error < T::ZERO);
+        if !winding_up {
+            self.integral = integral;
+        }
+
+        let output = (p + self.integral + d + f).clamp_to(self.output_min, self.output_max);
+        self.terms = PidTerms {
+            p,
+            i: self.integral,
+            d,
+            f,
+            output,
+        };
+        output
+    }
+}
@@ -0,0 +1,118 @@
+//! 直流电机模型，用于在主机上测试 PID 参数和控制器
+//! 一阶惯性模型: 转速以时间常数 `time_constant` 趋近占空比对应的稳态转速，
+//! 占空比低于静摩擦时电机不转，负载相当于抵消一部分占空比
+//!
+//! ```rust
+//! let mut motor = SimMotor::new(6000.0, 0.05);
+//! let mut pid = Pid::new(0.2, 2.0, 0.0).output_limits(-1000.0, 1000.0);
+//! for _ in 0..100 {
+//!     let duty = pid.update(1000.0, motor.speed(), 0.01);
+//!     motor.set_drive(SimDrive::Duty(duty as i16));
+//!     motor.step(0.01);
+//! }
+//! ```
+
+/// 满占空比(‰)
+pub const SIM_MAX_DUTY: i16 = 1000;
+
+/// H 桥输出状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SimDrive {
+    /// 按占空比(‰)驱动
+    Duty(i16),
+    Coast,
+    Brake,
+}
+
+/// 直流电机模型
+#[derive(Debug, Clone)]
+pub struct SimMotor {
+    /// 满占空比时的空载转速(计数/s)
+    pub max_speed: f32,
+    /// 机械时间常数(s)
+    pub time_constant: f32,
+    /// 静摩擦(‰)，占空比低于该值时电机不转
+    pub stiction: i16,
+    /// 负载(‰)，与转向相反
+    pub load: i16,
+    drive: SimDrive,
+    speed: f32,
+    position: f32,
+}
+
+impl SimMotor {
+    pub fn new(max_speed: f32, time_constant: f32) -> Self {
+        SimMotor {
+            max_speed,
+            time_constant,
+            stiction: 0,
+            load: 0,
+            drive: SimDrive::Coast,
+            speed: 0.0,
+            position: 0.0,
+        }
+    }
+
+    /// 前进 dt 秒
+    pub fn step(&mut self, dt: f32) {
+        let (target, time_constant) = match self.drive {
+            // 滑行时只有摩擦力，减速较慢
+            SimDrive::Coast => (0.0, self.time_constant * 4.0),
+            // 刹车时电机两端短接，反电动势产生制动力矩
+            SimDrive::Brake => (0.0, self.time_constant / 4.0),
+            SimDrive::Duty(duty) => {
+                let duty = duty as f32;
+                let load = if self.speed != 0.0 {
+                    self.load as f32 * self.speed.signum()
+                } else {
+                    self.load as f32 * duty.signum()
+                };
+                let effective = duty - load;
+                let target = if self.speed == 0.0 && effective.abs() <= self.stiction as f32 {
+                    0.0
+                } else {
+                    self.max_speed * effective / SIM_MAX_DUTY as f32
+                };
+                (target, self.time_constant)
+            }
+        };
+
+        let alpha = if time_constant > 0.0 {
+            (dt / time_constant).min(1.0)
+        } else {
+            1.0
+        };
+        let speed = self.speed + (target - self.speed) * alpha;
+        // 没有驱动力时速度过零即停止，不会反向
+        self.speed = if target == 0.0 && speed * self.speed <= 0.0 {
+            0.0
+        } else {
+            speed
+        };
+        self.position += self.speed * dt;
+    }
+
+    /// H 桥输出状态
+    pub fn drive(&self) -> SimDrive {
+        self.drive
+    }
+
+    /// 设置 H 桥输出，占空比限制在 ±`SIM_MAX_DUTY` 之间
+    pub fn set_drive(&mut self, drive: SimDrive) {
+        self.drive = match drive {
+            SimDrive::Duty(duty) => SimDrive::Duty(duty.clamp(-SIM_MAX_DUTY, SIM_MAX_DUTY)),
+            drive => drive,
+        };
+    }
+
+    /// 转速(计数/s)
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// 位置(计数)
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+}
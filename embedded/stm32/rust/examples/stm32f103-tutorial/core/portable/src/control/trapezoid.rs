@@ -0,0 +1,130 @@
+//! 梯形速度规划
+//! 以最大加速度加速到最大速度，匀速运动，再以相同的加速度减速停在目标位置，
+//! 距离较短时来不及加速到最大速度，速度曲线为三角形
+//!
+//! 每个控制周期在线计算下一个位置和速度，运动中可以随时修改目标位置
+//! 加速度小于等于0时不限制加速度，以最大速度直接运动到目标位置
+//!
+//! 位置为 f32，超过 2^24 后精度不足1，需要较大行程时以整数记录原点，只规划相对原点的偏移
+
+use libm::sqrtf;
+
+/// 规划得到的位置和速度
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Setpoint {
+    pub position: f32,
+    pub velocity: f32,
+}
+
+/// 梯形速度规划器
+#[derive(Debug, Clone, Copy)]
+pub struct Trapezoid {
+    /// 最大速度(单位/s)
+    pub max_velocity: f32,
+    /// 加速度(单位/s²)，小于等于0时不限制
+    pub acceleration: f32,
+    target: f32,
+    current: Setpoint,
+}
+
+impl Trapezoid {
+    pub fn new(max_velocity: f32, acceleration: f32) -> Self {
+        Trapezoid {
+            max_velocity,
+            acceleration,
+            target: 0.0,
+            current: Setpoint::default(),
+        }
+    }
+
+    /// 从指定的位置和速度开始规划，目标位置为当前位置
+    pub fn reset(&mut self, position: f32, velocity: f32) {
+        self.target = position;
+        self.current = Setpoint { position, velocity };
+    }
+
+    /// 设置目标位置
+    pub fn set_target(&mut self, target: f32) {
+        self.target = target;
+    }
+
+    /// 目标位置
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    /// 当前规划的位置和速度
+    pub fn setpoint(&self) -> Setpoint {
+        self.current
+    }
+
+    /// 是否已经停在目标位置
+    pub fn is_done(&self) -> bool {
+        self.current.position == self.target && self.current.velocity == 0.0
+    }
+
+    /// 前进 dt 秒，返回新的位置和速度
+    pub fn update(&mut self, dt: f32) -> Setpoint {
+        if self.is_done() || dt <= 0.0 {
+            return self.current;
+        }
+
+        let acceleration = self.acceleration;
+        let max_velocity = self.max_velocity.abs();
+        let Setpoint { position, velocity } = self.current;
+        let distance = self.target - position;
+
+        if acceleration <= 0.0 {
+            return self.update_unlimited(distance, max_velocity, dt);
+        }
+
+        // 剩余距离内能减速到0的最大速度
+        // 每周期速度减少 step，再按新速度移动，减速距离为 v²/2a + v·dt/2
+        let step = acceleration * dt;
+        let half_step = step / 2.0;
+        let stoppable =
+            sqrtf(half_step * half_step + 2.0 * acceleration * distance.abs()) - half_step;
+        let desired = max_velocity.min(stoppable).copysign(distance);
+
+        // 按加速度限制向期望速度靠近
+        let velocity = if desired > velocity {
+            desired.min(velocity + step)
+        } else {
+            desired.max(velocity - step)
+        };
+
+        let moved = velocity * dt;
+        let arrived = moved * distance >= 0.0 && moved.abs() >= distance.abs();
+        self.current = if arrived && velocity.abs() <= 2.0 * step {
+            // 本周期内到达目标位置，且速度已经降到可以直接停下
+            Setpoint {
+                position: self.target,
+                velocity: 0.0,
+            }
+        } else {
+            Setpoint {
+                position: position + moved,
+                velocity,
+            }
+        };
+        self.current
+    }
+
+    /// 不限制加速度，速度直接变为最大速度，本周期内能到达时停在目标位置
+    fn update_unlimited(&mut self, distance: f32, max_velocity: f32, dt: f32) -> Setpoint {
+        self.current = if distance.abs() <= max_velocity * dt {
+            Setpoint {
+                position: self.target,
+                velocity: 0.0,
+            }
+        } else {
+            let velocity = max_velocity.copysign(distance);
+            Setpoint {
+                position: self.current.position + velocity * dt,
+                velocity,
+            }
+        };
+        self.current
+    }
+}
@@ -0,0 +1,187 @@
+//! 串口调参命令
+//! 每行一条命令，命令和参数之间用空格分隔，以 `\n` 结尾，忽略 `\r`
+//!
+//! | 命令 | 说明 |
+//! | ---- | ---- |
+//! | `kp 0.2` / `ki 2` / `kd 0` / `kf 0.1` | 速度环参数 |
+//! | `pkp 5` / `pki 0` / `pkd 0` / `pkf 0` | 位置环参数 |
+//! | `speed 1000` | 速度模式，目标速度(计数/s) |
+//! | `goto 2000` | 位置模式，运动到绝对位置(计数) |
+//! | `move -500` | 位置模式，相对移动 |
+//! | `vmax 3000` / `accel 5000` | 最大速度、加速度，加速度小于等于0时不限制 |
+//! | `stop` | 减速停止 |
+//! | `idle` | 停止控制，电机滑行 |
+//! | `zero` | 当前位置设为0 |
+//! | `status` / `?` | 打印状态 |
+
+use core::str::FromStr;
+
+use heapless::Vec;
+
+/// 控制环
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Loop {
+    Speed,
+    Position,
+}
+
+/// PID 参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Gain {
+    Kp,
+    Ki,
+    Kd,
+    Kf,
+}
+
+/// 调参命令
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Command {
+    /// 修改 PID 参数
+    Gain {
+        target: Loop,
+        gain: Gain,
+        value: f32,
+    },
+    /// 速度模式
+    Speed(f32),
+    /// 运动到绝对位置
+    MoveTo(i32),
+    /// 相对移动
+    MoveBy(i32),
+    /// 最大速度
+    MaxVelocity(f32),
+    /// 加速度
+    Acceleration(f32),
+    /// 减速停止
+    Stop,
+    /// 停止控制
+    Idle,
+    /// 当前位置设为0
+    Zero,
+    /// 打印状态
+    Status,
+}
+
+/// 命令解析错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParseError {
+    /// 空行
+    Empty,
+    /// 未知命令
+    UnknownCommand,
+    /// 缺少参数
+    MissingValue,
+    /// 参数不是有效的数字
+    InvalidValue,
+    /// 多余的参数
+    TrailingInput,
+    /// 一行超过缓冲区长度
+    TooLong,
+}
+
+impl FromStr for Command {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut words = line.split_ascii_whitespace();
+        let name = words.next().ok_or(ParseError::Empty)?;
+
+        let command = match name {
+            "stop" => Command::Stop,
+            "idle" => Command::Idle,
+            "zero" => Command::Zero,
+            "status" | "?" => Command::Status,
+            "speed" => Command::Speed(value(&mut words)?),
+            "goto" => Command::MoveTo(value(&mut words)?),
+            "move" => Command::MoveBy(value(&mut words)?),
+            "vmax" => Command::MaxVelocity(value(&mut words)?),
+            "accel" => Command::Acceleration(value(&mut words)?),
+            _ => {
+                let (target, gain) = match name {
+                    "kp" => (Loop::Speed, Gain::Kp),
+                    "ki" => (Loop::Speed, Gain::Ki),
+                    "kd" => (Loop::Speed, Gain::Kd),
+                    "kf" => (Loop::Speed, Gain::Kf),
+                    "pkp" => (Loop::Position, Gain::Kp),
+                    "pki" => (Loop::Position, Gain::Ki),
+                    "pkd" => (Loop::Position, Gain::Kd),
+                    "pkf" => (Loop::Position, Gain::Kf),
+                    _ => return Err(ParseError::UnknownCommand),
+                };
+                Command::Gain {
+                    target,
+                    gain,
+                    value: value(&mut words)?,
+                }
+            }
+        };
+
+        if words.next().is_some() {
+            return Err(ParseError::TrailingInput);
+        }
+        Ok(command)
+    }
+}
+
+/// 解析下一个参数
+fn value<'a, T: FromStr>(words: &mut impl Iterator<Item = &'a str>) -> Result<T, ParseError> {
+    words
+        .next()
+        .ok_or(ParseError::MissingValue)?
+        .parse()
+        .map_err(|_| ParseError::InvalidValue)
+}
+
+/// 按字节接收命令，收到一行后解析
+pub struct CommandReader<const N: usize> {
+    line: Vec<u8, N>,
+    overflow: bool,
+}
+
+impl<const N: usize> Default for CommandReader<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> CommandReader<N> {
+    pub fn new() -> Self {
+        CommandReader {
+            line: Vec::new(),
+            overflow: false,
+        }
+    }
+
+    /// 输入一个字节，收到 `\n` 时返回解析结果，空行返回 None
+    pub fn push(&mut self, byte: u8) -> Option<Result<Command, ParseError>> {
+        match byte {
+            b'\r' => None,
+            b'\n' => {
+                let result = if self.overflow {
+                    Err(ParseError::TooLong)
+                } else {
+                    core::str::from_utf8(&self.line)
+                        .map_err(|_| ParseError::UnknownCommand)
+                        .and_then(str::parse)
+                };
+                self.line.clear();
+                self.overflow = false;
+                match result {
+                    Err(ParseError::Empty) => None,
+                    result => Some(result),
+                }
+            }
+            _ => {
+                if self.line.push(byte).is_err() {
+                    self.overflow = true;
+                }
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! 互补滤波
+//! 陀螺仪积分的角度短期准确但会漂移，加速度计算的角度长期准确但噪声大，
+//! 按比例融合两者，计算量最小
+
+use super::Measurement;
+use super::{wrap_180, AttitudeFilter, EulerAngles, Quaternion};
+
+/// 互补滤波器
+#[derive(Debug, Clone, Copy)]
+pub struct Complementary {
+    /// 陀螺仪积分所占的比例，通常为0.95~0.99
+    pub alpha: f32,
+    angles: EulerAngles,
+    initialized: bool,
+}
+
+impl Complementary {
+    pub fn new(alpha: f32) -> Self {
+        Complementary {
+            alpha,
+            angles: EulerAngles::default(),
+            initialized: false,
+        }
+    }
+
+    /// 当前姿态角(°)
+    pub fn angles(&self) -> EulerAngles {
+        self.angles
+    }
+}
+
+impl Default for Complementary {
+    fn default() -> Self {
+        Self::new(0.98)
+    }
+}
+
+impl AttitudeFilter for Complementary {
+    fn update(&mut self, m: &Measurement, dt: f32) {
+        let accel = EulerAngles::from_accel(m);
+        // 第一次更新直接采用加速度计算的角度，避免从0开始缓慢收敛
+        if !self.initialized {
+            self.angles.roll = accel.roll;
+            self.angles.pitch = accel.pitch;
+            self.initialized = true;
+            return;
+        }
+
+        // 陀螺仪积分后，按与加速度计算角度的差值修正
+        // 差值按最短路径计算，横滚角在±180°附近跳变时不会被拉向0°
+        let alpha = self.alpha;
+        let roll = self.angles.roll + m.gyro_x * dt;
+        self.angles.roll = wrap_180(roll + (1.0 - alpha) * wrap_180(accel.roll - roll));
+        let pitch = self.angles.pitch + m.gyro_y * dt;
+        self.angles.pitch = pitch + (1.0 - alpha) * (accel.pitch - pitch);
+        // 偏航角没有参考，只能积分
+        self.angles.yaw = wrap_180(self.angles.yaw + m.gyro_z * dt);
+    }
+
+    fn quaternion(&self) -> Quaternion {
+        Quaternion::from_euler(&self.angles)
+    }
+
+    fn euler(&self) -> EulerAngles {
+        self.angles
+    }
+}
@@ -0,0 +1,105 @@
+//! Madgwick 滤波
+//! 用梯度下降法修正陀螺仪积分得到的四元数，使重力方向与加速度计测量值一致
+//! 参考: S. Madgwick, An efficient orientation filter for inertial and
+//! inertial/magnetic sensor arrays, 2010
+
+use super::Measurement;
+use super::{normalize3, AttitudeFilter, Quaternion, DEG_TO_RAD};
+
+/// Madgwick 滤波器
+#[derive(Debug, Clone, Copy)]
+pub struct Madgwick {
+    /// 梯度下降步长，越大收敛越快但噪声越大，通常为0.03~0.2
+    pub beta: f32,
+    q: Quaternion,
+}
+
+impl Madgwick {
+    pub fn new(beta: f32) -> Self {
+        Madgwick {
+            beta,
+            q: Quaternion::IDENTITY,
+        }
+    }
+
+    /// 设置初始姿态，例如用加速度计算的姿态角加快收敛
+    pub fn set_quaternion(&mut self, q: Quaternion) {
+        self.q = q;
+    }
+}
+
+impl Default for Madgwick {
+    fn default() -> Self {
+        Self::new(0.1)
+    }
+}
+
+impl AttitudeFilter for Madgwick {
+    fn update(&mut self, m: &Measurement, dt: f32) {
+        let Quaternion {
+            w: q0,
+            x: q1,
+            y: q2,
+            z: q3,
+        } = self.q;
+        let gx = m.gyro_x * DEG_TO_RAD;
+        let gy = m.gyro_y * DEG_TO_RAD;
+        let gz = m.gyro_z * DEG_TO_RAD;
+
+        // 陀螺仪得到的四元数变化率
+        let mut q_dot0 = 0.5 * (-q1 * gx - q2 * gy - q3 * gz);
+        let mut q_dot1 = 0.5 * (q0 * gx + q2 * gz - q3 * gy);
+        let mut q_dot2 = 0.5 * (q0 * gy - q1 * gz + q3 * gx);
+        let mut q_dot3 = 0.5 * (q0 * gz + q1 * gy - q2 * gx);
+
+        // 加速度为0(自由落体)时只积分陀螺仪
+        if let Some((ax, ay, az)) = normalize3(m.acc_x, m.acc_y, m.acc_z) {
+            let _2q0 = 2.0 * q0;
+            let _2q1 = 2.0 * q1;
+            let _2q2 = 2.0 * q2;
+            let _2q3 = 2.0 * q3;
+            let _4q0 = 4.0 * q0;
+            let _4q1 = 4.0 * q1;
+            let _4q2 = 4.0 * q2;
+            let _8q1 = 8.0 * q1;
+            let _8q2 = 8.0 * q2;
+            let q0q0 = q0 * q0;
+            let q1q1 = q1 * q1;
+            let q2q2 = q2 * q2;
+            let q3q3 = q3 * q3;
+
+            // 目标函数的梯度
+            let s0 = _4q0 * q2q2 + _2q2 * ax + _4q0 * q1q1 - _2q1 * ay;
+            let s1 = _4q1 * q3q3 - _2q3 * ax + 4.0 * q0q0 * q1 - _2q0 * ay - _4q1
+                + _8q1 * q1q1
+                + _8q1 * q2q2
+                + _4q1 * az;
+            let s2 = 4.0 * q0q0 * q2 + _2q0 * ax + _4q2 * q3q3 - _2q3 * ay - _4q2
+                + _8q2 * q1q1
+                + _8q2 * q2q2
+                + _4q2 * az;
+            let s3 = 4.0 * q1q1 * q3 - _2q1 * ax + 4.0 * q2q2 * q3 - _2q2 * ay;
+
+            let norm = libm::sqrtf(s0 * s0 + s1 * s1 + s2 * s2 + s3 * s3);
+            if norm > 0.0 {
+                let step = self.beta / norm;
+                q_dot0 -= step * s0;
+                q_dot1 -= step * s1;
+                q_dot2 -= step * s2;
+                q_dot3 -= step * s3;
+            }
+        }
+
+        self.q = Quaternion {
+            w: q0 + q_dot0 * dt,
+            x: q1 + q_dot1 * dt,
+            y: q2 + q_dot2 * dt,
+            z: q3 + q_dot3 * dt,
+        };
+        self.q.normalize();
+    }
+
+    fn quaternion(&self) -> Quaternion {
+        self.q
+    }
+}
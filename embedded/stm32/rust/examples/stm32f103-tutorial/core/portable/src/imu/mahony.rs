@@ -0,0 +1,103 @@
+//! Mahony 滤波
+//! 用 PI 控制器修正陀螺仪角速度，比例项修正姿态误差，积分项在线估计陀螺仪零偏
+//! 比 Madgwick 计算量更小
+
+use super::Measurement;
+use super::{normalize3, AttitudeFilter, Quaternion, DEG_TO_RAD, RAD_TO_DEG};
+
+/// Mahony 滤波器
+#[derive(Debug, Clone, Copy)]
+pub struct Mahony {
+    /// 比例增益，通常为0.5~2.0
+    pub kp: f32,
+    /// 积分增益，为0时不估计零偏，通常为0~0.1
+    pub ki: f32,
+    q: Quaternion,
+    /// 误差积分(rad/s)，即估计的陀螺仪零偏
+    integral: [f32; 3],
+}
+
+impl Mahony {
+    pub fn new(kp: f32, ki: f32) -> Self {
+        Mahony {
+            kp,
+            ki,
+            q: Quaternion::IDENTITY,
+            integral: [0.0; 3],
+        }
+    }
+
+    /// 设置初始姿态
+    pub fn set_quaternion(&mut self, q: Quaternion) {
+        self.q = q;
+    }
+
+    /// 估计的陀螺仪零偏(°/s)
+    pub fn gyro_bias(&self) -> [f32; 3] {
+        [
+            -self.integral[0] * RAD_TO_DEG,
+            -self.integral[1] * RAD_TO_DEG,
+            -self.integral[2] * RAD_TO_DEG,
+        ]
+    }
+}
+
+impl Default for Mahony {
+    fn default() -> Self {
+        Self::new(1.0, 0.02)
+    }
+}
+
+impl AttitudeFilter for Mahony {
+    fn update(&mut self, m: &Measurement, dt: f32) {
+        let Quaternion {
+            w: q0,
+            x: q1,
+            y: q2,
+            z: q3,
+        } = self.q;
+        let mut gx = m.gyro_x * DEG_TO_RAD;
+        let mut gy = m.gyro_y * DEG_TO_RAD;
+        let mut gz = m.gyro_z * DEG_TO_RAD;
+
+        // 加速度为0(自由落体)时只积分陀螺仪
+        if let Some((ax, ay, az)) = normalize3(m.acc_x, m.acc_y, m.acc_z) {
+            // 由当前姿态估计的重力方向
+            let vx = 2.0 * (q1 * q3 - q0 * q2);
+            let vy = 2.0 * (q0 * q1 + q2 * q3);
+            let vz = q0 * q0 - q1 * q1 - q2 * q2 + q3 * q3;
+
+            // 测量值与估计值的叉积即为姿态误差
+            let ex = ay * vz - az * vy;
+            let ey = az * vx - ax * vz;
+            let ez = ax * vy - ay * vx;
+
+            if self.ki > 0.0 {
+                self.integral[0] += self.ki * ex * dt;
+                self.integral[1] += self.ki * ey * dt;
+                self.integral[2] += self.ki * ez * dt;
+                gx += self.integral[0];
+                gy += self.integral[1];
+                gz += self.integral[2];
+            }
+
+            gx += self.kp * ex;
+            gy += self.kp * ey;
+            gz += self.kp * ez;
+        }
+
+        // 一阶龙格库塔法更新四元数
+        let half_dt = 0.5 * dt;
+        self.q = Quaternion {
+            w: q0 + (-q1 * gx - q2 * gy - q3 * gz) * half_dt,
+            x: q1 + (q0 * gx + q2 * gz - q3 * gy) * half_dt,
+            y: q2 + (q0 * gy - q1 * gz + q3 * gx) * half_dt,
+            z: q3 + (q0 * gz + q1 * gy - q2 * gx) * half_dt,
+        };
+        self.q.normalize();
+    }
+
+    fn quaternion(&self) -> Quaternion {
+        self.q
+    }
+}
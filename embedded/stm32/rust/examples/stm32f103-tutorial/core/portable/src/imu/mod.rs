@@ -0,0 +1,260 @@
+//! 姿态解算
+//! 由 MPU6050 的加速度和角速度数据计算姿态角(横滚、俯仰、偏航)和四元数
+//! 提供互补滤波、Madgwick 和 Mahony 三种算法，均使用 f32 运算
+//!
+//! F103 没有 FPU，浮点运算由软件实现，Madgwick/Mahony 单次更新约需数千个时钟周期，
+//! 72MHz 下 1kHz 的更新频率仍有充足余量
+//!
+//! ```rust
+//! let mut bias = imu::GyroBias::new();
+//! while !bias.is_ready() {
+//!     bias.add_sample(&mpu.get_measurement());
+//! }
+//! let mut filter = imu::Madgwick::new(0.1);
+//! loop {
+//!     let mut m = mpu.get_measurement();
+//!     bias.correct(&mut m);
+//!     filter.update(&m, 0.01);
+//!     let angles = filter.euler();
+//! }
+//! ```
+pub mod complementary;
+pub mod madgwick;
+pub mod mahony;
+
+pub use complementary::Complementary;
+pub use madgwick::Madgwick;
+pub use mahony::Mahony;
+
+use libm::{asinf, atan2f, cosf, sinf, sqrtf};
+
+/// 角度转弧度
+pub const DEG_TO_RAD: f32 = core::f32::consts::PI / 180.0;
+/// 弧度转角度
+pub const RAD_TO_DEG: f32 = 180.0 / core::f32::consts::PI;
+
+/// 物理单位的测量值
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Measurement {
+    /// 加速度(m/s²)
+    pub acc_x: f32,
+    pub acc_y: f32,
+    pub acc_z: f32,
+    /// 角速度(°/s)
+    pub gyro_x: f32,
+    pub gyro_y: f32,
+    pub gyro_z: f32,
+    /// 温度(°C)
+    pub temp: f32,
+}
+
+/// 将角度限制在 (-180°, 180°] 范围内
+pub fn wrap_180(angle: f32) -> f32 {
+    let angle = angle % 360.0;
+    if angle > 180.0 {
+        angle - 360.0
+    } else if angle <= -180.0 {
+        angle + 360.0
+    } else {
+        angle
+    }
+}
+
+/// 姿态角(°)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EulerAngles {
+    /// 横滚角，绕X轴
+    pub roll: f32,
+    /// 俯仰角，绕Y轴
+    pub pitch: f32,
+    /// 偏航角，绕Z轴，没有磁力计时会随时间漂移
+    pub yaw: f32,
+}
+
+impl EulerAngles {
+    /// 仅由加速度计算横滚角和俯仰角，偏航角为0
+    pub fn from_accel(m: &Measurement) -> Self {
+        EulerAngles {
+            roll: atan2f(m.acc_y, m.acc_z) * RAD_TO_DEG,
+            pitch: atan2f(-m.acc_x, sqrtf(m.acc_y * m.acc_y + m.acc_z * m.acc_z)) * RAD_TO_DEG,
+            yaw: 0.0,
+        }
+    }
+}
+
+/// 姿态四元数
+#[derive(Debug, Clone, Copy)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Quaternion::IDENTITY
+    }
+}
+
+impl Quaternion {
+    /// 单位四元数，即水平静止的初始姿态
+    pub const IDENTITY: Quaternion = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    /// 由姿态角(°)构造，旋转顺序为 Z-Y-X
+    pub fn from_euler(angles: &EulerAngles) -> Self {
+        let (sr, cr) = half_sin_cos(angles.roll * DEG_TO_RAD);
+        let (sp, cp) = half_sin_cos(angles.pitch * DEG_TO_RAD);
+        let (sy, cy) = half_sin_cos(angles.yaw * DEG_TO_RAD);
+
+        Quaternion {
+            w: cr * cp * cy + sr * sp * sy,
+            x: sr * cp * cy - cr * sp * sy,
+            y: cr * sp * cy + sr * cp * sy,
+            z: cr * cp * sy - sr * sp * cy,
+        }
+    }
+
+    /// 转换为姿态角(°)
+    pub fn to_euler(&self) -> EulerAngles {
+        let Quaternion { w, x, y, z } = *self;
+        // 俯仰角接近±90°时限制 asin 的输入，避免数值误差导致 NaN
+        let sin_pitch = (2.0 * (w * y - z * x)).clamp(-1.0, 1.0);
+
+        EulerAngles {
+            roll: atan2f(2.0 * (w * x + y * z), 1.0 - 2.0 * (x * x + y * y)) * RAD_TO_DEG,
+            pitch: asinf(sin_pitch) * RAD_TO_DEG,
+            yaw: atan2f(2.0 * (w * z + x * y), 1.0 - 2.0 * (y * y + z * z)) * RAD_TO_DEG,
+        }
+    }
+
+    /// 归一化
+    pub fn normalize(&mut self) {
+        let norm = sqrtf(self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z);
+        if norm > 0.0 {
+            let inv = 1.0 / norm;
+            self.w *= inv;
+            self.x *= inv;
+            self.y *= inv;
+            self.z *= inv;
+        }
+    }
+}
+
+/// 半角的正弦和余弦
+fn half_sin_cos(angle: f32) -> (f32, f32) {
+    (sinf(angle * 0.5), cosf(angle * 0.5))
+}
+
+/// 归一化三维向量，长度为0时返回 None
+fn normalize3(x: f32, y: f32, z: f32) -> Option<(f32, f32, f32)> {
+    let norm = sqrtf(x * x + y * y + z * z);
+    if norm == 0.0 {
+        return None;
+    }
+    let inv = 1.0 / norm;
+    Some((x * inv, y * inv, z * inv))
+}
+
+/// 姿态滤波器
+pub trait AttitudeFilter {
+    /// 输入一次测量值
+    /// m: 已去除零偏的测量值
+    /// dt: 距上次更新的时间(s)
+    fn update(&mut self, m: &Measurement, dt: f32);
+
+    /// 当前姿态四元数
+    fn quaternion(&self) -> Quaternion;
+
+    /// 当前姿态角(°)
+    fn euler(&self) -> EulerAngles {
+        self.quaternion().to_euler()
+    }
+}
+
+/// 陀螺仪零偏估计
+/// 上电后保持静止，累计一定数量的样本求平均值
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GyroBias {
+    sum: [f32; 3],
+    count: u32,
+    samples: u32,
+    bias: [f32; 3],
+}
+
+impl GyroBias {
+    /// 默认累计的样本数
+    pub const DEFAULT_SAMPLES: u32 = 200;
+
+    pub fn new() -> Self {
+        Self::with_samples(Self::DEFAULT_SAMPLES)
+    }
+
+    /// samples: 求平均的样本数
+    pub fn with_samples(samples: u32) -> Self {
+        GyroBias {
+            samples: samples.max(1),
+            ..Default::default()
+        }
+    }
+
+    /// 由已知的零偏(°/s)构造，例如从 FLASH 中读取的校准值
+    pub fn from_bias(bias: [f32; 3]) -> Self {
+        GyroBias {
+            samples: 0,
+            bias,
+            ..Default::default()
+        }
+    }
+
+    /// 累计一个静止时的样本
+    pub fn add_sample(&mut self, m: &Measurement) {
+        if self.is_ready() {
+            return;
+        }
+        self.sum[0] += m.gyro_x;
+        self.sum[1] += m.gyro_y;
+        self.sum[2] += m.gyro_z;
+        self.count += 1;
+        if self.is_ready() {
+            let n = self.count as f32;
+            self.bias = [self.sum[0] / n, self.sum[1] / n, self.sum[2] / n];
+        }
+    }
+
+    /// 是否已累计足够的样本
+    pub fn is_ready(&self) -> bool {
+        self.count >= self.samples
+    }
+
+    /// 零偏(°/s)
+    pub fn bias(&self) -> [f32; 3] {
+        self.bias
+    }
+
+    /// 静止时缓慢跟踪零偏的温度漂移
+    /// 角速度模长小于 threshold(°/s) 时认为静止
+    /// alpha: 跟踪系数，取值越小越平滑，例如0.001
+    pub fn track(&mut self, m: &Measurement, threshold: f32, alpha: f32) {
+        let gx = m.gyro_x - self.bias[0];
+        let gy = m.gyro_y - self.bias[1];
+        let gz = m.gyro_z - self.bias[2];
+        if gx * gx + gy * gy + gz * gz < threshold * threshold {
+            self.bias[0] += alpha * gx;
+            self.bias[1] += alpha * gy;
+            self.bias[2] += alpha * gz;
+        }
+    }
+
+    /// 从测量值中减去零偏
+    pub fn correct(&self, m: &mut Measurement) {
+        m.gyro_x -= self.bias[0];
+        m.gyro_y -= self.bias[1];
+        m.gyro_z -= self.bias[2];
+    }
+}
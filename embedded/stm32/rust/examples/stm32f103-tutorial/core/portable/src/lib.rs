@@ -0,0 +1,11 @@
+//! 与芯片外设无关的驱动和算法
+//! 只依赖 embedded-hal 1.0 等通用接口，既可以在 STM32 上运行，也可以在主机上编译和测试
+//! 固件通过 `hardware` 库使用，仿真器等测试工具只在本库中提供
+#![no_std]
+
+pub mod asynch;
+pub mod control;
+pub mod imu;
+pub mod nrf24;
+pub mod stats;
+pub mod w25q64;
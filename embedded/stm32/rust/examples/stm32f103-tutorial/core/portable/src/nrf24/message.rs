@@ -0,0 +1,245 @@
+//! 消息序列化
+//! 消息的第一个字节为类型ID，之后为按小端序依次写入的字段
+//!
+//! ```rust
+//! struct Reading {
+//!     counter: u32,
+//!     temperature: f32,
+//! }
+//!
+//! impl Message for Reading {
+//!     const ID: u8 = 1;
+//!
+//!     fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+//!         w.u32(self.counter)?;
+//!         w.f32(self.temperature)
+//!     }
+//!
+//!     fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+//!         Ok(Reading {
+//!             counter: r.u32()?,
+//!             temperature: r.f32()?,
+//!         })
+//!     }
+//! }
+//!
+//! let len = message::encode(&reading, &mut buf)?;
+//! let reading: Reading = message::decode(&buf[..len])?;
+//! ```
+
+/// 序列化错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CodecError {
+    /// 缓冲区空间不足
+    BufferTooSmall,
+    /// 数据不完整
+    UnexpectedEnd,
+    /// 类型ID与要解析的消息不符
+    UnknownId(u8),
+    /// 字段的值无效
+    Invalid,
+    /// 解析完成后还有多余的数据
+    TrailingBytes,
+}
+
+/// 可以序列化的消息
+pub trait Message: Sized {
+    /// 类型ID，接收方据此判断消息的类型
+    const ID: u8;
+
+    /// 写入字段，不含类型ID
+    fn encode(&self, w: &mut Writer) -> Result<(), CodecError>;
+
+    /// 读取字段，不含类型ID
+    fn decode(r: &mut Reader) -> Result<Self, CodecError>;
+}
+
+/// 序列化消息，返回写入的长度
+pub fn encode<M: Message>(message: &M, buf: &mut [u8]) -> Result<usize, CodecError> {
+    let mut w = Writer::new(buf);
+    w.u8(M::ID)?;
+    message.encode(&mut w)?;
+    Ok(w.len())
+}
+
+/// 解析消息
+pub fn decode<M: Message>(buf: &[u8]) -> Result<M, CodecError> {
+    let mut r = Reader::new(buf);
+    let id = r.u8()?;
+    if id != M::ID {
+        return Err(CodecError::UnknownId(id));
+    }
+    let message = M::decode(&mut r)?;
+    if !r.is_empty() {
+        return Err(CodecError::TrailingBytes);
+    }
+    Ok(message)
+}
+
+/// 消息的类型ID
+pub fn message_id(buf: &[u8]) -> Option<u8> {
+    buf.first().copied()
+}
+
+/// 按小端序写入字段
+pub struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Writer { buf, pos: 0 }
+    }
+
+    /// 已写入的长度
+    pub fn len(&self) -> usize {
+        self.pos
+    }
+
+    /// 是否还没有写入数据
+    pub fn is_empty(&self) -> bool {
+        self.pos == 0
+    }
+
+    /// 写入原始字节
+    pub fn bytes(&mut self, data: &[u8]) -> Result<(), CodecError> {
+        let end = self.pos + data.len();
+        if end > self.buf.len() {
+            return Err(CodecError::BufferTooSmall);
+        }
+        self.buf[self.pos..end].copy_from_slice(data);
+        self.pos = end;
+        Ok(())
+    }
+
+    pub fn u8(&mut self, value: u8) -> Result<(), CodecError> {
+        self.bytes(&[value])
+    }
+
+    pub fn i8(&mut self, value: i8) -> Result<(), CodecError> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub fn bool(&mut self, value: bool) -> Result<(), CodecError> {
+        self.u8(value as u8)
+    }
+
+    pub fn u16(&mut self, value: u16) -> Result<(), CodecError> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub fn i16(&mut self, value: i16) -> Result<(), CodecError> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub fn u32(&mut self, value: u32) -> Result<(), CodecError> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub fn i32(&mut self, value: i32) -> Result<(), CodecError> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub fn f32(&mut self, value: f32) -> Result<(), CodecError> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// 写入长度(u16)和数据
+    pub fn slice(&mut self, data: &[u8]) -> Result<(), CodecError> {
+        let len = u16::try_from(data.len()).map_err(|_| CodecError::BufferTooSmall)?;
+        self.u16(len)?;
+        self.bytes(data)
+    }
+
+    /// 写入长度(u16)和 UTF-8 字符串
+    pub fn str(&mut self, s: &str) -> Result<(), CodecError> {
+        self.slice(s.as_bytes())
+    }
+}
+
+/// 按小端序读取字段
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Reader { buf, pos: 0 }
+    }
+
+    /// 剩余的字节数
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    /// 是否已读完
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// 读取原始字节
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], CodecError> {
+        if len > self.remaining() {
+            return Err(CodecError::UnexpectedEnd);
+        }
+        let data = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(data)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], CodecError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, CodecError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn i8(&mut self) -> Result<i8, CodecError> {
+        self.array().map(i8::from_le_bytes)
+    }
+
+    pub fn bool(&mut self) -> Result<bool, CodecError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CodecError::Invalid),
+        }
+    }
+
+    pub fn u16(&mut self) -> Result<u16, CodecError> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    pub fn i16(&mut self) -> Result<i16, CodecError> {
+        self.array().map(i16::from_le_bytes)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, CodecError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    pub fn i32(&mut self) -> Result<i32, CodecError> {
+        self.array().map(i32::from_le_bytes)
+    }
+
+    pub fn f32(&mut self) -> Result<f32, CodecError> {
+        self.array().map(f32::from_le_bytes)
+    }
+
+    /// 读取长度(u16)和数据
+    pub fn slice(&mut self) -> Result<&'a [u8], CodecError> {
+        let len = self.u16()? as usize;
+        self.bytes(len)
+    }
+
+    /// 读取长度(u16)和 UTF-8 字符串
+    pub fn str(&mut self) -> Result<&'a str, CodecError> {
+        core::str::from_utf8(self.slice()?).map_err(|_| CodecError::Invalid)
+    }
+}
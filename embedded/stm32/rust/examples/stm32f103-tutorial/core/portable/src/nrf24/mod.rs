@@ -0,0 +1,26 @@
+//! nRF24L01 无线通信的协议层
+//! - `transport`: 在32字节数据包之上实现分片重组、应答重发和去重，通过 `Radio` 接口收发数据帧
+//! - `message`: 消息序列化
+//! - `sim`: 模拟信道，用于在主机上测试 `Transport`
+//!
+//! 芯片驱动及 `Radio` 的实现在 `hardware::nrf24` 中
+
+pub mod message;
+pub mod sim;
+pub mod transport;
+
+pub use message::{CodecError, Message, Reader, Writer};
+pub use transport::{LinkStats, Radio, Transport, TransportConfig, TxState};
+
+/// 单个数据包的最大长度
+pub const NRF24_MAX_PAYLOAD: usize = 32;
+
+/// 发送统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Observe {
+    /// 丢包计数，最大为15，修改频道后清零
+    pub lost: u8,
+    /// 上一个数据包的重发次数
+    pub retries: u8,
+}
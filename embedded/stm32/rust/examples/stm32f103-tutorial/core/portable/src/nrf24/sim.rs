@@ -0,0 +1,139 @@
+//! 模拟无线信道
+//! 在 RAM 中模拟两个 nRF24L01 之间的信道，按设定的概率丢弃数据帧，用于在主机上测试 `Transport`
+//! 丢包由固定种子的伪随机数决定，相同的种子和操作顺序得到相同的结果
+//!
+//! ```rust
+//! let channel = RefCell::new(SimChannel::new(30, 1));
+//! let mut a: Transport<_, 256> = Transport::new(SimRadio::new(&channel, Side::A), config, 1);
+//! let mut b: Transport<_, 256> = Transport::new(SimRadio::new(&channel, Side::B), config, 2);
+//! ```
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+
+use heapless::{Deque, Vec};
+
+use super::transport::Radio;
+use super::NRF24_MAX_PAYLOAD;
+
+/// 每个方向最多缓存的帧数，与 nRF24L01 的接收缓冲区相同
+pub const SIM_QUEUE_LEN: usize = 3;
+
+type Frame = Vec<u8, NRF24_MAX_PAYLOAD>;
+
+/// 信道的一端
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    A,
+    B,
+}
+
+/// 信道统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimStats {
+    /// 发送的帧数
+    pub sent: u32,
+    /// 按概率丢弃的帧数
+    pub lost: u32,
+    /// 接收缓冲区已满而丢弃的帧数
+    pub overflow: u32,
+}
+
+/// 模拟信道
+pub struct SimChannel {
+    /// 发往 A、B 的帧
+    queues: [Deque<Frame, SIM_QUEUE_LEN>; 2],
+    /// 丢包概率(%)
+    loss_percent: u8,
+    rng: u32,
+    stats: SimStats,
+}
+
+impl SimChannel {
+    /// 创建信道，`loss_percent` 为丢包概率(%)，`seed` 为伪随机数种子
+    pub fn new(loss_percent: u8, seed: u32) -> Self {
+        SimChannel {
+            queues: [Deque::new(), Deque::new()],
+            loss_percent: loss_percent.min(100),
+            rng: seed.max(1),
+            stats: SimStats::default(),
+        }
+    }
+
+    /// 修改丢包概率(%)
+    pub fn set_loss(&mut self, loss_percent: u8) {
+        self.loss_percent = loss_percent.min(100);
+    }
+
+    /// 信道统计
+    pub fn stats(&self) -> SimStats {
+        self.stats
+    }
+
+    /// xorshift32 伪随机数
+    fn random(&mut self) -> u32 {
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng = x;
+        x
+    }
+
+    /// 发送一帧，返回对方是否收到
+    fn send(&mut self, to: Side, frame: &[u8]) -> bool {
+        self.stats.sent += 1;
+        if self.random() % 100 < self.loss_percent as u32 {
+            self.stats.lost += 1;
+            return false;
+        }
+        let Ok(frame) = Frame::from_slice(frame) else {
+            self.stats.lost += 1;
+            return false;
+        };
+        if self.queues[to as usize].push_back(frame).is_err() {
+            self.stats.overflow += 1;
+            return false;
+        }
+        true
+    }
+
+    fn receive(&mut self, side: Side) -> Option<Frame> {
+        self.queues[side as usize].pop_front()
+    }
+}
+
+/// 连接到模拟信道的无线模块
+pub struct SimRadio<'a> {
+    channel: &'a RefCell<SimChannel>,
+    side: Side,
+}
+
+impl<'a> SimRadio<'a> {
+    pub fn new(channel: &'a RefCell<SimChannel>, side: Side) -> Self {
+        SimRadio { channel, side }
+    }
+}
+
+impl Radio for SimRadio<'_> {
+    type Error = Infallible;
+
+    /// 对方没有收到时返回 `Ok(false)`，相当于硬件自动应答超时
+    fn transmit(&mut self, frame: &[u8]) -> Result<bool, Infallible> {
+        let to = match self.side {
+            Side::A => Side::B,
+            Side::B => Side::A,
+        };
+        Ok(self.channel.borrow_mut().send(to, frame))
+    }
+
+    fn receive(&mut self, buf: &mut [u8; NRF24_MAX_PAYLOAD]) -> nb::Result<usize, Infallible> {
+        let frame = self
+            .channel
+            .borrow_mut()
+            .receive(self.side)
+            .ok_or(nb::Error::WouldBlock)?;
+        buf[..frame.len()].copy_from_slice(&frame);
+        Ok(frame.len())
+    }
+}
@@ -0,0 +1,529 @@
+//! 可靠消息传输
+//! 在 nRF24L01 32字节数据包之上实现分片重组、序号、应答、重发和去重，单条消息最长 `N` 字节
+//!
+//! 每个数据帧带有5字节帧头:
+//! - 第0字节: 高2位为帧类型(数据/应答)，低6位为本帧的数据长度
+//! - 第1字节: 会话号
+//! - 第2字节: 消息序号
+//! - 第3字节: 分片序号
+//! - 第4字节: 分片总数
+//!
+//! 发送方每次只发送一个分片(停等协议)，收到对应的应答后再发送下一个，超时后重发，
+//! 超过最大重发次数后放弃整条消息。接收方对每个收到的分片回复应答，
+//! 重复的分片只应答不保存，上一条消息还未被读取时不应答新消息，发送方稍后重发
+//!
+//! 消息序号每次上电都从0开始，为了区分复位前后的消息，每次上电使用不同的会话号，
+//! 接收方只在会话号相同时按序号去重，会话号变化说明发送方已复位，直接接收新消息
+//!
+//! ```rust
+//! let mut transport: Transport<_, 1024> = Transport::new(nrf24, TransportConfig::default(), session);
+//! transport.send(b"hello", time::now())?;
+//! loop {
+//!     transport.poll(time::now())?;
+//!     if let Some(message) = transport.receive() {
+//!         println!("{}", message);
+//!     }
+//!     match transport.tx_state() {
+//!         TxState::Delivered => {}
+//!         TxState::Failed => {}
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use super::message::{self, CodecError, Message};
+use super::{Observe, NRF24_MAX_PAYLOAD};
+
+/// 时间精度，1us
+pub const TIMER_HZ: u32 = 1_000_000;
+/// 时间点，与 `hardware::time::Instant` 相同
+pub type Instant = fugit::TimerInstantU64<TIMER_HZ>;
+/// 时间间隔
+pub type Duration = fugit::TimerDurationU64<TIMER_HZ>;
+
+/// 帧头长度
+pub const FRAME_HEADER_LEN: usize = 5;
+/// 每个分片的最大数据长度
+pub const FRAGMENT_LEN: usize = NRF24_MAX_PAYLOAD - FRAME_HEADER_LEN;
+/// 单条消息最多的分片数
+pub const MAX_FRAGMENTS: usize = u8::MAX as usize;
+
+/// 帧类型: 数据
+const FRAME_DATA: u8 = 0b00;
+/// 帧类型: 应答
+const FRAME_ACK: u8 = 0b01;
+/// 帧类型的偏移
+const FRAME_KIND_SHIFT: u8 = 6;
+/// 数据长度的掩码
+const FRAME_LEN_MASK: u8 = 0x3F;
+
+/// 收发数据帧的无线模块
+pub trait Radio {
+    type Error;
+
+    /// 发送一帧并等待发送完成，对方没有回复硬件应答时返回 `Ok(false)`
+    fn transmit(&mut self, frame: &[u8]) -> Result<bool, Self::Error>;
+
+    /// 读取一帧，返回帧长度，没有数据时返回 `WouldBlock`
+    fn receive(&mut self, buf: &mut [u8; NRF24_MAX_PAYLOAD]) -> nb::Result<usize, Self::Error>;
+
+    /// 上一帧的硬件重发次数和丢包计数
+    fn observe(&mut self) -> Result<Observe, Self::Error> {
+        Ok(Observe {
+            lost: 0,
+            retries: 0,
+        })
+    }
+
+    /// 是否检测到大于 -64dBm 的信号
+    fn carrier_detected(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
+
+/// 传输错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// 无线模块错误
+    Radio(E),
+    /// 消息为空或超过缓冲区长度
+    MessageLength,
+    /// 上一条消息还在发送
+    Busy,
+    /// 消息序列化错误
+    Codec(CodecError),
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(e: E) -> Self {
+        Error::Radio(e)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<E> defmt::Format for Error<E> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Error::Radio(_) => defmt::write!(f, "Radio"),
+            Error::MessageLength => defmt::write!(f, "MessageLength"),
+            Error::Busy => defmt::write!(f, "Busy"),
+            Error::Codec(e) => defmt::write!(f, "Codec({})", e),
+        }
+    }
+}
+
+/// 传输参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransportConfig {
+    /// 等待应答的时间
+    pub ack_timeout: Duration,
+    /// 单个分片的最大重发次数
+    pub max_retries: u8,
+}
+
+impl Default for TransportConfig {
+    /// 应答超时20ms，最多重发10次
+    fn default() -> Self {
+        TransportConfig {
+            ack_timeout: Duration::millis(20),
+            max_retries: 10,
+        }
+    }
+}
+
+/// 发送状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TxState {
+    /// 空闲
+    Idle,
+    /// 正在发送，等待应答
+    Sending,
+    /// 上一条消息已送达
+    Delivered,
+    /// 上一条消息超过最大重发次数，已放弃
+    Failed,
+}
+
+/// 链路统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LinkStats {
+    /// 发送的帧数，含应答和重发
+    pub frames_sent: u32,
+    /// 收到的有效帧数
+    pub frames_received: u32,
+    /// 应用层重发的分片数
+    pub retransmissions: u32,
+    /// 硬件自动重发的累计次数(OBSERVE_TX.ARC_CNT)
+    pub radio_retries: u32,
+    /// 硬件达到最大重发次数的帧数
+    pub radio_lost: u32,
+    /// 丢包计数(OBSERVE_TX.PLOS_CNT)，最大为15
+    pub packets_lost: u8,
+    /// 收到的重复分片数
+    pub duplicates: u32,
+    /// 格式错误或顺序错误而丢弃的帧数
+    pub dropped: u32,
+    /// 送达的消息数
+    pub delivered: u32,
+    /// 放弃的消息数
+    pub failed: u32,
+    /// 收到的完整消息数
+    pub received: u32,
+    /// 上一次收到数据时是否检测到大于 -64dBm 的信号(RPD)
+    pub carrier: bool,
+}
+
+/// 正在发送的消息
+struct Outgoing {
+    seq: u8,
+    len: usize,
+    fragments: u8,
+    fragment: u8,
+    retries: u8,
+    sent_at: Instant,
+}
+
+/// 正在接收的消息
+struct Incoming {
+    session: u8,
+    seq: u8,
+    fragments: u8,
+    next: u8,
+}
+
+/// 可靠消息传输
+/// `N` 为收发缓冲区的长度，即单条消息的最大长度
+pub struct Transport<R, const N: usize> {
+    radio: R,
+    config: TransportConfig,
+    /// 本次上电的会话号
+    session: u8,
+    tx_buf: [u8; N],
+    tx_seq: u8,
+    outgoing: Option<Outgoing>,
+    tx_state: TxState,
+    rx_buf: [u8; N],
+    incoming: Option<Incoming>,
+    /// 已完成接收的消息长度，被读取前不接收新消息
+    rx_ready: Option<usize>,
+    /// 上一条完整接收的消息的会话号和序号，用于去重
+    last: Option<(u8, u8)>,
+    stats: LinkStats,
+}
+
+impl<R: Radio, const N: usize> Transport<R, N> {
+    /// session: 会话号，每次上电都应不同，例如取自随机数或每次启动加1的计数
+    pub fn new(radio: R, config: TransportConfig, session: u8) -> Self {
+        Transport {
+            radio,
+            config,
+            session,
+            tx_buf: [0; N],
+            tx_seq: 0,
+            outgoing: None,
+            tx_state: TxState::Idle,
+            rx_buf: [0; N],
+            incoming: None,
+            rx_ready: None,
+            last: None,
+            stats: LinkStats::default(),
+        }
+    }
+
+    /// 释放无线模块
+    pub fn free(self) -> R {
+        self.radio
+    }
+
+    /// 无线模块
+    pub fn radio(&mut self) -> &mut R {
+        &mut self.radio
+    }
+
+    /// 链路统计
+    pub fn stats(&self) -> &LinkStats {
+        &self.stats
+    }
+
+    /// 清空链路统计
+    pub fn reset_stats(&mut self) {
+        self.stats = LinkStats::default();
+    }
+
+    /// 发送状态
+    pub fn tx_state(&self) -> TxState {
+        self.tx_state
+    }
+
+    /// 开始发送一条消息，之后需要不断调用 `poll`
+    pub fn send(&mut self, data: &[u8], now: Instant) -> Result<(), Error<R::Error>> {
+        if self.outgoing.is_some() {
+            return Err(Error::Busy);
+        }
+        if data.is_empty() || data.len() > N || data.len() > MAX_FRAGMENTS * FRAGMENT_LEN {
+            return Err(Error::MessageLength);
+        }
+
+        self.tx_buf[..data.len()].copy_from_slice(data);
+        self.start(data.len(), now)
+    }
+
+    /// 序列化并发送一条消息
+    pub fn send_message<M: Message>(
+        &mut self,
+        message: &M,
+        now: Instant,
+    ) -> Result<(), Error<R::Error>> {
+        if self.outgoing.is_some() {
+            return Err(Error::Busy);
+        }
+        let len = message::encode(message, &mut self.tx_buf).map_err(Error::Codec)?;
+        if len > MAX_FRAGMENTS * FRAGMENT_LEN {
+            return Err(Error::MessageLength);
+        }
+        self.start(len, now)
+    }
+
+    /// 开始发送缓冲区中的消息
+    fn start(&mut self, len: usize, now: Instant) -> Result<(), Error<R::Error>> {
+        self.outgoing = Some(Outgoing {
+            seq: self.tx_seq,
+            len,
+            fragments: len.div_ceil(FRAGMENT_LEN) as u8,
+            fragment: 0,
+            retries: 0,
+            sent_at: now,
+        });
+        self.tx_seq = self.tx_seq.wrapping_add(1);
+        self.tx_state = TxState::Sending;
+        self.send_fragment(now)
+    }
+
+    /// 发送一条消息并等待送达，`now` 用于读取当前时间
+    /// 超过最大重发次数时返回 `Ok(false)`
+    pub fn send_blocking(
+        &mut self,
+        data: &[u8],
+        mut now: impl FnMut() -> Instant,
+    ) -> Result<bool, Error<R::Error>> {
+        self.send(data, now())?;
+        loop {
+            self.poll(now())?;
+            match self.tx_state {
+                TxState::Delivered => return Ok(true),
+                TxState::Failed => return Ok(false),
+                _ => {}
+            }
+        }
+    }
+
+    /// 取出收到的完整消息，取出后才能接收下一条消息
+    pub fn receive(&mut self) -> Option<&[u8]> {
+        let len = self.rx_ready.take()?;
+        Some(&self.rx_buf[..len])
+    }
+
+    /// 查看收到的完整消息，不取出
+    pub fn peek(&self) -> Option<&[u8]> {
+        self.rx_ready.map(|len| &self.rx_buf[..len])
+    }
+
+    /// 取出并解析收到的消息，类型ID不符时消息不会被取出
+    pub fn receive_message<M: Message>(&mut self) -> Option<Result<M, CodecError>> {
+        let data = self.peek()?;
+        if message::message_id(data) != Some(M::ID) {
+            return None;
+        }
+        let result = message::decode(data);
+        self.rx_ready = None;
+        Some(result)
+    }
+
+    /// 处理收到的帧和超时重发，需要在主循环中不断调用
+    pub fn poll(&mut self, now: Instant) -> Result<(), Error<R::Error>> {
+        let mut frame = [0; NRF24_MAX_PAYLOAD];
+        loop {
+            match self.radio.receive(&mut frame) {
+                Ok(len) => self.handle_frame(&frame[..len], now)?,
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => return Err(Error::Radio(e)),
+            }
+        }
+
+        let Some(outgoing) = &mut self.outgoing else {
+            return Ok(());
+        };
+        if now.checked_duration_since(outgoing.sent_at) < Some(self.config.ack_timeout) {
+            return Ok(());
+        }
+        if outgoing.retries >= self.config.max_retries {
+            self.outgoing = None;
+            self.tx_state = TxState::Failed;
+            self.stats.failed += 1;
+            return Ok(());
+        }
+        outgoing.retries += 1;
+        self.stats.retransmissions += 1;
+        self.send_fragment(now)
+    }
+
+    /// 发送当前分片
+    fn send_fragment(&mut self, now: Instant) -> Result<(), Error<R::Error>> {
+        let Some(outgoing) = &mut self.outgoing else {
+            return Ok(());
+        };
+        outgoing.sent_at = now;
+
+        let start = outgoing.fragment as usize * FRAGMENT_LEN;
+        let end = (start + FRAGMENT_LEN).min(outgoing.len);
+        let header = [
+            (FRAME_DATA << FRAME_KIND_SHIFT) | (end - start) as u8,
+            self.session,
+            outgoing.seq,
+            outgoing.fragment,
+            outgoing.fragments,
+        ];
+        let mut frame = [0; NRF24_MAX_PAYLOAD];
+        frame[..FRAME_HEADER_LEN].copy_from_slice(&header);
+        frame[FRAME_HEADER_LEN..FRAME_HEADER_LEN + end - start]
+            .copy_from_slice(&self.tx_buf[start..end]);
+        self.transmit(&frame[..FRAME_HEADER_LEN + end - start])
+    }
+
+    /// 发送一帧并更新统计
+    fn transmit(&mut self, frame: &[u8]) -> Result<(), Error<R::Error>> {
+        let acked = self.radio.transmit(frame)?;
+        self.stats.frames_sent += 1;
+        if !acked {
+            self.stats.radio_lost += 1;
+        }
+        let observe = self.radio.observe()?;
+        self.stats.radio_retries += observe.retries as u32;
+        self.stats.packets_lost = observe.lost;
+        Ok(())
+    }
+
+    fn handle_frame(&mut self, frame: &[u8], now: Instant) -> Result<(), Error<R::Error>> {
+        if frame.len() < FRAME_HEADER_LEN {
+            self.stats.dropped += 1;
+            return Ok(());
+        }
+        let kind = frame[0] >> FRAME_KIND_SHIFT;
+        let len = (frame[0] & FRAME_LEN_MASK) as usize;
+        let (session, seq, fragment, fragments) = (frame[1], frame[2], frame[3], frame[4]);
+
+        match kind {
+            FRAME_ACK => {
+                self.stats.frames_received += 1;
+                self.handle_ack(session, seq, fragment, now)
+            }
+            FRAME_DATA if len <= FRAGMENT_LEN && FRAME_HEADER_LEN + len <= frame.len() => {
+                self.stats.frames_received += 1;
+                self.stats.carrier = self.radio.carrier_detected()?;
+                let data = &frame[FRAME_HEADER_LEN..FRAME_HEADER_LEN + len];
+                self.handle_data(session, seq, fragment, fragments, data)
+            }
+            _ => {
+                self.stats.dropped += 1;
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_ack(
+        &mut self,
+        session: u8,
+        seq: u8,
+        fragment: u8,
+        now: Instant,
+    ) -> Result<(), Error<R::Error>> {
+        let Some(outgoing) = &mut self.outgoing else {
+            return Ok(());
+        };
+        // 重发产生的重复应答，或复位前发送的消息的应答
+        if session != self.session || outgoing.seq != seq || outgoing.fragment != fragment {
+            self.stats.duplicates += 1;
+            return Ok(());
+        }
+        // 收到应答后立即发送下一个分片
+        if fragment + 1 < outgoing.fragments {
+            outgoing.fragment += 1;
+            outgoing.retries = 0;
+            return self.send_fragment(now);
+        }
+        self.outgoing = None;
+        self.tx_state = TxState::Delivered;
+        self.stats.delivered += 1;
+        Ok(())
+    }
+
+    fn handle_data(
+        &mut self,
+        session: u8,
+        seq: u8,
+        fragment: u8,
+        fragments: u8,
+        data: &[u8],
+    ) -> Result<(), Error<R::Error>> {
+        // 已完成接收的消息，应答丢失后发送方重发了最后一个分片
+        if self.incoming.is_none() && self.last == Some((session, seq)) {
+            self.stats.duplicates += 1;
+            return self.send_ack(session, seq, fragment);
+        }
+
+        match &mut self.incoming {
+            Some(incoming) if incoming.session == session && incoming.seq == seq => {
+                if fragment < incoming.next {
+                    self.stats.duplicates += 1;
+                    return self.send_ack(session, seq, fragment);
+                }
+                if fragment > incoming.next || fragments != incoming.fragments {
+                    self.stats.dropped += 1;
+                    return Ok(());
+                }
+            }
+            // 新消息，丢弃未完成的旧消息
+            _ => {
+                if fragment != 0 || fragments == 0 || self.rx_ready.is_some() {
+                    // 上一条消息还未被读取时不应答，发送方稍后重发
+                    self.stats.dropped += 1;
+                    return Ok(());
+                }
+                self.incoming = Some(Incoming {
+                    session,
+                    seq,
+                    fragments,
+                    next: 0,
+                });
+            }
+        }
+
+        let start = fragment as usize * FRAGMENT_LEN;
+        let end = start + data.len();
+        // 非最后一个分片必须是满的，消息不能超过缓冲区
+        let last = fragment + 1 == fragments;
+        if end > N || (!last && data.len() != FRAGMENT_LEN) {
+            self.incoming = None;
+            self.stats.dropped += 1;
+            return Ok(());
+        }
+        self.rx_buf[start..end].copy_from_slice(data);
+        self.send_ack(session, seq, fragment)?;
+
+        if last {
+            self.incoming = None;
+            self.last = Some((session, seq));
+            self.rx_ready = Some(end);
+            self.stats.received += 1;
+        } else if let Some(incoming) = &mut self.incoming {
+            incoming.next += 1;
+        }
+        Ok(())
+    }
+
+    fn send_ack(&mut self, session: u8, seq: u8, fragment: u8) -> Result<(), Error<R::Error>> {
+        self.transmit(&[FRAME_ACK << FRAME_KIND_SHIFT, session, seq, fragment, 0])
+    }
+}
@@ -0,0 +1,166 @@
+//! 耗时统计
+//! 按名称累计各代码段的执行次数、最小/最大/平均时钟周期数
+
+use heapless::Vec;
+
+/// 单个代码段的耗时统计(时钟周期数)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stats {
+    count: u32,
+    total: u64,
+    min: u32,
+    max: u32,
+    last: u32,
+}
+
+impl Stats {
+    pub const fn new() -> Self {
+        Stats {
+            count: 0,
+            total: 0,
+            min: u32::MAX,
+            max: 0,
+            last: 0,
+        }
+    }
+
+    /// 记录一次耗时
+    pub fn record(&mut self, cycles: u32) {
+        self.count = self.count.saturating_add(1);
+        self.total = self.total.saturating_add(cycles as u64);
+        self.min = self.min.min(cycles);
+        self.max = self.max.max(cycles);
+        self.last = cycles;
+    }
+
+    /// 合并另一组统计
+    pub fn merge(&mut self, other: &Stats) {
+        if other.is_empty() {
+            return;
+        }
+        self.count = self.count.saturating_add(other.count);
+        self.total = self.total.saturating_add(other.total);
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.last = other.last;
+    }
+
+    /// 清空统计
+    pub fn reset(&mut self) {
+        *self = Stats::new();
+    }
+
+    /// 是否还没有记录
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// 记录次数
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// 累计耗时
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// 最小耗时
+    pub fn min(&self) -> Option<u32> {
+        (!self.is_empty()).then_some(self.min)
+    }
+
+    /// 最大耗时
+    pub fn max(&self) -> Option<u32> {
+        (!self.is_empty()).then_some(self.max)
+    }
+
+    /// 平均耗时
+    pub fn mean(&self) -> Option<u32> {
+        (!self.is_empty()).then(|| (self.total / self.count as u64) as u32)
+    }
+
+    /// 最近一次耗时
+    pub fn last(&self) -> Option<u32> {
+        (!self.is_empty()).then_some(self.last)
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats::new()
+    }
+}
+
+/// 代码段数量已达到容量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ProfilerFull;
+
+/// 按名称保存多个代码段的耗时统计
+/// 最多保存 N 个代码段，按第一次记录的顺序排列
+#[derive(Debug, Clone)]
+pub struct Profiler<const N: usize> {
+    sections: Vec<(&'static str, Stats), N>,
+}
+
+impl<const N: usize> Profiler<N> {
+    pub const fn new() -> Self {
+        Profiler {
+            sections: Vec::new(),
+        }
+    }
+
+    /// 记录一次耗时，第一次出现的名称会新建一个代码段
+    pub fn record(&mut self, name: &'static str, cycles: u32) -> Result<(), ProfilerFull> {
+        if let Some((_, stats)) = self.sections.iter_mut().find(|(n, _)| *n == name) {
+            stats.record(cycles);
+            return Ok(());
+        }
+
+        let mut stats = Stats::new();
+        stats.record(cycles);
+        self.sections.push((name, stats)).map_err(|_| ProfilerFull)
+    }
+
+    /// 代码段的统计
+    pub fn get(&self, name: &str) -> Option<&Stats> {
+        self.sections
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, stats)| stats)
+    }
+
+    /// 遍历所有代码段
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &Stats)> {
+        self.sections.iter().map(|(name, stats)| (*name, stats))
+    }
+
+    /// 代码段数量
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// 清空各代码段的统计，保留代码段
+    pub fn reset(&mut self) {
+        for (_, stats) in self.sections.iter_mut() {
+            stats.reset();
+        }
+    }
+
+    /// 删除所有代码段
+    pub fn clear(&mut self) {
+        self.sections.clear();
+    }
+}
+
+impl<const N: usize> Default for Profiler<N> {
+    fn default() -> Self {
+        Profiler::new()
+    }
+}
@@ -0,0 +1,341 @@
+//! W25Q64 扩展命令
+//! 掉电模式、块保护、唯一ID、SFDP参数表、块擦除及擦除暂停/恢复
+
+use super::conf::*;
+use super::driver::{Error, W25Q64};
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::SpiDevice;
+
+/// SFDP 签名 "SFDP"
+const SFDP_SIGNATURE: u32 = 0x5044_4653;
+
+/// 写保护区域
+/// 由状态寄存器的 BP0~BP2、TB、SEC、CMP 位决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtectedRange {
+    /// 不保护
+    None,
+    /// 保护整个芯片
+    All,
+    /// 保护芯片顶部指定大小的区域
+    Upper(u32),
+    /// 保护芯片底部指定大小的区域
+    Lower(u32),
+    /// 保护除顶部指定大小以外的区域
+    AllButUpper(u32),
+    /// 保护除底部指定大小以外的区域
+    AllButLower(u32),
+}
+
+/// 擦除类型
+#[derive(Debug, Clone, Copy)]
+pub struct EraseType {
+    /// 擦除大小(字节)
+    pub size: u32,
+    /// 擦除指令
+    pub opcode: u8,
+}
+
+/// SFDP 参数表中的芯片几何信息
+#[derive(Debug, Clone, Copy)]
+pub struct SfdpInfo {
+    /// SFDP 主版本号
+    pub major: u8,
+    /// SFDP 次版本号
+    pub minor: u8,
+    /// 芯片容量(字节)
+    pub capacity: u32,
+    /// 页大小(字节)
+    pub page_size: u32,
+    /// 支持的擦除类型
+    pub erase_types: [Option<EraseType>; 4],
+}
+
+/// 保护区域大小对应的 SEC、BP 位
+fn encode_protect_size(size: u32) -> Option<(bool, u8)> {
+    match size {
+        0x1000 => Some((true, 1)),
+        0x2000 => Some((true, 2)),
+        0x4000 => Some((true, 3)),
+        0x8000 => Some((true, 4)),
+        0x20000 => Some((false, 1)),
+        0x40000 => Some((false, 2)),
+        0x80000 => Some((false, 3)),
+        0x100000 => Some((false, 4)),
+        0x200000 => Some((false, 5)),
+        0x400000 => Some((false, 6)),
+        _ => None,
+    }
+}
+
+/// SEC、BP 位对应的保护区域大小
+fn decode_protect_size(sec: bool, bp: u8) -> u32 {
+    match (sec, bp) {
+        (_, 0) => 0,
+        (_, 7) => W25Q64_CAPACITY as u32,
+        (true, bp) => 0x1000 << (bp.min(4) - 1),
+        (false, bp) => 0x20000 << (bp - 1),
+    }
+}
+
+impl<SPI, D> W25Q64<SPI, D>
+where
+    SPI: SpiDevice,
+    D: DelayNs,
+{
+    /// 进入掉电模式
+    /// 掉电模式下仅响应释放掉电命令，电流约1uA
+    pub fn power_down(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.spi_write(&[W25Q64_POWER_DOWN])?;
+        // 进入掉电模式最长需要3us
+        self.delay_us(3);
+        Ok(())
+    }
+
+    /// 释放掉电模式
+    /// 返回设备ID，W25Q64为0x16
+    pub fn release_power_down(&mut self) -> Result<u8, Error<SPI::Error>> {
+        let cmd = [
+            W25Q64_RELEASE_POWER_DOWN_HPM_DEVICE_ID,
+            W25Q64_DUMMY_BYTE,
+            W25Q64_DUMMY_BYTE,
+            W25Q64_DUMMY_BYTE,
+        ];
+        let mut device_id = [0];
+        self.spi_write_read(&cmd, &mut device_id)?;
+        // 退出掉电模式最长需要3us
+        self.delay_us(3);
+        Ok(device_id[0])
+    }
+
+    /// 读取64位唯一ID
+    /// 可以作为板卡序列号
+    pub fn read_unique_id(&mut self) -> Result<u64, Error<SPI::Error>> {
+        let cmd = [
+            W25Q64_READ_UNIQUE_ID,
+            W25Q64_DUMMY_BYTE,
+            W25Q64_DUMMY_BYTE,
+            W25Q64_DUMMY_BYTE,
+            W25Q64_DUMMY_BYTE,
+        ];
+        let mut id = [0; 8];
+        self.spi_write_read(&cmd, &mut id)?;
+        Ok(u64::from_be_bytes(id))
+    }
+
+    /// 写状态寄存器1和2
+    pub fn write_status_registers(
+        &mut self,
+        status1: u8,
+        status2: u8,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_enable()?;
+        self.spi_write(&[W25Q64_WRITE_STATUS_REGISTER, status1, status2])?;
+        self.wait_for_idle(W25Q64_WRITE_STATUS_TIMEOUT_MS)
+    }
+
+    /// 读取写保护区域
+    pub fn protected_range(&mut self) -> Result<ProtectedRange, Error<SPI::Error>> {
+        let status1 = self.read_status_register_1()?;
+        let status2 = self.read_status_register_2()?;
+
+        let bp = (status1 & W25Q64_STATUS_BP_MASK) >> 2;
+        let tb = status1 & W25Q64_STATUS_TB != 0;
+        let sec = status1 & W25Q64_STATUS_SEC != 0;
+        let cmp = status2 & W25Q64_STATUS_CMP != 0;
+
+        let size = decode_protect_size(sec, bp);
+        let range = match (size, cmp) {
+            (0, false) => ProtectedRange::None,
+            (0, true) => ProtectedRange::All,
+            (s, false) if s == W25Q64_CAPACITY as u32 => ProtectedRange::All,
+            (s, true) if s == W25Q64_CAPACITY as u32 => ProtectedRange::None,
+            (s, false) if tb => ProtectedRange::Lower(s),
+            (s, false) => ProtectedRange::Upper(s),
+            (s, true) if tb => ProtectedRange::AllButLower(s),
+            (s, true) => ProtectedRange::AllButUpper(s),
+        };
+        Ok(range)
+    }
+
+    /// 设置写保护区域
+    /// 区域大小仅支持 4/8/16/32KB 以及 128KB~4MB 中2的幂
+    pub fn set_protected_range(&mut self, range: ProtectedRange) -> Result<(), Error<SPI::Error>> {
+        let (sec, bp, tb, cmp) = match range {
+            ProtectedRange::None => (false, 0, false, false),
+            ProtectedRange::All => (false, 7, false, false),
+            ProtectedRange::Upper(size)
+            | ProtectedRange::Lower(size)
+            | ProtectedRange::AllButUpper(size)
+            | ProtectedRange::AllButLower(size) => {
+                let (sec, bp) = encode_protect_size(size).ok_or(Error::InvalidArgument)?;
+                let tb = matches!(
+                    range,
+                    ProtectedRange::Lower(_) | ProtectedRange::AllButLower(_)
+                );
+                let cmp = matches!(
+                    range,
+                    ProtectedRange::AllButUpper(_) | ProtectedRange::AllButLower(_)
+                );
+                (sec, bp, tb, cmp)
+            }
+        };
+
+        let mut status1 = self.read_status_register_1()?;
+        let mut status2 = self.read_status_register_2()?;
+
+        status1 &= !(W25Q64_STATUS_BP_MASK | W25Q64_STATUS_TB | W25Q64_STATUS_SEC);
+        status1 |= bp << 2;
+        if tb {
+            status1 |= W25Q64_STATUS_TB;
+        }
+        if sec {
+            status1 |= W25Q64_STATUS_SEC;
+        }
+
+        status2 &= !W25Q64_STATUS_CMP;
+        if cmp {
+            status2 |= W25Q64_STATUS_CMP;
+        }
+
+        self.write_status_registers(status1, status2)
+    }
+
+    /// 读取 SFDP 参数表
+    /// address: 参数表内的地址
+    /// data: 用于存放数据
+    pub fn read_sfdp(&mut self, address: u32, data: &mut [u8]) -> Result<(), Error<SPI::Error>> {
+        let cmd = [
+            W25Q64_READ_SFDP,
+            (address >> 16) as u8,
+            (address >> 8) as u8,
+            address as u8,
+            W25Q64_DUMMY_BYTE,
+        ];
+        self.spi_write_read(&cmd, data)?;
+        Ok(())
+    }
+
+    /// 解析 SFDP 基本参数表，获取芯片几何信息
+    pub fn read_sfdp_info(&mut self) -> Result<SfdpInfo, Error<SPI::Error>> {
+        // SFDP 头及第一个参数头
+        let mut header = [0; 16];
+        self.read_sfdp(0, &mut header)?;
+
+        let signature = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        if signature != SFDP_SIGNATURE {
+            return Err(Error::InvalidSfdp);
+        }
+        let minor = header[4];
+        let major = header[5];
+
+        // 第一个参数头必须为 JEDEC 基本参数表(ID 0x00)
+        if header[8] != 0x00 {
+            return Err(Error::InvalidSfdp);
+        }
+        let length = (header[11] as usize).min(16);
+        let table_address = u32::from_le_bytes([header[12], header[13], header[14], 0]);
+
+        let mut table = [0; 16 * 4];
+        self.read_sfdp(table_address, &mut table[..length * 4])?;
+        let dword = |i: usize| {
+            u32::from_le_bytes([
+                table[i * 4],
+                table[i * 4 + 1],
+                table[i * 4 + 2],
+                table[i * 4 + 3],
+            ])
+        };
+
+        if length < 9 {
+            return Err(Error::InvalidSfdp);
+        }
+
+        // 第2个双字: 容量(位)
+        let density = dword(1);
+        // 最高位为1时低31位为容量的指数，转换为字节后超出 u32 的视为无效
+        let capacity = if density & 0x8000_0000 == 0 {
+            (density + 1) / 8
+        } else {
+            (density & 0x7FFF_FFFF)
+                .checked_sub(3)
+                .and_then(|exponent| 1u32.checked_shl(exponent))
+                .ok_or(Error::InvalidSfdp)?
+        };
+
+        // 第8、9个双字: 擦除类型1~4，每个类型为大小指数和指令
+        let mut erase_types = [None; 4];
+        for (i, erase_type) in erase_types.iter_mut().enumerate() {
+            let value = dword(7 + i / 2) >> (16 * (i % 2));
+            let exponent = value as u8;
+            if exponent != 0 {
+                *erase_type = Some(EraseType {
+                    size: 1u32
+                        .checked_shl(exponent as u32)
+                        .ok_or(Error::InvalidSfdp)?,
+                    opcode: (value >> 8) as u8,
+                });
+            }
+        }
+
+        // JESD216A 及以后版本在第11个双字中给出页大小
+        let page_size = if length >= 11 {
+            1u32.checked_shl((dword(10) >> 4) & 0x0F)
+                .ok_or(Error::InvalidSfdp)?
+        } else {
+            W25Q64_PAGE_SIZE as u32
+        };
+
+        Ok(SfdpInfo {
+            major,
+            minor,
+            capacity,
+            page_size,
+            erase_types,
+        })
+    }
+
+    /// 擦除地址所在的32KB块
+    pub fn block_erase_32k(&mut self, address: u32) -> Result<(), Error<SPI::Error>> {
+        self.start_block_erase_32k(address)?;
+        self.wait_for_idle(W25Q64_BLOCK_ERASE_32KB_TIMEOUT_MS)
+    }
+
+    /// 开始擦除地址所在的32KB块，不等待完成
+    pub fn start_block_erase_32k(&mut self, address: u32) -> Result<(), Error<SPI::Error>> {
+        self.start_erase(W25Q64_BLOCK_ERASE_32KB, address)
+    }
+
+    /// 擦除地址所在的64KB块
+    pub fn block_erase_64k(&mut self, address: u32) -> Result<(), Error<SPI::Error>> {
+        self.start_block_erase_64k(address)?;
+        self.wait_for_idle(W25Q64_BLOCK_ERASE_64KB_TIMEOUT_MS)
+    }
+
+    /// 开始擦除地址所在的64KB块，不等待完成
+    pub fn start_block_erase_64k(&mut self, address: u32) -> Result<(), Error<SPI::Error>> {
+        self.start_erase(W25Q64_BLOCK_ERASE_64KB, address)
+    }
+
+    /// 暂停正在进行的扇区/块擦除
+    /// 暂停期间可以读取其他扇区的数据，之后调用 `erase_resume` 继续擦除
+    pub fn erase_suspend(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.spi_write(&[W25Q64_ERASE_SUSPEND])?;
+        // 暂停最长需要20us
+        self.delay_us(20);
+        Ok(())
+    }
+
+    /// 恢复被暂停的擦除
+    pub fn erase_resume(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.spi_write(&[W25Q64_ERASE_RESUME])?;
+        Ok(())
+    }
+
+    /// 擦除是否处于暂停状态
+    pub fn is_suspended(&mut self) -> Result<bool, Error<SPI::Error>> {
+        let status = self.read_status_register_2()?;
+        Ok(status & W25Q64_STATUS_SUS != 0)
+    }
+}
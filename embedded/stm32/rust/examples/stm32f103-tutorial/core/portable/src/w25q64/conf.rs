@@ -27,6 +27,8 @@ pub const W25Q64_RELEASE_POWER_DOWN_HPM_DEVICE_ID: u8 = 0xAB;
 // 读取芯片的制造商和设备ID
 pub const W25Q64_MANUFACTURER_DEVICE_ID: u8 = 0x90;
 pub const W25Q64_READ_UNIQUE_ID: u8 = 0x4B;
+// 读取SFDP参数表
+pub const W25Q64_READ_SFDP: u8 = 0x5A;
 // 读取芯片的JEDEC设备ID
 pub const W25Q64_JEDEC_DEVICE_ID: u8 = 0x9F;
 // 读数据命令
@@ -40,5 +42,32 @@ pub const W25Q64_OCTAL_WORD_READ_QUAD_IO: u8 = 0xE3;
 
 pub const W25Q64_DUMMY_BYTE: u8 = 0xFF;
 
+// 状态寄存器1: 忙标志位
+pub const W25Q64_STATUS_BUSY: u8 = 0x01;
+// 状态寄存器1: 写使能锁存位
+pub const W25Q64_STATUS_WEL: u8 = 0x02;
+// 状态寄存器1: 块保护位 BP0~BP2
+pub const W25Q64_STATUS_BP_MASK: u8 = 0x1C;
+// 状态寄存器1: 顶部/底部保护位
+pub const W25Q64_STATUS_TB: u8 = 0x20;
+// 状态寄存器1: 扇区/块保护位
+pub const W25Q64_STATUS_SEC: u8 = 0x40;
+// 状态寄存器2: 补码保护位
+pub const W25Q64_STATUS_CMP: u8 = 0x40;
+// 状态寄存器2: 擦除/编程暂停状态位
+pub const W25Q64_STATUS_SUS: u8 = 0x80;
+
+// 数据手册给出的最大操作时间(ms)
+pub const W25Q64_PAGE_PROGRAM_TIMEOUT_MS: u32 = 3;
+pub const W25Q64_SECTOR_ERASE_TIMEOUT_MS: u32 = 400;
+pub const W25Q64_BLOCK_ERASE_32KB_TIMEOUT_MS: u32 = 1600;
+pub const W25Q64_BLOCK_ERASE_64KB_TIMEOUT_MS: u32 = 2000;
+pub const W25Q64_WRITE_STATUS_TIMEOUT_MS: u32 = 15;
+pub const W25Q64_CHIP_ERASE_TIMEOUT_MS: u32 = 100_000;
+
 // 页大小为256字节
 pub const W25Q64_PAGE_SIZE: usize = 256;
+// 扇区大小为4KB
+pub const W25Q64_SECTOR_SIZE: usize = 4096;
+// 芯片容量为8MB
+pub const W25Q64_CAPACITY: usize = 8 * 1024 * 1024;
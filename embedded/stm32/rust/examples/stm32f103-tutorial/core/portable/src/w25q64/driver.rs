@@ -0,0 +1,373 @@
+//! W25Q64 驱动
+//! 基于 embedded-hal 1.0 的 `SpiDevice`，每条指令对应一次片选周期，
+//! 可以使用硬件 SPI、软件 SPI、共享总线的设备句柄或 `emulator` 中的仿真器
+//!
+//! ```rust
+//! let mut w25q = W25Q64::new(spi_device, delay);
+//! let (manufacturer_id, memory_type, capacity) = w25q.read_jedec_device_id().unwrap();
+//! w25q.sector_erase(0x000000).unwrap();
+//! w25q.page_program(0x000000, &[0x01, 0x02, 0x03, 0x04]).unwrap();
+//! let mut buffer = [0; 4];
+//! w25q.read_data(0x000000, &mut buffer).unwrap();
+//! ```
+
+use super::conf::*;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::{Operation, SpiDevice};
+
+/// W25Q64 操作错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// SPI 通信错误
+    Spi(E),
+    /// 等待芯片空闲超时
+    Timeout,
+    /// 目标区域处于写保护状态，命令被芯片忽略
+    WriteProtected,
+    /// 写使能后 WEL 位未置位
+    WriteEnableNotSet,
+    /// 参数无效
+    InvalidArgument,
+    /// SFDP 参数表无效
+    InvalidSfdp,
+    /// 地址或长度未按要求对齐
+    NotAligned,
+    /// 地址超出芯片容量
+    OutOfBounds,
+}
+
+pub struct W25Q64<SPI, D> {
+    spi: SPI,
+    /// 用于轮询间隔和指令之间的等待
+    delay: D,
+}
+
+impl<SPI, D> W25Q64<SPI, D>
+where
+    SPI: SpiDevice,
+    D: DelayNs,
+{
+    /// spi: SPI 设备，需要配置为模式0，高位先行
+    /// delay: 延时
+    pub fn new(spi: SPI, delay: D) -> Self {
+        W25Q64 { spi, delay }
+    }
+
+    /// 释放 SPI 设备和延时
+    pub fn release(self) -> (SPI, D) {
+        (self.spi, self.delay)
+    }
+
+    /// 在一个片选周期内写入数据
+    pub fn spi_write(&mut self, words: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.spi.write(words).map_err(Error::Spi)
+    }
+
+    /// 在一个片选周期内写入并返回数据
+    pub fn spi_transfer(&mut self, words: &mut [u8]) -> Result<(), Error<SPI::Error>> {
+        self.spi.transfer_in_place(words).map_err(Error::Spi)
+    }
+
+    /// 写入命令后读取数据
+    /// cmd: 命令及地址等参数
+    /// data: 用于存放读取的数据
+    pub fn spi_write_read(&mut self, cmd: &[u8], data: &mut [u8]) -> Result<(), Error<SPI::Error>> {
+        self.spi
+            .transaction(&mut [Operation::Write(cmd), Operation::Read(data)])
+            .map_err(Error::Spi)
+    }
+
+    /// 微秒级延时
+    pub(super) fn delay_us(&mut self, us: u32) {
+        self.delay.delay_us(us);
+    }
+
+    /// 启用写入功能
+    /// 发送写使能命令后检查 WEL 位是否置位
+    pub fn write_enable(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.spi_write(&[W25Q64_WRITE_ENABLE])?;
+
+        let status = self.read_status_register_1()?;
+        if status & W25Q64_STATUS_WEL == 0 {
+            return Err(Error::WriteEnableNotSet);
+        }
+        Ok(())
+    }
+
+    /// 禁用写入功能
+    pub fn write_disable(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.spi_write(&[W25Q64_WRITE_DISABLE])
+    }
+
+    /// 读取芯片的JEDEC设备ID
+    pub fn read_jedec_device_id(&mut self) -> Result<(u8, u8, u8), Error<SPI::Error>> {
+        let mut buffer = [0; 4];
+        buffer[0] = W25Q64_JEDEC_DEVICE_ID;
+        self.spi_transfer(&mut buffer)?;
+
+        let manufacturer_id = buffer[1];
+        let memory_type = buffer[2];
+        let capacity = buffer[3];
+        Ok((manufacturer_id, memory_type, capacity))
+    }
+
+    /// 读取芯片的制造商和设备ID
+    ///
+    /// 0xEF16: 代表W25Q64芯片
+    pub fn read_manufacturer_device_id(&mut self) -> Result<(u16, u16), Error<SPI::Error>> {
+        let mut buffer = [0; 7];
+        buffer[0] = W25Q64_MANUFACTURER_DEVICE_ID;
+        // 发送读取制造商和设备ID的命令
+        self.spi_transfer(&mut buffer)?;
+
+        let manufacturer_id = buffer[4] as u16;
+        let device_id = (buffer[5] as u16) << 8 | buffer[6] as u16;
+        Ok((manufacturer_id, device_id))
+    }
+
+    /// 读取状态寄存器1
+    pub fn read_status_register_1(&mut self) -> Result<u8, Error<SPI::Error>> {
+        let mut buffer = [W25Q64_READ_STATUS_REGISTER_1, 0];
+        self.spi_transfer(&mut buffer)?;
+        Ok(buffer[1])
+    }
+
+    /// 读取状态寄存器2
+    pub fn read_status_register_2(&mut self) -> Result<u8, Error<SPI::Error>> {
+        let mut buffer = [W25Q64_READ_STATUS_REGISTER_2, 0];
+        self.spi_transfer(&mut buffer)?;
+        Ok(buffer[1])
+    }
+
+    /// 检查是否有写保护标志
+    pub fn check_write_protect(&mut self) -> Result<bool, Error<SPI::Error>> {
+        let status = self.read_status_register_1()?;
+        let srp0 = status & 0x80;
+        let srp1 = status & 0x04;
+        // 任一位置位即有写保护
+        Ok(srp0 != 0 || srp1 != 0)
+    }
+
+    /// 芯片是否忙碌
+    /// 读取状态寄存器1的 BUSY 位
+    pub fn is_busy(&mut self) -> Result<bool, Error<SPI::Error>> {
+        let status = self.read_status_register_1()?;
+        Ok(status & W25Q64_STATUS_BUSY != 0)
+    }
+
+    /// 非阻塞查询编程/擦除操作是否完成
+    /// 芯片忙碌时返回 WouldBlock
+    /// 操作结束后 WEL 位仍然置位，说明命令因写保护被芯片忽略
+    pub fn poll_idle(&mut self) -> nb::Result<(), Error<SPI::Error>> {
+        let status = self.read_status_register_1()?;
+        if status & W25Q64_STATUS_BUSY != 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+        if status & W25Q64_STATUS_WEL != 0 {
+            // 擦除暂停期间 BUSY 位同样为0，操作并未完成
+            let status2 = self.read_status_register_2()?;
+            if status2 & W25Q64_STATUS_SUS != 0 {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.write_disable()?;
+            return Err(nb::Error::Other(Error::WriteProtected));
+        }
+        Ok(())
+    }
+
+    /// 等待W25Q64芯片空闲
+    /// 每隔约100us轮询一次状态寄存器，直到 BUSY 位清零
+    /// timeout_ms: 超时时间(ms)
+    pub fn wait_for_idle(&mut self, timeout_ms: u32) -> Result<(), Error<SPI::Error>> {
+        // 给定超时计数时间
+        let mut timeout = timeout_ms * 10;
+
+        loop {
+            match self.poll_idle() {
+                Ok(()) => return Ok(()),
+                Err(nb::Error::Other(e)) => return Err(e),
+                Err(nb::Error::WouldBlock) => {}
+            }
+            if timeout == 0 {
+                return Err(Error::Timeout);
+            }
+            timeout -= 1;
+            self.delay_us(100);
+        }
+    }
+
+    /// 页编程, 写入数据
+    /// page_address: 设定页地址
+    /// data: 要写入的数据
+    pub fn page_program(
+        &mut self,
+        page_address: u32,
+        data: &[u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        if data.len() > W25Q64_PAGE_SIZE {
+            return Err(Error::InvalidArgument);
+        }
+
+        self.write_enable()?;
+
+        let cmd = [
+            W25Q64_PAGE_PROGRAM,        // 页编程的指令
+            (page_address >> 16) as u8, // 地址23~16位
+            (page_address >> 8) as u8,  // 地址15~8位
+            page_address as u8,         // 地址7~0位
+        ];
+        self.spi
+            .transaction(&mut [Operation::Write(&cmd), Operation::Write(data)])
+            .map_err(Error::Spi)?;
+
+        // 等待W25Q64芯片空闲
+        self.wait_for_idle(W25Q64_PAGE_PROGRAM_TIMEOUT_MS)
+    }
+
+    /// 擦除地址所在的扇区
+    pub fn sector_erase(&mut self, address: u32) -> Result<(), Error<SPI::Error>> {
+        self.start_sector_erase(address)?;
+        self.wait_for_idle(W25Q64_SECTOR_ERASE_TIMEOUT_MS)
+    }
+
+    /// 开始擦除地址所在的扇区，不等待完成
+    /// 需要通过 `poll_idle` 查询擦除是否完成
+    pub fn start_sector_erase(&mut self, address: u32) -> Result<(), Error<SPI::Error>> {
+        self.start_erase(W25Q64_SECTOR_ERASE_4KB, address)
+    }
+
+    /// 发送擦除指令，不等待完成
+    /// erase_cmd: 扇区/块擦除的指令
+    /// address: 擦除区域内的地址
+    pub(super) fn start_erase(
+        &mut self,
+        erase_cmd: u8,
+        address: u32,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_enable()?;
+
+        let cmd = [
+            erase_cmd,             // 擦除的指令
+            (address >> 16) as u8, // 地址23~16位
+            (address >> 8) as u8,  // 地址15~8位
+            address as u8,         // 地址7~0位
+        ];
+        self.spi_write(&cmd)
+    }
+
+    /// 擦除闪存芯片上的所有扇区
+    /// 这是一项非常昂贵的手术
+    pub fn erase_chip(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.start_erase_chip()?;
+        self.wait_for_idle(W25Q64_CHIP_ERASE_TIMEOUT_MS)
+    }
+
+    /// 开始整片擦除，不等待完成
+    /// 需要通过 `poll_idle` 查询擦除是否完成
+    pub fn start_erase_chip(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.write_enable()?;
+        self.spi_write(&[W25Q64_CHIP_ERASE])
+    }
+
+    /// 读取数据
+    /// read_address: 目标地址
+    /// data: 用于存放数据
+    pub fn read_data(
+        &mut self,
+        read_address: u32,
+        data: &mut [u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        let cmd = [
+            W25Q64_READ_DATA,           // 读取数据的指令
+            (read_address >> 16) as u8, // 地址23~16位
+            (read_address >> 8) as u8,  // 地址15~8位
+            read_address as u8,         // 地址7~0位
+        ];
+        self.spi_write_read(&cmd, data)
+    }
+
+    /// 快速读取数据
+    /// 在地址后增加一个虚拟字节，SPI 时钟可以超过普通读取的上限
+    /// read_address: 目标地址
+    /// data: 用于存放数据
+    pub fn fast_read(
+        &mut self,
+        read_address: u32,
+        data: &mut [u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        let cmd = [
+            W25Q64_FAST_READ,           // 快速读取数据的指令
+            (read_address >> 16) as u8, // 地址23~16位
+            (read_address >> 8) as u8,  // 地址15~8位
+            read_address as u8,         // 地址7~0位
+            W25Q64_DUMMY_BYTE,          // 虚拟字节
+        ];
+        self.spi_write_read(&cmd, data)
+    }
+
+    /// 写入任意长度的数据
+    /// 按页边界自动拆分为多次页编程，避免在页内回卷覆盖
+    /// 注意: 目标区域需要提前擦除
+    /// address: 起始地址
+    /// data: 要写入的数据
+    pub fn write(&mut self, address: u32, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        let mut address = address;
+        let mut data = data;
+        while !data.is_empty() {
+            // 当前页剩余的字节数
+            let page_remain = W25Q64_PAGE_SIZE - address as usize % W25Q64_PAGE_SIZE;
+            let len = page_remain.min(data.len());
+
+            self.page_program(address, &data[..len])?;
+
+            address += len as u32;
+            data = &data[len..];
+        }
+        Ok(())
+    }
+
+    /// 更新任意地址的数据
+    /// 读出所在的4KB扇区，合并新数据后擦除并重新写入
+    /// 若只需要 1→0 的位变化，则跳过擦除直接编程
+    /// address: 起始地址
+    /// data: 要写入的数据
+    pub fn update(&mut self, address: u32, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        let mut buffer = [0; W25Q64_SECTOR_SIZE];
+        let mut address = address;
+        let mut data = data;
+        while !data.is_empty() {
+            // 扇区起始地址及扇区内偏移
+            let sector_address = address & !(W25Q64_SECTOR_SIZE as u32 - 1);
+            let offset = (address - sector_address) as usize;
+            let len = (W25Q64_SECTOR_SIZE - offset).min(data.len());
+            let (chunk, rest) = data.split_at(len);
+
+            self.read_data(sector_address, &mut buffer)?;
+            let target = &mut buffer[offset..offset + len];
+
+            if target == chunk {
+                // 数据没有变化，无需写入
+            } else if target.iter().zip(chunk).all(|(old, new)| old & new == *new) {
+                // 编程只能将位从1变为0，满足条件时无需擦除
+                self.write(address, chunk)?;
+            } else {
+                target.copy_from_slice(chunk);
+                self.sector_erase(sector_address)?;
+
+                // 擦除后为0xFF，全为0xFF的页无需重新编程
+                for (i, page) in buffer.chunks(W25Q64_PAGE_SIZE).enumerate() {
+                    if page.iter().all(|v| *v == 0xFF) {
+                        continue;
+                    }
+                    let page_address = sector_address + (i * W25Q64_PAGE_SIZE) as u32;
+                    self.page_program(page_address, page)?;
+                }
+            }
+
+            address += len as u32;
+            data = rest;
+        }
+        Ok(())
+    }
+}
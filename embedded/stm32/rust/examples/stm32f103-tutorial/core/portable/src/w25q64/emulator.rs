@@ -0,0 +1,615 @@
+//! W25Qxx 芯片仿真器
+//! 在 RAM 中模拟 SPI NOR FLASH 的行为，用于在主机上测试驱动、文件系统和数据记录器
+//! 实现 embedded-hal 1.0 的 `SpiDevice`，每个事务对应一次片选的拉低和拉高
+//! 配合 `driver::W25Q64` 使用，测试的是与真实芯片完全相同的命令序列
+//!
+//! 仿真的行为:
+//! - JEDEC ID、制造商/设备ID、唯一ID、SFDP 参数表
+//! - 读数据、快速读取，地址到达末尾后回绕到0
+//! - 页编程只能将位从1变为0，超出页末尾的数据回绕到页起始
+//! - 扇区/块/整片擦除将数据置为0xFF
+//! - WEL/BUSY 位、块保护、掉电模式、擦除暂停/恢复
+//!
+//! ```rust
+//! static mut MEMORY: [u8; W25Q64_CAPACITY] = [0xFF; W25Q64_CAPACITY];
+//! let mut flash = emulator::W25qEmulator::new(unsafe { &mut *addr_of_mut!(MEMORY) });
+//! // 页编程期间前2次查询状态寄存器返回 BUSY
+//! flash.set_timing(emulator::Timing {
+//!     page_program: 2,
+//!     ..Default::default()
+//! });
+//! // 完成3次编程/擦除后，第4次操作进行到一半时掉电
+//! flash.inject_power_loss_after(3);
+//! let mut w25q = W25Q64::new(flash, emulator::NoopDelay);
+//! ```
+
+use super::conf::*;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::{self, ErrorKind, Operation, SpiDevice};
+
+/// Winbond 制造商ID
+const MANUFACTURER_ID: u8 = 0xEF;
+/// 存储器类型
+const MEMORY_TYPE: u8 = 0x40;
+/// 整片擦除的另一个指令
+const CHIP_ERASE_ALT: u8 = 0x60;
+/// 状态寄存器1中可写的位: BP0~BP2、TB、SEC、SRP0
+const STATUS_1_WRITABLE: u8 = 0xFC;
+/// 状态寄存器2中可写的位: SRP1、QE、CMP
+const STATUS_2_WRITABLE: u8 = 0x43;
+
+/// SFDP 基本参数表的地址
+const SFDP_TABLE_ADDRESS: u32 = 0x80;
+/// SFDP 基本参数表的长度(双字)
+const SFDP_TABLE_LENGTH: usize = 9;
+
+/// 仿真器错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// 注入的总线错误
+    Injected,
+    /// 芯片已掉电，需要调用 `power_cycle` 重新上电
+    PowerLost,
+}
+
+impl spi::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// 空延时
+/// 仿真器的耗时以状态查询次数计算，驱动中的等待无需真正延时
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopDelay;
+
+impl DelayNs for NoopDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// 操作耗时
+/// 以查询状态寄存器1的次数表示，期间 BUSY 位保持为1
+/// 为0时操作立即完成
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timing {
+    pub page_program: u32,
+    pub write_status: u32,
+    pub sector_erase: u32,
+    pub block_erase: u32,
+    pub chip_erase: u32,
+}
+
+/// 操作统计
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    /// 完成的页编程次数
+    pub page_programs: u32,
+    /// 擦除的扇区数
+    pub sector_erases: u32,
+    /// 因写保护或写使能未设置而被忽略的编程/擦除次数
+    pub rejected: u32,
+}
+
+/// W25Qxx 芯片仿真器
+pub struct W25qEmulator<'m> {
+    memory: &'m mut [u8],
+    status1: u8,
+    status2: u8,
+    unique_id: u64,
+    timing: Timing,
+    stats: Stats,
+    /// 剩余的 BUSY 查询次数
+    busy_polls: u32,
+    /// 暂停时剩余的 BUSY 查询次数
+    suspended_polls: u32,
+    powered_down: bool,
+    power_lost: bool,
+    /// 剩余多少个事务后注入总线错误
+    error_after: Option<u32>,
+    /// 剩余多少次编程/擦除后掉电
+    power_loss_after: Option<u32>,
+
+    // 当前事务的状态
+    command: Option<u8>,
+    position: usize,
+    address: u32,
+    page: [u8; W25Q64_PAGE_SIZE],
+    status_write: [Option<u8>; 2],
+}
+
+impl<'m> W25qEmulator<'m> {
+    /// 创建仿真器
+    /// memory: 芯片的存储内容，长度即芯片容量，必须是4KB的2的幂倍
+    /// 全新的芯片应当将 memory 填充为0xFF
+    pub fn new(memory: &'m mut [u8]) -> Self {
+        assert!(memory.len().is_power_of_two() && memory.len() >= W25Q64_SECTOR_SIZE);
+        assert!(memory.len() <= 1 << 24);
+
+        W25qEmulator {
+            memory,
+            status1: 0,
+            status2: 0,
+            unique_id: 0xD267_2C5A_1F3B_4C21,
+            timing: Timing::default(),
+            stats: Stats::default(),
+            busy_polls: 0,
+            suspended_polls: 0,
+            powered_down: false,
+            power_lost: false,
+            error_after: None,
+            power_loss_after: None,
+            command: None,
+            position: 0,
+            address: 0,
+            page: [0xFF; W25Q64_PAGE_SIZE],
+            status_write: [None; 2],
+        }
+    }
+
+    /// 设置操作耗时
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    /// 设置64位唯一ID
+    pub fn set_unique_id(&mut self, unique_id: u64) {
+        self.unique_id = unique_id;
+    }
+
+    /// 第 n+1 个 SPI 事务返回 `Error::Injected`，芯片状态不受影响
+    pub fn inject_error_after(&mut self, transactions: u32) {
+        self.error_after = Some(transactions);
+    }
+
+    /// 完成 n 次编程/擦除后，第 n+1 次操作只完成一半即掉电
+    /// 掉电后所有事务返回 `Error::PowerLost`，直到调用 `power_cycle`
+    pub fn inject_power_loss_after(&mut self, operations: u32) {
+        self.power_loss_after = Some(operations);
+    }
+
+    /// 立即掉电
+    pub fn power_loss(&mut self) {
+        self.power_lost = true;
+        self.power_loss_after = None;
+    }
+
+    /// 重新上电
+    /// 清除 WEL、BUSY、SUS 等易失性状态，保留存储内容和保护位
+    pub fn power_cycle(&mut self) {
+        self.status1 &= !(W25Q64_STATUS_BUSY | W25Q64_STATUS_WEL);
+        self.status2 &= !W25Q64_STATUS_SUS;
+        self.busy_polls = 0;
+        self.suspended_polls = 0;
+        self.powered_down = false;
+        self.power_lost = false;
+    }
+
+    /// 是否已掉电
+    pub fn is_power_lost(&self) -> bool {
+        self.power_lost
+    }
+
+    /// 芯片的存储内容
+    pub fn memory(&self) -> &[u8] {
+        self.memory
+    }
+
+    /// 芯片容量(字节)
+    pub fn capacity(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// 操作统计
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// 状态寄存器1和2
+    pub fn status_registers(&self) -> (u8, u8) {
+        (self.status1, self.status2)
+    }
+
+    /// JEDEC ID 中的容量字节，为容量以2为底的对数
+    fn capacity_id(&self) -> u8 {
+        self.capacity().trailing_zeros() as u8
+    }
+
+    /// 设备ID，W25Q64为0x16
+    fn device_id(&self) -> u8 {
+        self.capacity_id() - 1
+    }
+
+    /// 拉低片选
+    fn select(&mut self) {
+        self.command = None;
+        self.position = 0;
+        self.address = 0;
+        self.status_write = [None; 2];
+    }
+
+    /// 交换一个字节
+    fn exchange(&mut self, mosi: u8) -> u8 {
+        let position = self.position;
+        self.position += 1;
+
+        if position == 0 {
+            if self.accepts(mosi) {
+                self.command = Some(mosi);
+                if mosi == W25Q64_PAGE_PROGRAM {
+                    self.page = [0xFF; W25Q64_PAGE_SIZE];
+                }
+            }
+            return 0xFF;
+        }
+
+        let Some(command) = self.command else {
+            return 0xFF;
+        };
+        match command {
+            W25Q64_JEDEC_DEVICE_ID => match position {
+                1 => MANUFACTURER_ID,
+                2 => MEMORY_TYPE,
+                3 => self.capacity_id(),
+                _ => 0xFF,
+            },
+            W25Q64_MANUFACTURER_DEVICE_ID => {
+                if position <= 3 {
+                    self.shift_address(mosi);
+                    return 0xFF;
+                }
+                // 地址为0时先输出制造商ID，为1时先输出设备ID
+                if (position - 4 + self.address as usize).is_multiple_of(2) {
+                    MANUFACTURER_ID
+                } else {
+                    self.device_id()
+                }
+            }
+            W25Q64_RELEASE_POWER_DOWN_HPM_DEVICE_ID => {
+                if position <= 3 {
+                    0xFF
+                } else {
+                    self.device_id()
+                }
+            }
+            W25Q64_READ_UNIQUE_ID => match position {
+                5..=12 => self.unique_id.to_be_bytes()[position - 5],
+                _ => 0xFF,
+            },
+            W25Q64_READ_STATUS_REGISTER_1 => {
+                // 连续读取时每个字节都是最新的状态
+                let status = self.status1;
+                self.poll();
+                status
+            }
+            W25Q64_READ_STATUS_REGISTER_2 => self.status2,
+            W25Q64_WRITE_STATUS_REGISTER => {
+                if position <= 2 {
+                    self.status_write[position - 1] = Some(mosi);
+                }
+                0xFF
+            }
+            W25Q64_READ_DATA | W25Q64_FAST_READ | W25Q64_READ_SFDP => {
+                let dummy = if command == W25Q64_READ_DATA { 0 } else { 1 };
+                if position <= 3 {
+                    self.shift_address(mosi);
+                    return 0xFF;
+                }
+                if position < 4 + dummy {
+                    return 0xFF;
+                }
+                let data = if command == W25Q64_READ_SFDP {
+                    self.sfdp_byte(self.address)
+                } else {
+                    self.memory[self.address as usize]
+                };
+                self.address = (self.address + 1) & (self.capacity() as u32 - 1);
+                data
+            }
+            W25Q64_PAGE_PROGRAM => {
+                if position <= 3 {
+                    self.shift_address(mosi);
+                } else {
+                    // 超出页末尾的数据回绕到页起始，后写入的覆盖先写入的
+                    let offset = (self.address as usize + position - 4) % W25Q64_PAGE_SIZE;
+                    self.page[offset] = mosi;
+                }
+                0xFF
+            }
+            W25Q64_SECTOR_ERASE_4KB | W25Q64_BLOCK_ERASE_32KB | W25Q64_BLOCK_ERASE_64KB => {
+                if position <= 3 {
+                    self.shift_address(mosi);
+                }
+                0xFF
+            }
+            _ => 0xFF,
+        }
+    }
+
+    /// 当前状态下是否接受指令
+    fn accepts(&self, command: u8) -> bool {
+        if self.powered_down {
+            return command == W25Q64_RELEASE_POWER_DOWN_HPM_DEVICE_ID;
+        }
+        if self.status1 & W25Q64_STATUS_BUSY != 0 {
+            return matches!(
+                command,
+                W25Q64_READ_STATUS_REGISTER_1
+                    | W25Q64_READ_STATUS_REGISTER_2
+                    | W25Q64_ERASE_SUSPEND
+            );
+        }
+        if self.status2 & W25Q64_STATUS_SUS != 0 {
+            return !matches!(
+                command,
+                W25Q64_WRITE_STATUS_REGISTER
+                    | W25Q64_SECTOR_ERASE_4KB
+                    | W25Q64_BLOCK_ERASE_32KB
+                    | W25Q64_BLOCK_ERASE_64KB
+                    | W25Q64_CHIP_ERASE
+                    | CHIP_ERASE_ALT
+            );
+        }
+        true
+    }
+
+    fn shift_address(&mut self, byte: u8) {
+        self.address = ((self.address << 8) | byte as u32) & (self.capacity() as u32 - 1);
+    }
+
+    /// 查询一次状态寄存器，忙状态计数减1
+    fn poll(&mut self) {
+        if self.busy_polls > 0 {
+            self.busy_polls -= 1;
+            if self.busy_polls == 0 {
+                self.status1 &= !(W25Q64_STATUS_BUSY | W25Q64_STATUS_WEL);
+            }
+        }
+    }
+
+    /// 开始一个耗时操作，完成后清除 WEL
+    fn start_busy(&mut self, polls: u32) {
+        if polls == 0 {
+            self.status1 &= !W25Q64_STATUS_WEL;
+        } else {
+            self.status1 |= W25Q64_STATUS_BUSY;
+            self.busy_polls = polls;
+        }
+    }
+
+    /// 拉高片选，执行写入类指令
+    fn deselect(&mut self) {
+        let Some(command) = self.command.take() else {
+            return;
+        };
+        let length = self.position;
+        let wel = self.status1 & W25Q64_STATUS_WEL != 0;
+
+        match command {
+            W25Q64_WRITE_ENABLE if length == 1 => self.status1 |= W25Q64_STATUS_WEL,
+            W25Q64_WRITE_DISABLE if length == 1 => self.status1 &= !W25Q64_STATUS_WEL,
+            W25Q64_POWER_DOWN if length == 1 => self.powered_down = true,
+            W25Q64_RELEASE_POWER_DOWN_HPM_DEVICE_ID => self.powered_down = false,
+            W25Q64_WRITE_STATUS_REGISTER if wel && length >= 2 => {
+                if let Some(status1) = self.status_write[0] {
+                    self.status1 =
+                        (self.status1 & !STATUS_1_WRITABLE) | (status1 & STATUS_1_WRITABLE);
+                }
+                if let Some(status2) = self.status_write[1] {
+                    self.status2 =
+                        (self.status2 & !STATUS_2_WRITABLE) | (status2 & STATUS_2_WRITABLE);
+                }
+                self.start_busy(self.timing.write_status);
+            }
+            W25Q64_PAGE_PROGRAM if length > 4 => self.page_program(wel),
+            W25Q64_SECTOR_ERASE_4KB if length == 4 => {
+                self.erase(wel, W25Q64_SECTOR_SIZE, self.timing.sector_erase)
+            }
+            W25Q64_BLOCK_ERASE_32KB if length == 4 => {
+                self.erase(wel, 32 * 1024, self.timing.block_erase)
+            }
+            W25Q64_BLOCK_ERASE_64KB if length == 4 => {
+                self.erase(wel, 64 * 1024, self.timing.block_erase)
+            }
+            W25Q64_CHIP_ERASE | CHIP_ERASE_ALT if length == 1 => {
+                self.address = 0;
+                self.erase(wel, self.capacity(), self.timing.chip_erase)
+            }
+            W25Q64_ERASE_SUSPEND if self.status1 & W25Q64_STATUS_BUSY != 0 => {
+                self.suspended_polls = self.busy_polls;
+                self.busy_polls = 0;
+                self.status1 &= !W25Q64_STATUS_BUSY;
+                self.status2 |= W25Q64_STATUS_SUS;
+            }
+            W25Q64_ERASE_RESUME if self.status2 & W25Q64_STATUS_SUS != 0 => {
+                self.status2 &= !W25Q64_STATUS_SUS;
+                self.start_busy(self.suspended_polls);
+                self.suspended_polls = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// 检查编程/擦除能否执行
+    /// 写保护时 WEL 保持为1，驱动据此判断操作被拒绝
+    fn check_writable(&mut self, wel: bool, start: usize, len: usize) -> bool {
+        if !wel || self.is_protected(start, len) {
+            self.stats.rejected += 1;
+            return false;
+        }
+        true
+    }
+
+    /// 检查是否触发掉电，返回本次操作是否只完成一半
+    fn check_power_loss(&mut self) -> bool {
+        match self.power_loss_after {
+            Some(0) => {
+                self.power_loss();
+                true
+            }
+            Some(n) => {
+                self.power_loss_after = Some(n - 1);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn page_program(&mut self, wel: bool) {
+        let page_start = self.address as usize & !(W25Q64_PAGE_SIZE - 1);
+        if !self.check_writable(wel, page_start, W25Q64_PAGE_SIZE) {
+            return;
+        }
+        let interrupted = self.check_power_loss();
+
+        // 超过256字节时只保留最后256字节
+        let received = self.position - 4;
+        let count = received.min(W25Q64_PAGE_SIZE);
+        let skipped = received - count;
+        let count = if interrupted { count / 2 } else { count };
+        for i in 0..count {
+            let offset = (self.address as usize + skipped + i) % W25Q64_PAGE_SIZE;
+            // 编程只能将位从1变为0
+            self.memory[page_start + offset] &= self.page[offset];
+        }
+
+        if !interrupted {
+            self.stats.page_programs += 1;
+            self.start_busy(self.timing.page_program);
+        }
+    }
+
+    fn erase(&mut self, wel: bool, size: usize, polls: u32) {
+        let start = self.address as usize & !(size - 1);
+        if !self.check_writable(wel, start, size) {
+            return;
+        }
+        let interrupted = self.check_power_loss();
+
+        let len = if interrupted { size / 2 } else { size };
+        self.memory[start..start + len].fill(0xFF);
+
+        if !interrupted {
+            self.stats.sector_erases += (size / W25Q64_SECTOR_SIZE) as u32;
+            self.start_busy(polls);
+        }
+    }
+
+    /// 区域内是否有被写保护的地址
+    fn is_protected(&self, start: usize, len: usize) -> bool {
+        let capacity = self.capacity();
+        let bp = (self.status1 & W25Q64_STATUS_BP_MASK) >> 2;
+        let tb = self.status1 & W25Q64_STATUS_TB != 0;
+        let sec = self.status1 & W25Q64_STATUS_SEC != 0;
+        let cmp = self.status2 & W25Q64_STATUS_CMP != 0;
+
+        let size = match (sec, bp) {
+            (_, 0) => 0,
+            (_, 7) => capacity,
+            (true, bp) => W25Q64_SECTOR_SIZE << (bp.min(4) - 1),
+            (false, bp) => (capacity / 64) << (bp - 1),
+        }
+        .min(capacity);
+        let (low, high) = if tb {
+            (0, size)
+        } else {
+            (capacity - size, capacity)
+        };
+
+        let overlaps = |a: usize, b: usize| a < b && start < b && a < start + len;
+        if cmp {
+            overlaps(0, low) || overlaps(high, capacity)
+        } else {
+            overlaps(low, high)
+        }
+    }
+
+    /// SFDP 头、JEDEC 基本参数表头及基本参数表
+    fn sfdp_byte(&self, address: u32) -> u8 {
+        let header: [u32; 4] = [
+            0x5044_4653, // "SFDP"
+            0xFF00_0100, // 版本1.0，1个参数头
+            0x0901_0000, // JEDEC 基本参数表，版本1.0，9个双字
+            0xFF00_0000 | SFDP_TABLE_ADDRESS,
+        ];
+        let table: [u32; SFDP_TABLE_LENGTH] = [
+            0xFFF1_20E5,                      // 支持4KB擦除，指令0x20
+            (self.capacity() as u32) * 8 - 1, // 容量(位)-1
+            0x6B08_EB44,
+            0x3B42_BB08,
+            0xFFFF_FFEE,
+            0xFF00_FFFF,
+            0xEB40_FFFF,
+            0x520F_200C, // 擦除类型1: 4KB 0x20，类型2: 32KB 0x52
+            0x0000_D810, // 擦除类型3: 64KB 0xD8，类型4不支持
+        ];
+
+        let address = address as usize;
+        let table_address = SFDP_TABLE_ADDRESS as usize;
+        let dword = if address < header.len() * 4 {
+            header[address / 4]
+        } else if (table_address..table_address + table.len() * 4).contains(&address) {
+            table[(address - table_address) / 4]
+        } else {
+            return 0xFF;
+        };
+        dword.to_le_bytes()[address % 4]
+    }
+}
+
+impl spi::ErrorType for W25qEmulator<'_> {
+    type Error = Error;
+}
+
+impl SpiDevice for W25qEmulator<'_> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Error> {
+        if self.power_lost {
+            return Err(Error::PowerLost);
+        }
+        match self.error_after {
+            Some(0) => {
+                self.error_after = None;
+                return Err(Error::Injected);
+            }
+            Some(n) => self.error_after = Some(n - 1),
+            None => {}
+        }
+
+        self.select();
+        for operation in operations {
+            match operation {
+                Operation::Read(read) => {
+                    for byte in read.iter_mut() {
+                        *byte = self.exchange(W25Q64_DUMMY_BYTE);
+                    }
+                }
+                Operation::Write(write) => {
+                    for &byte in write.iter() {
+                        self.exchange(byte);
+                    }
+                }
+                Operation::Transfer(read, write) => {
+                    for i in 0..read.len().max(write.len()) {
+                        let mosi = write.get(i).copied().unwrap_or(W25Q64_DUMMY_BYTE);
+                        let miso = self.exchange(mosi);
+                        if let Some(byte) = read.get_mut(i) {
+                            *byte = miso;
+                        }
+                    }
+                }
+                Operation::TransferInPlace(buffer) => {
+                    for byte in buffer.iter_mut() {
+                        *byte = self.exchange(*byte);
+                    }
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+        self.deselect();
+
+        if self.power_lost {
+            return Err(Error::PowerLost);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,360 @@
+//! W25Q64 文件系统
+//! 基于 littlefs 实现，写入采用写时复制，掉电后不会损坏已有数据
+//! 主机端可以使用 `scripts/w25q64_lfs.py` 制作或解包文件系统镜像
+//! 需要启用 `littlefs` 特性
+//!
+//! ```rust
+//! let mut storage = fs::LfsStorage::new(&mut w25q);
+//! let mut alloc = fs::FileSystem::allocate();
+//! let mut fs = fs::FileSystem::mount_or_format(&mut alloc, &mut storage).unwrap();
+//! fs.write_file("config.txt", b"baud=115200").unwrap();
+//! let mut buffer = [0; 32];
+//! let len = fs.read_file("config.txt", 0, &mut buffer).unwrap();
+//!
+//! // 通过文件句柄随机读写
+//! fs.open_file_and_then(
+//!     "data.bin",
+//!     |options| options.read(true).write(true).create(true),
+//!     |file| {
+//!         file.seek(SeekFrom::End(0))?;
+//!         file.write(b"tail")?;
+//!         file.seek(SeekFrom::Start(0))?;
+//!         file.read(&mut buffer)
+//!     },
+//! )
+//! .unwrap();
+//! ```
+
+use super::conf::*;
+use super::W25Q64;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::SpiDevice;
+use littlefs2::consts;
+use littlefs2::driver::Storage;
+use littlefs2::fs::{self as lfs, Allocation, Filesystem};
+use littlefs2::io;
+use littlefs2::path::PathBuf;
+
+pub use littlefs2::fs::{FileAllocation, OpenOptions};
+pub use littlefs2::io::SeekFrom;
+
+/// 读取的最小单位
+/// 需要与主机端镜像工具保持一致
+pub const LFS_READ_SIZE: usize = 16;
+/// 编程的最小单位，与页大小一致
+pub const LFS_PROG_SIZE: usize = W25Q64_PAGE_SIZE;
+/// 块大小，与扇区大小一致
+pub const LFS_BLOCK_SIZE: usize = W25Q64_SECTOR_SIZE;
+/// 块数量
+pub const LFS_BLOCK_COUNT: usize = W25Q64_CAPACITY / W25Q64_SECTOR_SIZE;
+
+/// littlefs 存储驱动
+pub struct LfsStorage<'b, SPI, D>
+where
+    SPI: SpiDevice,
+    D: DelayNs,
+{
+    w25q: &'b mut W25Q64<SPI, D>,
+}
+
+impl<'b, SPI, D> LfsStorage<'b, SPI, D>
+where
+    SPI: SpiDevice,
+    D: DelayNs,
+{
+    pub fn new(w25q: &'b mut W25Q64<SPI, D>) -> Self {
+        LfsStorage { w25q }
+    }
+}
+
+impl<'b, SPI, D> Storage for LfsStorage<'b, SPI, D>
+where
+    SPI: SpiDevice,
+    D: DelayNs,
+{
+    const READ_SIZE: usize = LFS_READ_SIZE;
+    const WRITE_SIZE: usize = LFS_PROG_SIZE;
+    const BLOCK_SIZE: usize = LFS_BLOCK_SIZE;
+    const BLOCK_COUNT: usize = LFS_BLOCK_COUNT;
+    // 擦写次数达到后迁移元数据，实现磨损均衡
+    const BLOCK_CYCLES: isize = 500;
+
+    type CACHE_SIZE = consts::U256;
+    type LOOKAHEAD_SIZE = consts::U4;
+
+    fn read(&mut self, off: usize, buf: &mut [u8]) -> io::Result<usize> {
+        self.w25q
+            .read_data(off as u32, buf)
+            .map_err(|_| io::Error::Io)?;
+        Ok(buf.len())
+    }
+
+    fn write(&mut self, off: usize, data: &[u8]) -> io::Result<usize> {
+        self.w25q
+            .write(off as u32, data)
+            .map_err(|_| io::Error::Io)?;
+        Ok(data.len())
+    }
+
+    fn erase(&mut self, off: usize, len: usize) -> io::Result<usize> {
+        for address in (off..off + len).step_by(LFS_BLOCK_SIZE) {
+            self.w25q
+                .sector_erase(address as u32)
+                .map_err(|_| io::Error::Io)?;
+        }
+        Ok(len)
+    }
+}
+
+/// 文件系统使用情况
+#[derive(Debug)]
+pub struct StatFs {
+    /// 块大小
+    pub block_size: usize,
+    /// 总块数
+    pub total_blocks: usize,
+    /// 空闲块数
+    pub free_blocks: usize,
+}
+
+/// 目录项
+pub struct DirEntry<'e> {
+    /// 文件名
+    pub name: &'e str,
+    /// 是否为目录
+    pub is_dir: bool,
+    /// 文件大小
+    pub size: usize,
+}
+
+/// 打开的文件
+/// littlefs 用链表记录打开的文件，离开作用域前必须调用 `close` 关闭
+pub struct File<'a, 'b, S>
+where
+    S: Storage,
+{
+    file: lfs::File<'a, 'b, S>,
+}
+
+impl<'a, 'b, S> File<'a, 'b, S>
+where
+    S: Storage,
+{
+    /// 从当前位置读取，返回实际读取的字节数
+    pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+
+    /// 从当前位置写入，返回实际写入的字节数
+    pub fn write(&self, data: &[u8]) -> io::Result<usize> {
+        self.file.write(data)
+    }
+
+    /// 移动读写位置，返回新的位置
+    pub fn seek(&self, pos: SeekFrom) -> io::Result<usize> {
+        self.file.seek(pos)
+    }
+
+    /// 文件大小
+    pub fn len(&self) -> io::Result<usize> {
+        self.file.len()
+    }
+
+    /// 文件是否为空
+    pub fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.file.len()? == 0)
+    }
+
+    /// 截断或扩展文件
+    pub fn set_len(&self, size: usize) -> io::Result<()> {
+        self.file.set_len(size)
+    }
+
+    /// 将缓存的数据写入存储器
+    pub fn sync(&self) -> io::Result<()> {
+        self.file.sync()
+    }
+
+    /// 关闭文件，缓存的数据写入存储器
+    pub fn close(self) -> io::Result<()> {
+        // 句柄被消耗，不会重复关闭
+        unsafe { self.file.close() }
+    }
+}
+
+/// 文件系统
+pub struct FileSystem<'fs, S>
+where
+    S: Storage,
+{
+    fs: Filesystem<'fs, S>,
+}
+
+impl<'fs, S> FileSystem<'fs, S>
+where
+    S: Storage,
+{
+    /// 分配文件系统所需的缓存
+    pub fn allocate() -> Allocation<S> {
+        Filesystem::allocate()
+    }
+
+    /// 格式化
+    /// 会清除所有文件
+    pub fn format(storage: &mut S) -> io::Result<()> {
+        Filesystem::format(storage)
+    }
+
+    /// 挂载文件系统
+    pub fn mount(alloc: &'fs mut Allocation<S>, storage: &'fs mut S) -> io::Result<Self> {
+        let fs = Filesystem::mount(alloc, storage)?;
+        Ok(FileSystem { fs })
+    }
+
+    /// 挂载文件系统，首次使用时先格式化
+    pub fn mount_or_format(alloc: &'fs mut Allocation<S>, storage: &'fs mut S) -> io::Result<Self> {
+        if !Filesystem::is_mountable(storage) {
+            Filesystem::format(storage)?;
+        }
+        Self::mount(alloc, storage)
+    }
+
+    /// 打开文件
+    /// alloc: 文件缓存，由 `FileAllocation::new()` 分配，文件关闭前不能再使用
+    /// options: 打开方式，如 `OpenOptions::new().read(true).write(true)`
+    ///
+    /// # Safety
+    /// 返回的文件必须在离开作用域前调用 `File::close` 关闭，
+    /// 否则 littlefs 的打开文件链表中会残留无效的指针
+    pub unsafe fn open_file<'b>(
+        &'b self,
+        alloc: &'b mut FileAllocation<S>,
+        path: &str,
+        options: &OpenOptions,
+    ) -> io::Result<File<'fs, 'b, S>> {
+        let file = options.open(&self.fs, alloc, &PathBuf::from(path))?;
+        Ok(File { file })
+    }
+
+    /// 打开文件并在回调中读写，回调返回后自动关闭文件
+    /// options: 设置打开方式
+    /// f: 读写文件的回调函数
+    pub fn open_file_and_then<R>(
+        &self,
+        path: &str,
+        options: impl FnOnce(&mut OpenOptions) -> &OpenOptions,
+        f: impl FnOnce(&File<'fs, '_, S>) -> io::Result<R>,
+    ) -> io::Result<R> {
+        let mut alloc = FileAllocation::new();
+        let mut open_options = OpenOptions::new();
+        let file = unsafe { self.open_file(&mut alloc, path, options(&mut open_options))? };
+        let result = f(&file);
+        file.close()?;
+        result
+    }
+
+    /// 读取文件内容
+    /// path: 文件路径
+    /// offset: 读取的起始位置
+    /// buf: 用于存放数据
+    /// 返回实际读取的字节数
+    pub fn read_file(&self, path: &str, offset: usize, buf: &mut [u8]) -> io::Result<usize> {
+        self.open_file_and_then(
+            path,
+            |options| options.read(true),
+            |file| {
+                file.seek(SeekFrom::Start(offset as u32))?;
+                file.read(buf)
+            },
+        )
+    }
+
+    /// 写入文件内容
+    /// 文件不存在时创建，存在时覆盖原有内容
+    pub fn write_file(&self, path: &str, data: &[u8]) -> io::Result<()> {
+        self.fs.write(&PathBuf::from(path), data)
+    }
+
+    /// 在文件末尾追加内容
+    /// 文件不存在时创建
+    pub fn append_file(&self, path: &str, data: &[u8]) -> io::Result<usize> {
+        self.open_file_and_then(
+            path,
+            |options| options.write(true).create(true).append(true),
+            |file| file.write(data),
+        )
+    }
+
+    /// 在指定位置写入文件内容
+    /// offset: 写入的起始位置
+    pub fn write_file_at(&self, path: &str, offset: usize, data: &[u8]) -> io::Result<usize> {
+        self.open_file_and_then(
+            path,
+            |options| options.write(true).create(true),
+            |file| {
+                file.seek(SeekFrom::Start(offset as u32))?;
+                file.write(data)
+            },
+        )
+    }
+
+    /// 获取文件大小
+    pub fn file_size(&self, path: &str) -> io::Result<usize> {
+        let metadata = self.fs.metadata(&PathBuf::from(path))?;
+        Ok(metadata.len())
+    }
+
+    /// 文件或目录是否存在
+    pub fn exists(&self, path: &str) -> bool {
+        self.fs.exists(&PathBuf::from(path))
+    }
+
+    /// 删除文件或空目录
+    pub fn remove(&self, path: &str) -> io::Result<()> {
+        self.fs.remove(&PathBuf::from(path))
+    }
+
+    /// 重命名文件或目录
+    pub fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        self.fs.rename(&PathBuf::from(from), &PathBuf::from(to))
+    }
+
+    /// 创建目录
+    pub fn create_dir(&self, path: &str) -> io::Result<()> {
+        self.fs.create_dir(&PathBuf::from(path))
+    }
+
+    /// 遍历目录
+    /// f: 对每一个目录项调用的回调函数，不包含 `.` 和 `..`
+    pub fn read_dir<F>(&self, path: &str, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(DirEntry),
+    {
+        self.fs.read_dir_and_then(&PathBuf::from(path), |dir| {
+            for entry in dir {
+                let entry = entry?;
+                let name: &str = entry.file_name().as_ref();
+                if name == "." || name == ".." {
+                    continue;
+                }
+                let metadata = entry.metadata();
+                f(DirEntry {
+                    name,
+                    is_dir: metadata.is_dir(),
+                    size: metadata.len(),
+                });
+            }
+            Ok(())
+        })
+    }
+
+    /// 获取文件系统使用情况
+    pub fn statfs(&self) -> io::Result<StatFs> {
+        Ok(StatFs {
+            block_size: S::BLOCK_SIZE,
+            total_blocks: self.fs.total_blocks(),
+            free_blocks: self.fs.available_blocks()?,
+        })
+    }
+}
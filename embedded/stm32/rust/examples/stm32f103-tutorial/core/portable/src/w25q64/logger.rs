@@ -0,0 +1,647 @@
+//! 掉电安全的循环数据记录器
+//! 在 NOR FLASH 的一段区域内循环追加变长记录，写满后擦除最旧的扇区
+//!
+//! 扇区布局:
+//! | 扇区头(16字节) | 记录 | 记录 | ... | 0xFF |
+//! 扇区头: 魔数(u32) + 扇区序号(u32) + 首条记录序号(u32) + CRC32(u32)
+//! 记录:   长度(u16) + 记录序号(u32) + 时间戳(u32) + CRC32(u32) + 数据
+//!
+//! 写入过程中掉电只会产生一条 CRC 校验失败或长度字段无效的记录，
+//! 挂载时该扇区被视为已写满，后续记录写入下一个扇区。
+//!
+//! 写入粒度大于1字节的 FLASH(如内部 FLASH 按半字编程)，每条记录的长度向上对齐到写入粒度，
+//! 对齐的部分填充 0xFF。擦除扇区耗时较长，可以在空闲时调用 `prepare` 提前擦除下一个扇区，
+//! 追加记录时只需写入扇区头。
+//!
+//! ```rust
+//! let mut logger = DataLogger::mount(&mut w25q, 0x000000, 0x100000).unwrap();
+//! logger.append(timestamp, &payload).unwrap();
+//! logger.prepare().unwrap();
+//! logger.dump_csv(&mut serial.tx, &mut buffer, logger::write_i16_columns).unwrap();
+//! ```
+
+use core::fmt;
+
+use embedded_storage::nor_flash::NorFlash;
+
+/// 扇区头魔数
+const SECTOR_MAGIC: u32 = 0x4C4F_4731;
+/// 扇区头大小
+const SECTOR_HEADER_SIZE: u32 = 16;
+/// 记录头大小
+const RECORD_HEADER_SIZE: u32 = 14;
+/// 未写入区域的长度字段
+const RECORD_LEN_ERASED: u16 = 0xFFFF;
+/// 支持的最大写入粒度，扇区头按该粒度对齐
+const MAX_WRITE_SIZE: usize = SECTOR_HEADER_SIZE as usize;
+/// 写入记录时的分块大小，需要是写入粒度的整数倍
+const WRITE_CHUNK_SIZE: usize = 32;
+
+/// 记录器错误
+#[derive(Debug)]
+pub enum Error<E> {
+    /// FLASH 读写错误
+    Flash(E),
+    /// 记录超过单个扇区可容纳的长度
+    TooLarge,
+    /// 缓冲区不足以存放记录数据
+    BufferTooSmall,
+    /// 格式化输出错误
+    Fmt,
+    /// FLASH 的读写粒度不受支持
+    /// 要求按字节读取，写入粒度为不超过16字节的2的幂
+    UnsupportedGranularity,
+    /// 区域未按扇区对齐、少于2个扇区或超出 FLASH 容量
+    InvalidRegion,
+}
+
+/// 记录信息，数据存放在调用者提供的缓冲区中
+#[derive(Debug, Clone, Copy)]
+pub struct Record {
+    /// 记录序号
+    pub seq: u32,
+    /// 时间戳
+    pub timestamp: u32,
+    /// 数据长度
+    pub len: usize,
+}
+
+/// 遍历记录的位置
+#[derive(Debug, Clone, Copy)]
+pub struct Cursor {
+    sector: u32,
+    offset: u32,
+}
+
+/// 扇区头
+struct SectorHeader {
+    seq: u32,
+    first_record_seq: u32,
+}
+
+/// 记录头
+struct RecordHeader {
+    len: u16,
+    seq: u32,
+    timestamp: u32,
+    crc: u32,
+}
+
+/// 循环数据记录器
+pub struct DataLogger<F>
+where
+    F: NorFlash,
+{
+    flash: F,
+    /// 区域起始地址
+    start: u32,
+    /// 区域内扇区数量
+    sector_count: u32,
+    /// 最旧的扇区
+    tail: u32,
+    /// 当前写入的扇区
+    head: u32,
+    /// 当前扇区的写入位置
+    head_offset: u32,
+    /// 当前扇区的序号
+    sector_seq: u32,
+    /// 下一条记录的序号
+    next_seq: u32,
+    /// 下一个扇区已经提前擦除
+    next_erased: bool,
+}
+
+impl<F> DataLogger<F>
+where
+    F: NorFlash,
+{
+    /// 扇区大小
+    const SECTOR_SIZE: u32 = F::ERASE_SIZE as u32;
+
+    /// 写入粒度
+    const WRITE_SIZE: u32 = F::WRITE_SIZE as u32;
+
+    /// 单条记录数据的最大长度
+    pub const MAX_RECORD_LEN: usize =
+        (Self::SECTOR_SIZE - SECTOR_HEADER_SIZE - RECORD_HEADER_SIZE) as usize;
+
+    /// 挂载记录器
+    /// 二分查找扇区头定位最新的扇区，再扫描该扇区找到写入位置
+    /// 区域内没有有效数据时自动初始化
+    /// start: 区域起始地址，需要按扇区对齐
+    /// end: 区域结束地址，需要按扇区对齐，区域至少包含2个扇区
+    pub fn mount(flash: F, start: u32, end: u32) -> Result<Self, Error<F::Error>> {
+        if F::READ_SIZE != 1
+            || !F::WRITE_SIZE.is_power_of_two()
+            || F::WRITE_SIZE > MAX_WRITE_SIZE
+            || !WRITE_CHUNK_SIZE.is_multiple_of(F::WRITE_SIZE)
+        {
+            return Err(Error::UnsupportedGranularity);
+        }
+        if !start.is_multiple_of(Self::SECTOR_SIZE)
+            || !end.is_multiple_of(Self::SECTOR_SIZE)
+            || end.saturating_sub(start) < 2 * Self::SECTOR_SIZE
+            || end as usize > flash.capacity()
+        {
+            return Err(Error::InvalidRegion);
+        }
+
+        let sector_count = (end - start) / Self::SECTOR_SIZE;
+
+        let mut logger = DataLogger {
+            flash,
+            start,
+            sector_count,
+            tail: 0,
+            head: 0,
+            head_offset: SECTOR_HEADER_SIZE,
+            sector_seq: 0,
+            next_seq: 0,
+            next_erased: false,
+        };
+
+        // 回收第0扇区时掉电，第0扇区会处于擦除状态，此时以第1扇区为基准
+        let (base, base_seq) = match logger.read_sector_header(0)? {
+            Some(header) => (0, header.seq),
+            None => match logger.read_sector_header(1)? {
+                Some(header) => (1, header.seq),
+                None => {
+                    // 第一次使用
+                    let address = logger.address(0, 0);
+                    logger
+                        .flash
+                        .erase(address, address + Self::SECTOR_SIZE)
+                        .map_err(Error::Flash)?;
+                    logger.init_sector(0, 0, 0)?;
+                    return Ok(logger);
+                }
+            },
+        };
+
+        // 从基准扇区开始，序号不小于基准序号的扇区构成连续的前缀
+        // 二分查找该前缀的最后一个扇区，即最新的扇区
+        let mut lo = base;
+        let mut hi = sector_count;
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            match logger.read_sector_header(mid)? {
+                Some(header) if header.seq >= base_seq => lo = mid,
+                _ => hi = mid,
+            }
+        }
+        let head = lo;
+        let header = logger.read_sector_header(head)?.unwrap();
+
+        // 最新扇区之后的第一个有效扇区即为最旧的扇区
+        let mut tail = head;
+        for i in 1..sector_count {
+            let sector = (head + i) % sector_count;
+            if logger.read_sector_header(sector)?.is_some() {
+                tail = sector;
+                break;
+            }
+        }
+
+        logger.head = head;
+        logger.tail = tail;
+        logger.sector_seq = header.seq;
+        logger.next_seq = header.first_record_seq;
+
+        // 扫描最新扇区，找到写入位置
+        let mut offset = SECTOR_HEADER_SIZE;
+        loop {
+            match logger.read_record_header(head, offset)? {
+                None => {
+                    // 记录头写到一半时掉电，长度字段可能无效，写入位置之后必须全部是擦除状态
+                    if !logger.is_erased(head, offset)? {
+                        offset = Self::SECTOR_SIZE;
+                    }
+                    break;
+                }
+                Some(record) => {
+                    if !logger.check_record(head, offset, &record)? {
+                        // 写入时掉电，该扇区不再写入
+                        offset = Self::SECTOR_SIZE;
+                        break;
+                    }
+                    offset += Self::record_size(record.len);
+                    logger.next_seq = record.seq.wrapping_add(1);
+                }
+            }
+        }
+        logger.head_offset = offset;
+
+        Ok(logger)
+    }
+
+    /// 追加一条记录
+    /// timestamp: 时间戳
+    /// data: 记录数据
+    /// 返回记录序号
+    pub fn append(&mut self, timestamp: u32, data: &[u8]) -> Result<u32, Error<F::Error>> {
+        if data.len() > Self::MAX_RECORD_LEN {
+            return Err(Error::TooLarge);
+        }
+
+        let len = data.len() as u32;
+        if self.head_offset + Self::record_size(len as u16) > Self::SECTOR_SIZE {
+            self.rotate()?;
+        }
+
+        let seq = self.next_seq;
+        let mut header = [0; RECORD_HEADER_SIZE as usize];
+        header[0..2].copy_from_slice(&(len as u16).to_le_bytes());
+        header[2..6].copy_from_slice(&seq.to_le_bytes());
+        header[6..10].copy_from_slice(&timestamp.to_le_bytes());
+        let crc = crc32_finish(crc32_update(crc32_update(CRC32_INIT, &header[0..10]), data));
+        header[10..14].copy_from_slice(&crc.to_le_bytes());
+
+        let address = self.address(self.head, self.head_offset);
+        self.write_record(address, &header, data)?;
+
+        self.head_offset += Self::record_size(len as u16);
+        self.next_seq = seq.wrapping_add(1);
+        Ok(seq)
+    }
+
+    /// 提前擦除下一个扇区
+    /// 在空闲时调用，写满当前扇区时不需要再等待擦除
+    /// 下一个扇区是最旧的扇区时，其中的记录会被提前丢弃
+    pub fn prepare(&mut self) -> Result<(), Error<F::Error>> {
+        if !self.next_erased {
+            self.erase_next()?;
+        }
+        Ok(())
+    }
+
+    /// 清空所有记录
+    pub fn clear(&mut self) -> Result<(), Error<F::Error>> {
+        let end = self.start + self.sector_count * Self::SECTOR_SIZE;
+        self.flash.erase(self.start, end).map_err(Error::Flash)?;
+
+        self.tail = 0;
+        self.head = 0;
+        self.init_sector(0, 0, 0)?;
+        // 所有扇区都已擦除
+        self.next_erased = true;
+        Ok(())
+    }
+
+    /// 最旧记录的位置，用于向后遍历
+    pub fn first(&self) -> Cursor {
+        Cursor {
+            sector: self.tail,
+            offset: SECTOR_HEADER_SIZE,
+        }
+    }
+
+    /// 最新记录之后的位置，用于向前遍历
+    pub fn last(&self) -> Cursor {
+        Cursor {
+            sector: self.head,
+            offset: self.head_offset,
+        }
+    }
+
+    /// 读取光标处的记录，并将光标移动到下一条记录
+    /// 没有更多记录时返回 None
+    pub fn read_next(
+        &mut self,
+        cursor: &mut Cursor,
+        buf: &mut [u8],
+    ) -> Result<Option<Record>, Error<F::Error>> {
+        loop {
+            if cursor.sector == self.head && cursor.offset >= self.head_offset {
+                return Ok(None);
+            }
+
+            if let Some(header) = self.read_record_header(cursor.sector, cursor.offset)? {
+                if let Some(record) =
+                    self.read_record(cursor.sector, cursor.offset, &header, buf)?
+                {
+                    cursor.offset += Self::record_size(header.len);
+                    return Ok(Some(record));
+                }
+            }
+
+            // 扇区结束，转到下一个扇区
+            if cursor.sector == self.head {
+                return Ok(None);
+            }
+            cursor.sector = (cursor.sector + 1) % self.sector_count;
+            cursor.offset = SECTOR_HEADER_SIZE;
+        }
+    }
+
+    /// 读取光标之前的记录，并将光标移动到该记录
+    /// 没有更多记录时返回 None
+    pub fn read_prev(
+        &mut self,
+        cursor: &mut Cursor,
+        buf: &mut [u8],
+    ) -> Result<Option<Record>, Error<F::Error>> {
+        loop {
+            // 记录没有反向指针，从扇区开头扫描找到光标之前的最后一条记录
+            let mut prev = None;
+            let mut offset = SECTOR_HEADER_SIZE;
+            while offset < cursor.offset {
+                let Some(header) = self.read_record_header(cursor.sector, offset)? else {
+                    break;
+                };
+                if !self.check_record(cursor.sector, offset, &header)? {
+                    break;
+                }
+                prev = Some(offset);
+                offset += Self::record_size(header.len);
+            }
+
+            if let Some(offset) = prev {
+                let header = self.read_record_header(cursor.sector, offset)?.unwrap();
+                cursor.offset = offset;
+                return self.read_record(cursor.sector, offset, &header, buf);
+            }
+
+            // 扇区开始，转到上一个扇区
+            if cursor.sector == self.tail {
+                return Ok(None);
+            }
+            cursor.sector = (cursor.sector + self.sector_count - 1) % self.sector_count;
+            cursor.offset = Self::SECTOR_SIZE;
+        }
+    }
+
+    /// 以 CSV 格式导出所有记录
+    /// 每行为: 序号,时间戳,数据列...
+    /// w: 输出目标，例如串口 Tx
+    /// buf: 读取记录的缓冲区
+    /// columns: 将记录数据格式化为数据列
+    pub fn dump_csv<W, C>(
+        &mut self,
+        w: &mut W,
+        buf: &mut [u8],
+        mut columns: C,
+    ) -> Result<(), Error<F::Error>>
+    where
+        W: fmt::Write,
+        C: FnMut(&mut W, &[u8]) -> fmt::Result,
+    {
+        let mut cursor = self.first();
+        while let Some(record) = self.read_next(&mut cursor, buf)? {
+            write!(w, "{},{}", record.seq, record.timestamp).map_err(|_| Error::Fmt)?;
+            columns(w, &buf[..record.len]).map_err(|_| Error::Fmt)?;
+            w.write_str("\r\n").map_err(|_| Error::Fmt)?;
+        }
+        Ok(())
+    }
+
+    /// 扇区内偏移对应的 FLASH 地址
+    fn address(&self, sector: u32, offset: u32) -> u32 {
+        self.start + sector * Self::SECTOR_SIZE + offset
+    }
+
+    /// 记录头和数据按写入粒度对齐后占用的长度
+    fn record_size(len: u16) -> u32 {
+        (RECORD_HEADER_SIZE + len as u32).next_multiple_of(Self::WRITE_SIZE)
+    }
+
+    /// 分块写入记录头和数据，末尾不足写入粒度的部分填充 0xFF
+    fn write_record(
+        &mut self,
+        mut address: u32,
+        header: &[u8],
+        data: &[u8],
+    ) -> Result<(), Error<F::Error>> {
+        let mut chunk = [0xFF; WRITE_CHUNK_SIZE];
+        let mut filled = 0;
+        for byte in header.iter().chain(data) {
+            chunk[filled] = *byte;
+            filled += 1;
+            if filled == chunk.len() {
+                self.flash.write(address, &chunk).map_err(Error::Flash)?;
+                address += chunk.len() as u32;
+                filled = 0;
+            }
+        }
+
+        if filled > 0 {
+            let len = filled.next_multiple_of(F::WRITE_SIZE);
+            chunk[filled..len].fill(0xFF);
+            self.flash
+                .write(address, &chunk[..len])
+                .map_err(Error::Flash)?;
+        }
+        Ok(())
+    }
+
+    /// 擦除下一个扇区，写满时丢弃最旧的扇区
+    fn erase_next(&mut self) -> Result<(), Error<F::Error>> {
+        let next = (self.head + 1) % self.sector_count;
+        if next == self.tail {
+            self.tail = (self.tail + 1) % self.sector_count;
+        }
+
+        let address = self.address(next, 0);
+        self.flash
+            .erase(address, address + Self::SECTOR_SIZE)
+            .map_err(Error::Flash)?;
+        self.next_erased = true;
+        Ok(())
+    }
+
+    /// 切换到下一个扇区，下一个扇区还没有擦除时先擦除
+    fn rotate(&mut self) -> Result<(), Error<F::Error>> {
+        if !self.next_erased {
+            self.erase_next()?;
+        }
+        let next = (self.head + 1) % self.sector_count;
+        self.next_erased = false;
+        self.init_sector(next, self.sector_seq.wrapping_add(1), self.next_seq)
+    }
+
+    /// 写入扇区头，扇区需要提前擦除
+    fn init_sector(
+        &mut self,
+        sector: u32,
+        seq: u32,
+        first_record_seq: u32,
+    ) -> Result<(), Error<F::Error>> {
+        let mut header = [0; SECTOR_HEADER_SIZE as usize];
+        header[0..4].copy_from_slice(&SECTOR_MAGIC.to_le_bytes());
+        header[4..8].copy_from_slice(&seq.to_le_bytes());
+        header[8..12].copy_from_slice(&first_record_seq.to_le_bytes());
+        let crc = crc32_finish(crc32_update(CRC32_INIT, &header[0..12]));
+        header[12..16].copy_from_slice(&crc.to_le_bytes());
+
+        let address = self.address(sector, 0);
+        self.flash.write(address, &header).map_err(Error::Flash)?;
+
+        self.head = sector;
+        self.head_offset = SECTOR_HEADER_SIZE;
+        self.sector_seq = seq;
+        self.next_seq = first_record_seq;
+        Ok(())
+    }
+
+    /// 读取扇区头，无效时返回 None
+    fn read_sector_header(&mut self, sector: u32) -> Result<Option<SectorHeader>, Error<F::Error>> {
+        let mut header = [0; SECTOR_HEADER_SIZE as usize];
+        let address = self.address(sector, 0);
+        self.flash
+            .read(address, &mut header)
+            .map_err(Error::Flash)?;
+
+        let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let crc = u32::from_le_bytes([header[12], header[13], header[14], header[15]]);
+        if magic != SECTOR_MAGIC || crc != crc32_finish(crc32_update(CRC32_INIT, &header[0..12])) {
+            return Ok(None);
+        }
+
+        Ok(Some(SectorHeader {
+            seq: u32::from_le_bytes([header[4], header[5], header[6], header[7]]),
+            first_record_seq: u32::from_le_bytes([header[8], header[9], header[10], header[11]]),
+        }))
+    }
+
+    /// 读取记录头，到达扇区内已写入区域的末尾时返回 None
+    fn read_record_header(
+        &mut self,
+        sector: u32,
+        offset: u32,
+    ) -> Result<Option<RecordHeader>, Error<F::Error>> {
+        if offset + RECORD_HEADER_SIZE > Self::SECTOR_SIZE {
+            return Ok(None);
+        }
+
+        let mut header = [0; RECORD_HEADER_SIZE as usize];
+        let address = self.address(sector, offset);
+        self.flash
+            .read(address, &mut header)
+            .map_err(Error::Flash)?;
+
+        let len = u16::from_le_bytes([header[0], header[1]]);
+        if len == RECORD_LEN_ERASED || offset + RECORD_HEADER_SIZE + len as u32 > Self::SECTOR_SIZE
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(RecordHeader {
+            len,
+            seq: u32::from_le_bytes([header[2], header[3], header[4], header[5]]),
+            timestamp: u32::from_le_bytes([header[6], header[7], header[8], header[9]]),
+            crc: u32::from_le_bytes([header[10], header[11], header[12], header[13]]),
+        }))
+    }
+
+    /// 扇区内从 offset 开始到扇区末尾是否全部处于擦除状态
+    fn is_erased(&mut self, sector: u32, mut offset: u32) -> Result<bool, Error<F::Error>> {
+        let mut chunk = [0; 32];
+        while offset < Self::SECTOR_SIZE {
+            let len = (Self::SECTOR_SIZE - offset).min(chunk.len() as u32) as usize;
+            let address = self.address(sector, offset);
+            self.flash
+                .read(address, &mut chunk[..len])
+                .map_err(Error::Flash)?;
+            if chunk[..len].iter().any(|&byte| byte != 0xFF) {
+                return Ok(false);
+            }
+            offset += len as u32;
+        }
+        Ok(true)
+    }
+
+    /// 分块读取记录数据并校验 CRC
+    fn check_record(
+        &mut self,
+        sector: u32,
+        offset: u32,
+        header: &RecordHeader,
+    ) -> Result<bool, Error<F::Error>> {
+        let mut crc = crc32_update(CRC32_INIT, &header.prefix());
+
+        let mut chunk = [0; 32];
+        let mut address = self.address(sector, offset + RECORD_HEADER_SIZE);
+        let mut remain = header.len as usize;
+        while remain > 0 {
+            let len = remain.min(chunk.len());
+            self.flash
+                .read(address, &mut chunk[..len])
+                .map_err(Error::Flash)?;
+            crc = crc32_update(crc, &chunk[..len]);
+            address += len as u32;
+            remain -= len;
+        }
+
+        Ok(crc32_finish(crc) == header.crc)
+    }
+
+    /// 读取记录数据到缓冲区并校验 CRC，校验失败时返回 None
+    fn read_record(
+        &mut self,
+        sector: u32,
+        offset: u32,
+        header: &RecordHeader,
+        buf: &mut [u8],
+    ) -> Result<Option<Record>, Error<F::Error>> {
+        let len = header.len as usize;
+        if buf.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+
+        let address = self.address(sector, offset + RECORD_HEADER_SIZE);
+        self.flash
+            .read(address, &mut buf[..len])
+            .map_err(Error::Flash)?;
+
+        let crc = crc32_update(crc32_update(CRC32_INIT, &header.prefix()), &buf[..len]);
+        if crc32_finish(crc) != header.crc {
+            return Ok(None);
+        }
+
+        Ok(Some(Record {
+            seq: header.seq,
+            timestamp: header.timestamp,
+            len,
+        }))
+    }
+}
+
+impl RecordHeader {
+    /// 参与 CRC 计算的记录头字段
+    fn prefix(&self) -> [u8; 10] {
+        let mut bytes = [0; 10];
+        bytes[0..2].copy_from_slice(&self.len.to_le_bytes());
+        bytes[2..6].copy_from_slice(&self.seq.to_le_bytes());
+        bytes[6..10].copy_from_slice(&self.timestamp.to_le_bytes());
+        bytes
+    }
+}
+
+/// 将记录数据按小端 i16 解析为 CSV 数据列
+/// 适用于 MPU6050 加速度/角速度、ADC 采样等数据
+pub fn write_i16_columns<W: fmt::Write>(w: &mut W, data: &[u8]) -> fmt::Result {
+    for value in data.chunks_exact(2) {
+        write!(w, ",{}", i16::from_le_bytes([value[0], value[1]]))?;
+    }
+    Ok(())
+}
+
+const CRC32_INIT: u32 = 0xFFFF_FFFF;
+
+/// CRC32(IEEE 802.3) 逐位计算
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn crc32_finish(crc: u32) -> u32 {
+    !crc
+}
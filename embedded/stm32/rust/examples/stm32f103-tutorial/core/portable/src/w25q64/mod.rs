@@ -0,0 +1,12 @@
+//! W25Q64 非易失性存储器
+//! 与具体芯片外设无关的部分: 命令定义、驱动、扩展命令、embedded-storage 接口、数据记录器、littlefs 文件系统及仿真器
+pub mod advanced;
+pub mod conf;
+pub mod driver;
+pub mod emulator;
+#[cfg(feature = "littlefs")]
+pub mod fs;
+pub mod logger;
+pub mod storage;
+
+pub use driver::{Error, W25Q64};
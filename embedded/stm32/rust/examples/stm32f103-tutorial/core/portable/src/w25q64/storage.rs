@@ -0,0 +1,110 @@
+//! embedded-storage 接口实现
+//! 使 W25Q64 可以直接用于 sequential-storage、ekv 等生态库
+
+use super::conf::*;
+use super::driver::{Error, W25Q64};
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::SpiDevice;
+use embedded_storage::nor_flash::{
+    check_erase, check_read, check_write, ErrorType, MultiwriteNorFlash, NorFlash, NorFlashError,
+    NorFlashErrorKind, ReadNorFlash, RmwMultiwriteNorFlashStorage,
+};
+
+impl<E: core::fmt::Debug> NorFlashError for Error<E> {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::NotAligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+impl<E> From<NorFlashErrorKind> for Error<E> {
+    fn from(kind: NorFlashErrorKind) -> Self {
+        match kind {
+            NorFlashErrorKind::NotAligned => Error::NotAligned,
+            _ => Error::OutOfBounds,
+        }
+    }
+}
+
+impl<SPI, D> ErrorType for W25Q64<SPI, D>
+where
+    SPI: SpiDevice,
+    D: DelayNs,
+{
+    type Error = Error<SPI::Error>;
+}
+
+impl<SPI, D> ReadNorFlash for W25Q64<SPI, D>
+where
+    SPI: SpiDevice,
+    D: DelayNs,
+{
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+        self.read_data(offset, bytes)?;
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        W25Q64_CAPACITY
+    }
+}
+
+impl<SPI, D> NorFlash for W25Q64<SPI, D>
+where
+    SPI: SpiDevice,
+    D: DelayNs,
+{
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = W25Q64_SECTOR_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+        for address in (from..to).step_by(W25Q64_SECTOR_SIZE) {
+            self.sector_erase(address)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+        // 调用按页拆分的写入函数
+        W25Q64::write(self, offset, bytes)
+    }
+}
+
+/// 页编程只会将位从1变为0，同一区域可以多次写入
+impl<SPI, D> MultiwriteNorFlash for W25Q64<SPI, D>
+where
+    SPI: SpiDevice,
+    D: DelayNs,
+{
+}
+
+impl<SPI, D> W25Q64<SPI, D>
+where
+    SPI: SpiDevice,
+    D: DelayNs,
+{
+    /// 创建按字节读写的存储适配器
+    /// 写入时自动完成扇区的读取、合并、擦除和回写
+    /// merge_buffer: 扇区合并缓冲区
+    /// ```rust
+    /// use embedded_storage::Storage;
+    /// let mut buffer = [0; W25Q64_SECTOR_SIZE];
+    /// let mut storage = w25q.storage(&mut buffer);
+    /// storage.write(0x000010, b"hello").unwrap();
+    /// ```
+    pub fn storage<'b>(
+        &'b mut self,
+        merge_buffer: &'b mut [u8; W25Q64_SECTOR_SIZE],
+    ) -> RmwMultiwriteNorFlashStorage<'b, &'b mut Self> {
+        RmwMultiwriteNorFlashStorage::new(self, merge_buffer)
+    }
+}
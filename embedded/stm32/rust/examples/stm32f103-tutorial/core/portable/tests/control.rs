@@ -0,0 +1,527 @@
+//! PID 控制器、梯形速度规划、调参命令和电机闭环控制器的测试，电机使用一阶惯性模型
+
+use std::cell::RefCell;
+
+use portable::control::sim::{SimDrive, SimMotor, SIM_MAX_DUTY};
+use portable::control::{
+    Command, CommandReader, ControlMode, ControllerConfig, DutyOutput, Encoder, Fixed, Gain, Loop,
+    MotorController, ParseError, Pid, Scalar, Setpoint, Trapezoid,
+};
+
+const DT: f32 = 0.01;
+
+/// 速度环，输出为占空比(‰)
+fn speed_pid() -> Pid {
+    let max = SIM_MAX_DUTY as f32;
+    Pid::new(0.2, 2.0, 0.0).output_limits(-max, max)
+}
+
+/// 以速度环驱动电机模型运行 `steps` 个周期
+fn run(pid: &mut Pid, motor: &mut SimMotor, setpoint: f32, steps: usize) {
+    for _ in 0..steps {
+        let duty = pid.update(setpoint, motor.speed(), DT);
+        motor.set_drive(SimDrive::Duty(duty as i16));
+        motor.step(DT);
+    }
+}
+
+#[test]
+fn step_response_converges() {
+    let mut motor = SimMotor::new(6000.0, 0.05);
+    motor.stiction = 30;
+    motor.load = 50;
+    let mut pid = speed_pid();
+
+    run(&mut pid, &mut motor, 1000.0, 300);
+    assert!(
+        (motor.speed() - 1000.0).abs() < 10.0,
+        "speed {}",
+        motor.speed()
+    );
+
+    // 反向也能稳定，负载由积分项补偿
+    run(&mut pid, &mut motor, -1500.0, 300);
+    assert!(
+        (motor.speed() + 1500.0).abs() < 15.0,
+        "speed {}",
+        motor.speed()
+    );
+    assert!(pid.integral() < 0.0);
+}
+
+#[test]
+fn anti_windup_bounds_integral() {
+    // 满占空比也达不到目标速度，输出一直饱和
+    let mut motor = SimMotor::new(1000.0, 0.05);
+    let mut pid = speed_pid();
+    run(&mut pid, &mut motor, 3000.0, 500);
+    // 饱和后停止积分，积分项加比例项不超过输出上限，输出停在上限附近
+    let terms = pid.terms();
+    assert!(terms.output > 0.95 * SIM_MAX_DUTY as f32, "{terms:?}");
+    assert!(terms.i + terms.p <= SIM_MAX_DUTY as f32 + 1.0, "{terms:?}");
+
+    // 目标值降低后很快退出饱和
+    run(&mut pid, &mut motor, 500.0, 20);
+    assert!(pid.terms().output < SIM_MAX_DUTY as f32);
+    run(&mut pid, &mut motor, 500.0, 300);
+    assert!(
+        (motor.speed() - 500.0).abs() < 5.0,
+        "speed {}",
+        motor.speed()
+    );
+}
+
+#[test]
+fn integral_clamped_to_output_limits() {
+    let mut pid = Pid::new(0.0, 100.0, 0.0).output_limits(-10.0, 10.0);
+    for _ in 0..100 {
+        pid.update(1.0, 0.0, DT);
+    }
+    assert_eq!(pid.integral(), 10.0);
+
+    // 缩小输出范围时积分项也被限制
+    pid.set_output_limits(5.0, -5.0);
+    assert_eq!(pid.get_output_limits(), (-5.0, 5.0));
+    assert_eq!(pid.integral(), 5.0);
+}
+
+#[test]
+fn derivative_on_measurement_has_no_kick() {
+    let mut pid = Pid::new(0.0, 0.0, 1.0).output_limits(-1000.0, 1000.0);
+    pid.update(0.0, 5.0, DT);
+    pid.update(0.0, 5.0, DT);
+
+    // 目标值跳变，测量值不变，微分项为0
+    let output = pid.update(100.0, 5.0, DT);
+    assert_eq!(pid.terms().d, 0.0);
+    assert_eq!(output, 0.0);
+
+    // 测量值增大时微分项为负
+    pid.update(100.0, 6.0, DT);
+    assert!((pid.terms().d + 100.0).abs() < 1e-3, "{:?}", pid.terms());
+
+    // 零点平移后微分项不跳变
+    pid.shift(-6.0);
+    pid.update(94.0, 0.0, DT);
+    assert_eq!(pid.terms().d, 0.0);
+}
+
+#[test]
+fn feedforward_adds_to_output() {
+    let mut pid = Pid::new(0.0, 0.0, 0.0)
+        .feedforward(0.5)
+        .output_limits(-100.0, 100.0);
+    assert_eq!(pid.update_with_feedforward(100.0, 0.0, 10.0, DT), 60.0);
+    assert_eq!(pid.terms().f, 60.0);
+}
+
+#[test]
+fn fixed_point_pid_matches_f32() {
+    let mut pid = Pid::new(0.5, 1.0, 0.0).output_limits(-100.0, 100.0);
+    let mut fixed = Pid::new(Fixed::from_f32(0.5), Fixed::from_f32(1.0), Fixed::ZERO)
+        .output_limits(Fixed::from_int(-100), Fixed::from_int(100));
+    let dt = Fixed::from_f32(DT);
+    for i in 0..50 {
+        let measurement = i as f32;
+        let a = pid.update(40.0, measurement, DT);
+        let b = fixed.update(Fixed::from_int(40), Fixed::from_f32(measurement), dt);
+        assert!((a - b.to_f32()).abs() < 0.05, "{a} != {}", b.to_f32());
+    }
+}
+
+/// 运行梯形规划直到完成，检查速度和加速度限制，返回经过的周期数
+fn run_profile(profile: &mut Trapezoid, target: f32) -> usize {
+    let start = profile.setpoint().position;
+    let direction = (target - start).signum();
+    profile.set_target(target);
+
+    let mut last = profile.setpoint();
+    for steps in 1..10_000 {
+        let Setpoint { position, velocity } = profile.update(DT);
+        // 不超过最大速度，不反向，不越过目标位置
+        assert!(velocity.abs() <= profile.max_velocity + 1e-3);
+        assert!(velocity * direction >= 0.0, "velocity {velocity}");
+        assert!((position - last.position) * direction >= -1e-3);
+        assert!(
+            (target - position) * direction >= -1e-3,
+            "overshoot {position}"
+        );
+        if profile.acceleration > 0.0 {
+            // 到达目标位置时剩余的速度不超过两个周期的变化量，直接停下
+            let step = profile.acceleration * DT;
+            let limit = if profile.is_done() { 2.0 * step } else { step };
+            assert!((velocity - last.velocity).abs() <= limit + 1e-2);
+        }
+        last = Setpoint { position, velocity };
+        if profile.is_done() {
+            return steps;
+        }
+    }
+    panic!("profile did not finish");
+}
+
+#[test]
+fn trapezoid_reaches_target_without_overshoot() {
+    let mut profile = Trapezoid::new(1000.0, 2000.0);
+    profile.reset(0.0, 0.0);
+
+    // 加速 0.5s、匀速、减速 0.5s，约 2s
+    let steps = run_profile(&mut profile, 1500.0);
+    assert!((195..=215).contains(&steps), "steps {steps}");
+    assert_eq!(profile.setpoint().position, 1500.0);
+
+    // 距离较短时为三角形
+    let steps = run_profile(&mut profile, 1400.0);
+    assert!(steps < 60, "steps {steps}");
+    assert_eq!(profile.setpoint().position, 1400.0);
+}
+
+#[test]
+fn trapezoid_retarget_while_moving() {
+    let mut profile = Trapezoid::new(1000.0, 2000.0);
+    profile.reset(0.0, 0.0);
+    profile.set_target(2000.0);
+    for _ in 0..80 {
+        profile.update(DT);
+    }
+    assert!(profile.setpoint().velocity > 0.0);
+
+    // 运动中改为反方向的目标，先减速再反向
+    profile.set_target(-500.0);
+    let mut last = profile.setpoint().velocity;
+    for _ in 0..10_000 {
+        let Setpoint { velocity, .. } = profile.update(DT);
+        assert!((velocity - last).abs() <= 2.0 * 2000.0 * DT + 1e-2);
+        last = velocity;
+        if profile.is_done() {
+            break;
+        }
+    }
+    assert!(profile.is_done());
+    assert_eq!(profile.setpoint().position, -500.0);
+}
+
+#[test]
+fn trapezoid_without_acceleration_limit() {
+    for acceleration in [0.0, -100.0] {
+        let mut profile = Trapezoid::new(1000.0, acceleration);
+        profile.reset(0.0, 0.0);
+        // 每周期移动 10，105 需要 11 个周期
+        assert_eq!(run_profile(&mut profile, 105.0), 11);
+        let stopped = Setpoint {
+            position: 105.0,
+            velocity: 0.0,
+        };
+        assert_eq!(profile.setpoint(), stopped);
+    }
+}
+
+#[test]
+fn commands_parse() {
+    let gain = |target, gain, value| Command::Gain {
+        target,
+        gain,
+        value,
+    };
+    let cases = [
+        ("kp 0.2", gain(Loop::Speed, Gain::Kp, 0.2)),
+        ("ki 2", gain(Loop::Speed, Gain::Ki, 2.0)),
+        ("kd 0", gain(Loop::Speed, Gain::Kd, 0.0)),
+        ("kf 0.1", gain(Loop::Speed, Gain::Kf, 0.1)),
+        ("pkp 5", gain(Loop::Position, Gain::Kp, 5.0)),
+        ("pki 0.5", gain(Loop::Position, Gain::Ki, 0.5)),
+        ("pkd 1", gain(Loop::Position, Gain::Kd, 1.0)),
+        ("pkf 0.8", gain(Loop::Position, Gain::Kf, 0.8)),
+        ("speed -1000", Command::Speed(-1000.0)),
+        ("goto 2000", Command::MoveTo(2000)),
+        ("  move   -500 ", Command::MoveBy(-500)),
+        ("vmax 3000", Command::MaxVelocity(3000.0)),
+        ("accel 0", Command::Acceleration(0.0)),
+        ("stop", Command::Stop),
+        ("idle", Command::Idle),
+        ("zero", Command::Zero),
+        ("status", Command::Status),
+        ("?", Command::Status),
+    ];
+    for (line, command) in cases {
+        assert_eq!(line.parse(), Ok(command), "{line}");
+    }
+
+    let errors = [
+        ("", ParseError::Empty),
+        ("jump 1", ParseError::UnknownCommand),
+        ("kp", ParseError::MissingValue),
+        ("goto 1.5", ParseError::InvalidValue),
+        ("speed fast", ParseError::InvalidValue),
+        ("stop now", ParseError::TrailingInput),
+    ];
+    for (line, error) in errors {
+        assert_eq!(line.parse::<Command>(), Err(error), "{line}");
+    }
+}
+
+#[test]
+fn command_reader_lines() {
+    let mut reader: CommandReader<8> = CommandReader::new();
+    let mut feed = |input: &[u8]| -> Vec<Result<Command, ParseError>> {
+        input.iter().filter_map(|&byte| reader.push(byte)).collect()
+    };
+
+    // 忽略 \r 和空行
+    assert_eq!(
+        feed(b"stop\r\n\r\ngoto 5\n"),
+        [Ok(Command::Stop), Ok(Command::MoveTo(5))]
+    );
+    // 超过缓冲区的行报错，之后恢复正常
+    assert_eq!(feed(b"speed 123456\n"), [Err(ParseError::TooLong)]);
+    assert_eq!(feed(b"idle\n"), [Ok(Command::Idle)]);
+}
+
+/// 电机模型的占空比输出
+struct SimOutput<'a>(&'a RefCell<SimMotor>);
+
+impl DutyOutput for SimOutput<'_> {
+    fn set_duty(&mut self, permille: i16) {
+        self.0.borrow_mut().set_drive(SimDrive::Duty(permille));
+    }
+
+    fn coast(&mut self) {
+        self.0.borrow_mut().set_drive(SimDrive::Coast);
+    }
+
+    fn brake(&mut self) {
+        self.0.borrow_mut().set_drive(SimDrive::Brake);
+    }
+}
+
+/// 电机模型的编码器，计数器初值为 `offset`
+struct SimEncoder<'a> {
+    motor: &'a RefCell<SimMotor>,
+    offset: u16,
+}
+
+impl Encoder for SimEncoder<'_> {
+    fn count(&self) -> u16 {
+        let position = self.motor.borrow().position().round() as i32;
+        self.offset.wrapping_add(position as u16)
+    }
+}
+
+type SimController<'a> = MotorController<SimOutput<'a>, SimEncoder<'a>>;
+
+fn controller(motor: &RefCell<SimMotor>, offset: u16) -> SimController<'_> {
+    let config = ControllerConfig {
+        max_velocity: 2000.0,
+        acceleration: 4000.0,
+        ..ControllerConfig::default()
+    };
+    let encoder = SimEncoder { motor, offset };
+    MotorController::new(SimOutput(motor), encoder, config)
+}
+
+fn sim_motor() -> RefCell<SimMotor> {
+    let mut motor = SimMotor::new(6000.0, 0.05);
+    motor.stiction = 30;
+    motor.load = 50;
+    RefCell::new(motor)
+}
+
+/// 控制器和电机模型同步运行 `steps` 个周期
+fn run_controller(controller: &mut SimController, motor: &RefCell<SimMotor>, steps: usize) {
+    for _ in 0..steps {
+        controller.update();
+        motor.borrow_mut().step(DT);
+    }
+}
+
+/// 运行到位置模式到位，返回经过的周期数
+fn run_until_settled(controller: &mut SimController, motor: &RefCell<SimMotor>) -> usize {
+    for steps in 1..5000 {
+        run_controller(controller, motor, 1);
+        if controller.is_settled() {
+            return steps;
+        }
+    }
+    panic!("controller did not settle at {}", controller.position());
+}
+
+/// 梯形规划当前的绝对位置
+fn planned_position(controller: &SimController) -> f64 {
+    controller.profile_origin() as f64 + controller.profile_setpoint().position as f64
+}
+
+#[test]
+fn controller_speed_and_position_cascade() {
+    let motor = sim_motor();
+    let mut controller = controller(&motor, 0);
+    assert_eq!(controller.mode(), ControlMode::Idle);
+
+    // 速度环按加速度爬升，稳定在目标速度，负载由积分项补偿
+    controller.set_speed(1000.0);
+    run_controller(&mut controller, &motor, 10);
+    assert!((controller.speed_setpoint() - 400.0).abs() < 1e-3);
+    run_controller(&mut controller, &motor, 300);
+    let speed = motor.borrow().speed();
+    assert!((speed - 1000.0).abs() < 10.0, "speed {speed}");
+    assert!((controller.speed() - 1000.0).abs() < 50.0);
+    assert!(controller.output() > 0);
+
+    // 位置环叠加在速度环外，运动到目标位置并停住
+    controller.move_to(5000);
+    assert_eq!(controller.mode(), ControlMode::Position);
+    run_until_settled(&mut controller, &motor);
+    // 静摩擦使电机在目标位置附近小幅振荡
+    let mut sum = 0;
+    for _ in 0..100 {
+        run_controller(&mut controller, &motor, 1);
+        let position = controller.position();
+        assert!((position - 5000).abs() <= 10, "position {position}");
+        sum += position;
+    }
+    assert!((sum / 100 - 5000).abs() <= 5, "average {}", sum / 100);
+}
+
+#[test]
+fn controller_position_survives_count_wrap() {
+    // 计数器从 65000 开始，正反转都会越过16位计数的边界
+    let motor = sim_motor();
+    let mut controller = controller(&motor, 65000);
+    controller.set_speed(2000.0);
+    run_controller(&mut controller, &motor, 300);
+    let position = controller.position();
+    assert!(position > 3000, "position {position}");
+    // 控制器在电机模型前进之前读取计数，落后一个周期
+    let lag = motor.borrow().position() - position as f32;
+    assert!((0.0..=2000.0 * DT + 1.0).contains(&lag), "lag {lag}");
+
+    controller.move_to(-2000);
+    run_until_settled(&mut controller, &motor);
+    assert!((controller.position() + 2000).abs() <= 5);
+    assert!((motor.borrow().position() + 2000.0).abs() <= 6.0);
+}
+
+#[test]
+fn controller_move_to_shifts_origin_without_jump() {
+    let motor = sim_motor();
+    let mut controller = controller(&motor, 0);
+    controller.move_to(10_000);
+    assert_eq!(controller.profile_origin(), 0);
+    run_controller(&mut controller, &motor, 100);
+
+    // 运动中改变目标，原点移到规划位置的整数部分，规划的绝对位置和速度不变
+    let before = planned_position(&controller);
+    let velocity = controller.profile_setpoint().velocity;
+    assert!(velocity > 0.0);
+    controller.move_to(3000);
+    let setpoint = controller.profile_setpoint();
+    assert_eq!(controller.profile_origin(), before as i32);
+    assert!((0.0..1.0).contains(&setpoint.position), "{setpoint:?}");
+    assert!((planned_position(&controller) - before).abs() < 1e-3);
+    assert_eq!(setpoint.velocity, velocity);
+    assert_eq!(controller.target_position(), 3000);
+
+    // 先减速再反向，加速度不超过限制
+    let mut last = controller.profile_setpoint().velocity;
+    for _ in 0..50 {
+        run_controller(&mut controller, &motor, 1);
+        let velocity = controller.profile_setpoint().velocity;
+        assert!((velocity - last).abs() <= 2.0 * 4000.0 * DT + 1e-2);
+        last = velocity;
+    }
+    run_until_settled(&mut controller, &motor);
+    assert!((controller.position() - 3000).abs() <= 5);
+
+    // 相对移动以目标位置为基准
+    controller.move_by(-500);
+    assert_eq!(controller.target_position(), 2500);
+}
+
+#[test]
+fn controller_stop_decelerates() {
+    // 位置模式匀速运动时减速停止，停止距离为 v²/2a
+    let motor = sim_motor();
+    let mut controller = controller(&motor, 0);
+    controller.move_to(100_000);
+    run_controller(&mut controller, &motor, 200);
+    let velocity = controller.profile_setpoint().velocity;
+    assert_eq!(velocity, 2000.0);
+    let start = planned_position(&controller);
+    controller.stop();
+    let expected = start + (velocity * velocity / (2.0 * 4000.0)) as f64;
+    assert!((controller.target_position() as f64 - expected).abs() <= 1.0);
+
+    let mut last = velocity;
+    for _ in 0..60 {
+        run_controller(&mut controller, &motor, 1);
+        let velocity = controller.profile_setpoint().velocity;
+        assert!(velocity <= last && last - velocity <= 2.0 * 4000.0 * DT + 1e-2);
+        last = velocity;
+    }
+    assert_eq!(last, 0.0);
+    run_until_settled(&mut controller, &motor);
+    assert!((controller.position() as f64 - expected).abs() <= 6.0);
+    assert_eq!(controller.mode(), ControlMode::Position);
+
+    // 速度模式减速到0，保持速度闭环
+    controller.set_speed(-1500.0);
+    run_controller(&mut controller, &motor, 200);
+    controller.stop();
+    let mut last = controller.speed_setpoint();
+    for _ in 0..50 {
+        run_controller(&mut controller, &motor, 1);
+        let setpoint = controller.speed_setpoint();
+        assert!(setpoint >= last && setpoint - last <= 4000.0 * DT + 1e-3);
+        last = setpoint;
+    }
+    assert_eq!(last, 0.0);
+    run_controller(&mut controller, &motor, 200);
+    let start = motor.borrow().position();
+    run_controller(&mut controller, &motor, 100);
+    let average = (motor.borrow().position() - start) / (100.0 * DT);
+    assert!(average.abs() < 10.0, "speed {average}");
+    assert_eq!(controller.mode(), ControlMode::Speed);
+}
+
+#[test]
+fn controller_enters_from_idle_without_bump() {
+    // 高速运行时切换到空闲，电机滑行，控制器仍然测速
+    let motor = sim_motor();
+    let mut controller = controller(&motor, 0);
+    controller.set_speed(1500.0);
+    run_controller(&mut controller, &motor, 200);
+    controller.idle();
+    assert_eq!(motor.borrow().drive(), SimDrive::Coast);
+    run_controller(&mut controller, &motor, 20);
+    assert_eq!(controller.output(), 0);
+    let speed = controller.speed();
+    assert!((500.0..1500.0).contains(&speed), "speed {speed}");
+
+    // 进入速度模式时目标速度从当前速度开始变化，不会阶跃到0
+    controller.set_speed(0.0);
+    assert_eq!(controller.speed_setpoint(), speed);
+    run_controller(&mut controller, &motor, 1);
+    assert!((controller.speed_setpoint() - (speed - 4000.0 * DT)).abs() < 1e-3);
+
+    // 进入位置模式时规划从当前位置和速度开始
+    controller.set_speed(1500.0);
+    run_controller(&mut controller, &motor, 200);
+    controller.brake();
+    assert_eq!(motor.borrow().drive(), SimDrive::Brake);
+    run_controller(&mut controller, &motor, 2);
+    let speed = controller.speed();
+    let position = controller.position();
+    assert!(speed > 100.0, "speed {speed}");
+    controller.move_to(position + 20_000);
+    assert_eq!(controller.profile_origin(), position);
+    assert_eq!(
+        controller.profile_setpoint(),
+        Setpoint {
+            position: 0.0,
+            velocity: speed
+        }
+    );
+    run_controller(&mut controller, &motor, 1);
+    let velocity = controller.profile_setpoint().velocity;
+    assert!((velocity - speed).abs() <= 4000.0 * DT + 1e-3, "{velocity}");
+    assert!((controller.speed_setpoint() - velocity).abs() < 50.0);
+}
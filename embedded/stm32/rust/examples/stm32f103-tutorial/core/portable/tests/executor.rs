@@ -0,0 +1,156 @@
+//! 协作式执行器的调度测试
+//! 每个测试使用独立的静态就绪队列，测试在不同线程中并行执行
+
+use core::cell::{Cell, RefCell};
+use core::future::poll_fn;
+use core::pin::pin;
+use core::task::Poll;
+
+use portable::asynch::{join, select, yield_now, Either, Executor, ReadyQueue};
+
+// 模拟中断中保存的 Waker
+thread_local! {
+    static WAKER: RefCell<Option<core::task::Waker>> = const { RefCell::new(None) };
+}
+
+/// 等待指定轮数
+async fn wait_rounds(rounds: usize) {
+    for _ in 0..rounds {
+        yield_now().await;
+    }
+}
+
+#[test]
+fn run_completes_all_tasks() {
+    static QUEUE: ReadyQueue = ReadyQueue::new();
+    static EXECUTOR: Executor = Executor::new(&QUEUE, |_| {});
+
+    let done = RefCell::new(Vec::new());
+    let task = |name: &'static str, rounds: usize| {
+        let done = &done;
+        async move {
+            wait_rounds(rounds).await;
+            done.borrow_mut().push(name);
+        }
+    };
+    EXECUTOR.run([pin!(task("a", 3)), pin!(task("b", 0)), pin!(task("c", 1))]);
+
+    // 等待轮数少的任务先完成
+    assert_eq!(*done.borrow(), ["b", "c", "a"]);
+}
+
+#[test]
+fn yield_now_round_robin() {
+    static QUEUE: ReadyQueue = ReadyQueue::new();
+    static EXECUTOR: Executor = Executor::new(&QUEUE, |_| {});
+
+    let log = RefCell::new(Vec::new());
+    let task = |id: u8| {
+        let log = &log;
+        async move {
+            for _ in 0..3 {
+                log.borrow_mut().push(id);
+                yield_now().await;
+            }
+        }
+    };
+    EXECUTOR.run([pin!(task(0)), pin!(task(1))]);
+
+    // 让出后其他任务先执行，每轮按任务顺序轮询
+    assert_eq!(*log.borrow(), [0, 1, 0, 1, 0, 1]);
+}
+
+#[test]
+fn pending_task_is_not_polled_until_woken() {
+    static QUEUE: ReadyQueue = ReadyQueue::new();
+    static EXECUTOR: Executor = Executor::new(&QUEUE, |_| {});
+
+    let polls = Cell::new(0);
+    let flag = Cell::new(false);
+    let waiter = async {
+        poll_fn(|cx| {
+            polls.set(polls.get() + 1);
+            if flag.get() {
+                Poll::Ready(())
+            } else {
+                // 模拟中断：保存 Waker，由另一个任务唤醒
+                WAKER.with(|w| *w.borrow_mut() = Some(cx.waker().clone()));
+                Poll::Pending
+            }
+        })
+        .await
+    };
+    let notifier = async {
+        wait_rounds(5).await;
+        flag.set(true);
+        WAKER.with(|w| w.borrow_mut().take().unwrap().wake());
+    };
+    EXECUTOR.run([pin!(waiter), pin!(notifier)]);
+
+    // 只在第一次和被唤醒后各轮询一次
+    assert_eq!(polls.get(), 2);
+}
+
+#[test]
+fn block_on_returns_output() {
+    static QUEUE: ReadyQueue = ReadyQueue::new();
+    static EXECUTOR: Executor = Executor::new(&QUEUE, |_| {});
+
+    let output = EXECUTOR.block_on(async {
+        wait_rounds(3).await;
+        42
+    });
+    assert_eq!(output, 42);
+}
+
+#[test]
+fn idle_called_between_rounds() {
+    static QUEUE: ReadyQueue = ReadyQueue::new();
+    static EXECUTOR: Executor = Executor::new(&QUEUE, |queue| {
+        // 让出的任务已重新就绪，不应休眠
+        assert!(queue.any());
+    });
+
+    EXECUTOR.block_on(wait_rounds(4));
+}
+
+#[test]
+fn select_returns_first() {
+    static QUEUE: ReadyQueue = ReadyQueue::new();
+    static EXECUTOR: Executor = Executor::new(&QUEUE, |_| {});
+
+    let result = EXECUTOR.block_on(select(
+        async {
+            wait_rounds(5).await;
+            'a'
+        },
+        async {
+            wait_rounds(2).await;
+            1
+        },
+    ));
+    assert_eq!(result, Either::Second(1));
+}
+
+#[test]
+fn join_waits_for_both() {
+    static QUEUE: ReadyQueue = ReadyQueue::new();
+    static EXECUTOR: Executor = Executor::new(&QUEUE, |_| {});
+
+    let rounds = Cell::new(0);
+    let result = EXECUTOR.block_on(join(
+        async {
+            wait_rounds(1).await;
+            'a'
+        },
+        async {
+            for _ in 0..4 {
+                rounds.set(rounds.get() + 1);
+                yield_now().await;
+            }
+            2
+        },
+    ));
+    assert_eq!(result, ('a', 2));
+    assert_eq!(rounds.get(), 4);
+}
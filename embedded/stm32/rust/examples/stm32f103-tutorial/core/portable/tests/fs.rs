@@ -0,0 +1,181 @@
+//! W25Q64 littlefs 文件系统在仿真器上的测试
+//! 需要启用 `littlefs` 特性
+
+use portable::w25q64::conf::*;
+use portable::w25q64::emulator::{NoopDelay, W25qEmulator};
+use portable::w25q64::fs::{FileSystem, LfsStorage, SeekFrom, LFS_BLOCK_COUNT};
+use portable::w25q64::W25Q64;
+
+type Storage<'w, 'm> = LfsStorage<'w, W25qEmulator<'m>, NoopDelay>;
+
+fn erased() -> Vec<u8> {
+    vec![0xFF; W25Q64_CAPACITY]
+}
+
+/// 格式化后的存储内容
+fn formatted() -> Vec<u8> {
+    let mut memory = erased();
+    let mut w25q = W25Q64::new(W25qEmulator::new(&mut memory), NoopDelay);
+    let mut storage = LfsStorage::new(&mut w25q);
+    FileSystem::format(&mut storage).unwrap();
+    memory
+}
+
+/// 挂载文件系统并在回调中操作，挂载失败时测试失败
+fn with_fs<R>(memory: &mut [u8], f: impl FnOnce(&FileSystem<'_, Storage<'_, '_>>) -> R) -> R {
+    let mut w25q = W25Q64::new(W25qEmulator::new(memory), NoopDelay);
+    let mut storage = LfsStorage::new(&mut w25q);
+    let mut alloc = FileSystem::allocate();
+    let fs = FileSystem::mount(&mut alloc, &mut storage).unwrap();
+    f(&fs)
+}
+
+#[test]
+fn mount_or_format_only_formats_blank_chip() {
+    let mut memory = erased();
+    for _ in 0..2 {
+        let mut w25q = W25Q64::new(W25qEmulator::new(&mut memory), NoopDelay);
+        let mut storage = LfsStorage::new(&mut w25q);
+        let mut alloc = FileSystem::allocate();
+        let fs = FileSystem::mount_or_format(&mut alloc, &mut storage).unwrap();
+        if !fs.exists("boot.txt") {
+            fs.write_file("boot.txt", b"first").unwrap();
+        } else {
+            // 第二次挂载不会重新格式化
+            let mut buffer = [0; 8];
+            let len = fs.read_file("boot.txt", 0, &mut buffer).unwrap();
+            assert_eq!(&buffer[..len], b"first");
+        }
+    }
+}
+
+#[test]
+fn write_read_and_remount() {
+    let mut memory = formatted();
+    with_fs(&mut memory, |fs| {
+        fs.write_file("config.txt", b"baud=115200").unwrap();
+        assert_eq!(fs.append_file("config.txt", b"\nparity=none").unwrap(), 12);
+        fs.write_file_at("config.txt", 5, b"9600  ").unwrap();
+    });
+
+    // 重新挂载后数据仍然存在
+    with_fs(&mut memory, |fs| {
+        let mut buffer = [0; 64];
+        let len = fs.read_file("config.txt", 0, &mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"baud=9600  \nparity=none");
+        assert_eq!(fs.file_size("config.txt").unwrap(), len);
+
+        let len = fs.read_file("config.txt", 12, &mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"parity=none");
+    });
+}
+
+#[test]
+fn file_handle_seek_and_truncate() {
+    let mut memory = formatted();
+    with_fs(&mut memory, |fs| {
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let tail = fs
+            .open_file_and_then(
+                "data.bin",
+                |options| options.read(true).write(true).create(true),
+                |file| {
+                    assert!(file.is_empty()?);
+                    file.write(&data)?;
+                    assert_eq!(file.seek(SeekFrom::Current(-10))?, 990);
+                    let mut tail = [0; 10];
+                    file.read(&mut tail)?;
+                    file.set_len(500)?;
+                    assert_eq!(file.len()?, 500);
+                    Ok(tail)
+                },
+            )
+            .unwrap();
+        assert_eq!(tail[..], data[990..]);
+
+        let mut buffer = vec![0; 1000];
+        let len = fs.read_file("data.bin", 0, &mut buffer).unwrap();
+        assert_eq!(buffer[..len], data[..500]);
+    });
+}
+
+#[test]
+fn directories_rename_and_remove() {
+    let mut memory = formatted();
+    with_fs(&mut memory, |fs| {
+        fs.create_dir("logs").unwrap();
+        fs.write_file("logs/a.txt", b"aa").unwrap();
+        fs.write_file("logs/b.txt", b"bbbb").unwrap();
+        fs.rename("logs/b.txt", "logs/c.txt").unwrap();
+
+        let mut entries = Vec::new();
+        fs.read_dir("logs", |entry| {
+            entries.push((entry.name.to_string(), entry.is_dir, entry.size))
+        })
+        .unwrap();
+        entries.sort();
+        assert_eq!(
+            entries,
+            [
+                ("a.txt".to_string(), false, 2),
+                ("c.txt".to_string(), false, 4)
+            ]
+        );
+
+        // 非空目录不能删除
+        assert!(fs.remove("logs").is_err());
+        fs.remove("logs/a.txt").unwrap();
+        fs.remove("logs/c.txt").unwrap();
+        fs.remove("logs").unwrap();
+        assert!(!fs.exists("logs"));
+    });
+}
+
+#[test]
+fn statfs_reports_usage() {
+    let mut memory = formatted();
+    with_fs(&mut memory, |fs| {
+        let before = fs.statfs().unwrap();
+        assert_eq!(before.block_size, W25Q64_SECTOR_SIZE);
+        assert_eq!(before.total_blocks, LFS_BLOCK_COUNT);
+
+        fs.write_file("big.bin", &[0x5A; 5 * W25Q64_SECTOR_SIZE])
+            .unwrap();
+        let after = fs.statfs().unwrap();
+        assert!(after.free_blocks + 5 <= before.free_blocks);
+    });
+}
+
+#[test]
+fn power_loss_keeps_old_or_new_content() {
+    const OLD: &[u8] = b"version=1";
+    let new: Vec<u8> = (0..1000).map(|i| b'a' + (i % 26) as u8).collect();
+
+    let mut memory = formatted();
+    with_fs(&mut memory, |fs| fs.write_file("config.txt", OLD).unwrap());
+
+    // 在覆盖文件的不同阶段掉电
+    for operations in 0..8 {
+        let mut copy = memory.clone();
+        let mut emulator = W25qEmulator::new(&mut copy);
+        emulator.inject_power_loss_after(operations);
+        let mut w25q = W25Q64::new(emulator, NoopDelay);
+        {
+            let mut storage = LfsStorage::new(&mut w25q);
+            let mut alloc = FileSystem::allocate();
+            let fs = FileSystem::mount(&mut alloc, &mut storage).unwrap();
+            let _ = fs.write_file("config.txt", &new);
+        }
+        let (emulator, _) = w25q.release();
+        if operations == 0 {
+            assert!(emulator.is_power_lost());
+        }
+
+        // 重新上电后文件系统可以挂载，文件内容为写入前或写入后的完整内容
+        with_fs(&mut copy, |fs| {
+            let mut buffer = vec![0; 1024];
+            let len = fs.read_file("config.txt", 0, &mut buffer).unwrap();
+            assert!(&buffer[..len] == OLD || buffer[..len] == new[..]);
+        });
+    }
+}
@@ -0,0 +1,213 @@
+//! 姿态解算算法的收敛和角度回绕测试
+
+use portable::imu::{
+    wrap_180, AttitudeFilter, Complementary, EulerAngles, Madgwick, Mahony, Measurement, DEG_TO_RAD,
+};
+
+const G: f32 = 9.81;
+const DT: f32 = 0.01;
+
+/// 静止时给定横滚角和俯仰角(°)对应的测量值，可以附加角速度(°/s)
+fn still(roll: f32, pitch: f32, gyro: [f32; 3]) -> Measurement {
+    let (roll, pitch) = (roll * DEG_TO_RAD, pitch * DEG_TO_RAD);
+    Measurement {
+        acc_x: -pitch.sin() * G,
+        acc_y: pitch.cos() * roll.sin() * G,
+        acc_z: pitch.cos() * roll.cos() * G,
+        gyro_x: gyro[0],
+        gyro_y: gyro[1],
+        gyro_z: gyro[2],
+        temp: 25.0,
+    }
+}
+
+/// 两个角度的最短距离
+fn angle_diff(a: f32, b: f32) -> f32 {
+    wrap_180(a - b).abs()
+}
+
+fn assert_attitude(angles: EulerAngles, roll: f32, pitch: f32) {
+    assert!(
+        angle_diff(angles.roll, roll) < 1.0 && angle_diff(angles.pitch, pitch) < 1.0,
+        "{angles:?} != ({roll}, {pitch})"
+    );
+}
+
+#[test]
+fn wrap_180_range() {
+    assert_eq!(wrap_180(0.0), 0.0);
+    assert_eq!(wrap_180(180.0), 180.0);
+    assert_eq!(wrap_180(-180.0), 180.0);
+    assert_eq!(wrap_180(190.0), -170.0);
+    assert_eq!(wrap_180(-190.0), 170.0);
+    assert_eq!(wrap_180(720.0 + 45.0), 45.0);
+}
+
+#[test]
+fn accel_attitude() {
+    let angles = EulerAngles::from_accel(&still(30.0, -20.0, [0.0; 3]));
+    assert_attitude(angles, 30.0, -20.0);
+}
+
+#[test]
+fn complementary_converges_to_accel() {
+    let mut filter = Complementary::new(0.98);
+    filter.update(&still(0.0, 0.0, [0.0; 3]), DT);
+    for _ in 0..500 {
+        filter.update(&still(25.0, -15.0, [0.0; 3]), DT);
+    }
+    assert_attitude(filter.euler(), 25.0, -15.0);
+}
+
+#[test]
+fn complementary_roll_stays_near_180() {
+    let mut filter = Complementary::new(0.98);
+    // 倒置时加速度计算的横滚角在 179° 和 -179° 之间跳变
+    for i in 0..500 {
+        let roll = if i % 2 == 0 { 179.0 } else { -179.0 };
+        filter.update(&still(roll, 0.0, [0.0; 3]), DT);
+        assert!(angle_diff(filter.euler().roll, 180.0) < 2.0);
+    }
+}
+
+#[test]
+fn complementary_yaw_wraps() {
+    let mut filter = Complementary::new(0.98);
+    // 以 90°/s 旋转 3 秒，共 270°，即 -90°
+    for _ in 0..=300 {
+        filter.update(&still(0.0, 0.0, [0.0, 0.0, 90.0]), DT);
+    }
+    let yaw = filter.euler().yaw;
+    assert!((-180.0..=180.0).contains(&yaw));
+    assert!(angle_diff(yaw, -90.0) < 1.0, "yaw = {yaw}");
+}
+
+#[test]
+fn madgwick_converges_to_accel() {
+    let mut filter = Madgwick::new(0.1);
+    for _ in 0..3000 {
+        filter.update(&still(20.0, 10.0, [0.0; 3]), DT);
+    }
+    assert_attitude(filter.euler(), 20.0, 10.0);
+}
+
+#[test]
+fn mahony_converges_to_accel() {
+    let mut filter = Mahony::new(2.0, 0.0);
+    for _ in 0..3000 {
+        filter.update(&still(-35.0, 15.0, [0.0; 3]), DT);
+    }
+    assert_attitude(filter.euler(), -35.0, 15.0);
+}
+
+#[test]
+fn mahony_estimates_gyro_bias() {
+    let mut filter = Mahony::new(2.0, 0.5);
+    // 静止水平时横滚轴和俯仰轴存在零偏，偏航轴的零偏加速度计无法观测
+    for _ in 0..5000 {
+        filter.update(&still(0.0, 0.0, [1.0, -0.5, 0.0]), DT);
+    }
+    assert_attitude(filter.euler(), 0.0, 0.0);
+    let bias = filter.gyro_bias();
+    assert!(
+        (bias[0] - 1.0).abs() < 0.02 && (bias[1] + 0.5).abs() < 0.02,
+        "bias {bias:?}"
+    );
+}
+
+/// 固定种子的伪随机数，范围 -1~1
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (self.0 >> 8) as f32 / (1 << 23) as f32 - 1.0
+    }
+}
+
+/// 按 MPU6050 默认量程(±2g、±250°/s)量化为原始值再换算回物理单位
+fn quantize(mut m: Measurement) -> Measurement {
+    const ACCEL_LSB: f32 = 16384.0 / G;
+    const GYRO_LSB: f32 = 131.0;
+    for acc in [&mut m.acc_x, &mut m.acc_y, &mut m.acc_z] {
+        *acc = (*acc * ACCEL_LSB).round() as i16 as f32 / ACCEL_LSB;
+    }
+    for gyro in [&mut m.gyro_x, &mut m.gyro_y, &mut m.gyro_z] {
+        *gyro = (*gyro * GYRO_LSB).round() as i16 as f32 / GYRO_LSB;
+    }
+    m
+}
+
+/// 100Hz 采样序列: 水平静止 2s，以 15°/s 横滚到 30°，保持 2s，再以 10°/s 俯仰到 -20°，保持 6s
+/// 陀螺仪带零偏，加速度和角速度带噪声，返回每个采样及真实的横滚角和俯仰角
+fn motion_sequence(gyro_bias: [f32; 3]) -> Vec<(Measurement, f32, f32)> {
+    // (持续周期数, 横滚角速度, 俯仰角速度)
+    let segments = [
+        (200, 0.0, 0.0),
+        (200, 15.0, 0.0),
+        (200, 0.0, 0.0),
+        (200, 0.0, -10.0),
+        (600, 0.0, 0.0),
+    ];
+    let mut noise = Noise(0x1234_5678);
+    let (mut roll, mut pitch) = (0.0_f32, 0.0_f32);
+    let mut samples = Vec::new();
+    for (steps, roll_rate, pitch_rate) in segments {
+        for _ in 0..steps {
+            roll += roll_rate * DT;
+            pitch += pitch_rate * DT;
+            // 欧拉角速率转换为机体角速度，偏航角速度为0
+            let (sin, cos) = (roll * DEG_TO_RAD).sin_cos();
+            let gyro = [roll_rate, cos * pitch_rate, -sin * pitch_rate];
+            let mut m = still(roll, pitch, [0.0; 3]);
+            m.gyro_x = gyro[0] + gyro_bias[0] + 0.3 * noise.next();
+            m.gyro_y = gyro[1] + gyro_bias[1] + 0.3 * noise.next();
+            m.gyro_z = gyro[2] + gyro_bias[2] + 0.3 * noise.next();
+            m.acc_x += 0.3 * noise.next();
+            m.acc_y += 0.3 * noise.next();
+            m.acc_z += 0.3 * noise.next();
+            samples.push((quantize(m), roll, pitch));
+        }
+    }
+    samples
+}
+
+/// 按采样序列运行滤波器，运动过程中误差不超过 `tracking`，结束时收敛到最终姿态
+fn track_sequence(
+    filter: &mut impl AttitudeFilter,
+    samples: &[(Measurement, f32, f32)],
+    tracking: f32,
+) {
+    for (i, (m, roll, pitch)) in samples.iter().enumerate() {
+        filter.update(m, DT);
+        // 跳过第一秒的初始收敛
+        if i >= 100 {
+            let angles = filter.euler();
+            let error = angle_diff(angles.roll, *roll).max(angle_diff(angles.pitch, *pitch));
+            assert!(
+                error < tracking,
+                "sample {i}: {angles:?} != ({roll}, {pitch})"
+            );
+        }
+    }
+    assert_attitude(filter.euler(), 30.0, -20.0);
+}
+
+#[test]
+fn filters_track_motion_sequence() {
+    let samples = motion_sequence([0.8, -0.6, 0.3]);
+
+    let mut complementary = Complementary::new(0.98);
+    track_sequence(&mut complementary, &samples, 5.0);
+    let mut madgwick = Madgwick::new(0.1);
+    track_sequence(&mut madgwick, &samples, 5.0);
+
+    // Mahony 在运动中同样能估计出横滚轴和俯仰轴的零偏
+    let mut mahony = Mahony::new(2.0, 0.5);
+    track_sequence(&mut mahony, &samples, 5.0);
+    let bias = mahony.gyro_bias();
+    assert!(
+        (bias[0] - 0.8).abs() < 0.2 && (bias[1] + 0.6).abs() < 0.2,
+        "bias {bias:?}"
+    );
+}
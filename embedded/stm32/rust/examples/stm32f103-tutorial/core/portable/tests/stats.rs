@@ -0,0 +1,87 @@
+//! 耗时统计的计算测试
+
+use portable::stats::{Profiler, ProfilerFull, Stats};
+
+#[test]
+fn empty_stats() {
+    let stats = Stats::new();
+    assert!(stats.is_empty());
+    assert_eq!(stats.count(), 0);
+    assert_eq!(stats.min(), None);
+    assert_eq!(stats.max(), None);
+    assert_eq!(stats.mean(), None);
+    assert_eq!(stats.last(), None);
+}
+
+#[test]
+fn min_max_mean_last() {
+    let mut stats = Stats::new();
+    for cycles in [30, 10, 50, 20] {
+        stats.record(cycles);
+    }
+    assert_eq!(stats.count(), 4);
+    assert_eq!(stats.total(), 110);
+    assert_eq!(stats.min(), Some(10));
+    assert_eq!(stats.max(), Some(50));
+    // 平均值向下取整
+    assert_eq!(stats.mean(), Some(27));
+    assert_eq!(stats.last(), Some(20));
+}
+
+#[test]
+fn total_does_not_overflow() {
+    let mut stats = Stats::new();
+    for _ in 0..4 {
+        stats.record(u32::MAX);
+    }
+    assert_eq!(stats.total(), 4 * u32::MAX as u64);
+    assert_eq!(stats.mean(), Some(u32::MAX));
+}
+
+#[test]
+fn merge_and_reset() {
+    let mut a = Stats::new();
+    a.record(5);
+    a.record(15);
+    let mut b = Stats::new();
+    b.record(1);
+    b.record(40);
+
+    // 合并空统计不影响结果
+    a.merge(&Stats::new());
+    assert_eq!(a.count(), 2);
+    assert_eq!(a.last(), Some(15));
+
+    a.merge(&b);
+    assert_eq!(a.count(), 4);
+    assert_eq!(a.min(), Some(1));
+    assert_eq!(a.max(), Some(40));
+    assert_eq!(a.mean(), Some(15));
+    assert_eq!(a.last(), Some(40));
+
+    a.reset();
+    assert_eq!(a, Stats::new());
+}
+
+#[test]
+fn profiler_sections() {
+    let mut profiler = Profiler::<2>::new();
+    profiler.record("read", 100).unwrap();
+    profiler.record("filter", 300).unwrap();
+    profiler.record("read", 200).unwrap();
+    assert_eq!(profiler.record("display", 1), Err(ProfilerFull));
+
+    // 按第一次记录的顺序排列
+    let names: Vec<_> = profiler.iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["read", "filter"]);
+    assert_eq!(profiler.get("read").unwrap().mean(), Some(150));
+    assert!(profiler.get("display").is_none());
+
+    // 清空统计后保留代码段
+    profiler.reset();
+    assert_eq!(profiler.len(), 2);
+    assert!(profiler.get("filter").unwrap().is_empty());
+
+    profiler.clear();
+    assert!(profiler.is_empty());
+}
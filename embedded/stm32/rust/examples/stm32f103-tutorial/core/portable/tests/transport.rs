@@ -0,0 +1,227 @@
+//! 可靠消息传输在模拟信道上的分片、重发、去重和复位测试
+
+use core::cell::RefCell;
+
+use portable::nrf24::message::{CodecError, Message, Reader, Writer};
+use portable::nrf24::sim::{Side, SimChannel, SimRadio};
+use portable::nrf24::transport::{Duration, Instant, FRAGMENT_LEN};
+use portable::nrf24::{Transport, TransportConfig, TxState};
+
+const LEN: usize = 256;
+
+type Node<'a> = Transport<SimRadio<'a>, LEN>;
+
+fn node(channel: &RefCell<SimChannel>, side: Side, session: u8) -> Node<'_> {
+    Transport::new(
+        SimRadio::new(channel, side),
+        TransportConfig::default(),
+        session,
+    )
+}
+
+/// 长度为 `len` 的测试消息，内容与 `tag` 有关
+fn payload(tag: u8, len: usize) -> Vec<u8> {
+    (0..len).map(|i| tag.wrapping_add(i as u8)).collect()
+}
+
+/// 每次前进1ms，交替处理双方收到的帧，直到发送完成，返回接收方收到的消息
+fn deliver(a: &mut Node, b: &mut Node, now: &mut Instant) -> (TxState, Vec<Vec<u8>>) {
+    let mut received = Vec::new();
+    for _ in 0..10_000 {
+        a.poll(*now).unwrap();
+        b.poll(*now).unwrap();
+        if let Some(message) = b.receive() {
+            received.push(message.to_vec());
+        }
+        if a.tx_state() != TxState::Sending {
+            // 最后一个应答到达前接收方可能已经完成接收，再处理一轮
+            b.poll(*now).unwrap();
+            if let Some(message) = b.receive() {
+                received.push(message.to_vec());
+            }
+            return (a.tx_state(), received);
+        }
+        *now += Duration::millis(1);
+    }
+    panic!("transport did not finish");
+}
+
+#[test]
+fn fragments_are_reassembled() {
+    let channel = RefCell::new(SimChannel::new(0, 1));
+    let mut a = node(&channel, Side::A, 1);
+    let mut b = node(&channel, Side::B, 2);
+    let mut now = Instant::from_ticks(0);
+
+    // 正好填满分片、多一个字节和单个字节
+    for len in [FRAGMENT_LEN, FRAGMENT_LEN * 3 + 1, 1, LEN] {
+        let data = payload(len as u8, len);
+        a.send(&data, now).unwrap();
+        let (state, received) = deliver(&mut a, &mut b, &mut now);
+        assert_eq!(state, TxState::Delivered);
+        assert_eq!(received, [data]);
+    }
+    assert_eq!(a.stats().retransmissions, 0);
+    assert_eq!(b.stats().received, 4);
+}
+
+#[test]
+fn lossy_channel_delivers_each_message_once() {
+    for seed in 1..=5 {
+        let channel = RefCell::new(SimChannel::new(30, seed));
+        let mut a = node(&channel, Side::A, 1);
+        let mut b = node(&channel, Side::B, 2);
+        let mut now = Instant::from_ticks(0);
+
+        let mut all = Vec::new();
+        let mut delivered = Vec::new();
+        for i in 0..20u8 {
+            let data = payload(i, 20 + i as usize * 7);
+            a.send(&data, now).unwrap();
+            let (state, received) = deliver(&mut a, &mut b, &mut now);
+            all.extend(received);
+            if state == TxState::Delivered {
+                delivered.push(data);
+            }
+        }
+
+        // 送达的消息都被接收，且没有重复；应答丢失时接收方可能收到了发送方认为失败的消息
+        assert!(channel.borrow().stats().lost > 0);
+        assert!(a.stats().retransmissions > 0);
+        for data in &delivered {
+            assert_eq!(all.iter().filter(|m| *m == data).count(), 1, "seed {seed}");
+        }
+        assert!(all.len() >= delivered.len());
+        assert!(b.stats().duplicates > 0, "seed {seed}");
+    }
+}
+
+#[test]
+fn gives_up_after_max_retries() {
+    let channel = RefCell::new(SimChannel::new(100, 1));
+    let mut a = node(&channel, Side::A, 1);
+    let mut b = node(&channel, Side::B, 2);
+    let mut now = Instant::from_ticks(0);
+
+    a.send(b"lost", now).unwrap();
+    assert!(a.send(b"busy", now).is_err());
+    let (state, received) = deliver(&mut a, &mut b, &mut now);
+    assert_eq!(state, TxState::Failed);
+    assert!(received.is_empty());
+    let config = TransportConfig::default();
+    assert_eq!(a.stats().retransmissions, config.max_retries as u32);
+    assert_eq!(a.stats().failed, 1);
+
+    // 信道恢复后可以继续发送
+    channel.borrow_mut().set_loss(0);
+    a.send(b"ok", now).unwrap();
+    let (state, received) = deliver(&mut a, &mut b, &mut now);
+    assert_eq!(state, TxState::Delivered);
+    assert_eq!(received, [b"ok".to_vec()]);
+}
+
+#[test]
+fn unread_message_blocks_next() {
+    let channel = RefCell::new(SimChannel::new(0, 1));
+    let mut a = node(&channel, Side::A, 1);
+    let mut b = node(&channel, Side::B, 2);
+    let mut now = Instant::from_ticks(0);
+
+    a.send(b"first", now).unwrap();
+    a.poll(now).unwrap();
+    b.poll(now).unwrap();
+    a.poll(now).unwrap();
+    assert_eq!(a.tx_state(), TxState::Delivered);
+
+    // 第一条消息未被读取，第二条消息不被应答
+    a.send(b"second", now).unwrap();
+    for _ in 0..5 {
+        now += Duration::millis(25);
+        a.poll(now).unwrap();
+        b.poll(now).unwrap();
+    }
+    assert_eq!(a.tx_state(), TxState::Sending);
+    assert_eq!(b.receive(), Some(&b"first"[..]));
+
+    let (state, received) = deliver(&mut a, &mut b, &mut now);
+    assert_eq!(state, TxState::Delivered);
+    assert_eq!(received, [b"second".to_vec()]);
+}
+
+#[test]
+fn sender_reboot_is_not_a_duplicate() {
+    let channel = RefCell::new(SimChannel::new(0, 1));
+    let mut b = node(&channel, Side::B, 2);
+    let mut now = Instant::from_ticks(0);
+
+    let mut a = node(&channel, Side::A, 7);
+    a.send(b"before", now).unwrap();
+    let (_, received) = deliver(&mut a, &mut b, &mut now);
+    assert_eq!(received, [b"before".to_vec()]);
+
+    // 复位后消息序号重新从0开始，会话号不同
+    let mut a = node(&channel, Side::A, 8);
+    a.send(b"after", now).unwrap();
+    let (state, received) = deliver(&mut a, &mut b, &mut now);
+    assert_eq!(state, TxState::Delivered);
+    assert_eq!(received, [b"after".to_vec()]);
+    assert_eq!(b.stats().duplicates, 0);
+}
+
+#[derive(Debug, PartialEq)]
+struct Reading {
+    counter: u32,
+    temperature: f32,
+    samples: [i16; 20],
+}
+
+impl Message for Reading {
+    const ID: u8 = 1;
+
+    fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+        w.u32(self.counter)?;
+        w.f32(self.temperature)?;
+        for sample in self.samples {
+            w.i16(sample)?;
+        }
+        Ok(())
+    }
+
+    fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+        let counter = r.u32()?;
+        let temperature = r.f32()?;
+        let mut samples = [0; 20];
+        for sample in samples.iter_mut() {
+            *sample = r.i16()?;
+        }
+        Ok(Reading {
+            counter,
+            temperature,
+            samples,
+        })
+    }
+}
+
+#[test]
+fn messages_are_serialized() {
+    let channel = RefCell::new(SimChannel::new(20, 3));
+    let mut a = node(&channel, Side::A, 1);
+    let mut b = node(&channel, Side::B, 2);
+    let mut now = Instant::from_ticks(0);
+
+    let reading = Reading {
+        counter: 42,
+        temperature: 25.5,
+        samples: core::array::from_fn(|i| i as i16 * -3),
+    };
+    a.send_message(&reading, now).unwrap();
+    loop {
+        a.poll(now).unwrap();
+        b.poll(now).unwrap();
+        if let Some(result) = b.receive_message::<Reading>() {
+            assert_eq!(result, Ok(reading));
+            break;
+        }
+        now += Duration::millis(1);
+    }
+}
@@ -0,0 +1,301 @@
+//! W25Q64 驱动、embedded-storage 接口及数据记录器在仿真器上的测试
+
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+use portable::w25q64::advanced::ProtectedRange;
+use portable::w25q64::conf::*;
+use portable::w25q64::emulator::{self, NoopDelay, Timing, W25qEmulator};
+use portable::w25q64::logger::{self, DataLogger};
+use portable::w25q64::{Error, W25Q64};
+
+type Flash<'m> = W25Q64<W25qEmulator<'m>, NoopDelay>;
+
+fn erased() -> Vec<u8> {
+    vec![0xFF; W25Q64_CAPACITY]
+}
+
+fn flash(memory: &mut [u8]) -> Flash<'_> {
+    W25Q64::new(W25qEmulator::new(memory), NoopDelay)
+}
+
+#[test]
+fn read_ids() {
+    let mut memory = erased();
+    let mut w25q = flash(&mut memory);
+
+    assert_eq!(w25q.read_jedec_device_id().unwrap(), (0xEF, 0x40, 0x17));
+    // 地址为0时交替输出制造商ID和设备ID
+    let (manufacturer_id, device_id) = w25q.read_manufacturer_device_id().unwrap();
+    assert_eq!(manufacturer_id, 0xEF);
+    assert_eq!(device_id, 0x16EF);
+}
+
+#[test]
+fn write_across_pages_and_read_back() {
+    let mut memory = erased();
+    let mut w25q = flash(&mut memory);
+
+    let data: Vec<u8> = (0..600).map(|i| i as u8).collect();
+    w25q.write(0x0000F0, &data).unwrap();
+
+    let mut buffer = vec![0; data.len()];
+    w25q.read_data(0x0000F0, &mut buffer).unwrap();
+    assert_eq!(buffer, data);
+    w25q.fast_read(0x0000F0, &mut buffer).unwrap();
+    assert_eq!(buffer, data);
+
+    // 跨越3个页边界，共4次页编程
+    let (emulator, _) = w25q.release();
+    assert_eq!(emulator.stats().page_programs, 4);
+}
+
+#[test]
+fn wait_for_busy_operations() {
+    let mut memory = erased();
+    let mut emulator = W25qEmulator::new(&mut memory);
+    emulator.set_timing(Timing {
+        page_program: 3,
+        sector_erase: 10,
+        ..Default::default()
+    });
+    let mut w25q = W25Q64::new(emulator, NoopDelay);
+
+    w25q.page_program(0x001000, &[0x12, 0x34]).unwrap();
+    w25q.start_sector_erase(0x001000).unwrap();
+    assert!(w25q.is_busy().unwrap());
+    assert_eq!(w25q.poll_idle(), Err(nb::Error::WouldBlock));
+    w25q.wait_for_idle(W25Q64_SECTOR_ERASE_TIMEOUT_MS).unwrap();
+
+    let mut buffer = [0; 2];
+    w25q.read_data(0x001000, &mut buffer).unwrap();
+    assert_eq!(buffer, [0xFF, 0xFF]);
+}
+
+#[test]
+fn update_merges_sector() {
+    let mut memory = erased();
+    let mut w25q = flash(&mut memory);
+
+    w25q.write(0x002000, b"hello world").unwrap();
+    // 需要将位从0变为1，必须擦除后回写
+    w25q.update(0x002006, b"there").unwrap();
+
+    let mut buffer = [0; 11];
+    w25q.read_data(0x002000, &mut buffer).unwrap();
+    assert_eq!(&buffer, b"hello there");
+}
+
+#[test]
+fn protected_range_rejects_program() {
+    let mut memory = erased();
+    let mut w25q = flash(&mut memory);
+
+    w25q.set_protected_range(ProtectedRange::Lower(0x1000))
+        .unwrap();
+    assert_eq!(
+        w25q.protected_range().unwrap(),
+        ProtectedRange::Lower(0x1000)
+    );
+
+    assert_eq!(
+        w25q.page_program(0x000000, &[0x00]),
+        Err(Error::WriteProtected)
+    );
+    // 保护区域以外可以正常编程
+    w25q.page_program(0x001000, &[0x00]).unwrap();
+
+    w25q.set_protected_range(ProtectedRange::None).unwrap();
+    w25q.page_program(0x000000, &[0x00]).unwrap();
+}
+
+#[test]
+fn sfdp_geometry() {
+    let mut memory = erased();
+    let mut w25q = flash(&mut memory);
+
+    let info = w25q.read_sfdp_info().unwrap();
+    assert_eq!(info.capacity, W25Q64_CAPACITY as u32);
+    assert_eq!(info.page_size, W25Q64_PAGE_SIZE as u32);
+    let erase = info.erase_types[0].unwrap();
+    assert_eq!(erase.size, W25Q64_SECTOR_SIZE as u32);
+    assert_eq!(erase.opcode, W25Q64_SECTOR_ERASE_4KB);
+}
+
+#[test]
+fn power_down_and_release() {
+    let mut memory = erased();
+    let mut w25q = flash(&mut memory);
+
+    w25q.power_down().unwrap();
+    assert_eq!(w25q.release_power_down().unwrap(), 0x16);
+    assert_eq!(w25q.read_jedec_device_id().unwrap().0, 0xEF);
+}
+
+#[test]
+fn spi_errors_are_reported() {
+    let mut memory = erased();
+    let mut emulator = W25qEmulator::new(&mut memory);
+    emulator.inject_error_after(0);
+    let mut w25q = W25Q64::new(emulator, NoopDelay);
+
+    assert_eq!(
+        w25q.read_status_register_1(),
+        Err(Error::Spi(emulator::Error::Injected))
+    );
+    assert!(w25q.read_status_register_1().is_ok());
+}
+
+#[test]
+fn nor_flash_round_trip() {
+    let mut memory = erased();
+    let mut w25q = flash(&mut memory);
+
+    assert_eq!(ReadNorFlash::capacity(&w25q), W25Q64_CAPACITY);
+    NorFlash::erase(&mut w25q, 0x010000, 0x012000).unwrap();
+    NorFlash::write(&mut w25q, 0x010FFE, &[1, 2, 3, 4]).unwrap();
+
+    let mut buffer = [0; 4];
+    ReadNorFlash::read(&mut w25q, 0x010FFE, &mut buffer).unwrap();
+    assert_eq!(buffer, [1, 2, 3, 4]);
+
+    // 擦除范围必须按扇区对齐
+    assert_eq!(
+        NorFlash::erase(&mut w25q, 0x010001, 0x011000),
+        Err(Error::NotAligned)
+    );
+    assert_eq!(
+        ReadNorFlash::read(&mut w25q, W25Q64_CAPACITY as u32, &mut buffer),
+        Err(Error::OutOfBounds)
+    );
+}
+
+#[test]
+fn logger_wraps_around() {
+    let mut memory = erased();
+    let mut w25q = flash(&mut memory);
+
+    let mut logger = DataLogger::mount(&mut w25q, 0x000000, 0x004000).unwrap();
+    for i in 0..2000u32 {
+        logger.append(i, &i.to_le_bytes()).unwrap();
+    }
+
+    // 最新的记录都在，序号连续
+    let mut cursor = logger.last();
+    let mut buffer = [0; 4];
+    let mut expected = 1999;
+    let mut count = 0;
+    while let Some(record) = logger.read_prev(&mut cursor, &mut buffer).unwrap() {
+        assert_eq!(record.seq, expected);
+        assert_eq!(record.timestamp, expected);
+        assert_eq!(u32::from_le_bytes(buffer), expected);
+        expected -= 1;
+        count += 1;
+    }
+    // 4个扇区中至少3个扇区的记录保留
+    assert!(count >= 3 * (W25Q64_SECTOR_SIZE - 16) / 18);
+}
+
+#[test]
+fn logger_survives_power_loss() {
+    let mut memory = erased();
+    let mut w25q = flash(&mut memory);
+    {
+        let mut logger = DataLogger::mount(&mut w25q, 0x000000, 0x008000).unwrap();
+        for i in 0..10u32 {
+            logger.append(i, &[i as u8; 8]).unwrap();
+        }
+    }
+
+    // 第11条记录写到一半时掉电
+    let (mut emulator, delay) = w25q.release();
+    emulator.inject_power_loss_after(0);
+    let mut w25q = W25Q64::new(emulator, delay);
+    {
+        let mut logger = DataLogger::mount(&mut w25q, 0x000000, 0x008000).unwrap();
+        assert!(logger.append(10, &[10; 8]).is_err());
+    }
+
+    let (mut emulator, delay) = w25q.release();
+    assert!(emulator.is_power_lost());
+    emulator.power_cycle();
+    let mut w25q = W25Q64::new(emulator, delay);
+
+    // 重新挂载后已有记录完整，可以继续追加
+    let mut logger = DataLogger::mount(&mut w25q, 0x000000, 0x008000).unwrap();
+    let seq = logger.append(11, &[11; 8]).unwrap();
+    assert!(seq >= 10);
+
+    let mut cursor = logger.first();
+    let mut buffer = [0; 8];
+    let mut timestamps = Vec::new();
+    while let Some(record) = logger.read_next(&mut cursor, &mut buffer).unwrap() {
+        assert_eq!(buffer, [record.timestamp as u8; 8]);
+        timestamps.push(record.timestamp);
+    }
+    assert_eq!(timestamps, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 11]);
+}
+
+#[test]
+fn logger_rejects_invalid_region() {
+    let mut memory = erased();
+    let mut w25q = flash(&mut memory);
+
+    let sector = W25Q64_SECTOR_SIZE as u32;
+    // 未按扇区对齐
+    assert!(matches!(
+        DataLogger::mount(&mut w25q, 0x000100, 0x004000),
+        Err(logger::Error::InvalidRegion)
+    ));
+    // 少于2个扇区
+    assert!(matches!(
+        DataLogger::mount(&mut w25q, 0x000000, sector),
+        Err(logger::Error::InvalidRegion)
+    ));
+    assert!(matches!(
+        DataLogger::mount(&mut w25q, 0x004000, 0x000000),
+        Err(logger::Error::InvalidRegion)
+    ));
+    // 超出芯片容量
+    assert!(matches!(
+        DataLogger::mount(&mut w25q, 0x000000, W25Q64_CAPACITY as u32 + 2 * sector),
+        Err(logger::Error::InvalidRegion)
+    ));
+}
+
+#[test]
+fn logger_skips_torn_record_header() {
+    let mut memory = erased();
+    {
+        let mut w25q = flash(&mut memory);
+        let mut logger = DataLogger::mount(&mut w25q, 0x000000, 0x008000).unwrap();
+        for i in 0..2u32 {
+            logger.append(i, &[i as u8; 10]).unwrap();
+        }
+    }
+
+    // 第3条记录的记录头只写入了长度字段，长度超出扇区
+    // 扇区头16字节，每条记录14+10字节
+    let offset = 16 + 2 * 24;
+    memory[offset..offset + 2].copy_from_slice(&0x2000u16.to_le_bytes());
+
+    let mut w25q = flash(&mut memory);
+    let mut logger = DataLogger::mount(&mut w25q, 0x000000, 0x008000).unwrap();
+    logger.append(2, &[2; 10]).unwrap();
+
+    let mut cursor = logger.first();
+    let mut buffer = [0; 10];
+    let mut timestamps = Vec::new();
+    while let Some(record) = logger.read_next(&mut cursor, &mut buffer).unwrap() {
+        assert_eq!(buffer, [record.timestamp as u8; 10]);
+        timestamps.push(record.timestamp);
+    }
+    assert_eq!(timestamps, [0, 1, 2]);
+
+    // 新记录写入下一个扇区，损坏的记录头没有被覆盖
+    let (emulator, _) = w25q.release();
+    let memory = emulator.memory();
+    assert_eq!(memory[offset..offset + 3], [0x00, 0x20, 0xFF]);
+    assert_eq!(
+        memory[W25Q64_SECTOR_SIZE + 16..W25Q64_SECTOR_SIZE + 18],
+        [10, 0]
+    );
+}
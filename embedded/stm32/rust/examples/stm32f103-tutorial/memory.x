@@ -1,6 +1,9 @@
 /* Linker script for the STM32F103C8T6 */
 MEMORY
 {
-  FLASH : ORIGIN = 0x08000000, LENGTH = 64K
+  /* 末尾9页不放程序代码，见 hardware::flash_store */
+  FLASH : ORIGIN = 0x08000000, LENGTH = 55K
+  /* flash_store::storage 预留区(8页，0x0800DC00)及 flash_store::store 参数页(1页，0x0800FC00) */
+  FLASH_STORE : ORIGIN = 0x0800DC00, LENGTH = 9K
   RAM : ORIGIN = 0x20000000, LENGTH = 20K
-}
\ No newline at end of file
+}
@@ -0,0 +1,131 @@
+# -*- encoding:utf-8 -*-
+"""W25Q64 littlefs 镜像工具
+
+在 PC 上将目录打包为文件系统镜像，或将镜像解包为目录。
+参数需要与 `core/hardware/src/w25q64/fs.rs` 中的配置保持一致。
+
+依赖:
+    pip install "littlefs-python>=0.9"
+
+用法:
+    # 将 data 目录打包为镜像
+    python scripts/w25q64_lfs.py build data w25q64.bin
+    # 将镜像解包到 out 目录
+    python scripts/w25q64_lfs.py extract w25q64.bin out
+    # 列出镜像中的文件
+    python scripts/w25q64_lfs.py list w25q64.bin
+"""
+
+import argparse
+import os
+
+from littlefs import LittleFS
+
+# W25Q64 扇区大小 4KB，容量 8MB
+BLOCK_SIZE = 4096
+BLOCK_COUNT = 2048
+READ_SIZE = 16
+PROG_SIZE = 256
+CACHE_SIZE = 256
+LOOKAHEAD_SIZE = 32
+BLOCK_CYCLES = 500
+# 固件使用的 littlefs2 0.4 内置 littlefs v2.2，磁盘格式版本为 2.0
+# 新版 littlefs-python 默认写入 2.1 格式，旧版本的固件无法挂载，需要固定版本
+DISK_VERSION = 0x00020000
+
+
+def new_fs(mount=True):
+    """创建与固件配置一致的文件系统"""
+    return LittleFS(
+        block_size=BLOCK_SIZE,
+        block_count=BLOCK_COUNT,
+        read_size=READ_SIZE,
+        prog_size=PROG_SIZE,
+        cache_size=CACHE_SIZE,
+        lookahead_size=LOOKAHEAD_SIZE,
+        block_cycles=BLOCK_CYCLES,
+        disk_version=DISK_VERSION,
+        mount=mount,
+    )
+
+
+def load_fs(image):
+    """从镜像文件加载文件系统"""
+    fs = new_fs(mount=False)
+    with open(image, "rb") as f:
+        data = f.read()
+    # 镜像可能被截断了末尾的空白区域，补齐为 0xFF
+    fs.context.buffer = bytearray(data.ljust(BLOCK_SIZE * BLOCK_COUNT, b"\xff"))
+    fs.mount()
+    return fs
+
+
+def build(src, image):
+    """将目录打包为镜像"""
+    fs = new_fs()
+    for root, _dirs, files in os.walk(src):
+        rel = os.path.relpath(root, src).replace(os.sep, "/")
+        if rel != ".":
+            fs.makedirs(rel, exist_ok=True)
+        for name in files:
+            path = name if rel == "." else rel + "/" + name
+            with open(os.path.join(root, name), "rb") as f:
+                data = f.read()
+            with fs.open(path, "wb") as f:
+                f.write(data)
+            print(f"add {path} ({len(data)} bytes)")
+
+    with open(image, "wb") as f:
+        f.write(fs.context.buffer)
+
+
+def extract(image, dst):
+    """将镜像解包为目录"""
+    fs = load_fs(image)
+    for root, _dirs, files in fs.walk("/"):
+        out = os.path.join(dst, root.strip("/"))
+        os.makedirs(out, exist_ok=True)
+        for name in files:
+            path = root.rstrip("/") + "/" + name
+            with fs.open(path, "rb") as f:
+                data = f.read()
+            with open(os.path.join(out, name), "wb") as f:
+                f.write(data)
+            print(f"extract {path} ({len(data)} bytes)")
+
+
+def list_files(image):
+    """列出镜像中的文件"""
+    fs = load_fs(image)
+    for root, _dirs, files in fs.walk("/"):
+        for name in files:
+            path = root.rstrip("/") + "/" + name
+            print(f"{fs.stat(path).size:>10}  {path}")
+
+
+def main():
+    parser = argparse.ArgumentParser(description="W25Q64 littlefs 镜像工具")
+    sub = parser.add_subparsers(dest="command", required=True)
+
+    p = sub.add_parser("build", help="将目录打包为镜像")
+    p.add_argument("src", help="源目录")
+    p.add_argument("image", help="输出镜像文件")
+
+    p = sub.add_parser("extract", help="将镜像解包为目录")
+    p.add_argument("image", help="镜像文件")
+    p.add_argument("dst", help="输出目录")
+
+    p = sub.add_parser("list", help="列出镜像中的文件")
+    p.add_argument("image", help="镜像文件")
+
+    args = parser.parse_args()
+    if args.command == "build":
+        build(args.src, args.image)
+    elif args.command == "extract":
+        extract(args.image, args.dst)
+    else:
+        list_files(args.image)
+
+
+if __name__ == "__main__":
+    main()